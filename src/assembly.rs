@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use core::utils::{Iso3, R3, V3};
+use core::{Anchor, AnchorPoint, MapDots, Tree};
+use errors::{ResultExt, ScadDotsError};
+
+/// A collection of named parts, placed relative to each other by joining
+/// their anchor points. Parts that aren't joined to anything stay where they
+/// are. Every other part is moved by the joint that places it, after the part
+/// it's joined to has been placed.
+#[derive(Default)]
+pub struct Assembly {
+    parts: Vec<(String, Box<Part>)>,
+    joints: HashMap<String, Joint>,
+}
+
+/// Something that can be placed in an Assembly.
+pub trait Part {
+    /// Return the named anchor point, before the part has been placed.
+    fn part_anchor(&self, name: &str) -> Result<AnchorPoint, ScadDotsError>;
+
+    /// Return the part's Tree, after moving it by the given placement.
+    fn placed_tree(&self, placement: Iso3) -> Result<Tree, ScadDotsError>;
+}
+
+/// A Part made from a struct of Dots, like a Cuboid, and a function that links
+/// it into a Tree, like `|c: &Cuboid| c.link(CuboidLink::Solid)`.
+pub struct DotsPart<T, F> {
+    pub shape: T,
+    pub link: F,
+}
+
+/// How to place a part relative to the anchor it's joined to.
+#[derive(Debug, Clone, Copy)]
+pub enum JointKind {
+    /// Move and rotate the part so its anchor has the same position and
+    /// orientation as the target anchor, then move it by the offset. The
+    /// offset is relative to the target anchor's orientation, so an offset
+    /// along z of a "top" anchor leaves a gap above a face.
+    Mate { offset: V3 },
+    /// Only move the part, so its anchor is at the target anchor's position
+    /// plus the offset. The part keeps its own orientation.
+    Translate { offset: V3 },
+}
+
+#[derive(Debug, Clone)]
+struct Joint {
+    anchor: String,
+    target: String,
+    target_anchor: String,
+    kind: JointKind,
+}
+
+impl Assembly {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a part with a unique name.
+    pub fn add<T>(&mut self, name: &str, part: T) -> Result<(), ScadDotsError>
+    where
+        T: Part + 'static,
+    {
+        if self.has_part(name) {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!("assembly already has a part named '{}'", name)
+            }));
+        }
+        self.parts.push((name.to_owned(), Box::new(part)));
+        Ok(())
+    }
+
+    /// Add a part made from a struct of Dots and the function that links it
+    /// into a Tree. See `DotsPart`.
+    pub fn add_dots<T, F>(
+        &mut self,
+        name: &str,
+        shape: T,
+        link: F,
+    ) -> Result<(), ScadDotsError>
+    where
+        T: Anchor + MapDots + 'static,
+        F: Fn(&T) -> Result<Tree, ScadDotsError> + 'static,
+    {
+        self.add(name, DotsPart { shape, link })
+    }
+
+    /// Place a part by joining one of its anchors to an anchor on another
+    /// part. The arguments are pairs of part name and anchor name. Each part
+    /// can only be placed by a single joint.
+    pub fn join(
+        &mut self,
+        (part, anchor): (&str, &str),
+        (target, target_anchor): (&str, &str),
+        kind: JointKind,
+    ) -> Result<(), ScadDotsError> {
+        for name in &[part, target] {
+            if !self.has_part(name) {
+                return Err(ScadDotsError::Args.with_context(|| {
+                    format!("assembly has no part named '{}'", name)
+                }));
+            }
+        }
+        if self.joints.contains_key(part) {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!("assembly part '{}' is already joined", part)
+            }));
+        }
+        self.joints.insert(
+            part.to_owned(),
+            Joint {
+                anchor: anchor.to_owned(),
+                target: target.to_owned(),
+                target_anchor: target_anchor.to_owned(),
+                kind,
+            },
+        );
+        Ok(())
+    }
+
+    /// Return the placement of every part.
+    pub fn solve(&self) -> Result<HashMap<String, Iso3>, ScadDotsError> {
+        let mut placements = HashMap::new();
+        for (name, _) in &self.parts {
+            self.place(name, &mut placements, &mut Vec::new())?;
+        }
+        Ok(placements)
+    }
+
+    /// Return the union of all the placed parts.
+    pub fn link(&self) -> Result<Tree, ScadDotsError> {
+        let placements = self.solve()?;
+        let mut trees = Vec::new();
+        for (name, part) in &self.parts {
+            trees.push(part.placed_tree(placements[name]).with_context(
+                || format!("failed to make tree for assembly part '{}'", name),
+            )?);
+        }
+        Ok(Tree::union(trees))
+    }
+
+    fn has_part(&self, name: &str) -> bool {
+        self.parts.iter().any(|(existing, _)| existing == name)
+    }
+
+    fn part(&self, name: &str) -> &Part {
+        self.parts
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, part)| part.as_ref())
+            .expect("assembly joint refers to a missing part")
+    }
+
+    /// Find the placement of the named part and store it, after recursively
+    /// placing the part it's joined to. `pending` holds the parts that are
+    /// currently being placed, to detect cycles.
+    fn place(
+        &self,
+        name: &str,
+        placements: &mut HashMap<String, Iso3>,
+        pending: &mut Vec<String>,
+    ) -> Result<Iso3, ScadDotsError> {
+        if let Some(placement) = placements.get(name) {
+            return Ok(*placement);
+        }
+        if pending.iter().any(|p| p == name) {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!("assembly joints form a cycle: {}", pending.join(", "))
+            }));
+        }
+        let placement = match self.joints.get(name) {
+            None => Iso3::identity(),
+            Some(joint) => {
+                pending.push(name.to_owned());
+                let target_placement =
+                    self.place(&joint.target, placements, pending)?;
+                pending.pop();
+
+                let target = self
+                    .part(&joint.target)
+                    .part_anchor(&joint.target_anchor)?;
+                let target = AnchorPoint {
+                    pos: target_placement * target.pos,
+                    rot: target_placement.rotation * target.rot,
+                };
+                let own = self.part(name).part_anchor(&joint.anchor)?;
+                joint.kind.placement(own, target)
+            }
+        };
+        placements.insert(name.to_owned(), placement);
+        Ok(placement)
+    }
+}
+
+impl JointKind {
+    /// Return the placement that moves the `own` anchor onto the `target`
+    /// anchor.
+    fn placement(self, own: AnchorPoint, target: AnchorPoint) -> Iso3 {
+        let (rot, pos) = match self {
+            JointKind::Mate { offset } => (
+                target.rot * own.rot.inverse(),
+                target.pos + target.rot * offset,
+            ),
+            JointKind::Translate { offset } => {
+                (R3::identity(), target.pos + offset)
+            }
+        };
+        // Rotate about the origin, then translate the rotated anchor to
+        // the target position.
+        let translation: V3 = pos - rot * own.pos;
+        Iso3::new(translation, rot.scaled_axis())
+    }
+}
+
+impl<T, F> Part for DotsPart<T, F>
+where
+    T: Anchor + MapDots,
+    F: Fn(&T) -> Result<Tree, ScadDotsError>,
+{
+    fn part_anchor(&self, name: &str) -> Result<AnchorPoint, ScadDotsError> {
+        self.shape.anchor(name)
+    }
+
+    fn placed_tree(&self, placement: Iso3) -> Result<Tree, ScadDotsError> {
+        (self.link)(&self.shape.map(&|dot| dot.transform(placement)))
+    }
+}
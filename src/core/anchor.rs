@@ -0,0 +1,141 @@
+use core::utils::{Axis, Corner3 as C3, P3, R3};
+use core::MapDots;
+use errors::{ResultExt, ScadDotsError};
+
+/// A position and orientation on a shape, for attaching other shapes to it.
+#[derive(Debug, Clone, Copy)]
+pub struct AnchorPoint {
+    pub pos: P3,
+    pub rot: R3,
+}
+
+/// A parsed anchor name like "top_left_inside". A name is made of words
+/// separated by underscores, in any order. Each axis can be specified by at
+/// most one word: "left" or "right" for x, "front" or "back" for y, and
+/// "bottom" or "top" for z. Any axis that isn't specified is centered. The
+/// word "inside" selects an inner point instead of an outer one, on shapes
+/// that have both. The name "centroid" (or "center") has no axis words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnchorName {
+    pub x: Option<bool>,
+    pub y: Option<bool>,
+    pub z: Option<bool>,
+    pub inside: bool,
+}
+
+/// This trait lets you look up named points on a shape, and attach shapes to
+/// each other by those points.
+pub trait Anchor {
+    /// The alignment type that anchors can be looked up by, like `DotAlign`.
+    type Align;
+
+    /// Return the position and orientation of the given alignment point. The
+    /// orientation is the rotation of the shape itself.
+    fn anchor_at(&self, align: Self::Align) -> AnchorPoint;
+
+    /// Convert a parsed anchor name to an alignment point, or return an error
+    /// if this kind of shape has no such point.
+    fn align_from_name(name: AnchorName) -> Result<Self::Align, ScadDotsError>;
+
+    /// Return the position and orientation of the named point. See
+    /// `AnchorName` for the naming scheme.
+    fn anchor(&self, name: &str) -> Result<AnchorPoint, ScadDotsError> {
+        let align = AnchorName::parse(name)
+            .and_then(Self::align_from_name)
+            .with_context(|| format!("invalid anchor name '{}'", name))?;
+        Ok(self.anchor_at(align))
+    }
+
+    /// Return a copy that's rotated and translated, so that its named anchor
+    /// point has the same position and orientation as the target.
+    fn place_at(
+        &self,
+        name: &str,
+        target: AnchorPoint,
+    ) -> Result<Self, ScadDotsError>
+    where
+        Self: MapDots,
+    {
+        let own = self.anchor(name)?;
+        let rot = target.rot * own.rot.inverse();
+        Ok(self
+            .map_translate(P3::origin() - own.pos)
+            .map_rotate(rot)
+            .map_translate(target.pos - P3::origin()))
+    }
+}
+
+impl AnchorName {
+    pub fn parse(name: &str) -> Result<Self, ScadDotsError> {
+        let mut parsed = Self {
+            x: None,
+            y: None,
+            z: None,
+            inside: false,
+        };
+        if name == "centroid" || name == "center" {
+            return Ok(parsed);
+        }
+        for word in name.split('_') {
+            let (axis, is_high) = match word {
+                "inside" => {
+                    parsed.inside = true;
+                    continue;
+                }
+                "outside" => continue,
+                "left" => (Axis::X, false),
+                "right" => (Axis::X, true),
+                "front" => (Axis::Y, false),
+                "back" => (Axis::Y, true),
+                "bottom" => (Axis::Z, false),
+                "top" => (Axis::Z, true),
+                _ => {
+                    return Err(ScadDotsError::Args.with_context(|| {
+                        format!("unknown anchor name word '{}'", word)
+                    }))
+                }
+            };
+            let slot = match axis {
+                Axis::X => &mut parsed.x,
+                Axis::Y => &mut parsed.y,
+                Axis::Z => &mut parsed.z,
+            };
+            if slot.is_some() {
+                return Err(ScadDotsError::Args.with_context(|| {
+                    format!("anchor name specifies the {:?} axis twice", axis)
+                }));
+            }
+            *slot = Some(is_high);
+        }
+        Ok(parsed)
+    }
+
+    /// Return true if every axis is specified, so the name refers to a single
+    /// corner.
+    pub fn is_corner(&self) -> bool {
+        self.x.is_some() && self.y.is_some() && self.z.is_some()
+    }
+
+    /// Return 2 opposite corners whose midpoint is the named point. Unspecified
+    /// axes are low on the first corner and high on the second. If every axis
+    /// is specified, both corners are the same.
+    pub fn corners(&self) -> (C3, C3) {
+        let corner = |unspecified: bool| {
+            C3::P000
+                .copy_to(Axis::X, self.x.unwrap_or(unspecified))
+                .copy_to(Axis::Y, self.y.unwrap_or(unspecified))
+                .copy_to(Axis::Z, self.z.unwrap_or(unspecified))
+        };
+        (corner(false), corner(true))
+    }
+
+    /// Return an error if the name uses "inside", for shapes that have no
+    /// inner points.
+    pub fn require_outside(&self) -> Result<(), ScadDotsError> {
+        if self.inside {
+            return Err(ScadDotsError::Args
+                .context("this shape has no inside anchor points"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+use core::utils::{Axis, Corner3 as C3, P3, R3, V3};
+use core::{
+    Dot, DotAlign, DotShape, Extrusion, MinMaxCoord, ScadModifier, Text, Torus,
+    Tree, TreeObject, TreeOperator,
+};
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: P3,
+    pub max: P3,
+}
+
+impl Bounds {
+    /// Return the smallest box containing all the points, or None if there
+    /// are no points.
+    pub fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = P3>,
+    {
+        points
+            .into_iter()
+            .map(|p| Self { min: p, max: p })
+            .fold(None, |acc, b| Some(Self::merge_option(acc, b)))
+    }
+
+    /// Return the box centered on `center` that extends by `half_extents`
+    /// along each axis.
+    pub(crate) fn around(center: P3, half_extents: V3) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    /// Return the smallest box containing both boxes.
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            min: P3::from_coordinates(
+                self.min.coords.zip_map(&other.min.coords, f32::min),
+            ),
+            max: P3::from_coordinates(
+                self.max.coords.zip_map(&other.max.coords, f32::max),
+            ),
+        }
+    }
+
+    /// Return the overlap of the 2 boxes, or None if they don't overlap.
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let overlap = Self {
+            min: P3::from_coordinates(
+                self.min.coords.zip_map(&other.min.coords, f32::max),
+            ),
+            max: P3::from_coordinates(
+                self.max.coords.zip_map(&other.max.coords, f32::min),
+            ),
+        };
+        if [Axis::X, Axis::Y, Axis::Z]
+            .iter()
+            .any(|&axis| axis.of_p3(overlap.min) > axis.of_p3(overlap.max))
+        {
+            None
+        } else {
+            Some(overlap)
+        }
+    }
+
+    /// Return the lengths of the box along each axis.
+    pub fn size(&self) -> V3 {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> P3 {
+        P3::from_coordinates((self.min.coords + self.max.coords) / 2.)
+    }
+
+    /// Return the position of a corner of the box.
+    pub fn corner(&self, corner: C3) -> P3 {
+        let coord = |axis: Axis| {
+            if corner.is_high(axis) {
+                axis.of_p3(self.max)
+            } else {
+                axis.of_p3(self.min)
+            }
+        };
+        P3::new(coord(Axis::X), coord(Axis::Y), coord(Axis::Z))
+    }
+
+    /// Return the smallest box containing the 8 corners of this box after
+    /// they've been transformed by `f`.
+    pub(crate) fn map_corners<F>(&self, f: F) -> Self
+    where
+        F: Fn(P3) -> P3,
+    {
+        Self::from_points(C3::all().into_iter().map(|c| f(self.corner(c))))
+            .expect("a box always has corners")
+    }
+
+    pub(crate) fn merge_option(acc: Option<Self>, other: Self) -> Self {
+        match acc {
+            Some(acc) => acc.merge(&other),
+            None => other,
+        }
+    }
+}
+
+impl Tree {
+    /// Return the bounding box of the tree, or None if the tree is empty. The
+    /// box is exact for unions, hulls, Minkowski sums, and all the objects
+    /// except text.
+    /// For other operators it's conservative: a difference uses the box of its
+    /// base, an intersection uses the overlap of its children's boxes, and a
+    /// rotated or mirrored tree uses the box around its rotated or mirrored
+    /// box. Trees with the background or disable modifiers are left out,
+    /// since they aren't part of the final render. Raw code and imported
+    /// files are left out too, since their sizes are unknown, so a
+    /// difference or intersection whose first child is one of them has no
+    /// bounds.
+    pub fn bounds(&self) -> Option<Bounds> {
+        match self {
+            Tree::Object(object) => object.bounds(),
+            Tree::Operator(operator) => operator.bounds(),
+        }
+    }
+}
+
+impl TreeObject {
+    /// Return the bounding box of the object, or None if it's unknown. See
+    /// `Tree::bounds()`.
+    pub fn bounds(&self) -> Option<Bounds> {
+        Some(match self {
+            TreeObject::Dot(dot) => dot.bounds(),
+            TreeObject::Cylinder(cylinder) => {
+                let start = cylinder.center_bot_pos;
+                let unit_axis = cylinder.unit_axis();
+                let disc =
+                    |diameter: f32| disc_half_extents(unit_axis, diameter / 2.);
+                Bounds::around(start, disc(cylinder.diameter)).merge(
+                    &Bounds::around(
+                        start + cylinder.axis(),
+                        disc(cylinder.top_diameter()),
+                    ),
+                )
+            }
+            TreeObject::Extrusion(extrusion) => extrusion.bounds(),
+            TreeObject::Torus(torus) => torus.bounds(),
+            TreeObject::Text(text) => text.bounds(),
+            TreeObject::RawScad(_) | TreeObject::Import { .. } => return None,
+        })
+    }
+}
+
+impl TreeOperator {
+    /// Return the bounding box of the operator's result, or None if it's
+    /// empty. See `Tree::bounds()`.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let merge_all = |children: &[Tree]| {
+            children
+                .iter()
+                .filter_map(|child| child.bounds())
+                .fold(None, |acc, b| Some(Bounds::merge_option(acc, b)))
+        };
+        match self {
+            TreeOperator::Union(children) | TreeOperator::Hull(children) => {
+                merge_all(children)
+            }
+            TreeOperator::Diff(children) => children.first()?.bounds(),
+            TreeOperator::Intersect(children) => {
+                let mut boxes = children.iter().map(|child| child.bounds());
+                let first = boxes.next()??;
+                boxes.fold(Some(first), |acc, b| acc?.intersect(&b?))
+            }
+            TreeOperator::Minkowski(children) => {
+                let mut boxes = children.iter().map(|child| child.bounds());
+                let first = boxes.next()??;
+                boxes.fold(Some(first), |acc, b| {
+                    let (acc, b) = (acc?, b?);
+                    Some(Bounds {
+                        min: acc.min + b.min.coords,
+                        max: acc.max + b.max.coords,
+                    })
+                })
+            }
+            TreeOperator::Color(_, child) => child.bounds(),
+            TreeOperator::Mirror(normal, child) => {
+                let unit = normal.normalize();
+                Some(child.bounds()?.map_corners(|p| {
+                    p - 2. * (p - P3::origin()).dot(&unit) * unit
+                }))
+            }
+            TreeOperator::Scale(factors, child) => {
+                Some(child.bounds()?.map_corners(|p| {
+                    P3::from_coordinates(p.coords.component_mul(factors))
+                }))
+            }
+            TreeOperator::Translate(offset, child) => {
+                let b = child.bounds()?;
+                Some(Bounds {
+                    min: b.min + *offset,
+                    max: b.max + *offset,
+                })
+            }
+            TreeOperator::Rotate(rot, child) => {
+                let rot: R3 = *rot;
+                Some(child.bounds()?.map_corners(|p| rot * p))
+            }
+            TreeOperator::Modifier(modifier, child) => match modifier {
+                // These don't appear in the final render.
+                ScadModifier::Background | ScadModifier::Disable => None,
+                ScadModifier::Debug | ScadModifier::Root => child.bounds(),
+            },
+            TreeOperator::Named(_, child)
+            | TreeOperator::Detail { child, .. } => child.bounds(),
+        }
+    }
+}
+
+impl Dot {
+    /// Return the bounding box of the dot's shape. It's exact, except for
+    /// prisms, which use the box around the cylinder they fit inside.
+    pub fn bounds(&self) -> Bounds {
+        let radius = self.size / 2.;
+        match self.shape {
+            DotShape::Cube | DotShape::ChamferedCube { .. } => {
+                Bounds::from_points(
+                    C3::all().into_iter().map(|corner| self.pos(corner)),
+                )
+                .expect("a dot always has corners")
+            }
+            DotShape::Sphere => Bounds::around(
+                self.pos(DotAlign::centroid()),
+                V3::new(radius, radius, radius),
+            ),
+            DotShape::Cylinder | DotShape::Prism { .. } => cylinder_bounds(
+                self.pos(DotAlign::centroid())
+                    - self.size / 2. * self.dim_unit_vec(Axis::Z),
+                self.size * self.dim_unit_vec(Axis::Z),
+                radius,
+            ),
+        }
+    }
+}
+
+impl Extrusion {
+    /// Return the bounding box of the extruded perimeter. If it's twisted,
+    /// this is the box around the circle that the perimeter sweeps through.
+    pub fn bounds(&self) -> Bounds {
+        let (bottom_z, top_z) = self.z_range();
+        let scale = self.params.scale;
+        let outline = if self.params.twist == 0. {
+            Bounds::from_points(self.perimeter.points().iter().flat_map(|p| {
+                vec![
+                    P3::new(p.x, p.y, bottom_z),
+                    P3::new(scale * p.x, scale * p.y, top_z),
+                ]
+            }))
+        } else {
+            let radius = self
+                .perimeter
+                .points()
+                .iter()
+                .map(|p| p.coords.norm())
+                .fold(0., f32::max)
+                * scale.max(1.);
+            Some(Bounds {
+                min: P3::new(-radius, -radius, bottom_z),
+                max: P3::new(radius, radius, top_z),
+            })
+        };
+        outline.unwrap_or_else(|| Bounds::around(P3::origin(), V3::zeros()))
+    }
+}
+
+impl Torus {
+    /// Return the exact bounding box of the torus.
+    pub fn bounds(&self) -> Bounds {
+        let disc = disc_half_extents(self.unit_axis(), self.major_radius);
+        let tube = self.minor_radius;
+        Bounds::around(self.center, disc + V3::new(tube, tube, tube))
+    }
+}
+
+impl Text {
+    /// Return a rough bounding box of the text. The font's glyph sizes aren't
+    /// known, so this assumes each character is at most `font_size` wide and
+    /// the line is at most twice `font_size` tall.
+    pub fn bounds(&self) -> Bounds {
+        let half_width = self.text.chars().count() as f32 * self.font_size / 2.;
+        let local = Bounds {
+            min: P3::new(-half_width, -self.font_size, 0.),
+            max: P3::new(half_width, self.font_size, self.thickness),
+        };
+        local.map_corners(|p| self.pos + self.rot * (p - P3::origin()))
+    }
+}
+
+impl MinMaxCoord for Tree {
+    /// Return the min and max coordinates of the tree's bounding box, or
+    /// nothing if the tree is empty.
+    fn all_coords(&self, axis: Axis) -> Vec<f32> {
+        match self.bounds() {
+            Some(b) => vec![axis.of_p3(b.min), axis.of_p3(b.max)],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Return the exact bounding box of a cylinder with one end centered on
+/// `start`, extending along `axis`.
+fn cylinder_bounds(start: P3, axis: V3, radius: f32) -> Bounds {
+    let disc = disc_half_extents(axis.normalize(), radius);
+    Bounds::around(start, disc).merge(&Bounds::around(start + axis, disc))
+}
+
+/// Return how far a circle with the given normal vector and radius extends
+/// from its center along each axis.
+fn disc_half_extents(unit_normal: V3, radius: f32) -> V3 {
+    // Use the other 2 components instead of `1 - n.x^2`, which loses
+    // precision when the normal is nearly along the axis.
+    let n = unit_normal;
+    let extent = |a: f32, b: f32| radius * (a * a + b * b).min(1.).sqrt();
+    V3::new(extent(n.y, n.z), extent(n.x, n.z), extent(n.x, n.y))
+}
@@ -5,21 +5,28 @@ use core::utils::Axis;
 
 use core::tree::Tree;
 use core::Dot;
-use errors::ScadDotsError;
-use std::collections::HashSet;
+use errors::{ResultExt, ScadDotsError};
+use std::collections::{HashMap, HashSet};
 
 /// Draw a taxicab path between two dots
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Snake {
-    pub dots: [Dot; 4],
+    pub dots: Vec<Dot>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum SnakeLink {
     Chain,
+    /// Like `Chain`, but with the elbows rounded off to the given radius.
+    /// See `chain_smooth()`.
+    Smooth {
+        radius: f32,
+    },
 }
 
 impl Snake {
+    /// Create a snake with 3 segments, moving along each axis once in the
+    /// given order. Each axis must appear exactly once.
     pub fn new(
         start: Dot,
         end: Dot,
@@ -28,13 +35,49 @@ impl Snake {
         if Self::has_repeated_axes(order) {
             return Err(ScadDotsError::Snake);
         }
-        let mut dots = [Dot::default(); 4];
-        dots[0] = start.to_owned();
+        Self::with_waypoints(start, end, &order)
+    }
+
+    /// Create a snake with one segment for each entry in `axes`, so it has
+    /// `axes.len() + 1` dots. Each segment moves parallel to its axis. If an
+    /// axis appears more than once, its total distance is split evenly
+    /// between its segments, making extra bends. An axis that doesn't appear
+    /// at all keeps the start dot's coordinate, so the snake won't reach the
+    /// end dot along that axis. The same axis can't appear twice in a row.
+    pub fn with_waypoints(
+        start: Dot,
+        end: Dot,
+        axes: &[Axis],
+    ) -> Result<Self, ScadDotsError> {
+        if axes.is_empty() {
+            return Err(
+                ScadDotsError::Snake.context("snake needs at least 1 axis")
+            );
+        }
+        if axes.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(ScadDotsError::Snake.context(
+                "snake can't move along the same axis twice in a row",
+            ));
+        }
+        let count = |axis: Axis| axes.iter().filter(|&&a| a == axis).count();
+        let mut done: HashMap<Axis, usize> = HashMap::new();
+        let mut dots = vec![start];
 
         // Some dots in the snake may be redundant, and have the same positions.
         // That seems fine.
-        for (index, axis) in order.iter().enumerate() {
-            dots[index + 1] = dots[index].copy_to_other_dim(end, *axis);
+        for &axis in axes {
+            let step = done.entry(axis).or_insert(0);
+            *step += 1;
+            let previous = dots[dots.len() - 1];
+            let next = if *step == count(axis) {
+                previous.copy_to_other_dim(end, axis)
+            } else {
+                let from = axis.of_p3(start.p000);
+                let to = axis.of_p3(end.p000);
+                let fraction = *step as f32 / count(axis) as f32;
+                previous.with_coord(from + (to - from) * fraction, axis)
+            };
+            dots.push(next);
         }
         Ok(Self { dots })
     }
@@ -47,6 +90,7 @@ impl Snake {
     pub fn link(&self, style: SnakeLink) -> Result<Tree, ScadDotsError> {
         match style {
             SnakeLink::Chain => chain(&self.dots),
+            SnakeLink::Smooth { radius } => chain_smooth(&self.dots, radius),
         }
     }
 }
@@ -63,6 +107,34 @@ where
     Ok(Tree::union(segments))
 }
 
+/// Like `chain()`, but round off the sharp creases at the joints. Each dot
+/// between the first and last is replaced by a copy with its edges rounded
+/// to the given radius, so the outside of every elbow is rounded too. See
+/// `Dot::rounded()`.
+pub fn chain_smooth(dots: &[Dot], radius: f32) -> Result<Tree, ScadDotsError> {
+    chain(&smooth_joints(dots, radius)?)
+}
+
+/// Return the joints used by `chain_smooth()`: the first and last dots as
+/// they are, and rounded copies of the ones between them.
+pub(crate) fn smooth_joints(
+    dots: &[Dot],
+    radius: f32,
+) -> Result<Vec<Tree>, ScadDotsError> {
+    let last = dots.len().saturating_sub(1);
+    dots.iter()
+        .enumerate()
+        .map(|(i, dot)| {
+            if i == 0 || i == last {
+                Ok(Tree::from(*dot))
+            } else {
+                dot.rounded(radius)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to smooth chain")
+}
+
 pub fn chain_loop<T>(things: &[T]) -> Result<Tree, ScadDotsError>
 where
     T: Clone + Into<Tree>,
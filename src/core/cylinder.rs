@@ -1,34 +1,54 @@
 use core::{Tree, TreeObject};
+use errors::ScadDotsError;
 
-use core::utils::{Axis, Corner1 as C1, P3, R3, V3};
+use core::utils::{
+    check_finite, cos_deg, sin_deg, Axis, Corner1 as C1, Fraction, Plane, P3,
+    R3, V3,
+};
 
 // Cylinders have only basic support, without all the nice features of Dots.
 // They should only be used for making discs that are shorter than their
 // diameter.
 // The default orientation is for the cylinder's axis (height) to be the z axis.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Cylinder {
     pub center_bot_pos: P3,
+    /// The diameter of the bottom end.
     pub diameter: f32,
+    /// The diameter of the top end, if it's different from the bottom, making
+    /// a cone or frustum. See `with_diameter_top()`.
+    pub diameter_top: Option<f32>,
     pub height: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
     pub rot: R3,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct CylinderSpec {
     pub pos: P3,
     pub align: CylinderAlign,
     pub diameter: f32,
     pub height: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
     pub rot: R3,
 }
 
 /// Specify an alignment point on a Cylinder. This does not depend on a particular Cylinder's dimensions.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum CylinderAlign {
     /// The center of the circle at the bottom (C1::P0) or top (C1::P1) of the cylinder.
     EndCenter(C1),
     /// The centroid of the cylinder (the center of the circular cross-section, at half of the total height).
+    /// For a cone, this is still at half the height, not at the center of mass.
     /// TODO is this name accurate?
     Centroid,
 }
@@ -39,11 +59,47 @@ impl Cylinder {
         Self {
             center_bot_pos: spec.center_bot_pos(),
             diameter: spec.diameter,
+            diameter_top: None,
             height: spec.height,
             rot: spec.rot,
         }
     }
 
+    /// Create a new cylinder, or return a `Numeric` error if the spec has any
+    /// NaN or infinite values.
+    pub fn try_new(spec: CylinderSpec) -> Result<Self, ScadDotsError> {
+        check_finite("cylinder position", spec.pos.coords.iter().cloned())?;
+        check_finite("cylinder dimensions", vec![spec.diameter, spec.height])?;
+        check_finite("cylinder rotation", spec.rot.coords.iter().cloned())?;
+        Ok(Self::new(spec))
+    }
+
+    /// Make a copy with a different diameter at the top end, like for a
+    /// countersink or funnel. The position of the bottom end stays the same.
+    pub fn with_diameter_top(self, diameter_top: f32) -> Self {
+        Self {
+            diameter_top: Some(diameter_top),
+            ..self
+        }
+    }
+
+    /// Return the diameter of the top end.
+    pub fn top_diameter(&self) -> f32 {
+        self.diameter_top.unwrap_or(self.diameter)
+    }
+
+    /// Return true if the top and bottom ends have different diameters.
+    pub fn is_cone(&self) -> bool {
+        self.top_diameter() != self.diameter
+    }
+
+    /// Return the radius of the cross-section at the given fraction of the
+    /// height, from 0 at the bottom end to 1 at the top.
+    pub fn radius_at(&self, height_fraction: Fraction) -> f32 {
+        let t = height_fraction.unwrap();
+        ((1. - t) * self.diameter + t * self.top_diameter()) / 2.
+    }
+
     pub fn pos(&self, align: CylinderAlign) -> P3 {
         self.center_bot_pos + align.offset(self.diameter, self.height, self.rot)
     }
@@ -56,6 +112,39 @@ impl Cylinder {
     pub fn axis(&self) -> V3 {
         self.height * self.unit_axis()
     }
+
+    /// Return a point on the curved side surface of the cylinder. The angle is
+    /// measured in degrees around the cylinder's axis, starting from its local
+    /// x axis. The height fraction is 0 at the bottom end and 1 at the top.
+    pub fn pos_on_surface(
+        &self,
+        degrees: f32,
+        height_fraction: Fraction,
+    ) -> P3 {
+        let height = height_fraction.unwrap() * self.height;
+        self.center_bot_pos
+            + self.rot * V3::new(0., 0., height)
+            + self.radius_at(height_fraction) * self.unit_radial(degrees)
+    }
+
+    /// Return the plane that touches the side surface of the cylinder along
+    /// the line at the given angle (see `pos_on_surface()`). The plane's
+    /// normal points away from the cylinder's axis. For a cone, it's tilted
+    /// to match the slope of the side.
+    pub fn tangent_plane(&self, degrees: f32) -> Plane {
+        let middle = Fraction::new(0.5).expect("bad fraction");
+        let shrink = (self.diameter - self.top_diameter()) / 2.;
+        Plane::new(
+            self.pos_on_surface(degrees, middle),
+            self.height * self.unit_radial(degrees) + shrink * self.unit_axis(),
+        )
+    }
+
+    /// Return a unit vector perpendicular to the cylinder's axis, pointing
+    /// outwards at the given angle around it.
+    pub fn unit_radial(&self, degrees: f32) -> V3 {
+        self.rot * V3::new(cos_deg(degrees), sin_deg(degrees), 0.)
+    }
 }
 
 impl From<Cylinder> for Tree {
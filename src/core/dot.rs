@@ -1,33 +1,47 @@
 use std::f32::consts::PI;
 
+use nalgebra::{Matrix3, Rotation3};
+
 use core::utils::{
-    axis_radians, map_float, radial_offset, radians_to_degrees, rotate,
-    translate_p3_along_until, unwrap_rot_axis, Axis, Corner3 as C3, CubeFace,
-    P2, P3, R3, V3,
+    axis_radians, check_finite, map_float, radial_offset, radians_to_degrees,
+    rotate, translate_p3_along_until, unwrap_rot_axis, Axis, Corner3 as C3,
+    CubeFace, Fraction, Iso3, Line3, Plane, P2, P3, R3, V3,
 };
 
-use core::{Snake, Tree};
+use core::{Anchor, AnchorName, AnchorPoint, Snake, Tree};
 use errors::ScadDotsError;
+use pattern::polar_array;
 
 /// The smallest building block of the 3d model.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Dot {
     pub shape: DotShape,
     pub p000: P3,
     pub size: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
     pub rot: R3,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct DotSpec {
     pub pos: P3,
     pub align: DotAlign,
     pub size: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
     pub rot: R3,
     pub shape: DotShape,
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum DotAlign {
     Corner(C3),
     Midpoint(C3, C3),
@@ -35,10 +49,41 @@ pub enum DotAlign {
 
 /// The possible shapes of a dot
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum DotShape {
     Cube,
     Sphere,
     Cylinder,
+    /// A cube with all 12 edges cut off at 45 degrees. The chamfer is the
+    /// fraction of half the dot's size that's cut off of each face along
+    /// each edge, so 0 is a plain cube.
+    ChamferedCube {
+        chamfer: Fraction,
+    },
+    /// A prism with a regular polygon cross-section, like a hexagonal nut.
+    /// It's oriented like a cylinder dot, with one corner of the polygon
+    /// pointing along the dot's x axis. The size is the distance across
+    /// opposite corners, so it fits exactly inside a cylinder dot of the same
+    /// size. Use `prism_size()` to find the size from the distance across
+    /// flats. It must have at least 3 sides.
+    Prism {
+        sides: u32,
+    },
+}
+
+/// Return the size of a `DotShape::Prism` dot with the given number of sides
+/// and distance across flats (the diameter of the inscribed circle).
+pub fn prism_size(sides: u32, across_flats: f32) -> f32 {
+    across_flats / (PI / sides as f32).cos()
+}
+
+fn check_scale_factor(factor: f32) -> Result<(), ScadDotsError> {
+    if !(factor > 0.) || !factor.is_finite() {
+        return Err(ScadDotsError::Dimension.with_context(|| {
+            format!("scale factor must be positive, got {}", factor)
+        }));
+    }
+    Ok(())
 }
 
 /// This trait lets you apply a closure to every Dot within a struct.
@@ -59,6 +104,89 @@ pub trait MapDots: Sized {
     fn map_rotate(&self, rot: R3) -> Self {
         self.map(&|d: &Dot| d.rotate(rot))
     }
+
+    /// Rotate every dot around the given pivot point, instead of the origin.
+    /// See `Dot::rotate_about()`.
+    fn map_rotate_about(&self, pivot: P3, rot: R3) -> Self {
+        let to_pivot = pivot - P3::origin();
+        self.map_translate(-to_pivot)
+            .map_rotate(rot)
+            .map_translate(to_pivot)
+    }
+
+    /// Apply the isometry to every dot: rotate around the origin, and then
+    /// translate. See `Dot::transform()`. This does both steps in the right
+    /// order, so a whole assembly can be placed with one transform.
+    fn map_transform(&self, isometry: Iso3) -> Self {
+        self.map(&|d: &Dot| d.transform(isometry))
+    }
+
+    /// Scale the positions and sizes of every dot by the same factor, away
+    /// from (or towards) the given point. See `Dot::scale_about()`.
+    fn map_scale_about(
+        &self,
+        point: P3,
+        factor: f32,
+    ) -> Result<Self, ScadDotsError> {
+        check_scale_factor(factor)?;
+        Ok(self.map(&|d: &Dot| {
+            d.scale_about(point, factor)
+                .expect("scale factor was already checked")
+        }))
+    }
+
+    /// Mirror every dot across the plane through the origin with the given
+    /// normal vector. See `Dot::mirror()`. Mirroring reverses handedness, so
+    /// types with named corners, like Rect and Cuboid, override this to swap
+    /// their corners back into place.
+    fn map_mirror(&self, normal: V3) -> Self {
+        self.map(&|d: &Dot| d.mirror(normal))
+    }
+
+    /// Project every dot onto the plane. See `Dot::project_onto()`. Each dot
+    /// moves separately, so a curved arrangement gets flattened.
+    fn map_project_onto(
+        &self,
+        plane: Plane,
+        align: DotAlign,
+        reorient: bool,
+    ) -> Self {
+        self.map(&|d: &Dot| d.project_onto(plane, align, reorient))
+    }
+
+    /// Return `count` copies, spaced evenly around the given axis line that
+    /// passes through `pivot`. If `adjust_rotations` is true, each copy is
+    /// rotated about the axis, so it faces the same way relative to the axis.
+    /// Otherwise each copy is only translated, keeping its original
+    /// orientation, and its bounding-box midpoint is what gets moved around the
+    /// circle.
+    fn explode_radially_about(
+        &self,
+        pivot: P3,
+        axis: V3,
+        count: usize,
+        adjust_rotations: bool,
+    ) -> Vec<Self>
+    where
+        Self: MinMaxCoord,
+    {
+        let start = self.midpoint3();
+
+        let mut copies = Vec::new();
+        for i in 0..count {
+            let radians = (i as f32) / (count as f32) * 2. * PI;
+            let rot = axis_radians(axis, radians);
+
+            let copy = if adjust_rotations {
+                self.map_rotate_about(pivot, rot)
+            } else {
+                let end = pivot + rot * (start - pivot);
+                self.map_translate(end - start)
+            };
+            copies.push(copy);
+        }
+        copies
+    }
 }
 
 /// This provides methods that involve recursively checking all the coordinates within a struct.
@@ -108,6 +236,60 @@ pub trait MinMaxCoord {
         )
     }
 
+    /// Return the average of all the coordinates along the axis. See
+    /// `centroid_of_dots()`.
+    fn mean_coord(&self, axis: Axis) -> f32 {
+        let coords = self.all_coords(axis);
+        coords.iter().sum::<f32>() / coords.len() as f32
+    }
+
+    /// Return the average position of all the dots. Each dot's 8 corners
+    /// average out to its center, so this weights every dot equally,
+    /// regardless of size. Things that only report their bounding box, like
+    /// Trees, give the center of the box instead. It's NaN if there are no
+    /// coordinates at all.
+    fn centroid_of_dots(&self) -> P3 {
+        P3::new(
+            self.mean_coord(Axis::X),
+            self.mean_coord(Axis::Y),
+            self.mean_coord(Axis::Z),
+        )
+    }
+
+    /// Return the coordinate along the axis that the given percentage of all
+    /// the coordinates fall below, interpolating between them. For dots,
+    /// those are the coordinates of their corners. A percentile of 0 gives
+    /// `min_coord()`, 50 gives the median, and 100 gives `max_coord()`.
+    fn coord_percentile(
+        &self,
+        axis: Axis,
+        percentile: f32,
+    ) -> Result<f32, ScadDotsError> {
+        if !(percentile >= 0. && percentile <= 100.) {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!(
+                    "percentile must be between 0 and 100, got {}",
+                    percentile
+                )
+            }));
+        }
+        let mut coords = self.all_coords(axis);
+        if coords.is_empty() {
+            return Err(
+                ScadDotsError::Args.context("can't find percentile of nothing")
+            );
+        }
+        if coords.iter().any(|c| c.is_nan()) {
+            return Err(ScadDotsError::Args
+                .context("can't find percentile of NaN coordinates"));
+        }
+        coords.sort_by(|a, b| a.partial_cmp(b).expect("already checked NaN"));
+        let index = percentile / 100. * (coords.len() - 1) as f32;
+        let (low, high) = (index.floor() as usize, index.ceil() as usize);
+        let fraction = index - low as f32;
+        Ok(coords[low] + (coords[high] - coords[low]) * fraction)
+    }
+
     fn map_float(f: fn(f32, f32) -> f32, floats: Vec<f32>) -> f32 {
         // Use the version from the core
         map_float(f, floats)
@@ -125,6 +307,15 @@ impl Dot {
         }
     }
 
+    /// Create a new dot, or return a `Numeric` error if the spec has any NaN
+    /// or infinite values, like from normalizing a zero-length vector.
+    pub fn try_new(spec: DotSpec) -> Result<Self, ScadDotsError> {
+        check_finite("dot position", spec.pos.coords.iter().cloned())?;
+        check_finite("dot size", Some(spec.size))?;
+        check_finite("dot rotation", spec.rot.coords.iter().cloned())?;
+        Ok(Self::new(spec))
+    }
+
     pub fn dim_unit_vec(&self, axis: Axis) -> V3 {
         rotate(self.rot, axis)
     }
@@ -166,6 +357,35 @@ impl Dot {
         })
     }
 
+    /// Return a cube that fills the same space as the dot, but with its
+    /// edges and corners rounded off to the given radius. It's the hull of 8
+    /// small spheres tucked into the dot's corners. A radius of half the
+    /// dot's size gives a sphere.
+    pub fn rounded(&self, radius: f32) -> Result<Tree, ScadDotsError> {
+        if !(radius > 0.) || 2. * radius > self.size {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "rounding radius must be between 0 and half the dot \
+                     size, but it's {}",
+                    radius
+                )
+            }));
+        }
+        let corners: Vec<_> = C3::all()
+            .into_iter()
+            .map(|corner| {
+                Self::new(DotSpec {
+                    pos: self.pos(corner),
+                    align: corner.into(),
+                    size: 2. * radius,
+                    rot: self.rot,
+                    shape: DotShape::Sphere,
+                })
+            })
+            .collect();
+        Ok(Tree::hull(corners))
+    }
+
     pub fn translate(&self, offset: V3) -> Self {
         Self {
             shape: self.shape,
@@ -184,6 +404,99 @@ impl Dot {
         }
     }
 
+    /// Rotate the dot around the given pivot point, instead of the origin.
+    pub fn rotate_about(&self, pivot: P3, rot: R3) -> Self {
+        let to_pivot = pivot - P3::origin();
+        self.translate(-to_pivot).rotate(rot).translate(to_pivot)
+    }
+
+    /// Mirror the dot across the plane through the origin with the given
+    /// normal vector. A mirror image can't be described by a rotation, so the
+    /// new rotation also flips the dot's local y axis. Every dot shape is
+    /// symmetric across its local xz plane (a prism has a corner on the x
+    /// axis), so the result fills the mirrored space.
+    pub fn mirror(&self, normal: V3) -> Self {
+        let unit = normal.normalize();
+        let reflect = |v: V3| v - 2. * v.dot(&unit) * unit;
+        let old_rot = self.rot.to_rotation_matrix();
+        let columns = [
+            reflect(old_rot * V3::x()),
+            -reflect(old_rot * V3::y()),
+            reflect(old_rot * V3::z()),
+        ];
+        let rot = R3::from_rotation_matrix(&Rotation3::from_matrix_unchecked(
+            Matrix3::from_columns(&columns),
+        ));
+        let centroid = self.pos(DotAlign::centroid()) - P3::origin();
+        Self::new(DotSpec {
+            pos: P3::origin() + reflect(centroid),
+            align: DotAlign::centroid(),
+            size: self.size,
+            rot,
+            shape: self.shape,
+        })
+    }
+
+    /// Make a copy of the dot that's moved perpendicular to the plane, so
+    /// that its given alignment point is on the plane. If `reorient` is true,
+    /// the copy is also turned by the smallest rotation that points its local
+    /// z axis along the plane's normal.
+    pub fn project_onto(
+        &self,
+        plane: Plane,
+        align: DotAlign,
+        reorient: bool,
+    ) -> Self {
+        let rot = if reorient {
+            let z = rotate(self.rot, Axis::Z);
+            let turn = R3::rotation_between(&z, &plane.normal).unwrap_or_else(
+                // They point in opposite directions, so flip it over.
+                || axis_radians(rotate(self.rot, Axis::X), PI),
+            );
+            turn * self.rot
+        } else {
+            self.rot
+        };
+        Self::new(DotSpec {
+            pos: plane.project(self.pos(align)),
+            align,
+            size: self.size,
+            rot,
+            shape: self.shape,
+        })
+    }
+
+    /// Make a copy of the dot that's moved perpendicular to the line, so
+    /// that its given alignment point is on the line.
+    pub fn translate_to_line(&self, line: &Line3, align: DotAlign) -> Self {
+        let pos = self.pos(align);
+        self.translate(line.closest_point(pos) - pos)
+    }
+
+    /// Make a copy of the dot that's scaled by the factor, away from (or
+    /// towards) the given point. Its size is scaled too, and its rotation
+    /// stays the same. The factor must be positive.
+    pub fn scale_about(
+        &self,
+        point: P3,
+        factor: f32,
+    ) -> Result<Self, ScadDotsError> {
+        check_scale_factor(factor)?;
+        Ok(Self {
+            shape: self.shape,
+            p000: point + (self.p000 - point) * factor,
+            size: self.size * factor,
+            rot: self.rot,
+        })
+    }
+
+    /// Apply the isometry to the dot: rotate it around the origin, and then
+    /// translate it.
+    pub fn transform(&self, isometry: Iso3) -> Self {
+        self.rotate(isometry.rotation)
+            .translate(isometry.translation.vector)
+    }
+
     pub fn rotate_to(&self, new_rot: R3) -> Self {
         // TODO check
         let rot_difference = self.rot.rotation_to(&new_rot);
@@ -279,9 +592,25 @@ impl Dot {
         other: Self,
         order: [Axis; 3],
     ) -> Result<[Self; 4], ScadDotsError> {
-        Ok(Snake::new(*self, other, order)?.dots)
+        let dots = Snake::new(*self, other, order)?.dots;
+        Ok([dots[0], dots[1], dots[2], dots[3]])
+    }
+
+    /// Make `count` copies of the dot. Each copy is transformed by the
+    /// isometry that `f` returns for its index. This can make any kind of
+    /// array: linear, radial, grid, spiral, staggered, etc.
+    pub fn array_with<F>(&self, count: usize, f: F) -> Vec<Self>
+    where
+        F: Fn(usize) -> Iso3,
+    {
+        (0..count).map(|i| self.transform(f(i))).collect()
     }
 
+    /// Return `count` copies of the dot, spaced evenly around a circle of the
+    /// given radius centered on this dot. The circle goes around `axis`, or
+    /// the dot's z axis if it's None. The first copy is `radius` away along
+    /// the direction that `radial_offset()` uses for an angle of 0. See
+    /// `polar_array()` and `explode_radially_about()` for the rotations.
     pub fn explode_radially(
         &self,
         radius: f32,
@@ -290,29 +619,13 @@ impl Dot {
         adjust_dot_rotations: bool,
     ) -> Result<Vec<Self>, ScadDotsError> {
         let axis = axis.unwrap_or_else(|| rotate(self.rot, Axis::Z));
-
-        let mut dots = Vec::new();
-        for i in 0..count {
-            let radians = (i as f32) / (count as f32) * 2. * PI;
-            let offset = radial_offset(radians, radius, axis)?;
-
-            let rot = if adjust_dot_rotations {
-                axis_radians(axis, radians) * self.rot
-            } else {
-                self.rot
-            };
-
-            let new = Self::new(DotSpec {
-                pos: self.pos(DotAlign::centroid()) + offset,
-                align: DotAlign::centroid(),
-                size: self.size,
-                rot,
-                shape: self.shape,
-            });
-
-            dots.push(new)
-        }
-        Ok(dots)
+        let center = self.pos(DotAlign::centroid());
+        let first = self.translate(radial_offset(0., radius, axis)?);
+        Ok(if adjust_dot_rotations {
+            polar_array(&first, count, center, axis)
+        } else {
+            first.explode_radially_about(center, axis, count, false)
+        })
     }
 }
 
@@ -401,23 +714,75 @@ impl From<C3> for DotAlign {
     }
 }
 
+impl Anchor for Dot {
+    type Align = DotAlign;
+
+    fn anchor_at(&self, align: DotAlign) -> AnchorPoint {
+        AnchorPoint {
+            pos: self.pos(align),
+            rot: self.rot,
+        }
+    }
+
+    fn align_from_name(name: AnchorName) -> Result<DotAlign, ScadDotsError> {
+        name.require_outside()?;
+        let (a, b) = name.corners();
+        Ok(if name.is_corner() {
+            DotAlign::Corner(a)
+        } else {
+            DotAlign::Midpoint(a, b)
+        })
+    }
+}
+
 impl MapDots for Dot {
     fn map(&self, f: &Fn(&Dot) -> Dot) -> Dot {
         f(self)
     }
 }
 
-impl<T> MapDots for [T; 4]
+impl<T> MapDots for Vec<T>
 where
     T: MapDots,
 {
     fn map(&self, f: &Fn(&Dot) -> Dot) -> Self {
-        [
-            self[0].map(f),
-            self[1].map(f),
-            self[2].map(f),
-            self[3].map(f),
-        ]
+        self.iter().map(|thing| thing.map(f)).collect()
+    }
+
+    fn map_mirror(&self, normal: V3) -> Self {
+        self.iter().map(|thing| thing.map_mirror(normal)).collect()
+    }
+}
+
+impl<T> MapDots for Option<T>
+where
+    T: MapDots,
+{
+    fn map(&self, f: &Fn(&Dot) -> Dot) -> Self {
+        match self {
+            Some(thing) => Some(thing.map(f)),
+            None => None,
+        }
+    }
+
+    fn map_mirror(&self, normal: V3) -> Self {
+        match self {
+            Some(thing) => Some(thing.map_mirror(normal)),
+            None => None,
+        }
+    }
+}
+
+impl<T> MapDots for Box<T>
+where
+    T: MapDots,
+{
+    fn map(&self, f: &Fn(&Dot) -> Dot) -> Self {
+        Box::new((**self).map(f))
+    }
+
+    fn map_mirror(&self, normal: V3) -> Self {
+        Box::new((**self).map_mirror(normal))
     }
 }
 
@@ -475,31 +840,73 @@ where
     }
 }
 
-impl<T> MinMaxCoord for [T; 3]
+impl<T> MinMaxCoord for Option<T>
 where
     T: MinMaxCoord,
 {
     fn all_coords(&self, axis: Axis) -> Vec<f32> {
-        let mut v = self[0].all_coords(axis);
-        v.extend(self[1].all_coords(axis));
-        v.extend(self[2].all_coords(axis));
-        v
+        match self {
+            Some(thing) => thing.all_coords(axis),
+            None => Vec::new(),
+        }
     }
 }
 
-impl<T> MinMaxCoord for [T; 4]
+impl<T> MinMaxCoord for Box<T>
 where
     T: MinMaxCoord,
 {
     fn all_coords(&self, axis: Axis) -> Vec<f32> {
-        let mut v = self[0].all_coords(axis);
-        v.extend(self[1].all_coords(axis));
-        v.extend(self[2].all_coords(axis));
-        v.extend(self[3].all_coords(axis));
-        v
+        (**self).all_coords(axis)
     }
 }
 
+/// Implement MapDots and MinMaxCoord for fixed-size arrays of the given
+/// lengths. Each length needs its full list of indices, because arrays can't
+/// be built from an iterator.
+macro_rules! impl_for_arrays {
+    ($($len:expr => [$($index:expr),*]),* $(,)*) => {
+        $(
+            impl<T> MapDots for [T; $len]
+            where
+                T: MapDots,
+            {
+                fn map(&self, f: &Fn(&Dot) -> Dot) -> Self {
+                    [$(self[$index].map(f)),*]
+                }
+
+                fn map_mirror(&self, normal: V3) -> Self {
+                    [$(self[$index].map_mirror(normal)),*]
+                }
+            }
+
+            impl<T> MinMaxCoord for [T; $len]
+            where
+                T: MinMaxCoord,
+            {
+                fn all_coords(&self, axis: Axis) -> Vec<f32> {
+                    let mut v = Vec::new();
+                    for thing in self.iter() {
+                        v.extend(thing.all_coords(axis))
+                    }
+                    v
+                }
+            }
+        )*
+    };
+}
+
+impl_for_arrays!(
+    1 => [0],
+    2 => [0, 1],
+    3 => [0, 1, 2],
+    4 => [0, 1, 2, 3],
+    5 => [0, 1, 2, 3, 4],
+    6 => [0, 1, 2, 3, 4, 5],
+    7 => [0, 1, 2, 3, 4, 5, 6],
+    8 => [0, 1, 2, 3, 4, 5, 6, 7],
+);
+
 pub fn mark(pos: P3, size: f32) -> Tree {
     // Put a little sphere at the given position, for debugging
     // TODO make it red
@@ -1,14 +1,53 @@
-use core::utils::{P2, P3};
+use core::utils::{cos_deg, sin_deg, Axis, P2, P3, V2, V3};
 
-use core::{Dot, DotAlign, Tree, TreeObject};
-use errors::ScadDotsError;
+use core::{
+    convex_hull_2d, Dot, DotAlign, MinMaxCoord, OffsetJoin, Polygon2D, Tree,
+    TreeObject,
+};
+use errors::{ResultExt, ScadDotsError};
 
 /// Extrude the given perimeter into the z dimension. The bottom surface of the extrusion will be on the z=`bottom_z` plane, and have the given z `thickness`.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Extrusion {
-    pub perimeter: Vec<P2>,
+    pub perimeter: Polygon2D,
+    /// Polygons inside the perimeter to cut out of the extrusion. They must
+    /// not overlap each other or the perimeter.
+    pub holes: Vec<Polygon2D>,
     pub bottom_z: f32,
     pub thickness: f32,
+    pub params: ExtrudeParams,
+}
+
+/// Extra options for how a perimeter is extruded, matching OpenSCAD's
+/// `linear_extrude()`. The default is a plain straight extrusion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ExtrudeParams {
+    /// How many degrees the top is twisted clockwise (looking down), relative
+    /// to the bottom. The twist is around the z axis, not the perimeter's
+    /// center.
+    pub twist: f32,
+    /// How much larger the top is than the bottom. It's scaled about the z
+    /// axis, not the perimeter's center.
+    pub scale: f32,
+    /// How many layers a twisted extrusion is split into. More slices make
+    /// smoother twists.
+    pub slices: u32,
+    /// If true, the extrusion is centered on the z=`bottom_z` plane, instead
+    /// of having its bottom surface there.
+    pub center: bool,
+}
+
+impl Default for ExtrudeParams {
+    fn default() -> Self {
+        Self {
+            twist: 0.,
+            scale: 1.,
+            slices: 1,
+            center: false,
+        }
+    }
 }
 
 impl Extrusion {
@@ -28,13 +67,140 @@ impl Extrusion {
             .collect();
 
         Ok(Self {
-            perimeter: centers,
+            perimeter: Polygon2D::new(centers)
+                .context("Extrusion perimeter is invalid")?,
+            holes: Vec::new(),
+            bottom_z,
+            thickness,
+            params: ExtrudeParams::default(),
+        })
+    }
+
+    /// Create an extrusion whose perimeter is the convex hull of the dots'
+    /// centers, projected onto the xy plane. See `convex_hull_2d()`.
+    pub fn convex_hull_of_dots(
+        dots: &[Dot],
+        thickness: f32,
+        bottom_z: f32,
+    ) -> Result<Self, ScadDotsError> {
+        let centers: Vec<_> = dots
+            .iter()
+            .map(|dot| {
+                let center = dot.pos(DotAlign::centroid());
+                P2::new(center.x, center.y)
+            })
+            .collect();
+        let hull = convex_hull_2d(&centers);
+        if hull.len() < 3 {
+            return Err(ScadDotsError::Dimension.context(
+                "can't extrude the convex hull of dots that are all in a line",
+            ));
+        }
+        let polygon = Polygon2D::new(hull)?;
+        Self::from_polygon(polygon, thickness, bottom_z)
+    }
+
+    /// Create a plain extrusion of the polygon, with no holes.
+    pub fn from_polygon(
+        polygon: Polygon2D,
+        thickness: f32,
+        bottom_z: f32,
+    ) -> Result<Self, ScadDotsError> {
+        if thickness < 0. {
+            return Err(ScadDotsError::Dimension
+                .context("Extrusion thickness can't be negative"));
+        }
+        Ok(Self {
+            perimeter: polygon,
+            holes: Vec::new(),
             bottom_z,
             thickness,
+            params: ExtrudeParams::default(),
         })
     }
 }
 
+impl Extrusion {
+    /// Make a copy that's moved by the given offset. The z component moves
+    /// the bottom surface up or down. If the extrusion is twisted or scaled,
+    /// that's still done around the z axis, so the shape may change; use
+    /// `Tree::translate()` to move it rigidly instead.
+    pub fn translate(&self, offset: V3) -> Self {
+        let offset_2d = V2::new(offset.x, offset.y);
+        let shift = |polygon: &Polygon2D| polygon.map_points(|p| p + offset_2d);
+        Self {
+            perimeter: shift(&self.perimeter),
+            holes: self.holes.iter().map(shift).collect(),
+            bottom_z: self.bottom_z + offset.z,
+            thickness: self.thickness,
+            params: self.params,
+        }
+    }
+
+    /// Make a copy that's rotated by the given number of degrees around the
+    /// z axis.
+    pub fn rotate_z(&self, degrees: f32) -> Self {
+        let (sin, cos) = (sin_deg(degrees), cos_deg(degrees));
+        let rotate = |polygon: &Polygon2D| {
+            polygon.map_points(|p| {
+                P2::new(cos * p.x - sin * p.y, sin * p.x + cos * p.y)
+            })
+        };
+        Self {
+            perimeter: rotate(&self.perimeter),
+            holes: self.holes.iter().map(rotate).collect(),
+            bottom_z: self.bottom_z,
+            thickness: self.thickness,
+            params: self.params,
+        }
+    }
+
+    /// Return the z coordinates of the bottom and top surfaces.
+    pub fn z_range(&self) -> (f32, f32) {
+        let bottom = if self.params.center {
+            self.bottom_z - self.thickness / 2.
+        } else {
+            self.bottom_z
+        };
+        (bottom, bottom + self.thickness)
+    }
+
+    /// Make a copy with the perimeter moved outwards by `delta`, or inwards
+    /// if it's negative. The holes shrink by the same amount, so the walls
+    /// around them grow along with the outside. The polygons can be in
+    /// either winding order. See `Polygon2D::offset()`.
+    pub fn offset(
+        &self,
+        delta: f32,
+        join: OffsetJoin,
+    ) -> Result<Self, ScadDotsError> {
+        let mut holes = Vec::new();
+        for hole in &self.holes {
+            holes.push(
+                hole.offset(-delta, join)
+                    .context("failed to offset extrusion hole")?,
+            );
+        }
+        Ok(Self {
+            perimeter: self
+                .perimeter
+                .offset(delta, join)
+                .context("failed to offset extrusion perimeter")?,
+            holes,
+            bottom_z: self.bottom_z,
+            thickness: self.thickness,
+            params: self.params,
+        })
+    }
+}
+
+impl MinMaxCoord for Extrusion {
+    fn all_coords(&self, axis: Axis) -> Vec<f32> {
+        let bounds = self.bounds();
+        vec![axis.of_p3(bounds.min), axis.of_p3(bounds.max)]
+    }
+}
+
 impl From<Extrusion> for Tree {
     fn from(extrusion: Extrusion) -> Tree {
         Tree::Object(TreeObject::Extrusion(extrusion))
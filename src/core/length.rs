@@ -0,0 +1,137 @@
+//! Lengths with explicit units. Everything else in the crate uses bare `f32`
+//! millimeters, so convert a length with `.into()` when filling in a spec,
+//! like `size: Inch(0.25).into()`. Lengths can be added and subtracted even
+//! when their units differ, and the result has the left operand's unit.
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+pub const MM_PER_INCH: f32 = 25.4;
+
+/// A length in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Mm(pub f32);
+
+/// A length in inches.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Inch(pub f32);
+
+impl From<Inch> for Mm {
+    fn from(length: Inch) -> Mm {
+        Mm(length.0 * MM_PER_INCH)
+    }
+}
+
+impl From<Mm> for Inch {
+    fn from(length: Mm) -> Inch {
+        Inch(length.0 / MM_PER_INCH)
+    }
+}
+
+impl From<Mm> for f32 {
+    fn from(length: Mm) -> f32 {
+        length.0
+    }
+}
+
+impl From<Inch> for f32 {
+    fn from(length: Inch) -> f32 {
+        Mm::from(length).0
+    }
+}
+
+impl fmt::Display for Mm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}mm", self.0)
+    }
+}
+
+impl fmt::Display for Inch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}in", self.0)
+    }
+}
+
+macro_rules! impl_length_ops {
+    ($($unit:ident),*) => {
+        $(
+            impl<T> Add<T> for $unit
+            where
+                T: Into<$unit>,
+            {
+                type Output = $unit;
+                fn add(self, other: T) -> $unit {
+                    $unit(self.0 + other.into().0)
+                }
+            }
+
+            impl<T> Sub<T> for $unit
+            where
+                T: Into<$unit>,
+            {
+                type Output = $unit;
+                fn sub(self, other: T) -> $unit {
+                    $unit(self.0 - other.into().0)
+                }
+            }
+
+            impl<T> AddAssign<T> for $unit
+            where
+                T: Into<$unit>,
+            {
+                fn add_assign(&mut self, other: T) {
+                    self.0 += other.into().0;
+                }
+            }
+
+            impl<T> SubAssign<T> for $unit
+            where
+                T: Into<$unit>,
+            {
+                fn sub_assign(&mut self, other: T) {
+                    self.0 -= other.into().0;
+                }
+            }
+
+            impl Mul<f32> for $unit {
+                type Output = $unit;
+                fn mul(self, factor: f32) -> $unit {
+                    $unit(self.0 * factor)
+                }
+            }
+
+            impl Mul<$unit> for f32 {
+                type Output = $unit;
+                fn mul(self, length: $unit) -> $unit {
+                    $unit(self * length.0)
+                }
+            }
+
+            impl Div<f32> for $unit {
+                type Output = $unit;
+                fn div(self, divisor: f32) -> $unit {
+                    $unit(self.0 / divisor)
+                }
+            }
+
+            /// The ratio of two lengths, which has no unit.
+            impl Div<$unit> for $unit {
+                type Output = f32;
+                fn div(self, other: $unit) -> f32 {
+                    self.0 / other.0
+                }
+            }
+
+            impl Neg for $unit {
+                type Output = $unit;
+                fn neg(self) -> $unit {
+                    $unit(-self.0)
+                }
+            }
+        )*
+    };
+}
+
+impl_length_ops!(Mm, Inch);
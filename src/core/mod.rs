@@ -1,10 +1,21 @@
+pub use self::anchor::*;
+pub use self::bounds::*;
 pub use self::chain::*;
 pub use self::cylinder::*;
 pub use self::dot::*;
 pub use self::extrusion::*;
+pub use self::length::*;
+pub use self::path::*;
+pub use self::polygon::*;
+pub use self::split::*;
+pub use self::text::*;
+pub use self::torus::*;
 pub use self::tree::*;
 pub use self::utils::*;
+pub use self::validate::*;
 
+mod anchor;
+mod bounds;
 mod chain;
 pub mod utils;
 #[macro_use]
@@ -12,3 +23,12 @@ mod tree;
 mod cylinder;
 mod dot;
 mod extrusion;
+mod length;
+mod path;
+mod polygon;
+#[cfg(feature = "serialize")]
+pub mod serde_utils;
+mod split;
+mod text;
+mod torus;
+mod validate;
@@ -0,0 +1,128 @@
+use std::f32::consts::PI;
+
+use core::utils::{axis_radians, P3, V3};
+use core::{chain, Dot, DotAlign, DotSpec, Tree};
+use errors::ScadDotsError;
+
+/// A smooth path between two dots, sampled into a sequence of dots. The
+/// dots' sizes and rotations are interpolated between the start and end dot,
+/// and they all have the start dot's shape.
+#[derive(Debug, Clone)]
+pub struct Path {
+    pub dots: Vec<Dot>,
+}
+
+/// The shape of the curve that the centers of a Path's dots follow.
+#[derive(Debug, Clone, Copy)]
+pub enum PathShape {
+    /// A straight line.
+    Line,
+    /// A circular arc around the given axis line through `center`, turning
+    /// counterclockwise (by the right hand rule) from the start to the end.
+    /// If the start and end are different distances from the axis, the
+    /// radius changes smoothly along the arc.
+    Arc { center: P3, axis: V3 },
+    /// A cubic Bezier curve, with the 2 given control points.
+    Bezier { control_a: P3, control_b: P3 },
+}
+
+impl Path {
+    /// Create a path made of `segments` straight segments, so it has
+    /// `segments + 1` dots including the start and end.
+    pub fn new(
+        start: Dot,
+        end: Dot,
+        shape: PathShape,
+        segments: usize,
+    ) -> Result<Self, ScadDotsError> {
+        if segments == 0 {
+            return Err(
+                ScadDotsError::Args.context("Path needs at least 1 segment")
+            );
+        }
+        let start_pos = start.pos(DotAlign::centroid());
+        let end_pos = end.pos(DotAlign::centroid());
+        if let PathShape::Arc { axis, .. } = shape {
+            if axis.norm() == 0. {
+                return Err(ScadDotsError::Args
+                    .context("Path arc axis can't be the zero vector"));
+            }
+        }
+
+        let mut dots = Vec::new();
+        for i in 0..(segments + 1) {
+            let t = i as f32 / segments as f32;
+            let pos = shape.point(start_pos, end_pos, t);
+            let rot =
+                start.rot.try_slerp(&end.rot, t, 1e-6).unwrap_or(start.rot);
+            dots.push(Dot::try_new(DotSpec {
+                pos,
+                align: DotAlign::centroid(),
+                size: start.size + t * (end.size - start.size),
+                rot,
+                shape: start.shape,
+            })?);
+        }
+        Ok(Self { dots })
+    }
+
+    /// Chain hulls between each pair of adjacent dots.
+    pub fn link(&self) -> Result<Tree, ScadDotsError> {
+        chain(&self.dots)
+    }
+}
+
+impl PathShape {
+    /// Return the point at fraction `t` of the way along the curve.
+    fn point(self, start: P3, end: P3, t: f32) -> P3 {
+        match self {
+            PathShape::Line => start + t * (end - start),
+            PathShape::Arc { center, axis } => {
+                let axis = axis.normalize();
+                // Split the offsets from the center into components along the
+                // axis and in the plane of the arc.
+                let split = |pos: P3| {
+                    let offset = pos - center;
+                    let height = offset.dot(&axis);
+                    (height, offset - height * axis)
+                };
+                let (start_height, start_flat) = split(start);
+                let (end_height, end_flat) = split(end);
+
+                let mut angle = signed_angle(start_flat, end_flat, axis);
+                if angle < 0. {
+                    angle += 2. * PI;
+                }
+                let start_radius = start_flat.norm();
+                let radius =
+                    start_radius + t * (end_flat.norm() - start_radius);
+                let height = start_height + t * (end_height - start_height);
+                let direction = if start_radius == 0. {
+                    V3::zeros()
+                } else {
+                    axis_radians(axis, t * angle) * start_flat / start_radius
+                };
+                center + height * axis + radius * direction
+            }
+            PathShape::Bezier {
+                control_a,
+                control_b,
+            } => {
+                let s = 1. - t;
+                let weighted =
+                    |pos: P3, weight: f32| weight * (pos - P3::origin());
+                P3::origin()
+                    + weighted(start, s * s * s)
+                    + weighted(control_a, 3. * s * s * t)
+                    + weighted(control_b, 3. * s * t * t)
+                    + weighted(end, t * t * t)
+            }
+        }
+    }
+}
+
+/// Return the angle in radians from `a` to `b`, measured counterclockwise
+/// around `axis`. Both vectors should be perpendicular to the axis.
+fn signed_angle(a: V3, b: V3, axis: V3) -> f32 {
+    a.cross(&b).dot(&axis).atan2(a.dot(&b))
+}
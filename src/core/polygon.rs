@@ -0,0 +1,382 @@
+use std::f32::consts::PI;
+
+use core::utils::{P2, V2};
+use errors::{ResultExt, ScadDotsError};
+
+/// A simple polygon on the xy plane: a closed loop of points whose edges
+/// don't cross or touch each other, except that neighboring edges share a
+/// point. The last point connects back to the first, so it shouldn't be
+/// repeated. The points can go in either winding order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon2D {
+    points: Vec<P2>,
+}
+
+/// Specify how the corners of an offset perimeter are joined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OffsetJoin {
+    /// Extend the offset edges until they meet in a sharp corner.
+    Miter,
+    /// Round the corners that the offset moves away from, using the given
+    /// number of segments per full circle. Other corners are mitered.
+    Round { segments: usize },
+}
+
+impl Polygon2D {
+    /// Create a polygon, checking that it has at least 3 points, that they're
+    /// all finite, and that it doesn't intersect itself.
+    pub fn new(points: Vec<P2>) -> Result<Self, ScadDotsError> {
+        let n = points.len();
+        if n < 3 {
+            return Err(
+                ScadDotsError::Args.context("polygon needs at least 3 points")
+            );
+        }
+        if points.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+            return Err(
+                ScadDotsError::Args.context("polygon points must be finite")
+            );
+        }
+        for i in 0..n {
+            let (a, b, c) =
+                (points[i], points[(i + 1) % n], points[(i + 2) % n]);
+            if a == b {
+                return Err(ScadDotsError::Dimension
+                    .context("polygon can't have repeated points"));
+            }
+            // Neighboring edges can't fold back over each other.
+            if cross(b - a, c - b) == 0. && (b - a).dot(&(c - b)) < 0. {
+                return Err(ScadDotsError::Dimension
+                    .context("polygon can't fold back on itself"));
+            }
+        }
+        for i in 0..n {
+            // Skip the edges that share a point with edge i.
+            for j in (i + 2)..n {
+                if i == 0 && j == n - 1 {
+                    continue;
+                }
+                let edge_i = (points[i], points[(i + 1) % n]);
+                let edge_j = (points[j], points[(j + 1) % n]);
+                if segments_touch(edge_i, edge_j) {
+                    return Err(ScadDotsError::Dimension.with_context(|| {
+                        format!(
+                            "polygon intersects itself: edges {} and {} touch",
+                            i, j
+                        )
+                    }));
+                }
+            }
+        }
+        if signed_area(&points) == 0. {
+            return Err(
+                ScadDotsError::Dimension.context("polygon must have some area")
+            );
+        }
+        Ok(Self { points })
+    }
+
+    /// Return the points in order.
+    pub fn points(&self) -> &[P2] {
+        &self.points
+    }
+
+    pub fn into_points(self) -> Vec<P2> {
+        self.points
+    }
+
+    /// Return the enclosed area. It's positive if the points go
+    /// counterclockwise and negative if they go clockwise.
+    pub fn signed_area(&self) -> f32 {
+        signed_area(&self.points)
+    }
+
+    /// Return the enclosed area.
+    pub fn area(&self) -> f32 {
+        self.signed_area().abs()
+    }
+
+    pub fn is_counterclockwise(&self) -> bool {
+        self.signed_area() > 0.
+    }
+
+    /// Return a copy with the points in the opposite winding order.
+    pub fn reversed(&self) -> Self {
+        let mut points = self.points.clone();
+        points.reverse();
+        Self { points }
+    }
+
+    /// Return the center of mass of the enclosed area.
+    pub fn centroid(&self) -> P2 {
+        let n = self.points.len();
+        let mut sum = V2::zeros();
+        for i in 0..n {
+            let (a, b) = (self.points[i], self.points[(i + 1) % n]);
+            let weight = a.x * b.y - b.x * a.y;
+            sum += (a.coords + b.coords) * weight;
+        }
+        P2::from_coordinates(sum / (6. * self.signed_area()))
+    }
+
+    /// Return true if the point is inside the polygon. Points exactly on the
+    /// boundary may be counted as inside or outside.
+    pub fn contains(&self, point: P2) -> bool {
+        let n = self.points.len();
+        let mut inside = false;
+        for i in 0..n {
+            let (a, b) = (self.points[i], self.points[(i + 1) % n]);
+            // Count the edges that cross a ray going in the +x direction.
+            if (a.y > point.y) != (b.y > point.y) {
+                let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if point.x < x {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    /// Split the polygon into triangles by ear clipping. The triangles'
+    /// points go counterclockwise, and their total area equals the polygon's.
+    /// It's an error if rounding errors hide every ear, which can happen with
+    /// nearly degenerate polygons.
+    pub fn triangulate(&self) -> Result<Vec<[P2; 3]>, ScadDotsError> {
+        let mut remaining = self.points.clone();
+        if !self.is_counterclockwise() {
+            remaining.reverse();
+        }
+        let mut triangles = Vec::new();
+        while remaining.len() > 3 {
+            let n = remaining.len();
+            let ear =
+                (0..n).find(|&i| is_ear(&remaining, i)).ok_or_else(|| {
+                    ScadDotsError::Dimension.context(
+                        "can't triangulate a nearly degenerate polygon",
+                    )
+                })?;
+            let (a, b, c) = (
+                remaining[(ear + n - 1) % n],
+                remaining[ear],
+                remaining[(ear + 1) % n],
+            );
+            // A corner with no area doesn't need a triangle.
+            if cross(b - a, c - b) != 0. {
+                triangles.push([a, b, c]);
+            }
+            remaining.remove(ear);
+        }
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+        Ok(triangles)
+    }
+
+    /// Return a copy moved outwards by `delta`, or inwards if it's negative.
+    /// It's an error if the result intersects itself or turns inside out,
+    /// like when the inset is larger than the polygon.
+    pub fn offset(
+        &self,
+        delta: f32,
+        join: OffsetJoin,
+    ) -> Result<Self, ScadDotsError> {
+        if !delta.is_finite() {
+            return Err(
+                ScadDotsError::Args.context("polygon offset must be finite")
+            );
+        }
+        let points = offset_contour(&self.points, delta, join)?;
+        let offset = Self::new(points).context("offset polygon is invalid")?;
+        if offset.is_counterclockwise() != self.is_counterclockwise() {
+            return Err(ScadDotsError::Dimension
+                .context("offset polygon turned inside out"));
+        }
+        Ok(offset)
+    }
+}
+
+impl Polygon2D {
+    /// Return a copy with each point moved by `f`. The result isn't checked,
+    /// so it's only for transformations that keep a polygon simple, like
+    /// rotations and translations.
+    pub(crate) fn map_points<F>(&self, f: F) -> Self
+    where
+        F: Fn(P2) -> P2,
+    {
+        Self {
+            points: self.points.iter().map(|p| f(*p)).collect(),
+        }
+    }
+}
+
+impl From<Polygon2D> for Vec<P2> {
+    fn from(polygon: Polygon2D) -> Vec<P2> {
+        polygon.points
+    }
+}
+
+/// Return the convex hull of the points, going counterclockwise and starting
+/// from the point with the lowest x (and then lowest y) coordinate. Points
+/// along the hull's edges are left out. If all the points are on a single
+/// line, only its 2 ends are returned.
+pub fn convex_hull_2d(points: &[P2]) -> Vec<P2> {
+    let mut sorted: Vec<P2> = points.to_vec();
+    sorted.sort_by(|a, b| {
+        (a.x, a.y)
+            .partial_cmp(&(b.x, b.y))
+            .expect("can't find convex hull of NaN points")
+    });
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    // Andrew's monotone chain: build the lower hull left to right and the
+    // upper hull right to left, dropping any point that doesn't turn left.
+    let mut hull: Vec<P2> = Vec::new();
+    for pass in 0..2 {
+        let start = hull.len();
+        for &p in &sorted {
+            while hull.len() >= start + 2 {
+                let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+                if cross(b - a, p - b) > 0. {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last point is the first point of the other half.
+        hull.pop();
+        if pass == 0 {
+            sorted.reverse();
+        }
+    }
+    hull
+}
+
+/// Return the contour moved outwards by `delta`. See `Polygon2D::offset()`.
+/// The result isn't checked for self-intersections.
+pub(crate) fn offset_contour(
+    contour: &[P2],
+    delta: f32,
+    join: OffsetJoin,
+) -> Result<Vec<P2>, ScadDotsError> {
+    let n = contour.len();
+    if n < 3 {
+        return Err(ScadDotsError::Args
+            .context("can't offset a contour with fewer than 3 points"));
+    }
+    let area = signed_area(contour);
+    if area == 0. {
+        return Err(ScadDotsError::Dimension
+            .context("can't offset a contour with no area"));
+    }
+    // The outward normal is on the right of each edge for counterclockwise
+    // contours, and on the left for clockwise ones.
+    let winding = area.signum();
+    let mut normals = Vec::with_capacity(n);
+    for i in 0..n {
+        let edge = contour[(i + 1) % n] - contour[i];
+        if edge.norm() == 0. {
+            return Err(ScadDotsError::Dimension
+                .context("can't offset a contour with repeated points"));
+        }
+        normals.push(winding * V2::new(edge.y, -edge.x).normalize());
+    }
+
+    let mut offset = Vec::new();
+    for i in 0..n {
+        let point = contour[i];
+        let before = normals[(i + n - 1) % n];
+        let after = normals[i];
+        let turn = before.perp(&after).atan2(before.dot(&after));
+        // Only round the corners that the offset moves away from.
+        let convex = turn * winding * delta > 0.;
+        match join {
+            OffsetJoin::Round { segments } if convex => {
+                let steps = ((turn.abs() / (2. * PI)) * segments as f32).ceil();
+                let steps = steps.max(1.) as usize;
+                for step in 0..(steps + 1) {
+                    let angle = turn * step as f32 / steps as f32;
+                    let (sin, cos) = angle.sin_cos();
+                    let normal = V2::new(
+                        cos * before.x - sin * before.y,
+                        sin * before.x + cos * before.y,
+                    );
+                    offset.push(point + delta * normal);
+                }
+            }
+            _ => {
+                let cos = before.dot(&after);
+                if cos <= -1. + 1e-6 {
+                    return Err(ScadDotsError::Dimension.context(
+                        "can't offset a contour that folds back on itself",
+                    ));
+                }
+                let miter = (before + after) / (1. + cos);
+                offset.push(point + delta * miter);
+            }
+        }
+    }
+    Ok(offset)
+}
+
+/// Return the area enclosed by the contour. It's positive if the contour goes
+/// counterclockwise and negative if it goes clockwise.
+pub(crate) fn signed_area(contour: &[P2]) -> f32 {
+    let n = contour.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (contour[i], contour[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f32>()
+        / 2.
+}
+
+/// Return the z component of the cross product of 2 vectors on the xy plane.
+fn cross(a: V2, b: V2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Return true if the 2 segments cross or touch, including if they overlap
+/// along the same line.
+fn segments_touch(s: (P2, P2), t: (P2, P2)) -> bool {
+    let side = |a: P2, b: P2, p: P2| cross(b - a, p - a);
+    let within = |a: P2, b: P2, p: P2| {
+        p.x >= a.x.min(b.x)
+            && p.x <= a.x.max(b.x)
+            && p.y >= a.y.min(b.y)
+            && p.y <= a.y.max(b.y)
+    };
+    let (d1, d2) = (side(t.0, t.1, s.0), side(t.0, t.1, s.1));
+    let (d3, d4) = (side(s.0, s.1, t.0), side(s.0, s.1, t.1));
+    if d1 * d2 < 0. && d3 * d4 < 0. {
+        return true;
+    }
+    (d1 == 0. && within(t.0, t.1, s.0))
+        || (d2 == 0. && within(t.0, t.1, s.1))
+        || (d3 == 0. && within(s.0, s.1, t.0))
+        || (d4 == 0. && within(s.0, s.1, t.1))
+}
+
+/// Return true if the corner at index `i` of the counterclockwise contour
+/// can be clipped off: it's convex, and no other point is inside the
+/// triangle it makes with its neighbors.
+fn is_ear(contour: &[P2], i: usize) -> bool {
+    let n = contour.len();
+    let (prev, next) = ((i + n - 1) % n, (i + 1) % n);
+    let (a, b, c) = (contour[prev], contour[i], contour[next]);
+    if cross(b - a, c - b) < 0. {
+        return false;
+    }
+    contour.iter().enumerate().all(|(j, &p)| {
+        j == prev
+            || j == i
+            || j == next
+            || p == a
+            || p == b
+            || p == c
+            || !(cross(b - a, p - a) >= 0.
+                && cross(c - b, p - b) >= 0.
+                && cross(a - c, p - c) >= 0.)
+    })
+}
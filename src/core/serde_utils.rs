@@ -0,0 +1,104 @@
+//! Serde support for types that need more than a derive.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use core::utils::{Fraction, P2};
+use core::Polygon2D;
+
+/// Serialize rotations as quaternions with named components, like
+/// `{"w": 1, "i": 0, "j": 0, "k": 0}`, instead of nalgebra's unlabeled
+/// `[i, j, k, w]` array. Deserialized rotations are normalized, so they can
+/// be written by hand. Use it with
+/// `#[serde(with = "::core::serde_utils::rot")]`.
+pub mod rot {
+    use nalgebra::{Quaternion, Unit};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use core::utils::R3;
+
+    #[derive(Serialize, Deserialize)]
+    struct NamedQuaternion {
+        w: f32,
+        i: f32,
+        j: f32,
+        k: f32,
+    }
+
+    pub fn serialize<S>(rot: &R3, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let coords = rot.coords;
+        NamedQuaternion {
+            w: coords[3],
+            i: coords[0],
+            j: coords[1],
+            k: coords[2],
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<R3, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let q = NamedQuaternion::deserialize(deserializer)?;
+        let quaternion = Quaternion::new(q.w, q.i, q.j, q.k);
+        let norm = quaternion.norm();
+        if !norm.is_normal() {
+            return Err(D::Error::custom(
+                "rotation quaternion must not be zero",
+            ));
+        }
+        // Leave unit quaternions exactly as they were serialized.
+        if (norm - 1.).abs() < 1e-6 {
+            Ok(Unit::new_unchecked(quaternion))
+        } else {
+            Ok(Unit::new_normalize(quaternion))
+        }
+    }
+}
+
+/// A fraction is serialized as a plain number, and checked when it's
+/// deserialized.
+impl Serialize for Fraction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.unwrap().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Fraction {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = f32::deserialize(deserializer)?;
+        Fraction::new(value).map_err(D::Error::custom)
+    }
+}
+
+/// A polygon is serialized as its list of points, and checked when it's
+/// deserialized.
+impl Serialize for Polygon2D {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.points().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Polygon2D {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let points = Vec::<P2>::deserialize(deserializer)?;
+        Polygon2D::new(points).map_err(D::Error::custom)
+    }
+}
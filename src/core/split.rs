@@ -0,0 +1,122 @@
+use core::utils::{
+    check_finite, rotation_from_axes, Axis, Corner1 as C1, Corner3 as C3,
+    CubeFace, Plane, CUT_OVERLAP, P3, R3,
+};
+use core::{
+    Cylinder, CylinderAlign, CylinderSpec, Dot, DotAlign, DotShape, DotSpec,
+    Tree,
+};
+use errors::{ResultExt, ScadDotsError};
+
+/// Holes for alignment pins, cut into both halves of a split tree so the
+/// halves line up when they're glued back together.
+#[derive(Debug, Clone)]
+pub struct PinHoles {
+    /// Where the pins go. Points that aren't on the cut plane are projected
+    /// onto it.
+    pub positions: Vec<P3>,
+    pub diameter: f32,
+    /// How deep each hole goes into each half.
+    pub depth: f32,
+}
+
+impl Tree {
+    /// Split the tree in two along the plane, like for printing a model
+    /// that's too big for the printer. Return the part on the side that the
+    /// plane's normal points to, and then the part on the other side. Each
+    /// part is the tree minus a cube that covers the other side of its
+    /// bounding box. It's an error if the tree has no bounds, or if the plane
+    /// doesn't cross them.
+    pub fn split(&self, plane: Plane) -> Result<(Tree, Tree), ScadDotsError> {
+        self.split_with_holes(plane, Vec::new())
+    }
+
+    /// Like `split()`, but also cut alignment pin holes into both halves.
+    pub fn split_with_pins(
+        &self,
+        plane: Plane,
+        pins: &PinHoles,
+    ) -> Result<(Tree, Tree), ScadDotsError> {
+        check_finite("pin holes", [pins.diameter, pins.depth].iter().cloned())?;
+        if pins.diameter <= 0. || pins.depth <= 0. {
+            return Err(ScadDotsError::Dimension
+                .context("pin hole diameter and depth must be positive"));
+        }
+        let rot = normal_rot(plane)?;
+        let mut holes = Vec::new();
+        for &pos in &pins.positions {
+            check_finite("pin hole position", pos.coords.iter().cloned())?;
+            holes.push(Tree::from(Cylinder::new(CylinderSpec {
+                pos: plane.project(pos) - plane.normal * pins.depth,
+                align: CylinderAlign::EndCenter(C1::P0),
+                diameter: pins.diameter,
+                height: 2. * pins.depth,
+                rot,
+            })));
+        }
+        self.split_with_holes(plane, holes)
+    }
+
+    fn split_with_holes(
+        &self,
+        plane: Plane,
+        holes: Vec<Tree>,
+    ) -> Result<(Tree, Tree), ScadDotsError> {
+        check_finite(
+            "split plane",
+            plane
+                .point
+                .coords
+                .iter()
+                .chain(plane.normal.iter())
+                .cloned(),
+        )?;
+        let bounds = self.bounds().ok_or_else(|| {
+            ScadDotsError::Args.context("can't split a tree with no bounds")
+        })?;
+        let distances: Vec<_> = C3::all()
+            .into_iter()
+            .map(|corner| plane.signed_distance(bounds.corner(corner)))
+            .collect();
+        if distances.iter().all(|&d| d >= 0.)
+            || distances.iter().all(|&d| d <= 0.)
+        {
+            return Err(ScadDotsError::Args
+                .context("split plane doesn't cross the tree's bounds"));
+        }
+
+        // A cube this big covers the whole bounding box on one side of the
+        // plane, wherever the plane crosses it.
+        // It extends past the bounding box so it cuts cleanly.
+        let size = 2. * (bounds.size().norm() + CUT_OVERLAP);
+        let center = plane.project(bounds.center());
+        let half = |normal_plane: Plane| -> Result<Tree, ScadDotsError> {
+            Ok(Dot::new(DotSpec {
+                pos: center,
+                align: DotAlign::center_face(CubeFace::Z0),
+                size,
+                rot: normal_rot(normal_plane)?,
+                shape: DotShape::Cube,
+            })
+            .into())
+        };
+        let flipped = Plane::new(plane.point, -plane.normal);
+        let cut = |other_side: Tree| {
+            let mut cutouts = vec![other_side];
+            cutouts.extend(holes.iter().cloned());
+            Tree::cut(self.clone(), cutouts)
+        };
+        Ok((cut(half(flipped)?), cut(half(plane)?)))
+    }
+}
+
+/// Return a rotation that takes the z axis to the plane's normal.
+fn normal_rot(plane: Plane) -> Result<R3, ScadDotsError> {
+    let handle = if plane.normal.x.abs() < 0.9 {
+        Axis::X
+    } else {
+        Axis::Y
+    };
+    rotation_from_axes(handle.into(), plane.normal)
+        .context("invalid split plane")
+}
@@ -0,0 +1,28 @@
+use core::utils::{P3, R3};
+use core::{Tree, TreeObject};
+
+/// A line of text, extruded into a solid for embossing or engraving labels.
+/// The default orientation is for the text to lie flat on the xy plane,
+/// reading along the x axis, and extruded upwards along the z axis.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Text {
+    pub text: String,
+    /// The height of capital letters, roughly.
+    pub font_size: f32,
+    /// How far the text is extruded.
+    pub thickness: f32,
+    /// The center of the text's bottom face.
+    pub pos: P3,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
+    pub rot: R3,
+}
+
+impl From<Text> for Tree {
+    fn from(text: Text) -> Tree {
+        Tree::Object(TreeObject::Text(text))
+    }
+}
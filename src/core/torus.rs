@@ -0,0 +1,113 @@
+use core::{Tree, TreeObject};
+
+use core::utils::{check_finite, Axis, Corner1 as C1, P3, R3, V3};
+use errors::ScadDotsError;
+
+/// A ring-shaped object, formed by sweeping a circle around an axis.
+/// The default orientation is for the torus's axis to be the z axis, so it lies
+/// flat on the xy plane.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Torus {
+    pub center: P3,
+    /// The distance from the center of the torus to the center of the tube.
+    pub major_radius: f32,
+    /// The radius of the tube.
+    pub minor_radius: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
+    pub rot: R3,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct TorusSpec {
+    pub pos: P3,
+    pub align: TorusAlign,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
+    pub rot: R3,
+}
+
+/// Specify an alignment point on a Torus. This does not depend on a particular Torus's dimensions.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum TorusAlign {
+    /// The center of the hole in the middle of the torus.
+    Centroid,
+    /// The center of the hole, on the plane that touches the bottom (C1::P0) or top (C1::P1) surface of the torus.
+    FaceCenter(C1),
+}
+
+impl Torus {
+    /// Create a new torus.
+    pub fn new(spec: TorusSpec) -> Result<Self, ScadDotsError> {
+        check_finite("torus position", spec.pos.coords.iter().cloned())?;
+        check_finite(
+            "torus radii",
+            vec![spec.major_radius, spec.minor_radius],
+        )?;
+        check_finite("torus rotation", spec.rot.coords.iter().cloned())?;
+        if spec.minor_radius <= 0. || spec.minor_radius > spec.major_radius {
+            return Err(ScadDotsError::Dimension.context(
+                "Torus minor radius must be positive and no larger than the \
+                 major radius",
+            ));
+        }
+        Ok(Self {
+            center: spec.center(),
+            major_radius: spec.major_radius,
+            minor_radius: spec.minor_radius,
+            rot: spec.rot,
+        })
+    }
+
+    pub fn pos(&self, align: TorusAlign) -> P3 {
+        self.center + align.offset(self.minor_radius, self.rot)
+    }
+
+    pub fn unit_axis(&self) -> V3 {
+        let z: V3 = Axis::Z.into();
+        self.rot * z
+    }
+
+    /// The diameter of the whole torus, measured across its outer edge.
+    pub fn outer_diameter(&self) -> f32 {
+        2. * (self.major_radius + self.minor_radius)
+    }
+
+    /// The diameter of the hole in the middle of the torus.
+    pub fn inner_diameter(&self) -> f32 {
+        2. * (self.major_radius - self.minor_radius)
+    }
+}
+
+impl From<Torus> for Tree {
+    fn from(torus: Torus) -> Tree {
+        Tree::Object(TreeObject::Torus(torus))
+    }
+}
+
+impl TorusSpec {
+    fn center(&self) -> P3 {
+        self.pos - self.align.offset(self.minor_radius, self.rot)
+    }
+}
+
+impl TorusAlign {
+    /// Return a vector from a torus's canonical alignment point (the centroid) to this alignment point.
+    fn offset(self, minor_radius: f32, rot: R3) -> V3 {
+        match self {
+            TorusAlign::Centroid => V3::zeros(),
+            TorusAlign::FaceCenter(end) => {
+                rot * V3::new(0., 0., end.sign() * minor_radius)
+            }
+        }
+    }
+}
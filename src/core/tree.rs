@@ -1,13 +1,15 @@
-use core::utils::{ColorSpec, V3};
-use core::{Cylinder, Dot, DotShape, Extrusion};
+use core::utils::{ColorSpec, R3, V3};
+use core::{Cylinder, Dot, DotShape, Extrusion, Text, Torus};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Tree {
     Object(TreeObject),
     Operator(TreeOperator),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum TreeObject {
     /// A primitive object representing a dot with equal side lengths.
     Dot(Dot),
@@ -15,9 +17,41 @@ pub enum TreeObject {
     Cylinder(Cylinder),
     /// A primitive object representing a 2d polygon that it is extruded into the 3rd dimension.
     Extrusion(Extrusion),
+    /// A primitive object representing a ring, swept around an axis.
+    Torus(Torus),
+    /// A primitive object representing a line of text, extruded into the 3rd
+    /// dimension.
+    Text(Text),
+    /// OpenSCAD code that's written out exactly as given, as an escape hatch
+    /// for things this crate can't make. It's up to you to make it valid, and
+    /// it can't contain the `emit::RAW_END` comment that marks where it ends.
+    /// The crate can't see inside it, so it has no bounds, and
+    /// `scad_relative_eq()` only compares it as text, ignoring differences in
+    /// whitespace. Since it has no bounds, neither does a difference or
+    /// intersection whose first child is raw code, and the `layout` functions
+    /// treat that tree as empty.
+    RawScad(String),
+    /// A shape loaded from another file when OpenSCAD runs. Like `RawScad`,
+    /// it has no bounds, and `scad_relative_eq()` only compares the paths.
+    Import { path: String, kind: ImportKind },
+}
+
+/// The kinds of files that can be imported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ImportKind {
+    /// A 3D mesh, loaded with `import()`.
+    Stl,
+    /// An OpenSCAD file, added with `include <>`. Its top level shapes are
+    /// placed here, and its modules can be called from `RawScad` code.
+    Scad,
+    /// A 2D drawing, loaded with `import()`. It needs to be extruded, like
+    /// with `Tree::raw_scad()`, before it's used with 3D shapes.
+    Dxf,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum TreeOperator {
     /// An operator that takes the union of its children.
     Union(Vec<Tree>),
@@ -26,8 +60,58 @@ pub enum TreeOperator {
     /// Subtract all following elements from the first
     Diff(Vec<Tree>),
     Intersect(Vec<Tree>),
+    /// Take the Minkowski sum of all the elements, like tracing the second
+    /// element's shape around the surface of the first.
+    Minkowski(Vec<Tree>),
     Color(ColorSpec, Box<Tree>),
     Mirror(V3, Box<Tree>), // Mirrors across plane with the given normal vec
+    /// Scale the child by a separate factor along each axis.
+    Scale(V3, Box<Tree>),
+    /// Move the child by the given offset.
+    Translate(V3, Box<Tree>),
+    /// Rotate the child around the origin.
+    Rotate(
+        #[cfg_attr(
+            feature = "serialize",
+            serde(with = "::core::serde_utils::rot")
+        )]
+        R3,
+        Box<Tree>,
+    ),
+    /// Change how the child is shown in OpenSCAD, for debugging.
+    Modifier(ScadModifier, Box<Tree>),
+    /// Give the child a name, so it's rendered as a separate OpenSCAD
+    /// module. It doesn't change the shape.
+    Named(String, Box<Tree>),
+    /// Override how finely the child's curves are split into segments, like
+    /// OpenSCAD's `$fn`, `$fa`, and `$fs` variables. Any that are None keep
+    /// the value from outside.
+    Detail {
+        /// The number of segments in a full circle. If it's 0, `fa` and `fs`
+        /// are used instead.
+        fn_: Option<i32>,
+        /// The largest angle in degrees of a single segment.
+        fa: Option<f32>,
+        /// The longest length of a single segment.
+        fs: Option<f32>,
+        child: Box<Tree>,
+    },
+}
+
+/// OpenSCAD's debugging modifier characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ScadModifier {
+    /// `%`: Show the child as transparent reference geometry in the preview,
+    /// but leave it out of the final render.
+    Background,
+    /// `#`: Render the child normally, but also highlight it in pink in the
+    /// preview.
+    Debug,
+    /// `!`: Ignore everything except the child.
+    Root,
+    /// `*`: Ignore the child.
+    Disable,
 }
 
 #[macro_export]
@@ -57,6 +141,19 @@ macro_rules! diff {
     }
 }
 
+/// Subtract holes from a base, like `cut![base; hole1, hole2]`. This is the
+/// same as `diff![base, hole1, hole2]`, but the semicolon makes it clear which
+/// element is the base.
+#[macro_export]
+macro_rules! cut {
+    ( $base:expr; $( $tree_like:expr),* $(,)* ) => {
+        Tree::cut(
+            $base,
+            vec![ $(Tree::from($tree_like),)* ]
+        )
+    }
+}
+
 #[macro_export]
 macro_rules! intersect {
     ( $( $tree_like:expr),* $(,)* ) => {
@@ -66,6 +163,15 @@ macro_rules! intersect {
     }
 }
 
+#[macro_export]
+macro_rules! minkowski {
+    ( $( $tree_like:expr),* $(,)* ) => {
+        Tree::minkowski(
+            vec![ $(Tree::from($tree_like),)* ]
+        )
+    }
+}
+
 #[macro_export]
 macro_rules! mirror {
     ($normal:expr, $tree_like:expr $(,)* ) => {
@@ -73,6 +179,54 @@ macro_rules! mirror {
     };
 }
 
+/// Union the tree with a mirrored copy of itself, like
+/// `mirror_copy![normal, tree]`. See `Tree::mirror_copy()`.
+#[macro_export]
+macro_rules! mirror_copy {
+    ($normal:expr, $tree_like:expr $(,)* ) => {
+        Tree::mirror_copy($normal, Tree::from($tree_like))
+    };
+}
+
+#[macro_export]
+macro_rules! scale {
+    ($factors:expr, $tree_like:expr $(,)* ) => {
+        Tree::scale($factors, Tree::from($tree_like))
+    };
+}
+
+#[macro_export]
+macro_rules! translate {
+    ($offset:expr, $tree_like:expr $(,)* ) => {
+        Tree::translate($offset, Tree::from($tree_like))
+    };
+}
+
+#[macro_export]
+macro_rules! rotate {
+    ($rot:expr, $tree_like:expr $(,)* ) => {
+        Tree::rotate($rot, Tree::from($tree_like))
+    };
+}
+
+/// Show the tree as transparent reference geometry in the preview only, like
+/// `ghost![tree]`. See `ScadModifier::Background`.
+#[macro_export]
+macro_rules! ghost {
+    ($tree_like:expr $(,)* ) => {
+        Tree::modifier(ScadModifier::Background, Tree::from($tree_like))
+    };
+}
+
+/// Highlight the tree in the preview, like `highlight![tree]`. See
+/// `ScadModifier::Debug`.
+#[macro_export]
+macro_rules! highlight {
+    ($tree_like:expr $(,)* ) => {
+        Tree::modifier(ScadModifier::Debug, Tree::from($tree_like))
+    };
+}
+
 #[macro_export]
 macro_rules! red {
     ($tree_like:expr $(,)* ) => {
@@ -81,6 +235,27 @@ macro_rules! red {
 }
 
 impl Tree {
+    /// Write the OpenSCAD code exactly as given. Rendering fails if the code
+    /// contains `emit::RAW_END`. See `TreeObject::RawScad`.
+    pub fn raw_scad<S>(code: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Tree::Object(TreeObject::RawScad(code.into()))
+    }
+
+    /// Load a shape from another file. Relative paths are relative to the
+    /// generated scad file. See `TreeObject::Import`.
+    pub fn import<S>(path: S, kind: ImportKind) -> Self
+    where
+        S: Into<String>,
+    {
+        Tree::Object(TreeObject::Import {
+            path: path.into(),
+            kind,
+        })
+    }
+
     pub fn union<T>(tree_like: Vec<T>) -> Self
     where
         T: Into<Self>,
@@ -108,6 +283,17 @@ impl Tree {
         ))
     }
 
+    /// Subtract all of the holes from the base.
+    pub fn cut<S, T>(base: S, holes: Vec<T>) -> Self
+    where
+        S: Into<Self>,
+        T: Into<Self>,
+    {
+        let mut children = vec![base.into()];
+        children.extend(holes.into_iter().map(|x| x.into()));
+        Tree::Operator(TreeOperator::Diff(children))
+    }
+
     pub fn intersect<T>(tree_like: Vec<T>) -> Self
     where
         T: Into<Self>,
@@ -117,6 +303,15 @@ impl Tree {
         ))
     }
 
+    pub fn minkowski<T>(tree_like: Vec<T>) -> Self
+    where
+        T: Into<Self>,
+    {
+        Tree::Operator(TreeOperator::Minkowski(
+            tree_like.into_iter().map(|x| x.into()).collect(),
+        ))
+    }
+
     pub fn mirror<S, T>(normal: S, tree_like: T) -> Self
     where
         T: Into<Self>,
@@ -128,12 +323,266 @@ impl Tree {
         ))
     }
 
+    /// Return the union of the original tree and a copy of it mirrored across
+    /// the plane through the origin with the given normal vector. Useful for
+    /// symmetric parts.
+    pub fn mirror_copy<S, T>(normal: S, tree_like: T) -> Self
+    where
+        T: Into<Self>,
+        S: Into<V3>,
+    {
+        let tree = tree_like.into();
+        union![tree.clone(), Tree::mirror(normal, tree)]
+    }
+
+    /// Scale the tree by a separate factor along each of the global x, y, and
+    /// z axes. Non-uniform factors can be used to stretch round dots into
+    /// ellipsoids.
+    pub fn scale<S, T>(factors: S, tree_like: T) -> Self
+    where
+        T: Into<Self>,
+        S: Into<V3>,
+    {
+        Tree::Operator(TreeOperator::Scale(
+            factors.into(),
+            Box::new(tree_like.into()),
+        ))
+    }
+
+    /// Move the whole tree by the offset. To move a struct of Dots before
+    /// it's been linked into a tree, use `MapDots::map_translate()` instead.
+    pub fn translate<S, T>(offset: S, tree_like: T) -> Self
+    where
+        T: Into<Self>,
+        S: Into<V3>,
+    {
+        Tree::Operator(TreeOperator::Translate(
+            offset.into(),
+            Box::new(tree_like.into()),
+        ))
+    }
+
+    /// Rotate the whole tree around the origin. To rotate a struct of Dots
+    /// before it's been linked into a tree, use `MapDots::map_rotate()`
+    /// instead.
+    pub fn rotate<T>(rot: R3, tree_like: T) -> Self
+    where
+        T: Into<Self>,
+    {
+        Tree::Operator(TreeOperator::Rotate(rot, Box::new(tree_like.into())))
+    }
+
     pub fn color<T>(color: ColorSpec, tree_like: T) -> Self
     where
         T: Into<Self>,
     {
         Tree::Operator(TreeOperator::Color(color, Box::new(tree_like.into())))
     }
+
+    pub fn modifier<T>(modifier: ScadModifier, tree_like: T) -> Self
+    where
+        T: Into<Self>,
+    {
+        Tree::Operator(TreeOperator::Modifier(
+            modifier,
+            Box::new(tree_like.into()),
+        ))
+    }
+
+    /// Give the tree a name, so it's rendered as a separate OpenSCAD module,
+    /// like `module part_lid() {...}`, which is then called where the tree
+    /// was. Named trees can be nested, to keep the hierarchy of parts
+    /// readable in the generated code. Characters that aren't allowed in
+    /// OpenSCAD names are replaced with underscores, and different trees with
+    /// the same name get numbered.
+    pub fn named<S, T>(name: S, tree_like: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<Self>,
+    {
+        Tree::Operator(TreeOperator::Named(
+            name.into(),
+            Box::new(tree_like.into()),
+        ))
+    }
+
+    /// Render the tree with its own curve detail, instead of the one from
+    /// the `RenderQuality`. For example, small holes can use more segments
+    /// than large hulls. See `TreeOperator::Detail`. It's rendered as a
+    /// `let()` statement, which needs OpenSCAD 2019.05 or newer.
+    pub fn detail<T>(
+        fn_: Option<i32>,
+        fa: Option<f32>,
+        fs: Option<f32>,
+        tree_like: T,
+    ) -> Self
+    where
+        T: Into<Self>,
+    {
+        Tree::Operator(TreeOperator::Detail {
+            fn_,
+            fa,
+            fs,
+            child: Box::new(tree_like.into()),
+        })
+    }
+
+    /// Return an equivalent tree that's simpler to render. Unions nested
+    /// directly inside unions are flattened into a single union, and the same
+    /// for hulls. A union with a single child is replaced by that child.
+    /// Operators with no children are removed, along with any operators that
+    /// only contain removed operators. An intersection or Minkowski sum with
+    /// an empty child is empty, so it's removed too. If `push_colors` is true, colors
+    /// applied to unions are moved down to the union's children. The result
+    /// is an empty union if the whole tree was empty.
+    pub fn normalize(self, push_colors: bool) -> Self {
+        self.normalized(push_colors)
+            .unwrap_or_else(|| Tree::union(Vec::<Tree>::new()))
+    }
+
+    /// Return the normalized tree, or None if it's empty.
+    fn normalized(self, push_colors: bool) -> Option<Self> {
+        let op = match self {
+            Tree::Object(_) => return Some(self),
+            Tree::Operator(op) => op,
+        };
+        let normalize_all = |trees: Vec<Tree>| -> Vec<Tree> {
+            trees
+                .into_iter()
+                .filter_map(|t| t.normalized(push_colors))
+                .collect()
+        };
+        // Return None if any of the trees is empty.
+        let normalize_each = |trees: Vec<Tree>| -> Option<Vec<Tree>> {
+            trees
+                .into_iter()
+                .map(|t| t.normalized(push_colors))
+                .collect()
+        };
+        let normalize_box = |tree: Box<Tree>| tree.normalized(push_colors);
+        let new_op = match op {
+            TreeOperator::Union(children) => {
+                let mut flat = flatten(normalize_all(children), true);
+                if flat.len() == 1 {
+                    return flat.pop();
+                }
+                TreeOperator::Union(flat)
+            }
+            TreeOperator::Hull(children) => {
+                TreeOperator::Hull(flatten(normalize_all(children), false))
+            }
+            TreeOperator::Diff(children) => {
+                let mut children = children.into_iter();
+                // Without the base, there's nothing to subtract from.
+                let base = children.next()?.normalized(push_colors)?;
+                let mut kept = vec![base];
+                kept.extend(normalize_all(children.collect()));
+                TreeOperator::Diff(kept)
+            }
+            TreeOperator::Intersect(children) => {
+                TreeOperator::Intersect(normalize_each(children)?)
+            }
+            TreeOperator::Minkowski(children) => {
+                TreeOperator::Minkowski(normalize_each(children)?)
+            }
+            TreeOperator::Color(color, child) => {
+                let child = normalize_box(child)?;
+                if push_colors {
+                    return Some(push_color(color, child));
+                }
+                TreeOperator::Color(color, Box::new(child))
+            }
+            TreeOperator::Mirror(normal, child) => {
+                TreeOperator::Mirror(normal, Box::new(normalize_box(child)?))
+            }
+            TreeOperator::Scale(factors, child) => {
+                TreeOperator::Scale(factors, Box::new(normalize_box(child)?))
+            }
+            TreeOperator::Translate(offset, child) => {
+                TreeOperator::Translate(offset, Box::new(normalize_box(child)?))
+            }
+            TreeOperator::Rotate(rot, child) => {
+                TreeOperator::Rotate(rot, Box::new(normalize_box(child)?))
+            }
+            TreeOperator::Modifier(modifier, child) => TreeOperator::Modifier(
+                modifier,
+                Box::new(normalize_box(child)?),
+            ),
+            TreeOperator::Named(name, child) => {
+                TreeOperator::Named(name, Box::new(normalize_box(child)?))
+            }
+            TreeOperator::Detail { fn_, fa, fs, child } => {
+                TreeOperator::Detail {
+                    fn_,
+                    fa,
+                    fs,
+                    child: Box::new(normalize_box(child)?),
+                }
+            }
+        };
+        if new_op.children().is_empty() {
+            None
+        } else {
+            Some(Tree::Operator(new_op))
+        }
+    }
+}
+
+impl TreeOperator {
+    /// Return references to all the operator's children.
+    pub fn children(&self) -> Vec<&Tree> {
+        match self {
+            TreeOperator::Union(children)
+            | TreeOperator::Hull(children)
+            | TreeOperator::Diff(children)
+            | TreeOperator::Intersect(children)
+            | TreeOperator::Minkowski(children) => children.iter().collect(),
+            TreeOperator::Color(_, child)
+            | TreeOperator::Mirror(_, child)
+            | TreeOperator::Scale(_, child)
+            | TreeOperator::Translate(_, child)
+            | TreeOperator::Rotate(_, child)
+            | TreeOperator::Modifier(_, child)
+            | TreeOperator::Named(_, child)
+            | TreeOperator::Detail { child, .. } => vec![child.as_ref()],
+        }
+    }
+}
+
+/// Replace any child that's a union (if `unions` is true) or a hull
+/// (otherwise) with that child's own children.
+fn flatten(children: Vec<Tree>, unions: bool) -> Vec<Tree> {
+    let mut flat = Vec::new();
+    for child in children {
+        match child {
+            Tree::Operator(TreeOperator::Union(grandchildren)) if unions => {
+                flat.extend(grandchildren)
+            }
+            Tree::Operator(TreeOperator::Hull(grandchildren)) if !unions => {
+                flat.extend(grandchildren)
+            }
+            other => flat.push(other),
+        }
+    }
+    flat
+}
+
+/// Apply the color to every child of the tree's unions, instead of to the
+/// whole tree. Colors that are already applied further down take priority,
+/// so they're left alone.
+fn push_color(color: ColorSpec, tree: Tree) -> Tree {
+    match tree {
+        Tree::Operator(TreeOperator::Union(children)) => {
+            Tree::Operator(TreeOperator::Union(
+                children
+                    .into_iter()
+                    .map(|child| push_color(color, child))
+                    .collect(),
+            ))
+        }
+        Tree::Operator(TreeOperator::Color(..)) => tree,
+        _ => Tree::color(color, tree),
+    }
 }
 
 impl From<Dot> for Tree {
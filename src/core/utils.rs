@@ -1,11 +1,12 @@
 pub use nalgebra::distance;
 use nalgebra::{
-    Point2, Point3, Unit, UnitQuaternion, Vector2, Vector3, Vector4,
+    Isometry3, Matrix3, Point2, Point3, Rotation3, Unit, UnitQuaternion,
+    Vector2, Vector3, Vector4,
 };
 use std::f32;
 use std::f32::consts::PI;
 
-use errors::ScadDotsError;
+use errors::{ResultExt, ScadDotsError};
 
 pub type P3 = Point3<f32>;
 pub type P2 = Point2<f32>;
@@ -13,9 +14,17 @@ pub type V2 = Vector2<f32>;
 pub type V3 = Vector3<f32>;
 pub type V4 = Vector4<f32>;
 pub type R3 = UnitQuaternion<f32>;
+/// A rotation followed by a translation.
+pub type Iso3 = Isometry3<f32>;
 
 const MAX_REL: f32 = 0.0001;
 
+/// How far a shape that's subtracted from a part extends past the surfaces
+/// it cuts through, so the cut is clean instead of leaving a zero-thickness
+/// skin. It's also how far a piece that's unioned onto a part extends into
+/// it, so they overlap.
+pub const CUT_OVERLAP: f32 = 1.;
+
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Axis {
     X,
@@ -24,12 +33,14 @@ pub enum Axis {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Corner1 {
     P0,
     P1,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Corner2 {
     P00,
     P01,
@@ -38,6 +49,7 @@ pub enum Corner2 {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Corner3 {
     P000,
     P010,
@@ -57,7 +69,27 @@ pub enum RectEdge {
     Y1,
 }
 
+/// One of the 12 edges of a cube. The letter is the axis the edge runs
+/// along, and the digits are its position on the other 2 axes, in xyz order.
+/// For example, `Y01` runs along the y axis at x=0 and z=1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CuboidEdge {
+    X00,
+    X01,
+    X10,
+    X11,
+    Y00,
+    Y01,
+    Y10,
+    Y11,
+    Z00,
+    Z01,
+    Z10,
+    Z11,
+}
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum CubeFace {
     X0,
     X1,
@@ -67,13 +99,54 @@ pub enum CubeFace {
     Z1,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Fraction(f32);
 
+/// How to handle a part whose lengths are smaller than its dot size, which
+/// would make inverted geometry. See `RectSpecTrait::check_dimensions()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DimensionMode {
+    /// Return a `Dimension` error. This is what the part constructors do.
+    Strict,
+    /// Raise the lengths to the dot size, making the part as small as it can
+    /// be.
+    Clamp,
+}
+
+/// An infinite plane, described by a point on the plane and a unit vector
+/// normal to it. Unlike the slope form, it can describe vertical planes.
+#[derive(Debug, Clone, Copy)]
+pub struct Plane {
+    pub point: P3,
+    pub normal: V3,
+}
+
+/// An infinite line, described by a point on the line and a unit vector
+/// along it.
+#[derive(Debug, Clone, Copy)]
+pub struct Line3 {
+    pub point: P3,
+    pub direction: V3,
+}
+
+/// A straight line segment between 2 points.
+#[derive(Debug, Clone, Copy)]
+pub struct Segment3 {
+    pub start: P3,
+    pub end: P3,
+}
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum ColorSpec {
     Red,
     Green,
+    /// Any color, with red, green, and blue components from 0 to 1, and an
+    /// alpha from 0 (invisible) to 1 (opaque).
+    Custom {
+        rgb: V3,
+        alpha: f32,
+    },
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -313,6 +386,20 @@ impl Corner3 {
         ]
     }
 
+    /// Return the pairs of corners at the ends of each of the 12 edges of a
+    /// cube.
+    pub fn all_edges() -> Vec<(Self, Self)> {
+        let mut edges = Vec::new();
+        for corner in Self::all() {
+            for &axis in &[Axis::X, Axis::Y, Axis::Z] {
+                if !corner.is_high(axis) {
+                    edges.push((corner, corner.copy_invert(axis)));
+                }
+            }
+        }
+        edges
+    }
+
     fn to_bools(self) -> (bool, bool, bool) {
         match self {
             Corner3::P000 => (false, false, false),
@@ -415,6 +502,13 @@ impl CubeFace {
             CubeFace::Z0 | CubeFace::Z1 => Axis::Z,
         }
     }
+    /// Return the unit vector pointing out of this face of an unrotated
+    /// cube.
+    pub fn normal(self) -> V3 {
+        let sign = if self.is_high() { 1. } else { -1. };
+        self.axis().v3(sign)
+    }
+
     pub fn corners(self) -> (Corner3, Corner3) {
         match self {
             CubeFace::X0 => (Corner3::P000, Corner3::P011),
@@ -438,6 +532,101 @@ impl CubeFace {
     }
 }
 
+impl CuboidEdge {
+    /// Return the axis that the edge runs along.
+    pub fn axis(self) -> Axis {
+        match self {
+            CuboidEdge::X00
+            | CuboidEdge::X01
+            | CuboidEdge::X10
+            | CuboidEdge::X11 => Axis::X,
+            CuboidEdge::Y00
+            | CuboidEdge::Y01
+            | CuboidEdge::Y10
+            | CuboidEdge::Y11 => Axis::Y,
+            CuboidEdge::Z00
+            | CuboidEdge::Z01
+            | CuboidEdge::Z10
+            | CuboidEdge::Z11 => Axis::Z,
+        }
+    }
+
+    /// Return the corners at the low and high ends of the edge.
+    pub fn corners(self) -> (Corner3, Corner3) {
+        match self {
+            CuboidEdge::X00 => (Corner3::P000, Corner3::P100),
+            CuboidEdge::X01 => (Corner3::P001, Corner3::P101),
+            CuboidEdge::X10 => (Corner3::P010, Corner3::P110),
+            CuboidEdge::X11 => (Corner3::P011, Corner3::P111),
+            CuboidEdge::Y00 => (Corner3::P000, Corner3::P010),
+            CuboidEdge::Y01 => (Corner3::P001, Corner3::P011),
+            CuboidEdge::Y10 => (Corner3::P100, Corner3::P110),
+            CuboidEdge::Y11 => (Corner3::P101, Corner3::P111),
+            CuboidEdge::Z00 => (Corner3::P000, Corner3::P001),
+            CuboidEdge::Z01 => (Corner3::P010, Corner3::P011),
+            CuboidEdge::Z10 => (Corner3::P100, Corner3::P101),
+            CuboidEdge::Z11 => (Corner3::P110, Corner3::P111),
+        }
+    }
+
+    pub fn all() -> Vec<Self> {
+        vec![
+            CuboidEdge::X00,
+            CuboidEdge::X01,
+            CuboidEdge::X10,
+            CuboidEdge::X11,
+            CuboidEdge::Y00,
+            CuboidEdge::Y01,
+            CuboidEdge::Y10,
+            CuboidEdge::Y11,
+            CuboidEdge::Z00,
+            CuboidEdge::Z01,
+            CuboidEdge::Z10,
+            CuboidEdge::Z11,
+        ]
+    }
+}
+
+impl DimensionMode {
+    /// Check that the dot size is positive, and that each of the named
+    /// lengths is at least the dot size. In clamp mode, lengths that are too
+    /// small are raised to the dot size instead of causing an error. NaN or
+    /// infinite values are always an error.
+    pub fn fit_lengths(
+        self,
+        size: f32,
+        lengths: &mut [(&str, &mut f32)],
+    ) -> Result<(), ScadDotsError> {
+        if !size.is_finite() || size <= 0. {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!("dot size {} must be positive and finite", size)
+            }));
+        }
+        for (name, length) in lengths.iter_mut() {
+            if !length.is_finite() {
+                return Err(ScadDotsError::Dimension.with_context(|| {
+                    format!("{} {} must be finite", name, length)
+                }));
+            }
+            if **length >= size {
+                continue;
+            }
+            match self {
+                DimensionMode::Strict => {
+                    return Err(ScadDotsError::Dimension.with_context(|| {
+                        format!(
+                            "{} {} is smaller than dot size {}",
+                            name, length, size
+                        )
+                    }));
+                }
+                DimensionMode::Clamp => **length = size,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Fraction {
     pub fn new(value: f32) -> Result<Self, ScadDotsError> {
         if value < 0. || value > 1. {
@@ -465,6 +654,236 @@ impl Fraction {
     }
 }
 
+impl Plane {
+    /// Create a plane through the given point. The normal vector will be
+    /// normalized.
+    pub fn new(point: P3, normal: V3) -> Self {
+        Self {
+            point,
+            normal: normal.normalize(),
+        }
+    }
+
+    /// Create a plane through the given point, checking that the normal
+    /// vector isn't zero. The normal vector will be normalized.
+    pub fn from_normal_point(
+        normal: V3,
+        point: P3,
+    ) -> Result<Self, ScadDotsError> {
+        if !(normal.norm() > 0.) {
+            return Err(ScadDotsError::Args
+                .context("plane normal can't be the zero vector"));
+        }
+        Ok(Self::new(point, normal))
+    }
+
+    /// Create the plane through 3 points, which must not be in a line. The
+    /// normal follows the right hand rule, so it points towards the viewer if
+    /// the points go counterclockwise.
+    pub fn from_points(a: P3, b: P3, c: P3) -> Result<Self, ScadDotsError> {
+        Self::from_normal_point(get_plane_normal(a, b, c), a)
+            .context("can't make a plane from points in a line")
+    }
+
+    /// Create a non-vertical plane from the slope form `z = z_intercept +
+    /// x_slope * x + y_slope * y`. The normal points up.
+    pub fn from_slopes(x_slope: f32, y_slope: f32, z_intercept: f32) -> Self {
+        Self::new(
+            P3::new(0., 0., z_intercept),
+            V3::new(-x_slope, -y_slope, 1.),
+        )
+    }
+
+    /// Return the z coordinate of the plane at the given x and y. It's an
+    /// error if the plane is vertical.
+    pub fn z(&self, x: f32, y: f32) -> Result<f32, ScadDotsError> {
+        if self.normal.z == 0. {
+            return Err(ScadDotsError::Args
+                .context("can't find z coordinate of a vertical plane"));
+        }
+        let n = self.normal;
+        Ok(self.point.z
+            - (n.x * (x - self.point.x) + n.y * (y - self.point.y)) / n.z)
+    }
+
+    /// Return the distance from the plane to the point. It's positive if the
+    /// point is on the side of the plane that the normal points towards.
+    pub fn signed_distance(&self, pos: P3) -> f32 {
+        (pos - self.point).dot(&self.normal)
+    }
+
+    /// Return the closest point on the plane to the given point.
+    pub fn project(&self, pos: P3) -> P3 {
+        pos - self.normal * self.signed_distance(pos)
+    }
+
+    /// Return the point where the line through `point` in the given
+    /// direction crosses the plane, or None if the line is parallel to the
+    /// plane.
+    pub fn intersect_line(&self, point: P3, direction: V3) -> Option<P3> {
+        let speed = direction.dot(&self.normal);
+        if speed.abs() < 1e-6 * direction.norm() || !speed.is_finite() {
+            return None;
+        }
+        Some(point - direction * self.signed_distance(point) / speed)
+    }
+
+    /// Return the line where this plane crosses the other one, as a point on
+    /// the line and a unit vector along it. Return None if the planes are
+    /// parallel.
+    pub fn intersect_plane(&self, other: &Self) -> Option<(P3, V3)> {
+        let direction = self.normal.cross(&other.normal);
+        if direction.norm() < 1e-6 {
+            return None;
+        }
+        // Find the point on the line closest to this plane's point by moving
+        // within this plane, perpendicular to the line, onto the other plane.
+        let across = direction.cross(&self.normal);
+        let point = other.intersect_line(self.point, across)?;
+        Some((point, direction.normalize()))
+    }
+}
+
+impl Line3 {
+    /// Create a line through the point in the given direction, which will be
+    /// normalized. It can't be the zero vector.
+    pub fn new(point: P3, direction: V3) -> Result<Self, ScadDotsError> {
+        let direction = direction.try_normalize(0.).ok_or_else(|| {
+            ScadDotsError::Args.context("line direction can't be zero")
+        })?;
+        Ok(Self { point, direction })
+    }
+
+    /// Create the line through 2 different points, pointing from `a` to `b`.
+    pub fn through(a: P3, b: P3) -> Result<Self, ScadDotsError> {
+        Self::new(a, b - a)
+            .context("can't make a line through 2 identical points")
+    }
+
+    /// Return the point on the line closest to the given point.
+    pub fn closest_point(&self, pos: P3) -> P3 {
+        self.point + self.direction * (pos - self.point).dot(&self.direction)
+    }
+
+    /// Return the shortest distance from the line to the point.
+    pub fn distance_to(&self, pos: P3) -> f32 {
+        (pos - self.closest_point(pos)).norm()
+    }
+
+    /// Return the point on this line and the point on the other line that
+    /// are closest to each other. If the lines cross, they're the same
+    /// point. Return None if the lines are parallel.
+    pub fn closest_points(&self, other: &Self) -> Option<(P3, P3)> {
+        let (d1, d2) = (self.direction, other.direction);
+        let between = self.point - other.point;
+        let alignment = d1.dot(&d2);
+        let denominator = 1. - alignment * alignment;
+        if denominator < 1e-6 {
+            return None;
+        }
+        let (e, f) = (d1.dot(&between), d2.dot(&between));
+        let s = (alignment * f - e) / denominator;
+        let t = (f - alignment * e) / denominator;
+        Some((self.point + d1 * s, other.point + d2 * t))
+    }
+
+    /// Return the point where the line crosses the plane, or None if it's
+    /// parallel to the plane. See `Plane::intersect_line()`.
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<P3> {
+        plane.intersect_line(self.point, self.direction)
+    }
+}
+
+impl Segment3 {
+    pub fn new(start: P3, end: P3) -> Self {
+        Self { start, end }
+    }
+
+    /// Return the vector from the start to the end.
+    pub fn vector(&self) -> V3 {
+        self.end - self.start
+    }
+
+    pub fn length(&self) -> f32 {
+        self.vector().norm()
+    }
+
+    /// Return the infinite line that the segment lies on. It's an error if
+    /// the segment has zero length.
+    pub fn line(&self) -> Result<Line3, ScadDotsError> {
+        Line3::through(self.start, self.end)
+    }
+
+    /// Return the point on the segment closest to the given point.
+    pub fn closest_point(&self, pos: P3) -> P3 {
+        let length_squared = self.vector().norm_squared();
+        if length_squared == 0. {
+            return self.start;
+        }
+        let t = (pos - self.start).dot(&self.vector()) / length_squared;
+        self.start + self.vector() * t.max(0.).min(1.)
+    }
+
+    /// Return the shortest distance from the segment to the point.
+    pub fn distance_to(&self, pos: P3) -> f32 {
+        (pos - self.closest_point(pos)).norm()
+    }
+
+    /// Return the point on this segment and the point on the other segment
+    /// that are closest to each other. Unlike with lines, this works for
+    /// parallel segments too, though then the points aren't unique.
+    pub fn closest_points(&self, other: &Self) -> (P3, P3) {
+        let (d1, d2) = (self.vector(), other.vector());
+        let r = self.start - other.start;
+        let (a, e) = (d1.norm_squared(), d2.norm_squared());
+        let f = d2.dot(&r);
+        let clamp = |x: f32| x.max(0.).min(1.);
+        // Find the parameters s and t along each segment, clamping them to
+        // the segments and handling segments that are only points.
+        let (s, t) = if a == 0. && e == 0. {
+            (0., 0.)
+        } else if a == 0. {
+            (0., clamp(f / e))
+        } else {
+            let c = d1.dot(&r);
+            if e == 0. {
+                (clamp(-c / a), 0.)
+            } else {
+                let b = d1.dot(&d2);
+                let denominator = a * e - b * b;
+                let s = if denominator > 1e-6 * a * e {
+                    clamp((b * f - c * e) / denominator)
+                } else {
+                    // Parallel, so pick any s.
+                    0.
+                };
+                let t = (b * s + f) / e;
+                if t < 0. {
+                    (clamp(-c / a), 0.)
+                } else if t > 1. {
+                    (clamp((b - c) / a), 1.)
+                } else {
+                    (s, t)
+                }
+            }
+        };
+        (self.start + d1 * s, other.start + d2 * t)
+    }
+
+    /// Return the point where the segment crosses the plane, or None if it
+    /// doesn't reach the plane or is parallel to it.
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<P3> {
+        let (from, to) = (
+            plane.signed_distance(self.start),
+            plane.signed_distance(self.end),
+        );
+        if from * to > 0. || from == to {
+            return None;
+        }
+        Some(self.start + self.vector() * (from / (from - to)))
+    }
+}
+
 /// Apply a rotation to a vector. Why doesn't nalgebra give a method for this?
 pub fn rotate<T>(rot: R3, v: T) -> V3
 where
@@ -510,6 +929,91 @@ where
     )
 }
 
+/// Create a rotation from yaw, pitch, and roll angles in degrees. The roll
+/// is about the x axis, the pitch is about the y axis, and the yaw is about
+/// the z axis, all fixed global axes. The roll is applied first and the yaw
+/// last, so it matches OpenSCAD's `rotate([roll, pitch, yaw])`.
+pub fn euler_deg(yaw: f32, pitch: f32, roll: f32) -> R3 {
+    R3::from_euler_angles(
+        degrees_to_radians(roll),
+        degrees_to_radians(pitch),
+        degrees_to_radians(yaw),
+    )
+}
+
+/// Return the yaw, pitch, and roll angles in degrees that make the rotation.
+/// See `euler_deg()`. The pitch is between -90 and 90 degrees. When it's
+/// close to either end, the yaw and roll aren't unique.
+pub fn r3_to_euler_deg(rot: R3) -> (f32, f32, f32) {
+    let (roll, pitch, yaw) = rot.euler_angles();
+    (
+        radians_to_degrees(yaw),
+        radians_to_degrees(pitch),
+        radians_to_degrees(roll),
+    )
+}
+
+/// Build a rotation out of steps around the fixed global axes, applied in
+/// the order they're chained. For example, OpenSCAD's `rotate([10, 20, 30])`
+/// is `Rot::new().about_x(10.).about_y(20.).about_z(30.)`.
+#[derive(Debug, Clone, Copy)]
+pub struct Rot {
+    rot: R3,
+}
+
+impl Rot {
+    /// Start with no rotation.
+    pub fn new() -> Self {
+        Self {
+            rot: R3::identity(),
+        }
+    }
+
+    pub fn about_x(self, degrees: f32) -> Self {
+        self.about(Axis::X, degrees)
+    }
+
+    pub fn about_y(self, degrees: f32) -> Self {
+        self.about(Axis::Y, degrees)
+    }
+
+    pub fn about_z(self, degrees: f32) -> Self {
+        self.about(Axis::Z, degrees)
+    }
+
+    /// Add a step around any axis.
+    pub fn about<T>(self, axis: T, degrees: f32) -> Self
+    where
+        T: Into<V3>,
+    {
+        self.then(axis_degrees(axis, degrees))
+    }
+
+    /// Add a step with an existing rotation.
+    pub fn then(self, rot: R3) -> Self {
+        Self {
+            rot: rot * self.rot,
+        }
+    }
+
+    /// Return the combined rotation.
+    pub fn r3(self) -> R3 {
+        self.rot
+    }
+}
+
+impl Default for Rot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Rot> for R3 {
+    fn from(rot: Rot) -> R3 {
+        rot.rot
+    }
+}
+
 pub fn rotation_between<T, U>(a: T, b: U) -> Result<R3, ScadDotsError>
 where
     T: Into<V3>,
@@ -524,6 +1028,24 @@ where
     })
 }
 
+/// Return the rotation that takes the z axis to the direction of `z`, and the
+/// x axis to the part of `x_handle` that's perpendicular to `z`.
+pub fn rotation_from_axes(x_handle: V3, z: V3) -> Result<R3, ScadDotsError> {
+    let unit_z = z.try_normalize(0.).ok_or_else(|| {
+        ScadDotsError::Rotation.context("z axis can't be the zero vector")
+    })?;
+    let unit_x = (x_handle - x_handle.dot(&unit_z) * unit_z)
+        .try_normalize(1e-6)
+        .ok_or_else(|| {
+            ScadDotsError::Rotation
+                .context("x axis handle can't be parallel to the z axis")
+        })?;
+    let columns = [unit_x, unit_z.cross(&unit_x), unit_z];
+    Ok(R3::from_rotation_matrix(&Rotation3::from_matrix_unchecked(
+        Matrix3::from_columns(&columns),
+    )))
+}
+
 pub fn midpoint(a: P3, b: P3) -> P3 {
     Fraction::new(0.5)
         .expect("bad fraction")
@@ -593,6 +1115,20 @@ pub fn cos_deg(degrees: f32) -> f32 {
 pub fn relative_less_eq(a: f32, b: f32) -> bool {
     a < b || relative_eq!(a, b, max_relative = MAX_REL)
 }
+/// Return a `Numeric` error if any of the values is NaN or infinite. The
+/// name describes the values in the error message.
+pub fn check_finite<I>(name: &str, values: I) -> Result<(), ScadDotsError>
+where
+    I: IntoIterator<Item = f32>,
+{
+    if values.into_iter().all(f32::is_finite) {
+        Ok(())
+    } else {
+        Err(ScadDotsError::Numeric
+            .with_context(|| format!("{} must be finite", name)))
+    }
+}
+
 pub fn relative_less(a: f32, b: f32) -> bool {
     a < b && !relative_eq!(a, b, max_relative = MAX_REL)
 }
@@ -621,11 +1157,37 @@ pub(crate) fn unwrap_rot_axis(rot: R3) -> Result<V3, ScadDotsError> {
     }
 }
 
+/// Clean up the floating point error that builds up when combining rotations.
+/// If the rotation's angle is within `tolerance` radians of 0, return the
+/// identity rotation. If its axis is within `tolerance` of the x, y, or z axis
+/// (in either direction), return a rotation by the same angle around that
+/// exact axis. Otherwise, return the rotation unchanged.
+pub fn snap_rotation(rot: R3, tolerance: f32) -> R3 {
+    let angle = rot.angle();
+    if angle <= tolerance {
+        return R3::identity();
+    }
+    let axis = match rot.axis() {
+        Some(unit) => unit.into_inner(),
+        None => return rot,
+    };
+    for &exact in &[Axis::X, Axis::Y, Axis::Z] {
+        let exact: V3 = exact.into();
+        for &sign in &[1., -1.] {
+            if (axis - sign * exact).norm() <= tolerance {
+                return axis_radians(sign * exact, angle);
+            }
+        }
+    }
+    rot
+}
+
 impl ColorSpec {
     pub fn name(self) -> String {
         match self {
             ColorSpec::Red => "red",
             ColorSpec::Green => "green",
+            ColorSpec::Custom { .. } => "custom",
         }
         .to_owned()
     }
@@ -633,15 +1195,27 @@ impl ColorSpec {
         match self {
             ColorSpec::Red => V3::new(1., 0., 0.),
             ColorSpec::Green => V3::new(0., 1., 0.),
+            ColorSpec::Custom { rgb, .. } => rgb,
         }
-        .to_owned()
     }
-    pub fn rgba(self) -> V4 {
-        let alpha = 0.5;
+    /// Return the opacity, from 0 (invisible) to 1. The named colors are
+    /// opaque.
+    pub fn alpha(self) -> f32 {
         match self {
-            ColorSpec::Red => V4::new(1., 0., 0., alpha),
-            ColorSpec::Green => V4::new(0., 1., 0., alpha),
+            ColorSpec::Red | ColorSpec::Green => 1.,
+            ColorSpec::Custom { alpha, .. } => alpha,
+        }
+    }
+    pub fn rgba(self) -> V4 {
+        let rgb = self.rgb();
+        V4::new(rgb.x, rgb.y, rgb.z, self.alpha())
+    }
+    /// Return the same color with a different opacity, like
+    /// `ColorSpec::Red.with_alpha(0.2)` for a faint overlay.
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        ColorSpec::Custom {
+            rgb: self.rgb(),
+            alpha,
         }
-        .to_owned()
     }
 }
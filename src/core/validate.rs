@@ -0,0 +1,258 @@
+use std::fmt;
+
+use core::utils::{P3, R3, V3};
+use core::{Extrusion, Tree, TreeObject, TreeOperator};
+
+/// A likely mistake in a tree, found by `Tree::validate()`. These usually
+/// only show up later as confusing OpenSCAD errors or missing geometry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationWarning {
+    /// The node types from the root down to the node with the problem, with
+    /// the index of each one among its parent's children, like
+    /// `union/hull[1]/dot[0]`.
+    pub path: String,
+    pub problem: ValidationProblem,
+}
+
+/// The kinds of problems found by `Tree::validate()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationProblem {
+    /// Some coordinate, size, or rotation is NaN or infinite, like from
+    /// normalizing a zero-length vector.
+    NotFinite,
+    /// An object has a size, diameter, or length that's zero or negative.
+    ZeroSize,
+    /// A hull has only one child. That child is usually meant to be hulled
+    /// with something else.
+    SingleHull,
+    /// A difference's first child is empty, so there's nothing to subtract
+    /// from and the whole difference is empty.
+    EmptyDiffBase,
+    /// An extrusion has a hole that isn't inside its perimeter, so it won't
+    /// cut anything out.
+    HoleOutsidePerimeter,
+}
+
+impl Tree {
+    /// Check the tree for common mistakes that make degenerate or
+    /// non-manifold models, and return a warning for each one found. An empty
+    /// list doesn't guarantee that OpenSCAD will render the tree correctly.
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+        validate(self, node_name(self).to_owned(), &mut warnings);
+        warnings
+    }
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.problem)
+    }
+}
+
+impl fmt::Display for ValidationProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationProblem::NotFinite => {
+                write!(f, "has NaN or infinite values")
+            }
+            ValidationProblem::ZeroSize => {
+                write!(f, "has zero or negative size")
+            }
+            ValidationProblem::SingleHull => {
+                write!(f, "hull has only one child")
+            }
+            ValidationProblem::EmptyDiffBase => {
+                write!(f, "difference has an empty first child")
+            }
+            ValidationProblem::HoleOutsidePerimeter => {
+                write!(f, "extrusion hole isn't inside its perimeter")
+            }
+        }
+    }
+}
+
+fn validate(tree: &Tree, path: String, warnings: &mut Vec<ValidationWarning>) {
+    let mut problems = Vec::new();
+    match tree {
+        Tree::Object(object) => object_problems(object, &mut problems),
+        Tree::Operator(operator) => operator_problems(operator, &mut problems),
+    }
+    warnings.extend(problems.into_iter().map(|problem| ValidationWarning {
+        path: path.clone(),
+        problem,
+    }));
+    if let Tree::Operator(operator) = tree {
+        for (i, child) in operator.children().into_iter().enumerate() {
+            let child_path = format!("{}/{}[{}]", path, node_name(child), i);
+            validate(child, child_path, warnings);
+        }
+    }
+}
+
+fn object_problems(object: &TreeObject, problems: &mut Vec<ValidationProblem>) {
+    let (finite, positive) = match object {
+        TreeObject::Dot(dot) => (
+            point_finite(dot.p000)
+                && rot_finite(dot.rot)
+                && dot.size.is_finite(),
+            dot.size > 0.,
+        ),
+        TreeObject::Cylinder(cylinder) => {
+            let top = cylinder.diameter_top.unwrap_or(cylinder.diameter);
+            (
+                point_finite(cylinder.center_bot_pos)
+                    && rot_finite(cylinder.rot)
+                    && cylinder.diameter.is_finite()
+                    && top.is_finite()
+                    && cylinder.height.is_finite(),
+                // A cone can come to a point at one end, but not both.
+                cylinder.height > 0.
+                    && cylinder.diameter >= 0.
+                    && top >= 0.
+                    && cylinder.diameter + top > 0.,
+            )
+        }
+        TreeObject::Torus(torus) => (
+            point_finite(torus.center)
+                && rot_finite(torus.rot)
+                && torus.major_radius.is_finite()
+                && torus.minor_radius.is_finite(),
+            torus.minor_radius > 0. && torus.major_radius > 0.,
+        ),
+        TreeObject::Text(text) => (
+            point_finite(text.pos)
+                && rot_finite(text.rot)
+                && text.font_size.is_finite()
+                && text.thickness.is_finite(),
+            text.font_size > 0. && text.thickness > 0.,
+        ),
+        TreeObject::Extrusion(extrusion) => {
+            return extrusion_problems(extrusion, problems);
+        }
+        TreeObject::RawScad(_) | TreeObject::Import { .. } => return,
+    };
+    if !finite {
+        problems.push(ValidationProblem::NotFinite);
+    } else if !positive {
+        problems.push(ValidationProblem::ZeroSize);
+    }
+}
+
+fn extrusion_problems(
+    extrusion: &Extrusion,
+    problems: &mut Vec<ValidationProblem>,
+) {
+    let outlines = Some(&extrusion.perimeter)
+        .into_iter()
+        .chain(&extrusion.holes);
+    if !extrusion.bottom_z.is_finite()
+        || !extrusion.thickness.is_finite()
+        || !extrusion.params.twist.is_finite()
+        || !extrusion.params.scale.is_finite()
+        || outlines
+            .clone()
+            .flat_map(|outline| outline.points())
+            .any(|p| !p.x.is_finite() || !p.y.is_finite())
+    {
+        problems.push(ValidationProblem::NotFinite);
+        return;
+    }
+    if extrusion.thickness <= 0. {
+        problems.push(ValidationProblem::ZeroSize);
+    }
+    if extrusion.holes.iter().any(|hole| {
+        hole.points()
+            .iter()
+            .any(|p| !extrusion.perimeter.contains(*p))
+    }) {
+        problems.push(ValidationProblem::HoleOutsidePerimeter);
+    }
+}
+
+fn operator_problems(
+    operator: &TreeOperator,
+    problems: &mut Vec<ValidationProblem>,
+) {
+    let finite = match operator {
+        TreeOperator::Mirror(v, _)
+        | TreeOperator::Scale(v, _)
+        | TreeOperator::Translate(v, _) => vector_finite(*v),
+        TreeOperator::Rotate(rot, _) => rot_finite(*rot),
+        TreeOperator::Detail { fa, fs, .. } => {
+            fa.iter().chain(fs).all(|x| x.is_finite())
+        }
+        _ => true,
+    };
+    if !finite {
+        problems.push(ValidationProblem::NotFinite);
+    }
+    match operator {
+        TreeOperator::Hull(children) if children.len() == 1 => {
+            problems.push(ValidationProblem::SingleHull)
+        }
+        TreeOperator::Diff(children) if empty_base(children) => {
+            problems.push(ValidationProblem::EmptyDiffBase)
+        }
+        _ => (),
+    }
+}
+
+/// Return true if the tree has no objects in it, matching which trees
+/// `Tree::normalize()` removes.
+fn is_empty(tree: &Tree) -> bool {
+    match tree {
+        Tree::Object(_) => false,
+        Tree::Operator(TreeOperator::Diff(children)) => empty_base(children),
+        Tree::Operator(operator) => {
+            operator.children().into_iter().all(is_empty)
+        }
+    }
+}
+
+/// Return true if a difference with these children has nothing to subtract
+/// from.
+fn empty_base(children: &[Tree]) -> bool {
+    children.is_empty() || is_empty(&children[0])
+}
+
+fn node_name(tree: &Tree) -> &'static str {
+    match tree {
+        Tree::Object(object) => match object {
+            TreeObject::Dot(_) => "dot",
+            TreeObject::Cylinder(_) => "cylinder",
+            TreeObject::Extrusion(_) => "extrusion",
+            TreeObject::Torus(_) => "torus",
+            TreeObject::Text(_) => "text",
+            TreeObject::RawScad(_) => "raw_scad",
+            TreeObject::Import { .. } => "import",
+        },
+        Tree::Operator(operator) => match operator {
+            TreeOperator::Union(_) => "union",
+            TreeOperator::Hull(_) => "hull",
+            TreeOperator::Diff(_) => "diff",
+            TreeOperator::Intersect(_) => "intersect",
+            TreeOperator::Minkowski(_) => "minkowski",
+            TreeOperator::Color(..) => "color",
+            TreeOperator::Mirror(..) => "mirror",
+            TreeOperator::Scale(..) => "scale",
+            TreeOperator::Translate(..) => "translate",
+            TreeOperator::Rotate(..) => "rotate",
+            TreeOperator::Modifier(..) => "modifier",
+            TreeOperator::Named(..) => "named",
+            TreeOperator::Detail { .. } => "detail",
+        },
+    }
+}
+
+fn point_finite(p: P3) -> bool {
+    vector_finite(p.coords)
+}
+
+fn vector_finite(v: V3) -> bool {
+    v.iter().all(|x| x.is_finite())
+}
+
+fn rot_finite(rot: R3) -> bool {
+    rot.coords.iter().all(|x| x.is_finite())
+}
@@ -1,13 +1,19 @@
+use std::fmt;
+
 use core::utils::{
-    midpoint, Axis, Corner1 as C1, Corner2 as C2, Corner3 as C3, CubeFace,
-    Fraction, P3, R3, V3,
+    midpoint, rotate, Axis, ColorSpec, Corner1 as C1, Corner2 as C2,
+    Corner3 as C3, CubeFace, CuboidEdge, DimensionMode, Fraction, Plane, P3,
+    R3, V3,
+};
+use core::{
+    drop_solid, mark, Anchor, AnchorName, AnchorPoint, Bounds, Dot, DotShape,
+    MapDots, MinMaxCoord, Tree,
 };
-use core::{mark, Dot, DotShape, MapDots, MinMaxCoord, Tree};
-use errors::ScadDotsError;
+use errors::{ResultExt, ScadDotsError};
 use post::{Post, PostLink};
-use rect::{Rect, RectAlign, RectLink, RectShapes, RectSpec};
+use rect::{Rect, RectAlign, RectLink, RectShapes, RectSpec, Silhouette};
 
-#[derive(Debug, Clone, Copy, MapDots, MinMaxCoord)]
+#[derive(Debug, Clone, Copy, MinMaxCoord)]
 /// A cuboid (box) is made of 2 rects, one above the other
 pub struct Cuboid {
     pub top: Rect,
@@ -15,6 +21,7 @@ pub struct Cuboid {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct CuboidSpec {
     pub pos: P3,
     pub align: CuboidAlign,
@@ -22,11 +29,16 @@ pub struct CuboidSpec {
     pub y_length: f32,
     pub z_length: f32,
     pub size: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
     pub rot: R3,
     pub shapes: CuboidShapes,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct CuboidSpecChamferZHole {
     pub pos: P3,
     pub align: CuboidAlign,
@@ -34,11 +46,29 @@ pub struct CuboidSpecChamferZHole {
     pub y_length: f32,
     pub z_length: f32,
     pub chamfer: Fraction,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
     pub rot: R3,
     pub shapes: CuboidShapes,
 }
 
+/// Specify an axis-aligned Cuboid that spans the space between 2 opposite
+/// outer corners, like the min and max coordinates of existing geometry.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct CuboidSpecCorners {
+    /// The outer corner with the smallest coordinates.
+    pub p000: P3,
+    /// The outer corner with the largest coordinates.
+    pub p111: P3,
+    pub size: f32,
+    pub shapes: CuboidShapes,
+}
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum CuboidAlign {
     Corner {
         cuboid: C3,
@@ -53,6 +83,7 @@ pub enum CuboidAlign {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum CuboidShapes {
     Cube,
     Sphere,
@@ -71,6 +102,7 @@ pub enum CuboidShapes {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum CuboidLink {
     Solid,
     Frame,
@@ -80,12 +112,42 @@ pub enum CuboidLink {
     OpenBot,
     ZPost(C2),
     ChamferZ,
+    /// A closed hollow box with walls of the given thickness. See
+    /// `Cuboid::shell()`.
+    Shell {
+        wall: f32,
+    },
+    /// Diagonal braces across one face. See `RectLink::CrossBrace`.
+    CrossBrace(CubeFace),
+}
+
+/// Specify stiffening ribs on one face of a Cuboid. See `Cuboid::ribs()`.
+#[derive(Debug, Clone, Copy)]
+pub struct RibSpec {
+    /// The outer face of the Cuboid that the ribs stick out of.
+    pub face: CubeFace,
+    /// The axis that each rib runs along, relative to the Cuboid's default
+    /// orientation. It must lie in the plane of the face.
+    pub along: Axis,
+    pub count: usize,
+    /// The width of each rib, measured across the face.
+    pub thickness: f32,
+    /// How far each rib sticks out from the face.
+    pub depth: f32,
 }
 
 /// Any struct implementing this trait can be used to construct a Cuboid, by
 /// constructing the upper and lower Rects that together form a Cuboid.
 pub trait CuboidSpecTrait: Copy {
     fn to_rect(&self, upper_or_lower: C1) -> Result<Rect, ScadDotsError>;
+
+    /// In strict mode, return an error if any of the lengths are smaller than
+    /// the dot size. In clamp mode, return a copy with those lengths raised to
+    /// the dot size instead. See `RectSpecTrait::check_dimensions()`.
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError>;
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -213,10 +275,29 @@ impl From<CuboidAlign> for RectAlign {
 }
 
 impl Cuboid {
+    /// Create a new Cuboid. If it fails, the error includes the spec.
     pub fn new<T>(spec: T) -> Result<Self, ScadDotsError>
+    where
+        T: CuboidSpecTrait + fmt::Debug,
+    {
+        Self::from_spec(spec).map_err(|e| e.in_part("cuboid", None, &spec))
+    }
+
+    /// Like `new()`, but include the name in the error, to tell which part
+    /// of a model failed.
+    pub fn new_named<T>(name: &str, spec: T) -> Result<Self, ScadDotsError>
+    where
+        T: CuboidSpecTrait + fmt::Debug,
+    {
+        Self::from_spec(spec)
+            .map_err(|e| e.in_part("cuboid", Some(name), &spec))
+    }
+
+    fn from_spec<T>(spec: T) -> Result<Self, ScadDotsError>
     where
         T: CuboidSpecTrait,
     {
+        let spec = spec.check_dimensions(DimensionMode::Strict)?;
         Ok(Self {
             bot: spec.to_rect(C1::P0)?,
             top: spec.to_rect(C1::P1)?,
@@ -249,6 +330,62 @@ impl Cuboid {
         Self::new(spec)
     }
 
+    /// Return the smallest Cuboid that encloses the thing's bounding box, with
+    /// a gap of `margin` on every side. The Cuboid is rotated by `rot`, and
+    /// if that's not the identity it's made large enough to enclose the whole
+    /// axis-aligned bounding box, so it may be looser than necessary.
+    pub fn bounding<T, S>(
+        thing: &T,
+        size: f32,
+        margin: f32,
+        rot: R3,
+        shapes: S,
+    ) -> Result<Self, ScadDotsError>
+    where
+        T: MinMaxCoord,
+        S: Into<CuboidShapes>,
+    {
+        let axes = [Axis::X, Axis::Y, Axis::Z];
+        if axes.iter().any(|&axis| thing.all_coords(axis).is_empty()) {
+            return Err(ScadDotsError::Args
+                .context("can't make a cuboid around an empty thing"));
+        }
+        let world = Bounds {
+            min: P3::new(
+                thing.min_coord(Axis::X),
+                thing.min_coord(Axis::Y),
+                thing.min_coord(Axis::Z),
+            ),
+            max: P3::new(
+                thing.max_coord(Axis::X),
+                thing.max_coord(Axis::Y),
+                thing.max_coord(Axis::Z),
+            ),
+        };
+        // Find the bounds in the cuboid's own rotated frame.
+        let inverse = rot.inverse();
+        let local = Bounds::from_points(
+            C3::all().into_iter().map(|c| inverse * world.corner(c)),
+        )
+        .expect("a box always has corners");
+        let gap = V3::new(margin, margin, margin);
+        let (p000, p111) = (local.min - gap, local.max + gap);
+        CuboidSpecCorners {
+            p000,
+            p111,
+            size,
+            shapes: shapes.into(),
+        }
+        .to_spec()
+        .map(|spec| CuboidSpec {
+            pos: rot * p000,
+            rot,
+            ..spec
+        })
+        .and_then(Self::new)
+        .context("failed to make bounding cuboid")
+    }
+
     /// Return a vector describing the direction and length of 1 edge of the
     /// Cuboid (starting from the Cuboid's origin). The edge's axis is relative to
     /// the Cuboid's default orientation, not it's actual rotated orientation.
@@ -275,6 +412,21 @@ impl Cuboid {
         self.top.size()
     }
 
+    /// Return the outer dimensions of the Cuboid, and the dimensions of the
+    /// cavity inside it.
+    pub fn silhouette(&self) -> Silhouette {
+        Silhouette::new(
+            V3::new(
+                self.edge_length(Axis::X),
+                self.edge_length(Axis::Y),
+                self.edge_length(Axis::Z),
+            ),
+            self.size(),
+            true,
+            C3::all().into_iter().map(|corner| self.dot(corner).shape),
+        )
+    }
+
     pub fn pos(&self, align: CuboidAlign) -> P3 {
         match align {
             CuboidAlign::Corner { cuboid, dot } => self.pos_corner(cuboid, dot),
@@ -318,6 +470,33 @@ impl Cuboid {
         }
     }
 
+    /// Return a post between the Dots at the ends of the given edge, from
+    /// the low corner to the high corner. Like `vertical_post()`, but for
+    /// any of the 12 edges.
+    pub fn edge_post(&self, edge: CuboidEdge) -> Post {
+        let (low, high) = edge.corners();
+        Post {
+            bot: self.dot(low),
+            top: self.dot(high),
+        }
+    }
+
+    /// Return the unit vector pointing out of the given face of the
+    /// Cuboid, in its actual rotated orientation.
+    pub fn face_normal(&self, face: CubeFace) -> V3 {
+        rotate(self.rot(), face.normal())
+    }
+
+    /// Return the plane of the given outer face of the Cuboid, with its
+    /// normal pointing out of the Cuboid. Use it to place other parts flush
+    /// against the face.
+    pub fn face_plane(&self, face: CubeFace) -> Plane {
+        Plane::new(
+            self.pos(CuboidAlign::center_face(face)),
+            self.face_normal(face),
+        )
+    }
+
     pub fn rect(&self, face: CubeFace) -> Rect {
         match face {
             CubeFace::Z0 => self.bot,
@@ -349,6 +528,75 @@ impl Cuboid {
         }
     }
 
+    /// Return the hull of the Cuboid's dots and copies of them dropped down
+    /// to `bottom_z`. See `Dot::drop()`.
+    pub fn drop_solid(&self, bottom_z: f32, shape: Option<DotShape>) -> Tree {
+        let dots: Vec<_> = C3::all().into_iter().map(|c| self.dot(c)).collect();
+        drop_solid(&dots, bottom_z, shape)
+    }
+
+    /// Return thin Cuboids that stick out of the given face as stiffening
+    /// ribs. Each rib spans the whole face along `spec.along`. The centers of
+    /// the ribs are evenly spaced across the face, with the same spacing
+    /// between the outer ribs and the edges of the face.
+    pub fn ribs(&self, spec: RibSpec) -> Result<Vec<Self>, ScadDotsError> {
+        let normal = spec.face.axis();
+        if spec.along == normal {
+            return Err(ScadDotsError::Args
+                .context("ribs must run along the face, not out of it"));
+        }
+        if spec.thickness <= 0. || spec.depth <= 0. {
+            return Err(ScadDotsError::Dimension
+                .context("rib thickness and depth must be positive"));
+        }
+        let across = [Axis::X, Axis::Y, Axis::Z]
+            .iter()
+            .cloned()
+            .find(|&axis| axis != normal && axis != spec.along)
+            .expect("failed to find axis across face");
+
+        let outer = self.silhouette().outer;
+        if spec.count as f32 * spec.thickness > outer[across.index()] {
+            return Err(ScadDotsError::Dimension
+                .context("ribs are too thick to fit on the face"));
+        }
+
+        let mut lengths = V3::zeros();
+        lengths[spec.along.index()] = outer[spec.along.index()];
+        lengths[across.index()] = spec.thickness;
+        lengths[normal.index()] = spec.depth;
+        let size = spec
+            .thickness
+            .min(spec.depth)
+            .min(outer[spec.along.index()]);
+
+        let spacing = outer[across.index()] / (spec.count as f32 + 1.);
+        let mut ribs = Vec::new();
+        for i in 0..spec.count {
+            // Find the rib's origin corner, relative to the Cuboid's origin
+            // and default orientation.
+            let mut offset = V3::zeros();
+            offset[across.index()] =
+                spacing * (i as f32 + 1.) - spec.thickness / 2.;
+            offset[normal.index()] = if spec.face.is_high() {
+                outer[normal.index()]
+            } else {
+                -spec.depth
+            };
+            ribs.push(Self::new(CuboidSpec {
+                pos: self.pos(CuboidAlign::origin()) + self.rot() * offset,
+                align: CuboidAlign::origin(),
+                x_length: lengths.x,
+                y_length: lengths.y,
+                z_length: lengths.z,
+                size,
+                rot: self.rot(),
+                shapes: CuboidShapes::Cube,
+            })?);
+        }
+        Ok(ribs)
+    }
+
     pub fn mark_corners(&self) -> Tree {
         // for debugging
         let mut marks = Vec::new();
@@ -358,6 +606,26 @@ impl Cuboid {
         Tree::union(marks)
     }
 
+    /// For debugging. Return the union of 12 small red spheres placed at the
+    /// midpoint of each outer edge of the Cuboid.
+    pub fn mark_edges(&self) -> Tree {
+        let mut marks = Vec::new();
+        for (a, b) in C3::all_edges() {
+            marks.push(mark(self.pos(CuboidAlign::outside_midpoint(a, b)), 1.));
+        }
+        Tree::color(ColorSpec::Red, Tree::union(marks))
+    }
+
+    /// For debugging. Return the union of 6 small green spheres placed at the
+    /// center of each outer face of the Cuboid.
+    pub fn mark_faces(&self) -> Tree {
+        let mut marks = Vec::new();
+        for face in CubeFace::all() {
+            marks.push(mark(self.pos(CuboidAlign::center_face(face)), 1.));
+        }
+        Tree::color(ColorSpec::Green, Tree::union(marks))
+    }
+
     pub fn link(&self, style: CuboidLink) -> Result<Tree, ScadDotsError> {
         Ok(match style {
             CuboidLink::Solid => hull![
@@ -394,11 +662,128 @@ impl Cuboid {
                 self.bot.link(RectLink::Chamfer)?,
                 self.top.link(RectLink::Chamfer)?,
             ],
+            CuboidLink::Shell { wall } => self.shell(wall, &[])?,
+            CuboidLink::CrossBrace(face) => {
+                self.rect(face).link(RectLink::CrossBrace)?
+            }
+        })
+    }
+
+    /// Return the solid cuboid with its inside hollowed out, leaving walls of
+    /// the given thickness. The faces in `open` are left out entirely, like
+    /// the top of a tray. The inside is a plain box, so the walls can be
+    /// thinner than `wall` near corners with round dots.
+    pub fn shell(
+        &self,
+        wall: f32,
+        open: &[CubeFace],
+    ) -> Result<Tree, ScadDotsError> {
+        if !(wall > 0.) {
+            return Err(ScadDotsError::Dimension
+                .context("shell wall thickness must be positive"));
+        }
+        let lengths = V3::new(
+            self.edge_length(Axis::X),
+            self.edge_length(Axis::Y),
+            self.edge_length(Axis::Z),
+        );
+        // The corners of the hollow space, in the cuboid's own frame.
+        let mut min = V3::new(wall, wall, wall);
+        let mut max = lengths - min;
+        for face in open {
+            // Extend the hollow space past the face, so it cuts cleanly.
+            let i = face.axis().index();
+            if face.is_high() {
+                max[i] = lengths[i] + wall;
+            } else {
+                min[i] = -wall;
+            }
+        }
+        let inner_lengths = max - min;
+        if inner_lengths.iter().any(|&length| !(length > 0.)) {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "shell walls of thickness {} don't fit in a cuboid of \
+                     size {:?}",
+                    wall, lengths
+                )
+            }));
+        }
+        let inner = Cuboid::new(CuboidSpec {
+            pos: self.pos(CuboidAlign::origin()) + self.rot() * min,
+            align: CuboidAlign::origin(),
+            x_length: inner_lengths.x,
+            y_length: inner_lengths.y,
+            z_length: inner_lengths.z,
+            size: inner_lengths
+                .iter()
+                .cloned()
+                .fold(std::f32::INFINITY, f32::min),
+            rot: self.rot(),
+            shapes: CuboidShapes::Cube,
+        })
+        .context("failed to make inside of shell")?;
+        Ok(diff![
+            self.link(CuboidLink::Solid)?,
+            inner.link(CuboidLink::Solid)?
+        ])
+    }
+}
+
+impl MapDots for Cuboid {
+    fn map(&self, f: &Fn(&Dot) -> Dot) -> Self {
+        Self {
+            top: self.top.map(f),
+            bot: self.bot.map(f),
+        }
+    }
+
+    fn map_mirror(&self, normal: V3) -> Self {
+        Self {
+            top: self.top.map_mirror(normal),
+            bot: self.bot.map_mirror(normal),
+        }
+    }
+}
+
+impl Anchor for Cuboid {
+    type Align = CuboidAlign;
+
+    fn anchor_at(&self, align: CuboidAlign) -> AnchorPoint {
+        AnchorPoint {
+            pos: self.pos(align),
+            rot: self.rot(),
+        }
+    }
+
+    fn align_from_name(name: AnchorName) -> Result<CuboidAlign, ScadDotsError> {
+        let (a, b) = name.corners();
+        Ok(match (name.is_corner(), name.inside) {
+            (true, false) => CuboidAlign::outside(a),
+            (true, true) => CuboidAlign::inside(a),
+            (false, false) => CuboidAlign::outside_midpoint(a, b),
+            (false, true) => CuboidAlign::inside_midpoint(a, b),
         })
     }
 }
 
 impl CuboidSpecTrait for CuboidSpec {
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError> {
+        let mut spec = *self;
+        mode.fit_lengths(
+            spec.size,
+            &mut [
+                ("x_length", &mut spec.x_length),
+                ("y_length", &mut spec.y_length),
+                ("z_length", &mut spec.z_length),
+            ],
+        )?;
+        Ok(spec)
+    }
+
     fn to_rect(&self, upper_or_lower: C1) -> Result<Rect, ScadDotsError> {
         let dot_lengths = V3::new(self.size, self.size, self.size);
         let cuboid_lengths = V3::new(
@@ -420,7 +805,7 @@ impl CuboidSpecTrait for CuboidSpec {
             rot: self.rot,
             shapes: self.shapes.get(upper_or_lower),
         };
-        Rect::new(spec)
+        Rect::from_spec(spec)
     }
 }
 
@@ -440,6 +825,19 @@ impl From<CuboidSpecChamferZHole> for CuboidSpec {
 }
 
 impl CuboidSpecTrait for CuboidSpecChamferZHole {
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError> {
+        // The chamfer keeps the dot size within the x and y lengths.
+        let mut spec = *self;
+        mode.fit_lengths(
+            CuboidSpec::from(spec).size,
+            &mut [("z_length", &mut spec.z_length)],
+        )?;
+        Ok(spec)
+    }
+
     fn to_rect(&self, upper_or_lower: C1) -> Result<Rect, ScadDotsError> {
         // TODO this is a bit inefficient because we'll re-convert self to
         // CuboidSpec every time this is called. But it makes the API a lot
@@ -449,6 +847,57 @@ impl CuboidSpecTrait for CuboidSpecChamferZHole {
     }
 }
 
+impl CuboidSpecCorners {
+    fn to_spec(&self) -> Result<CuboidSpec, ScadDotsError> {
+        let lengths = self.p111 - self.p000;
+        if lengths.iter().any(|&length| !(length >= self.size)) {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "cuboid corners must be at least the dot size ({}) apart \
+                     along each axis, but they're {:?} apart",
+                    self.size, lengths
+                )
+            }));
+        }
+        Ok(CuboidSpec {
+            pos: self.p000,
+            align: CuboidAlign::origin(),
+            x_length: lengths.x,
+            y_length: lengths.y,
+            z_length: lengths.z,
+            size: self.size,
+            rot: R3::identity(),
+            shapes: self.shapes,
+        })
+    }
+}
+
+impl CuboidSpecTrait for CuboidSpecCorners {
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError> {
+        let lengths = self.p111 - self.p000;
+        let (mut x, mut y, mut z) = (lengths.x, lengths.y, lengths.z);
+        mode.fit_lengths(
+            self.size,
+            &mut [
+                ("x_length", &mut x),
+                ("y_length", &mut y),
+                ("z_length", &mut z),
+            ],
+        )?;
+        Ok(Self {
+            p111: self.p000 + V3::new(x, y, z),
+            ..*self
+        })
+    }
+
+    fn to_rect(&self, upper_or_lower: C1) -> Result<Rect, ScadDotsError> {
+        self.to_spec()?.to_rect(upper_or_lower)
+    }
+}
+
 impl CuboidShapes {
     fn get(self, upper_or_lower: C1) -> RectShapes {
         match self {
@@ -492,6 +941,18 @@ impl From<DotShape> for CuboidShapes {
             DotShape::Cube => CuboidShapes::Cube,
             DotShape::Sphere => CuboidShapes::Sphere,
             DotShape::Cylinder => CuboidShapes::Cylinder,
+            DotShape::ChamferedCube { .. } | DotShape::Prism { .. } => {
+                CuboidShapes::Custom {
+                    p000: shape,
+                    p010: shape,
+                    p100: shape,
+                    p110: shape,
+                    p001: shape,
+                    p101: shape,
+                    p011: shape,
+                    p111: shape,
+                }
+            }
         }
     }
 }
@@ -0,0 +1,641 @@
+use std::collections::HashMap;
+
+use core::utils::{radians_to_degrees, unwrap_rot_axis, ColorSpec, P2, R3, V3};
+use core::{ExtrudeParams, ImportKind, ScadModifier};
+use errors::ScadDotsError;
+use render::{Backend, Combine};
+
+/// A piece of OpenSCAD code. It's either a single statement like
+/// `cube([1,1,1]);`, or an operator like `translate([1,2,3])` that applies to
+/// its children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScadNode {
+    call: String,
+    children: Option<Vec<ScadNode>>,
+    /// If set, the node is a call to the module named by `call`, and this is
+    /// the module's body.
+    module: Option<Vec<ScadNode>>,
+    /// If true, `call` is written as is, without a semicolon.
+    raw: bool,
+}
+
+/// The minimum number of nodes in a subtree for `ScadNode::deduplicated()`
+/// to share it. Smaller ones aren't worth a module.
+const MIN_SHARED_SIZE: usize = 3;
+
+/// A module definition found while writing the code.
+struct Module<'a> {
+    /// The name it was given, which may be shared with other modules.
+    requested: &'a str,
+    /// The unique name it's written with.
+    name: String,
+    body: &'a [ScadNode],
+}
+
+impl ScadNode {
+    /// Make a statement with no children. The trailing semicolon is added
+    /// automatically.
+    pub fn statement<S>(call: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            call: call.into(),
+            children: None,
+            module: None,
+            raw: false,
+        }
+    }
+
+    /// Make a node that's written exactly as given, without a trailing
+    /// semicolon.
+    pub fn raw<S>(code: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            call: code.into(),
+            children: None,
+            module: None,
+            raw: true,
+        }
+    }
+
+    /// Make an operator, with no children yet.
+    pub fn operator<S>(call: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            call: call.into(),
+            children: Some(Vec::new()),
+            module: None,
+            raw: false,
+        }
+    }
+
+    /// Make a call to a module with the given body. The module's definition
+    /// is written at the top of the code. The name gets a `part_` prefix, and
+    /// characters that OpenSCAD doesn't allow are replaced with underscores.
+    /// If modules with different bodies have the same name, the later ones
+    /// are numbered.
+    pub fn module(name: &str, body: Vec<ScadNode>) -> Self {
+        Self {
+            call: identifier(name),
+            children: None,
+            module: Some(body),
+            raw: false,
+        }
+    }
+
+    /// Add a child to an operator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is a statement.
+    pub fn add_child(&mut self, child: ScadNode) {
+        self.children
+            .as_mut()
+            .expect("can't add a child to a scad statement")
+            .push(child)
+    }
+
+    /// Add a child to an operator, and return the operator.
+    pub fn with_child(mut self, child: ScadNode) -> Self {
+        self.add_child(child);
+        self
+    }
+
+    /// Return a copy where every subtree that appears more than once is
+    /// replaced with a call to a shared module, so its code is only written
+    /// once. Only the outermost repeated subtrees are shared, along with any
+    /// that are also repeated elsewhere inside them. Small subtrees and
+    /// module bodies are left alone.
+    pub fn deduplicated(&self) -> ScadNode {
+        let mut counts = HashMap::new();
+        self.count_subtrees(&mut counts);
+        self.share_repeated(&counts, 1)
+    }
+
+    /// Count how many times each subtree appears, and return the number of
+    /// nodes in this one.
+    fn count_subtrees<'a>(
+        &'a self,
+        counts: &mut HashMap<&'a ScadNode, usize>,
+    ) -> usize {
+        let mut size = 1;
+        if let Some(ref children) = self.children {
+            for child in children {
+                size += child.count_subtrees(counts);
+            }
+        }
+        if size >= MIN_SHARED_SIZE {
+            *counts.entry(self).or_insert(0) += 1;
+        }
+        size
+    }
+
+    /// Replace repeated subtrees with module calls. A subtree inside a shared
+    /// one only needs its own module if it appears more often than the
+    /// enclosing one, which appears `enclosing` times.
+    fn share_repeated(
+        &self,
+        counts: &HashMap<&ScadNode, usize>,
+        enclosing: usize,
+    ) -> ScadNode {
+        let count = counts.get(self).cloned().unwrap_or(0);
+        let shared = count > 1 && count > enclosing;
+        let inner = if shared { count } else { enclosing };
+        let node = ScadNode {
+            call: self.call.clone(),
+            children: self.children.as_ref().map(|children| {
+                children
+                    .iter()
+                    .map(|child| child.share_repeated(counts, inner))
+                    .collect()
+            }),
+            module: self.module.clone(),
+            raw: self.raw,
+        };
+        if shared {
+            ScadNode::module("shared", vec![node])
+        } else {
+            node
+        }
+    }
+
+    /// Return the code, indented with tabs. Any module definitions come
+    /// first.
+    pub fn code(&self) -> String {
+        let mut modules = Vec::new();
+        self.find_modules(&mut modules);
+        let mut code = String::new();
+        for module in &modules {
+            code.push_str(&format!("module {}()\n{{\n", module.name));
+            for child in module.body {
+                child.write(1, &mut code, &modules);
+            }
+            code.push_str("}\n");
+        }
+        self.write(0, &mut code, &modules);
+        code
+    }
+
+    /// Add the definitions of all modules called inside this node, with the
+    /// innermost ones first.
+    fn find_modules<'a>(&'a self, modules: &mut Vec<Module<'a>>) {
+        if let Some(ref children) = self.children {
+            for child in children {
+                child.find_modules(modules);
+            }
+        }
+        if let Some(ref body) = self.module {
+            for child in body {
+                child.find_modules(modules);
+            }
+            if find_module(modules, &self.call, body).is_some() {
+                return;
+            }
+            let mut name = self.call.clone();
+            let mut number = 1;
+            while modules.iter().any(|m| m.name == name) {
+                number += 1;
+                name = format!("{}_{}", self.call, number);
+            }
+            modules.push(Module {
+                requested: &self.call,
+                name,
+                body,
+            });
+        }
+    }
+
+    fn write(&self, depth: usize, code: &mut String, modules: &[Module]) {
+        let indent = "\t".repeat(depth);
+        if let Some(ref body) = self.module {
+            let name = find_module(modules, &self.call, body)
+                .expect("module definition wasn't found");
+            code.push_str(&format!("{}{}();\n", indent, name));
+            return;
+        }
+        match self.children {
+            None if self.raw => {
+                code.push_str(&format!("{}{}\n", indent, self.call));
+            }
+            None => {
+                code.push_str(&format!("{}{};\n", indent, self.call));
+            }
+            Some(ref children) => {
+                code.push_str(&format!(
+                    "{}{}\n{}{{\n",
+                    indent, self.call, indent
+                ));
+                for child in children {
+                    child.write(depth + 1, code, modules);
+                }
+                code.push_str(&format!("{}}}\n", indent));
+            }
+        }
+    }
+}
+
+/// Return the unique name of the module with the given requested name and
+/// body, if it's been found.
+fn find_module<'a>(
+    modules: &'a [Module],
+    requested: &str,
+    body: &[ScadNode],
+) -> Option<&'a str> {
+    modules
+        .iter()
+        .find(|m| m.requested == requested && m.body == body)
+        .map(|m| m.name.as_str())
+}
+
+/// Turn the name into a module name that OpenSCAD allows. The prefix keeps
+/// it from hiding built in modules like `cube()`.
+fn identifier(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("part_{}", name)
+}
+
+/// The comment before raw code.
+pub const RAW_START: &str = "/* raw scad */";
+/// The comment after raw code.
+pub const RAW_END: &str = "/* end raw scad */";
+
+/// The backend that renders to OpenSCAD code.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScadBackend {
+    /// If set, round numbers to this many decimal places.
+    pub precision: Option<usize>,
+}
+
+impl ScadBackend {
+    pub fn new(precision: Option<usize>) -> Self {
+        Self { precision }
+    }
+
+    fn num(&self, value: f32) -> Result<String, ScadDotsError> {
+        precise_num(value, self.precision)
+    }
+
+    fn vec3(&self, v: V3) -> Result<String, ScadDotsError> {
+        Ok(format!(
+            "[{},{},{}]",
+            self.num(v.x)?,
+            self.num(v.y)?,
+            self.num(v.z)?
+        ))
+    }
+
+    fn points2(&self, points: &[P2]) -> Result<String, ScadDotsError> {
+        let mut formatted = String::new();
+        for p in points {
+            formatted.push_str(&format!(
+                "[{},{}],",
+                self.num(p.x)?,
+                self.num(p.y)?
+            ));
+        }
+        Ok(format!("[{}]", formatted))
+    }
+
+    fn operator(&self, call: String, children: Vec<ScadNode>) -> ScadNode {
+        let mut node = ScadNode::operator(call);
+        for child in children {
+            node.add_child(child);
+        }
+        node
+    }
+}
+
+impl Backend for ScadBackend {
+    type Node = ScadNode;
+
+    fn cube(&self, lengths: V3) -> Result<ScadNode, ScadDotsError> {
+        Ok(ScadNode::statement(format!(
+            "cube({})",
+            self.vec3(lengths)?
+        )))
+    }
+
+    fn sphere(&self, diameter: f32) -> Result<ScadNode, ScadDotsError> {
+        Ok(ScadNode::statement(format!(
+            "sphere(d={})",
+            self.num(diameter)?
+        )))
+    }
+
+    fn cylinder(
+        &self,
+        height: f32,
+        bottom_diameter: f32,
+        top_diameter: f32,
+    ) -> Result<ScadNode, ScadDotsError> {
+        Ok(if bottom_diameter == top_diameter {
+            ScadNode::statement(format!(
+                "cylinder(h={},d={})",
+                self.num(height)?,
+                self.num(bottom_diameter)?
+            ))
+        } else {
+            ScadNode::statement(format!(
+                "cylinder(h={},d1={},d2={})",
+                self.num(height)?,
+                self.num(bottom_diameter)?,
+                self.num(top_diameter)?
+            ))
+        })
+    }
+
+    fn circle(&self, radius: f32) -> Result<ScadNode, ScadDotsError> {
+        Ok(ScadNode::statement(format!(
+            "circle(r={})",
+            self.num(radius)?
+        )))
+    }
+
+    fn polygon(&self, contours: &[&[P2]]) -> Result<ScadNode, ScadDotsError> {
+        if contours.len() == 1 {
+            return Ok(ScadNode::statement(format!(
+                "polygon(points={},paths=undef,convexity=10)",
+                self.points2(contours[0])?
+            )));
+        }
+        let mut points = Vec::new();
+        let mut indices = Vec::new();
+        for contour in contours {
+            let start = points.len();
+            points.extend_from_slice(contour);
+            indices.push((start..points.len()).collect::<Vec<_>>());
+        }
+        Ok(ScadNode::statement(format!(
+            "polygon(points={},paths={},convexity=10)",
+            self.points2(&points)?,
+            paths(&indices)
+        )))
+    }
+
+    fn text(&self, text: &str, size: f32) -> Result<ScadNode, ScadDotsError> {
+        Ok(ScadNode::statement(format!(
+            "text({},size={},halign=\"center\",valign=\"center\")",
+            string(text),
+            self.num(size)?
+        )))
+    }
+
+    fn raw_scad(&self, code: &str) -> Result<ScadNode, ScadDotsError> {
+        // Mark where it starts and ends, so it can be compared in tests.
+        if code.contains(RAW_END) {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!("raw scad code can't contain '{}'", RAW_END)
+            }));
+        }
+        Ok(ScadNode::raw(format!(
+            "{}\n{}\n{}",
+            RAW_START, code, RAW_END
+        )))
+    }
+
+    fn import(
+        &self,
+        path: &str,
+        kind: ImportKind,
+    ) -> Result<ScadNode, ScadDotsError> {
+        Ok(match kind {
+            ImportKind::Stl | ImportKind::Dxf => {
+                ScadNode::statement(format!("import({})", string(path)))
+            }
+            ImportKind::Scad => {
+                if path.contains(|c| c == '>' || c == '\n') {
+                    return Err(ScadDotsError::Args.with_context(|| {
+                        format!("can't include scad file '{}'", path)
+                    }));
+                }
+                ScadNode::raw(format!("include <{}>", path))
+            }
+        })
+    }
+
+    fn linear_extrude(
+        &self,
+        height: f32,
+        params: ExtrudeParams,
+        children: Vec<ScadNode>,
+    ) -> Result<ScadNode, ScadDotsError> {
+        // Only mention the scale if it's used, so the output matches older
+        // versions.
+        let scale = if params.scale == 1. {
+            String::new()
+        } else {
+            format!(",scale={}", self.num(params.scale)?)
+        };
+        let call = format!(
+            "linear_extrude(height={},center={},convexity=10,twist={},\
+             slices={}{})",
+            self.num(height)?,
+            params.center,
+            self.num(params.twist)?,
+            params.slices,
+            scale
+        );
+        Ok(self.operator(call, children))
+    }
+
+    fn rotate_extrude(&self, children: Vec<ScadNode>) -> ScadNode {
+        self.operator("rotate_extrude(angle=360,convexity=10)".into(), children)
+    }
+
+    fn combine(&self, op: Combine, children: Vec<ScadNode>) -> ScadNode {
+        let call = match op {
+            Combine::Union => "union()",
+            Combine::Hull => "hull()",
+            Combine::Diff => "difference()",
+            Combine::Intersect => "intersection()",
+            Combine::Minkowski => "minkowski()",
+        };
+        self.operator(call.to_owned(), children)
+    }
+
+    fn color(
+        &self,
+        color: ColorSpec,
+        children: Vec<ScadNode>,
+    ) -> Result<ScadNode, ScadDotsError> {
+        // Leave out the alpha if it's opaque, to match older versions.
+        let call = if color.alpha() == 1. {
+            format!("color({})", self.vec3(color.rgb())?)
+        } else {
+            format!(
+                "color({},{})",
+                self.vec3(color.rgb())?,
+                self.num(color.alpha())?
+            )
+        };
+        Ok(self.operator(call, children))
+    }
+
+    fn mirror(
+        &self,
+        normal: V3,
+        children: Vec<ScadNode>,
+    ) -> Result<ScadNode, ScadDotsError> {
+        Ok(self.operator(format!("mirror({})", self.vec3(normal)?), children))
+    }
+
+    fn scale(
+        &self,
+        factors: V3,
+        children: Vec<ScadNode>,
+    ) -> Result<ScadNode, ScadDotsError> {
+        Ok(self.operator(format!("scale({})", self.vec3(factors)?), children))
+    }
+
+    fn translate(
+        &self,
+        offset: V3,
+        children: Vec<ScadNode>,
+    ) -> Result<ScadNode, ScadDotsError> {
+        Ok(self
+            .operator(format!("translate({})", self.vec3(offset)?), children))
+    }
+
+    fn rotate(
+        &self,
+        rot: R3,
+        children: Vec<ScadNode>,
+    ) -> Result<ScadNode, ScadDotsError> {
+        let degrees = radians_to_degrees(rot.angle());
+        let axis = unwrap_rot_axis(rot)?;
+        Ok(self.operator(
+            format!("rotate({},{})", self.num(degrees)?, self.vec3(axis)?),
+            children,
+        ))
+    }
+
+    fn modifier(
+        &self,
+        modifier: ScadModifier,
+        children: Vec<ScadNode>,
+    ) -> ScadNode {
+        let symbol = match modifier {
+            ScadModifier::Background => "%",
+            ScadModifier::Debug => "#",
+            ScadModifier::Root => "!",
+            ScadModifier::Disable => "*",
+        };
+        // Wrap the children in a union so the modifier applies to all of
+        // them.
+        self.operator(format!("{}union()", symbol), children)
+    }
+
+    fn named(&self, name: &str, children: Vec<ScadNode>) -> ScadNode {
+        ScadNode::module(name, children)
+    }
+
+    fn detail(
+        &self,
+        fn_: Option<i32>,
+        fa: Option<f32>,
+        fs: Option<f32>,
+        children: Vec<ScadNode>,
+    ) -> Result<ScadNode, ScadDotsError> {
+        let mut args = Vec::new();
+        if let Some(fn_) = fn_ {
+            args.push(format!("$fn={}", fn_));
+        }
+        if let Some(fa) = fa {
+            args.push(format!("$fa={}", self.num(fa)?));
+        }
+        if let Some(fs) = fs {
+            args.push(format!("$fs={}", self.num(fs)?));
+        }
+        Ok(self.operator(format!("let({})", args.join(",")), children))
+    }
+}
+
+/// Return the code for a whole file, starting with the default number of
+/// segments used for curves.
+pub fn file_code(detail: i32, node: &ScadNode) -> String {
+    format!("$fn={};\n{}", detail, node.code())
+}
+
+/// Format a number the way OpenSCAD writes it. See `precise_num()`.
+pub fn num(value: f32) -> Result<String, ScadDotsError> {
+    precise_num(value, None)
+}
+
+/// Format a number, rounded to the given number of decimal places if it's
+/// set. Trailing zeros are left out, and negative zero is written as `0`, so
+/// the same shape always gets the same code. OpenSCAD can't read NaN or
+/// infinite numbers, so they're a `Numeric` error.
+pub fn precise_num(
+    value: f32,
+    precision: Option<usize>,
+) -> Result<String, ScadDotsError> {
+    if !value.is_finite() {
+        return Err(ScadDotsError::Numeric.with_context(|| {
+            format!("can't write {} to OpenSCAD code", value)
+        }));
+    }
+    let formatted = match precision {
+        Some(places) if places > 0 => {
+            let rounded = format!("{:.*}", places, value);
+            rounded
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_owned()
+        }
+        Some(_) => format!("{:.0}", value),
+        None => format!("{}", value),
+    };
+    Ok(if formatted == "-0" {
+        "0".to_owned()
+    } else {
+        formatted
+    })
+}
+
+/// Format a vector like `[1,2,3]`.
+pub fn vec3(v: V3) -> Result<String, ScadDotsError> {
+    ScadBackend::default().vec3(v)
+}
+
+/// Format a list of 2d points like `[[1,2],[3,4],]`.
+pub fn points2(points: &[P2]) -> Result<String, ScadDotsError> {
+    ScadBackend::default().points2(points)
+}
+
+/// Format lists of point indices like `[[0,1,2,],[3,4,5,],]`.
+pub fn paths(indices: &[Vec<usize>]) -> String {
+    let formatted: Vec<_> = indices
+        .iter()
+        .map(|path| {
+            let path: Vec<_> = path.iter().map(|i| format!("{},", i)).collect();
+            format!("[{}],", path.concat())
+        })
+        .collect();
+    format!("[{}]", formatted.concat())
+}
+
+/// Format a string literal, escaping quotes, backslashes, and whitespace
+/// control characters.
+pub fn string(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '\r' => literal.push_str("\\r"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
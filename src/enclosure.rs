@@ -0,0 +1,310 @@
+use core::utils::{
+    check_finite, degrees_to_radians, Corner1 as C1, Segment3, CUT_OVERLAP, P2,
+    P3, R3,
+};
+use core::{
+    Cylinder, CylinderAlign, CylinderSpec, Dot, Extrusion, OffsetJoin,
+    Polygon2D, Tree,
+};
+use errors::{ResultExt, ScadDotsError};
+use fastener::{HoleHead, ScrewHole, ScrewHoleSpec};
+
+/// How many segments a full circle of rounded corner is split into.
+const CORNER_SEGMENTS: usize = 32;
+
+/// How many degrees the outline has to turn at a point for it to get a screw
+/// boss. Smaller turns are along straight or curved sides, not at corners.
+const MIN_BOSS_TURN: f32 = 30.;
+
+/// Specify an open-topped box and its lid.
+#[derive(Debug, Clone)]
+pub struct EnclosureSpec {
+    /// The inside of the box, on the xy plane. The walls are built outwards
+    /// from it, so the contents only need to fit inside this outline.
+    pub outline: Polygon2D,
+    /// The thickness of the walls, the floor, and the lid.
+    pub wall: f32,
+    /// The height of the body, from the bottom of the floor to the top of the
+    /// walls. The lid sits on top of this.
+    pub height: f32,
+    /// The radius of the outside corners, seen from above. If it's larger
+    /// than the wall thickness, the inside corners are rounded too.
+    pub corner_radius: f32,
+    pub lid: LidStyle,
+}
+
+/// How the lid is held on.
+#[derive(Debug, Clone, Copy)]
+pub enum LidStyle {
+    /// A lip under the lid fits snugly inside the walls. The lip is as thick
+    /// as the walls, and `clearance` is the gap between them on each side.
+    FrictionFit { lip_depth: f32, clearance: f32 },
+    /// Screws go through the lid into bosses in each corner of the outline,
+    /// where it turns by at least 30 degrees. The screw holes in the bosses
+    /// are `pilot_diameter` wide, for the screw to tap into, and go down to
+    /// the floor. The bosses must fit inside the rounded outside corners.
+    Screwed {
+        boss_diameter: f32,
+        pilot_diameter: f32,
+        clearance_diameter: f32,
+        head: Option<HoleHead>,
+    },
+}
+
+/// The two parts of a box. They're positioned as if the box were closed, with
+/// the bottom of the body on the z=0 plane and the lid sitting on top.
+#[derive(Debug, Clone)]
+pub struct Enclosure {
+    pub body: Tree,
+    pub lid: Tree,
+}
+
+impl Enclosure {
+    pub fn new(spec: EnclosureSpec) -> Result<Self, ScadDotsError> {
+        spec.check()?;
+        // Round the corners by moving the outline in past the start of the
+        // rounding, and then back out with round joins. A contour `distance`
+        // outside the outline has corners rounded to `distance - corner`,
+        // so the walls are the same thickness all the way around.
+        let corner = spec.wall - spec.corner_radius;
+        let too_small =
+            "enclosure outline is too small for its walls and corners";
+        let base = offset(&spec.outline, corner, OffsetJoin::Miter)
+            .context(too_small)?;
+        let contour = |distance: f32| {
+            if distance > corner {
+                offset(&base, distance - corner, round())
+            } else {
+                offset(&spec.outline, distance, OffsetJoin::Miter)
+            }
+            .context(too_small)
+        };
+        let outer = contour(spec.wall)?;
+        let inner = contour(0.)?;
+
+        let shell = diff![
+            extrude(&outer, 0., spec.height)?,
+            extrude(&inner, spec.wall, spec.height - spec.wall + CUT_OVERLAP)?,
+        ];
+        let plate = extrude(&outer, spec.height, spec.wall)?;
+        Ok(match spec.lid {
+            LidStyle::FrictionFit {
+                lip_depth,
+                clearance,
+            } => {
+                let lip_outer = contour(-clearance)?;
+                let lip_inner = contour(-clearance - spec.wall)?;
+                let mut lip =
+                    extrude(&lip_outer, spec.height - lip_depth, lip_depth)?;
+                lip.holes.push(lip_inner);
+                Self {
+                    body: shell,
+                    lid: union![plate, lip],
+                }
+            }
+            LidStyle::Screwed {
+                boss_diameter,
+                pilot_diameter,
+                clearance_diameter,
+                head,
+            } => {
+                let too_small = "enclosure is too small for its screw bosses";
+                let centers = corners(
+                    &offset(
+                        &spec.outline,
+                        -boss_diameter / 2.,
+                        OffsetJoin::Miter,
+                    )
+                    .context(too_small)?,
+                );
+                // A boss sticks out of the box if its center isn't at least
+                // its radius inside the outside of the walls.
+                if centers.iter().any(|center| {
+                    !outer.contains(*center)
+                        || distance_to_edges(&outer, *center)
+                            < boss_diameter / 2.
+                }) {
+                    return Err(ScadDotsError::Dimension.context(
+                        "enclosure screw bosses poke through the rounded \
+                         corners, so the corner radius must be smaller or \
+                         the walls thicker",
+                    ));
+                }
+                let mut bosses = Vec::new();
+                let mut pilots = Vec::new();
+                let mut clearances = Vec::new();
+                for center in &centers {
+                    bosses.push(Tree::from(Cylinder::new(CylinderSpec {
+                        pos: P3::new(center.x, center.y, 0.),
+                        align: CylinderAlign::EndCenter(C1::P0),
+                        diameter: boss_diameter,
+                        height: spec.height,
+                        rot: R3::identity(),
+                    })));
+                    pilots.push(screw_hole(
+                        *center,
+                        spec.height + CUT_OVERLAP,
+                        pilot_diameter,
+                        spec.height - spec.wall + CUT_OVERLAP,
+                        None,
+                    )?);
+                    clearances.push(screw_hole(
+                        *center,
+                        spec.height + spec.wall,
+                        clearance_diameter,
+                        spec.wall + CUT_OVERLAP,
+                        head,
+                    )?);
+                }
+                Self {
+                    body: Tree::cut(union![shell, Tree::union(bosses)], pilots),
+                    lid: Tree::cut(plate, clearances),
+                }
+            }
+        })
+    }
+}
+
+impl EnclosureSpec {
+    fn check(&self) -> Result<(), ScadDotsError> {
+        check_finite(
+            "enclosure dimensions",
+            [self.wall, self.height, self.corner_radius].iter().cloned(),
+        )?;
+        if self.wall <= 0. || self.height <= self.wall {
+            return Err(ScadDotsError::Dimension.context(
+                "enclosure wall must be positive and thinner than its height",
+            ));
+        }
+        if self.corner_radius < 0. {
+            return Err(ScadDotsError::Dimension
+                .context("enclosure corner radius can't be negative"));
+        }
+        match self.lid {
+            LidStyle::FrictionFit {
+                lip_depth,
+                clearance,
+            } => {
+                check_finite(
+                    "enclosure lid",
+                    [lip_depth, clearance].iter().cloned(),
+                )?;
+                if lip_depth <= 0.
+                    || lip_depth > self.height - self.wall
+                    || clearance < 0.
+                {
+                    return Err(ScadDotsError::Dimension.context(
+                        "enclosure lid lip must be positive and fit above \
+                         the floor, and its clearance can't be negative",
+                    ));
+                }
+            }
+            LidStyle::Screwed {
+                boss_diameter,
+                pilot_diameter,
+                clearance_diameter,
+                ..
+            } => {
+                check_finite(
+                    "enclosure screws",
+                    [boss_diameter, pilot_diameter, clearance_diameter]
+                        .iter()
+                        .cloned(),
+                )?;
+                if pilot_diameter <= 0.
+                    || clearance_diameter < pilot_diameter
+                    || boss_diameter <= clearance_diameter
+                {
+                    return Err(ScadDotsError::Dimension.context(
+                        "enclosure screw bosses must be wider than the \
+                         clearance holes, which can't be narrower than the \
+                         pilot holes",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Return the convex hull of the dots' centers, projected onto the xy plane,
+/// for use as an `EnclosureSpec` outline.
+pub fn outline_of_dots(dots: &[Dot]) -> Result<Polygon2D, ScadDotsError> {
+    Ok(Extrusion::convex_hull_of_dots(dots, 0., 0.)?.perimeter)
+}
+
+/// Return the points where the polygon turns by at least `MIN_BOSS_TURN`.
+fn corners(polygon: &Polygon2D) -> Vec<P2> {
+    let points = polygon.points();
+    let count = points.len();
+    (0..count)
+        .filter(|&i| {
+            let before = points[i] - points[(i + count - 1) % count];
+            let after = points[(i + 1) % count] - points[i];
+            before.angle(&after) >= degrees_to_radians(MIN_BOSS_TURN)
+        })
+        .map(|i| points[i])
+        .collect()
+}
+
+/// Return the shortest distance from the point to the polygon's edges.
+fn distance_to_edges(polygon: &Polygon2D, point: P2) -> f32 {
+    let flat = |p: P2| P3::new(p.x, p.y, 0.);
+    let points = polygon.points();
+    (0..points.len())
+        .map(|i| {
+            let end = points[(i + 1) % points.len()];
+            Segment3::new(flat(points[i]), flat(end)).distance_to(flat(point))
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+fn round() -> OffsetJoin {
+    OffsetJoin::Round {
+        segments: CORNER_SEGMENTS,
+    }
+}
+
+fn offset(
+    polygon: &Polygon2D,
+    delta: f32,
+    join: OffsetJoin,
+) -> Result<Polygon2D, ScadDotsError> {
+    let offset = polygon.offset(delta, join)?;
+    // An inset that's too large can flip a polygon over without changing its
+    // winding order, so check that it's still inside.
+    if delta < 0. && !offset.points().iter().all(|p| polygon.contains(*p)) {
+        return Err(ScadDotsError::Dimension
+            .context("offset polygon turned inside out"));
+    }
+    Ok(offset)
+}
+
+fn extrude(
+    polygon: &Polygon2D,
+    bottom_z: f32,
+    thickness: f32,
+) -> Result<Extrusion, ScadDotsError> {
+    Extrusion::from_polygon(polygon.clone(), thickness, bottom_z)
+}
+
+/// Return a vertical screw hole whose opening is centered at the given point
+/// and height.
+fn screw_hole(
+    center: P2,
+    top_z: f32,
+    diameter: f32,
+    length: f32,
+    head: Option<HoleHead>,
+) -> Result<Tree, ScadDotsError> {
+    let hole = ScrewHole::new(ScrewHoleSpec {
+        pos: P3::new(center.x, center.y, top_z),
+        align: CylinderAlign::EndCenter(C1::P1),
+        diameter,
+        length,
+        head,
+        rot: R3::identity(),
+    })
+    .context("invalid enclosure screw hole")?;
+    Ok(hole.into())
+}
@@ -17,11 +17,27 @@ pub enum ScadDotsError {
     /// (probably when using the scad-dots test harness).
     External(Box<Error>),
     Ratio(f32),
+    /// A number was NaN or infinite.
+    Numeric,
     Io(io::Error),
+    /// The openscad program failed, with the given error output.
+    Openscad(String),
+    /// A scene file couldn't be deserialized, with the deserializer's
+    /// message.
+    Scene(String),
     Context {
         message: String,
         cause: Box<ScadDotsError>,
     },
+    /// Creating a part failed. It says what kind of part it was, like
+    /// `cuboid`, the name it was given (if any), and the debug representation
+    /// of the spec it was created from.
+    Part {
+        kind: String,
+        name: Option<String>,
+        spec: String,
+        cause: Box<ScadDotsError>,
+    },
 }
 
 pub trait ResultExt<T> {
@@ -67,12 +83,37 @@ impl ScadDotsError {
     {
         self.context(&message_creator())
     }
+
+    /// Wrap the error with the kind of part that failed, its name if it has
+    /// one, and the spec it was created from.
+    pub fn in_part<T>(self, kind: &str, name: Option<&str>, spec: &T) -> Self
+    where
+        T: fmt::Debug,
+    {
+        ScadDotsError::Part {
+            kind: kind.to_owned(),
+            name: name.map(|name| name.to_owned()),
+            spec: format!("{:?}", spec),
+            cause: Box::new(self),
+        }
+    }
+
+    /// Return the innermost error, skipping past any context and part
+    /// information wrapped around it.
+    pub fn root(&self) -> &Self {
+        match self {
+            ScadDotsError::Context { cause, .. }
+            | ScadDotsError::Part { cause, .. } => cause.root(),
+            _ => self,
+        }
+    }
 }
 
 impl Error for ScadDotsError {
     fn cause(&self) -> Option<&Error> {
         match self {
-            ScadDotsError::Context { ref cause, .. } => Some(cause),
+            ScadDotsError::Context { ref cause, .. }
+            | ScadDotsError::Part { ref cause, .. } => Some(cause),
             ScadDotsError::Io(ref cause) => Some(cause),
             _ => None,
         }
@@ -90,6 +131,12 @@ impl fmt::Display for ScadDotsError {
         match self {
             ScadDotsError::Ratio(x) => write!(f, "Invalid ratio: {}", x),
             ScadDotsError::Io(err) => write!(f, "Input/output error: {}", err),
+            ScadDotsError::Openscad(output) => {
+                write!(f, "OpenSCAD failed:\n{}", output)
+            }
+            ScadDotsError::Scene(message) => {
+                write!(f, "Invalid scene: {}", message)
+            }
             ScadDotsError::Rotation => write!(f, "Failed to compute rotation"),
             ScadDotsError::Chain => {
                 write!(f, "Need at least 2 elements to chain")
@@ -99,6 +146,9 @@ impl fmt::Display for ScadDotsError {
                 write!(f, "A Midpoint can only be made from 2 Corners.")
             }
             ScadDotsError::Dimension => write!(f, "Invalid dimensions"),
+            ScadDotsError::Numeric => {
+                write!(f, "Number is NaN or infinite")
+            }
             ScadDotsError::Args => write!(f, "Invalid argument(s)"),
             ScadDotsError::TestView => write!(
                 f,
@@ -117,6 +167,18 @@ impl fmt::Display for ScadDotsError {
             ScadDotsError::Context { message, cause } => {
                 write!(f, "{}\n  caused by: {}", message, cause)
             }
+            ScadDotsError::Part {
+                kind,
+                name,
+                spec,
+                cause,
+            } => {
+                write!(f, "{}", kind)?;
+                if let Some(name) = name {
+                    write!(f, " '{}'", name)?;
+                }
+                write!(f, ": {}\n  spec: {}", cause, spec)
+            }
         }
     }
 }
@@ -0,0 +1,105 @@
+use std::fs;
+
+use core::utils::P2;
+use core::Extrusion;
+use emit::num;
+use errors::{ResultExt, ScadDotsError};
+
+/// The file format to use for a 2D outline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlineFormat {
+    /// An SVG image, with one path per contour. The units are millimeters,
+    /// and the y axis points up like in OpenSCAD.
+    Svg,
+    /// An AutoCAD R12 DXF drawing, with one closed polyline per contour.
+    Dxf,
+}
+
+/// Return the code for a file containing the outline of the extrusion's
+/// perimeter and holes, for laser cutting or other 2D tools. To export a
+/// closed chain of dots, use `Extrusion::from_dot_centers()` first.
+pub fn outline_code(
+    extrusion: &Extrusion,
+    format: OutlineFormat,
+) -> Result<String, ScadDotsError> {
+    let contours = contours(extrusion);
+    match format {
+        OutlineFormat::Svg => svg(&contours),
+        OutlineFormat::Dxf => dxf(&contours),
+    }
+}
+
+/// Write the extrusion's outline to a file. See `outline_code()`.
+pub fn outline_to_file(
+    extrusion: &Extrusion,
+    path: String,
+    format: OutlineFormat,
+) -> Result<(), ScadDotsError> {
+    let code = outline_code(extrusion, format)?;
+    fs::write(&path, code)
+        .with_context(|| format!("failed to write outline file '{}'", path))
+}
+
+/// Return the perimeter followed by the holes.
+fn contours(extrusion: &Extrusion) -> Vec<&[P2]> {
+    let mut contours = vec![extrusion.perimeter.points()];
+    contours.extend(extrusion.holes.iter().map(|hole| hole.points()));
+    contours
+}
+
+fn svg(contours: &[&[P2]]) -> Result<String, ScadDotsError> {
+    let points = contours.iter().flat_map(|contour| contour.iter());
+    let (mut min, mut max) = (contours[0][0], contours[0][0]);
+    for p in points {
+        min = P2::new(min.x.min(p.x), min.y.min(p.y));
+        max = P2::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    let (width, height) = (max.x - min.x, max.y - min.y);
+    let mut data = String::new();
+    for contour in contours {
+        for (i, p) in contour.iter().enumerate() {
+            // Flip the y axis, since it points down in SVG.
+            data.push_str(&format!(
+                "{}{},{} ",
+                if i == 0 { "M" } else { "L" },
+                num(p.x - min.x)?,
+                num(max.y - p.y)?
+            ));
+        }
+        data.push_str("Z ");
+    }
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" \
+         height=\"{h}mm\" viewBox=\"0 0 {w} {h}\">\n\
+         <path d=\"{d}\" fill=\"none\" stroke=\"black\" \
+         stroke-width=\"0.1\"/>\n\
+         </svg>\n",
+        w = num(width)?,
+        h = num(height)?,
+        d = data.trim_end()
+    ))
+}
+
+fn dxf(contours: &[&[P2]]) -> Result<String, ScadDotsError> {
+    // An R12 file, which most 2D tools can read.
+    let mut code = String::from(
+        "0\nSECTION\n2\nHEADER\n9\n$ACADVER\n1\nAC1009\n0\nENDSEC\n\
+         0\nSECTION\n2\nENTITIES\n",
+    );
+    for contour in contours {
+        // A closed polyline on layer 0. Its own location is a dummy point
+        // that R12 requires before the vertices.
+        code.push_str("0\nPOLYLINE\n8\n0\n66\n1\n10\n0\n20\n0\n30\n0\n70\n1\n");
+        for p in contour.iter() {
+            code.push_str(&format!(
+                "0\nVERTEX\n8\n0\n10\n{}\n20\n{}\n30\n0\n",
+                num(p.x)?,
+                num(p.y)?
+            ));
+        }
+        code.push_str("0\nSEQEND\n");
+    }
+    code.push_str("0\nENDSEC\n0\nEOF\n");
+    Ok(code)
+}
@@ -0,0 +1,160 @@
+use core::utils::{Axis, Corner1 as C1, P3, R3, V3};
+use core::{Cylinder, CylinderAlign, CylinderSpec, Tree};
+
+use errors::ScadDotsError;
+
+/// The fraction of the countersink depth used for the thickness of the disc at
+/// its wide end. The countersink cone is approximated by the hull of 2 discs.
+const COUNTERSINK_DISC_RATIO: f32 = 0.01;
+
+/// The negative space for a screw, to be subtracted from a solid. The default
+/// orientation is for the hole's axis to be the z axis, with the opening
+/// (where the screw head goes) at the top end and the tip at the bottom end.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrewHole {
+    pub center_bot_pos: P3,
+    /// The diameter of the shaft of the hole.
+    pub diameter: f32,
+    /// The total length of the hole, including the head recess.
+    pub length: f32,
+    pub head: Option<HoleHead>,
+    pub rot: R3,
+}
+
+/// Specify a ScrewHole. The alignment points are the same as a Cylinder's:
+/// `CylinderAlign::EndCenter(C1::P1)` is the center of the hole's opening.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrewHoleSpec {
+    pub pos: P3,
+    pub align: CylinderAlign,
+    pub diameter: f32,
+    pub length: f32,
+    pub head: Option<HoleHead>,
+    pub rot: R3,
+}
+
+/// A wider recess at the opening of a ScrewHole, for the screw's head.
+#[derive(Debug, Clone, Copy)]
+pub enum HoleHead {
+    /// A 90 degree cone for a flat-head screw, with the given diameter at the
+    /// opening.
+    Countersink { diameter: f32 },
+    /// A cylindrical recess for a socket-head screw.
+    Counterbore { diameter: f32, depth: f32 },
+}
+
+impl ScrewHole {
+    /// Create a new screw hole.
+    pub fn new(spec: ScrewHoleSpec) -> Result<Self, ScadDotsError> {
+        if spec.diameter <= 0. || spec.length <= 0. {
+            return Err(ScadDotsError::Dimension
+                .context("ScrewHole diameter and length must be positive"));
+        }
+        if let Some(head) = spec.head {
+            if head.diameter() <= spec.diameter {
+                return Err(ScadDotsError::Dimension
+                    .context("ScrewHole head must be wider than the shaft"));
+            }
+            let depth = head.depth(spec.diameter);
+            if depth <= 0. || depth > spec.length {
+                return Err(ScadDotsError::Dimension.context(
+                    "ScrewHole head depth must be positive and no larger \
+                     than the hole length",
+                ));
+            }
+        }
+        Ok(Self {
+            center_bot_pos: spec.center_bot_pos(),
+            diameter: spec.diameter,
+            length: spec.length,
+            head: spec.head,
+            rot: spec.rot,
+        })
+    }
+
+    pub fn pos(&self, align: CylinderAlign) -> P3 {
+        self.shaft().pos(align)
+    }
+
+    /// Return a unit vector pointing from the tip of the hole to its opening.
+    pub fn unit_axis(&self) -> V3 {
+        let z: V3 = Axis::Z.into();
+        self.rot * z
+    }
+
+    fn shaft(&self) -> Cylinder {
+        self.disc(self.diameter, 0., self.length)
+    }
+
+    /// Return a cylinder coaxial with the hole, starting `start` above its
+    /// tip.
+    fn disc(&self, diameter: f32, start: f32, height: f32) -> Cylinder {
+        Cylinder::new(CylinderSpec {
+            pos: self.center_bot_pos + start * self.unit_axis(),
+            align: CylinderAlign::EndCenter(C1::P0),
+            diameter,
+            height,
+            rot: self.rot,
+        })
+    }
+
+    fn head_recess(&self, head: HoleHead) -> Tree {
+        let depth = head.depth(self.diameter);
+        let start = self.length - depth;
+        match head {
+            HoleHead::Counterbore { diameter, .. } => {
+                self.disc(diameter, start, depth).into()
+            }
+            HoleHead::Countersink { diameter } => {
+                let thickness = COUNTERSINK_DISC_RATIO * depth;
+                hull![
+                    self.disc(self.diameter, start, depth),
+                    self.disc(diameter, self.length - thickness, thickness),
+                ]
+            }
+        }
+    }
+}
+
+impl From<ScrewHole> for Tree {
+    fn from(hole: ScrewHole) -> Tree {
+        match hole.head {
+            Some(head) => union![hole.shaft(), hole.head_recess(head)],
+            None => hole.shaft().into(),
+        }
+    }
+}
+
+impl ScrewHoleSpec {
+    fn center_bot_pos(&self) -> P3 {
+        // A ScrewHole has the same alignment points as its shaft.
+        Cylinder::new(CylinderSpec {
+            pos: self.pos,
+            align: self.align,
+            diameter: self.diameter,
+            height: self.length,
+            rot: self.rot,
+        })
+        .center_bot_pos
+    }
+}
+
+impl HoleHead {
+    pub fn diameter(self) -> f32 {
+        match self {
+            HoleHead::Countersink { diameter }
+            | HoleHead::Counterbore { diameter, .. } => diameter,
+        }
+    }
+
+    /// Return how far the recess extends into the hole, for a hole with the
+    /// given shaft diameter.
+    pub fn depth(self, shaft_diameter: f32) -> f32 {
+        match self {
+            HoleHead::Countersink { diameter } => {
+                (diameter - shaft_diameter) / 2.
+            }
+            HoleHead::Counterbore { depth, .. } => depth,
+        }
+    }
+}
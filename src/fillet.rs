@@ -0,0 +1,193 @@
+use std::f32::EPSILON;
+
+use nalgebra::{Matrix3, Rotation3};
+
+use core::utils::{Corner1 as C1, Corner3 as C3, P3, R3, V3};
+use core::{
+    Cylinder, CylinderAlign, CylinderSpec, Dot, DotShape, DotSpec, Tree,
+};
+use post::{Post, PostAlign};
+use rect::{Rect, RectAlign};
+
+use errors::ScadDotsError;
+
+/// The largest allowed dot product between the unit vectors of a Fillet, for
+/// them to still count as perpendicular.
+const MAX_SKEW: f32 = 0.0001;
+
+/// A concave quarter-round fillet that fills in the inside corner along a
+/// straight edge, where two perpendicular surfaces meet. It's made by
+/// subtracting a cylinder from a square block that sits in the corner.
+#[derive(Debug, Clone, Copy)]
+pub struct Fillet {
+    start: P3,
+    end: P3,
+    dir_a: V3,
+    dir_b: V3,
+    radius: f32,
+    /// The dots at the start and end of the square block in the corner.
+    block: (Dot, Dot),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FilletSpec {
+    /// The ends of the edge where the two surfaces meet.
+    pub start: P3,
+    pub end: P3,
+    /// The directions pointing away from the edge along each of the 2
+    /// surfaces. They must be perpendicular to each other and to the edge.
+    pub dirs: (V3, V3),
+    pub radius: f32,
+}
+
+impl Fillet {
+    /// Create a new fillet.
+    pub fn new(spec: FilletSpec) -> Result<Self, ScadDotsError> {
+        let edge = spec.end - spec.start;
+        if !(spec.radius > 0.) {
+            return Err(ScadDotsError::Dimension
+                .context("Fillet radius must be positive"));
+        }
+        if edge.norm() < spec.radius {
+            return Err(ScadDotsError::Dimension
+                .context("Fillet edge can't be shorter than the radius"));
+        }
+        let unit_edge = edge.normalize();
+        let unit = |dir: V3| {
+            dir.try_normalize(EPSILON).ok_or_else(|| {
+                ScadDotsError::Dimension
+                    .context("Fillet directions can't be zero vectors")
+            })
+        };
+        let mut dir_a = unit(spec.dirs.0)?;
+        let mut dir_b = unit(spec.dirs.1)?;
+        if dir_a.dot(&dir_b).abs() > MAX_SKEW
+            || dir_a.dot(&unit_edge).abs() > MAX_SKEW
+            || dir_b.dot(&unit_edge).abs() > MAX_SKEW
+        {
+            return Err(ScadDotsError::Args.context(
+                "Fillet directions must be perpendicular to each other and \
+                 to the edge",
+            ));
+        }
+        // The fillet is symmetric, so swap the directions if needed to make
+        // them form a right-handed frame with the edge.
+        if dir_a.cross(&dir_b).dot(&unit_edge) < 0. {
+            ::std::mem::swap(&mut dir_a, &mut dir_b);
+        }
+        let rot = frame_rot(dir_a, dir_b, unit_edge);
+        let dot = |pos: P3, corner: C3| {
+            Dot::try_new(DotSpec {
+                pos,
+                align: corner.into(),
+                size: spec.radius,
+                rot,
+                shape: DotShape::Cube,
+            })
+        };
+        Ok(Self {
+            start: spec.start,
+            end: spec.end,
+            dir_a,
+            dir_b,
+            radius: spec.radius,
+            block: (dot(spec.start, C3::P000)?, dot(spec.end, C3::P001)?),
+        })
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn length(&self) -> f32 {
+        (self.end - self.start).norm()
+    }
+
+    /// Return the rotation that takes the x, y, and z axes to the 2 surface
+    /// directions and the direction of the edge.
+    fn rot(&self) -> R3 {
+        let unit_edge = (self.end - self.start).normalize();
+        frame_rot(self.dir_a, self.dir_b, unit_edge)
+    }
+
+    fn block(&self) -> Tree {
+        hull![self.block.0, self.block.1]
+    }
+
+    fn cutter(&self) -> Cylinder {
+        // Make the cylinder longer than the edge, so it cleanly cuts through
+        // the ends of the block.
+        let unit_edge = (self.end - self.start).normalize();
+        Cylinder::new(CylinderSpec {
+            pos: self.start + self.radius * (self.dir_a + self.dir_b)
+                - self.radius * unit_edge,
+            align: CylinderAlign::EndCenter(C1::P0),
+            diameter: 2. * self.radius,
+            height: self.length() + 2. * self.radius,
+            rot: self.rot(),
+        })
+    }
+}
+
+/// Return the rotation that takes the x, y, and z axes to the given
+/// perpendicular unit vectors.
+fn frame_rot(x: V3, y: V3, z: V3) -> R3 {
+    let matrix = Matrix3::from_columns(&[x, y, z]);
+    R3::from_rotation_matrix(&Rotation3::from_matrix_unchecked(matrix))
+}
+
+impl From<Fillet> for Tree {
+    fn from(fillet: Fillet) -> Tree {
+        diff![fillet.block(), fillet.cutter()]
+    }
+}
+
+impl FilletSpec {
+    /// Place the fillet along the edge between the same corner of 2 Dots.
+    pub fn along_dots(
+        a: &Dot,
+        b: &Dot,
+        corner: C3,
+        dirs: (V3, V3),
+        radius: f32,
+    ) -> Self {
+        Self {
+            start: a.pos(corner),
+            end: b.pos(corner),
+            dirs,
+            radius,
+        }
+    }
+
+    /// Place the fillet along the edge between 2 outer corners of a Post.
+    pub fn along_post(
+        post: &Post,
+        a: C3,
+        b: C3,
+        dirs: (V3, V3),
+        radius: f32,
+    ) -> Self {
+        Self {
+            start: post.pos(PostAlign::outside(a)),
+            end: post.pos(PostAlign::outside(b)),
+            dirs,
+            radius,
+        }
+    }
+
+    /// Place the fillet along the edge between 2 outer corners of a Rect.
+    pub fn along_rect(
+        rect: &Rect,
+        a: C3,
+        b: C3,
+        dirs: (V3, V3),
+        radius: f32,
+    ) -> Self {
+        Self {
+            start: rect.pos(RectAlign::outside(a)),
+            end: rect.pos(RectAlign::outside(b)),
+            dirs,
+            radius,
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::f32::consts::PI;
+
+use core::utils::{check_finite, cos_deg, degrees_to_radians, sin_deg, P2, V2};
+use core::{Extrusion, Polygon2D};
+use errors::{ResultExt, ScadDotsError};
+
+/// The most common pressure angle for spur gears, in degrees.
+pub const STANDARD_PRESSURE_ANGLE: f32 = 20.;
+
+/// How many points are used for each side of a tooth.
+const FLANK_POINTS: usize = 8;
+/// How many points are used for the top of each tooth, and for the bottom of
+/// each gap between teeth.
+const LAND_POINTS: usize = 5;
+
+/// Specify an involute spur gear. Two gears mesh if they have the same module
+/// and pressure angle. The gear is centered on the origin, with a tooth
+/// pointing along the +x axis.
+///
+/// The profile is the plain involute, without undercutting at the root. With
+/// fewer than about 17 teeth, a real gear would be undercut there to clear
+/// the mating gear's tips, so small gears may bind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GearSpec {
+    /// The pitch diameter divided by the number of teeth, in millimeters. The
+    /// teeth stick out this far past the pitch circle.
+    pub module: f32,
+    pub teeth: usize,
+    /// The angle between the line of action and the tangent to the pitch
+    /// circle, in degrees.
+    pub pressure_angle: f32,
+}
+
+impl GearSpec {
+    /// The diameter of the circle that rolls without slipping against the
+    /// mating gear's pitch circle.
+    pub fn pitch_diameter(&self) -> f32 {
+        self.module * self.teeth as f32
+    }
+
+    /// The diameter of the circle that the involute flanks unwind from.
+    pub fn base_diameter(&self) -> f32 {
+        self.pitch_diameter() * cos_deg(self.pressure_angle)
+    }
+
+    /// The diameter of the tips of the teeth.
+    pub fn outer_diameter(&self) -> f32 {
+        self.pitch_diameter() + 2. * self.module
+    }
+
+    /// The diameter of the bottoms of the gaps between teeth. It leaves a
+    /// quarter module of clearance under the mating gear's tips.
+    pub fn root_diameter(&self) -> f32 {
+        self.pitch_diameter() - 2.5 * self.module
+    }
+
+    /// The angle between neighboring teeth, in degrees.
+    pub fn tooth_angle(&self) -> f32 {
+        360. / self.teeth as f32
+    }
+
+    /// Return the outline of the gear.
+    pub fn profile(&self) -> Result<Polygon2D, ScadDotsError> {
+        self.check()?;
+        let base = self.base_diameter() / 2.;
+        let root = self.root_diameter() / 2.;
+        let outer = self.outer_diameter() / 2.;
+        let tip_angle = self.half_thickness(outer);
+        if tip_angle <= 0. {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!("gear with {} teeth has pointed teeth", self.teeth)
+            }));
+        }
+
+        // The flank is an involute above the base circle, and a radial line
+        // below it.
+        let start = root.max(base);
+        let mut flank = Vec::new();
+        if root < base {
+            flank.push((root, self.half_thickness(base)));
+        }
+        for i in 0..FLANK_POINTS {
+            let r =
+                start + (outer - start) * i as f32 / (FLANK_POINTS - 1) as f32;
+            flank.push((r, self.half_thickness(r)));
+        }
+        let root_angle = flank[0].1;
+        let gap_angle = 2. * PI / self.teeth as f32 - 2. * root_angle;
+        if gap_angle <= 0. {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "gear with {} teeth has no gaps between its teeth",
+                    self.teeth
+                )
+            }));
+        }
+
+        let mut points = Vec::new();
+        for tooth in 0..self.teeth {
+            let center = 2. * PI * tooth as f32 / self.teeth as f32;
+            for &(r, half) in &flank {
+                points.push(polar(r, center - half));
+            }
+            for i in 1..LAND_POINTS - 1 {
+                let t = i as f32 / (LAND_POINTS - 1) as f32;
+                points.push(polar(
+                    outer,
+                    center - tip_angle + 2. * tip_angle * t,
+                ));
+            }
+            for &(r, half) in flank.iter().rev() {
+                points.push(polar(r, center + half));
+            }
+            for i in 1..LAND_POINTS - 1 {
+                let t = i as f32 / (LAND_POINTS - 1) as f32;
+                points.push(polar(root, center + root_angle + gap_angle * t));
+            }
+        }
+        Polygon2D::new(points).with_context(|| {
+            format!("failed to make profile for {} tooth gear", self.teeth)
+        })
+    }
+
+    /// Return the gear extruded up from the z=`bottom_z` plane.
+    pub fn extrusion(
+        &self,
+        thickness: f32,
+        bottom_z: f32,
+    ) -> Result<Extrusion, ScadDotsError> {
+        Extrusion::from_polygon(self.profile()?, thickness, bottom_z)
+    }
+
+    fn check(&self) -> Result<(), ScadDotsError> {
+        check_finite(
+            "gear spec",
+            [self.module, self.pressure_angle].iter().cloned(),
+        )?;
+        if self.module <= 0. || self.teeth < 3 {
+            return Err(ScadDotsError::Dimension.context(
+                "gear module must be positive and it needs at least 3 teeth",
+            ));
+        }
+        if self.pressure_angle <= 0. || self.pressure_angle >= 45. {
+            return Err(ScadDotsError::Dimension.context(
+                "gear pressure angle must be between 0 and 45 degrees",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Return half of the angle that a tooth spans at the given radius, in
+    /// radians. The radius must be on or outside the base circle.
+    fn half_thickness(&self, radius: f32) -> f32 {
+        let base = self.base_diameter() / 2.;
+        let pressure = degrees_to_radians(self.pressure_angle);
+        let local_pressure = (base / radius).min(1.).acos();
+        PI / (2. * self.teeth as f32) + involute(pressure)
+            - involute(local_pressure)
+    }
+}
+
+/// Return the distance between the centers of two meshing gears.
+pub fn center_distance(
+    a: &GearSpec,
+    b: &GearSpec,
+) -> Result<f32, ScadDotsError> {
+    a.check()?;
+    b.check()?;
+    if a.module != b.module || a.pressure_angle != b.pressure_angle {
+        return Err(ScadDotsError::Args.context(
+            "gears must have the same module and pressure angle to mesh",
+        ));
+    }
+    Ok((a.pitch_diameter() + b.pitch_diameter()) / 2.)
+}
+
+/// Place gear `b` so it meshes with gear `a`, which is centered at `a_center`
+/// and not rotated. The direction from `a`'s center to `b`'s center is given
+/// in degrees counterclockwise from the +x axis. Return `b`'s center, and how
+/// many degrees to rotate it counterclockwise about its center so its teeth
+/// fit between `a`'s. The rotation can be applied with
+/// `Extrusion::rotate_z()` before moving it to the center.
+pub fn mesh_position(
+    a: &GearSpec,
+    a_center: P2,
+    b: &GearSpec,
+    direction: f32,
+) -> Result<(P2, f32), ScadDotsError> {
+    let distance = center_distance(a, b)?;
+    let center =
+        a_center + distance * V2::new(cos_deg(direction), sin_deg(direction));
+    // If `a` had a tooth pointing at `b`, `b` would need a gap pointing back
+    // at `a`, which is half a tooth past one of its teeth. Rotating `a` back
+    // to where it actually is turns `b` the other way, scaled by the ratio.
+    let ratio = a.teeth as f32 / b.teeth as f32;
+    let degrees = direction + 180. + b.tooth_angle() / 2. + direction * ratio;
+    Ok((center, degrees % 360.))
+}
+
+fn involute(angle: f32) -> f32 {
+    angle.tan() - angle
+}
+
+fn polar(radius: f32, radians: f32) -> P2 {
+    P2::new(radius * radians.cos(), radius * radians.sin())
+}
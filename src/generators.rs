@@ -0,0 +1,172 @@
+use core::utils::{axis_degrees, cos_deg, sin_deg, P3, R3, V3};
+use core::{chain, Dot, DotSpec, Tree};
+use errors::ScadDotsError;
+
+/// Return `count` dots evenly spaced around a full circle, ready for
+/// `chain_loop()`. Each dot's `dot_spec.align` point is placed on the circle,
+/// and `dot_spec.pos` is ignored. The dots are turned around the normal along
+/// with their positions, so they all face the same way relative to the
+/// center; `dot_spec.rot` is the rotation of the first one. See
+/// `arc_of_dots()` for where the circle starts.
+pub fn circle_of_dots(
+    center: P3,
+    radius: f32,
+    normal: V3,
+    count: usize,
+    dot_spec: DotSpec,
+) -> Result<Vec<Dot>, ScadDotsError> {
+    if count == 0 {
+        return Err(
+            ScadDotsError::Args.context("circle of dots needs at least 1 dot")
+        );
+    }
+    let step = 360. / count as f32;
+    let ring = Ring::new(center, radius, normal)?;
+    Ok((0..count)
+        .map(|i| ring.dot(i as f32 * step, dot_spec))
+        .collect())
+}
+
+/// Return `count` dots evenly spaced along a circular arc, including both
+/// ends, ready for `chain()`. The arc turns counterclockwise (by the right
+/// hand rule) around the normal from `start_deg` to `end_deg`. An angle of 0
+/// points along the x axis, flattened onto the circle's plane, or along the y
+/// axis if the normal is parallel to the x axis. The dots are placed and
+/// turned like in `circle_of_dots()`.
+pub fn arc_of_dots(
+    center: P3,
+    radius: f32,
+    normal: V3,
+    start_deg: f32,
+    end_deg: f32,
+    count: usize,
+    dot_spec: DotSpec,
+) -> Result<Vec<Dot>, ScadDotsError> {
+    if count < 2 {
+        return Err(
+            ScadDotsError::Args.context("arc of dots needs at least 2 dots")
+        );
+    }
+    if !start_deg.is_finite() || !end_deg.is_finite() {
+        return Err(ScadDotsError::Args.context("arc angles must be finite"));
+    }
+    let step = (end_deg - start_deg) / (count - 1) as f32;
+    let ring = Ring::new(center, radius, normal)?;
+    Ok((0..count)
+        .map(|i| ring.dot(start_deg + i as f32 * step, dot_spec))
+        .collect())
+}
+
+/// Return dots along a helix around the axis line that starts at
+/// `axis_start` and goes in the direction of `axis_dir`, ready for `chain()`.
+/// The helix turns counterclockwise (by the right hand rule) around the axis
+/// as it advances by `pitch` along it per turn, starting at angle 0 (see
+/// `arc_of_dots()`). There are `count_per_turn` segments per turn, rounded to
+/// fit a whole number of segments in total, and the dots are placed and
+/// turned like in `circle_of_dots()`. Use a negative pitch for a left-handed
+/// helix.
+pub fn helix_of_dots(
+    axis_start: P3,
+    axis_dir: V3,
+    radius: f32,
+    pitch: f32,
+    turns: f32,
+    count_per_turn: usize,
+    dot_spec: DotSpec,
+) -> Result<Vec<Dot>, ScadDotsError> {
+    if !(turns > 0.) || !pitch.is_finite() {
+        return Err(ScadDotsError::Args.context(
+            "helix turns must be positive, and pitch must be finite",
+        ));
+    }
+    if count_per_turn < 3 {
+        return Err(
+            ScadDotsError::Args.context("helix needs at least 3 dots per turn")
+        );
+    }
+    let ring = Ring::new(axis_start, radius, axis_dir)?;
+    let segments = (turns * count_per_turn as f32).round().max(1.) as usize;
+    Ok((0..(segments + 1))
+        .map(|i| {
+            let turn = turns * i as f32 / segments as f32;
+            ring.dot(turn * 360., dot_spec)
+                .translate(ring.normal * pitch * turn)
+        })
+        .collect())
+}
+
+/// Return a solid helix made by chaining together the dots from
+/// `helix_of_dots()`, like a spring.
+pub fn helix(
+    axis_start: P3,
+    axis_dir: V3,
+    radius: f32,
+    pitch: f32,
+    turns: f32,
+    count_per_turn: usize,
+    dot_spec: DotSpec,
+) -> Result<Tree, ScadDotsError> {
+    let dots = helix_of_dots(
+        axis_start,
+        axis_dir,
+        radius,
+        pitch,
+        turns,
+        count_per_turn,
+        dot_spec,
+    )?;
+    chain(&dots)
+}
+
+/// A circle in 3D, with a direction for the angle 0.
+struct Ring {
+    center: P3,
+    radius: f32,
+    normal: V3,
+    start: V3,
+}
+
+impl Ring {
+    fn new(center: P3, radius: f32, normal: V3) -> Result<Self, ScadDotsError> {
+        if !(radius > 0.) {
+            return Err(ScadDotsError::Dimension
+                .context("circle radius must be positive"));
+        }
+        let normal = normal.try_normalize(0.).ok_or_else(|| {
+            ScadDotsError::Args
+                .context("circle normal can't be the zero vector")
+        })?;
+        let flatten = |v: V3| (v - normal * v.dot(&normal)).try_normalize(1e-6);
+        let start = flatten(V3::x())
+            .or_else(|| flatten(V3::y()))
+            .expect("x and y axes can't both be parallel to the normal");
+        Ok(Self {
+            center,
+            radius,
+            normal,
+            start,
+        })
+    }
+
+    /// Return the point at the given angle around the circle.
+    fn point(&self, degrees: f32) -> P3 {
+        let side = self.normal.cross(&self.start);
+        self.center
+            + self.radius
+                * (cos_deg(degrees) * self.start + sin_deg(degrees) * side)
+    }
+
+    /// Return the rotation that turns things from the angle 0 to the given
+    /// angle.
+    fn turn(&self, degrees: f32) -> R3 {
+        axis_degrees(self.normal, degrees)
+    }
+
+    fn dot(&self, degrees: f32, spec: DotSpec) -> Dot {
+        Dot::new(DotSpec {
+            pos: self.point(degrees),
+            rot: self.turn(degrees) * spec.rot,
+            ..spec
+        })
+    }
+}
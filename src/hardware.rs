@@ -0,0 +1,328 @@
+//! Models of common off-the-shelf hardware, and the negative space to
+//! subtract from a part so the hardware fits. The dimensions are typical
+//! values from the ISO standards (ISO 4762 socket head cap screws, ISO 4032
+//! nuts, and ISO 7089 washers) and from common heat-set inserts, so check them
+//! against the actual parts before printing anything that needs a tight fit.
+
+use core::utils::{
+    check_finite, cos_deg, sin_deg, Corner1 as C1, CUT_OVERLAP, P2, P3, R3, V3,
+};
+use core::{Cylinder, CylinderAlign, CylinderSpec, Extrusion, Polygon2D, Tree};
+use errors::{ResultExt, ScadDotsError};
+
+/// The size of a metric screw, nut, washer, or insert.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricSize {
+    M2,
+    M3,
+    M4,
+    M5,
+    M6,
+    M8,
+}
+
+/// The dimensions of the hardware for one `MetricSize`, in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricDims {
+    /// The nominal diameter of the thread.
+    pub diameter: f32,
+    pub pitch: f32,
+    /// The diameter of a socket head cap screw's head.
+    pub head_diameter: f32,
+    pub head_height: f32,
+    /// The distance across the flats of a hex nut.
+    pub nut_width: f32,
+    pub nut_height: f32,
+    pub washer_inner_diameter: f32,
+    pub washer_outer_diameter: f32,
+    pub washer_thickness: f32,
+    /// The diameter of a hole the screw passes through freely.
+    pub clearance_diameter: f32,
+    /// The diameter of a hole the screw can cut its own thread into.
+    pub tap_diameter: f32,
+    /// The outer diameter of a heat-set insert's knurling.
+    pub insert_diameter: f32,
+    /// The diameter of the hole a heat-set insert is melted into.
+    pub insert_hole_diameter: f32,
+    pub insert_length: f32,
+}
+
+/// The size of a ball bearing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BearingSize {
+    /// 3mm bore, 10mm outer diameter, 4mm wide.
+    B623,
+    /// 5mm bore, 16mm outer diameter, 5mm wide.
+    B625,
+    /// 8mm bore, 22mm outer diameter, 7mm wide. The common skateboard
+    /// bearing.
+    B608,
+}
+
+/// A piece of hardware. Its axis is the z axis by default. Screws have their
+/// head at the top and their tip at z=0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hardware {
+    /// A socket head cap screw. The length doesn't include the head.
+    Screw {
+        size: MetricSize,
+        length: f32,
+    },
+    /// A hex nut, with a corner pointing along the x axis.
+    Nut(MetricSize),
+    Washer(MetricSize),
+    HeatSetInsert(MetricSize),
+    Bearing(BearingSize),
+}
+
+/// Specify where to put a piece of hardware. The alignment points are the
+/// same as a Cylinder's, for the cylinder around the hardware's model. For
+/// example, `CylinderAlign::EndCenter(C1::P1)` is the center of a screw's
+/// head, and `CylinderAlign::EndCenter(C1::P0)` is its tip.
+#[derive(Debug, Clone, Copy)]
+pub struct HardwareSpec {
+    pub pos: P3,
+    pub align: CylinderAlign,
+    pub rot: R3,
+}
+
+impl MetricSize {
+    pub fn dims(self) -> MetricDims {
+        // Each row of the table has the fields in the order they're declared.
+        let dims = |values: [f32; 14]| MetricDims {
+            diameter: values[0],
+            pitch: values[1],
+            head_diameter: values[2],
+            head_height: values[3],
+            nut_width: values[4],
+            nut_height: values[5],
+            washer_inner_diameter: values[6],
+            washer_outer_diameter: values[7],
+            washer_thickness: values[8],
+            clearance_diameter: values[9],
+            tap_diameter: values[10],
+            insert_diameter: values[11],
+            insert_hole_diameter: values[12],
+            insert_length: values[13],
+        };
+        match self {
+            MetricSize::M2 => dims([
+                2., 0.4, 3.8, 2., 4., 1.6, 2.2, 5., 0.3, 2.4, 1.6, 3.6, 3.2, 4.,
+            ]),
+            MetricSize::M3 => dims([
+                3., 0.5, 5.5, 3., 5.5, 2.4, 3.2, 7., 0.5, 3.4, 2.5, 4.6, 4.,
+                5.7,
+            ]),
+            MetricSize::M4 => dims([
+                4., 0.7, 7., 4., 7., 3.2, 4.3, 9., 0.8, 4.5, 3.3, 6.3, 5.6, 8.1,
+            ]),
+            MetricSize::M5 => dims([
+                5., 0.8, 8.5, 5., 8., 4.7, 5.3, 10., 1., 5.5, 4.2, 7.1, 6.4,
+                9.5,
+            ]),
+            MetricSize::M6 => dims([
+                6., 1., 10., 6., 10., 5.2, 6.4, 12., 1.6, 6.6, 5., 8.7, 8.,
+                12.7,
+            ]),
+            MetricSize::M8 => dims([
+                8., 1.25, 13., 8., 13., 6.8, 8.4, 16., 1.6, 9., 6.8, 10.5, 9.7,
+                12.7,
+            ]),
+        }
+    }
+}
+
+impl BearingSize {
+    /// Return the bore diameter, outer diameter, and width.
+    pub fn dims(self) -> (f32, f32, f32) {
+        match self {
+            BearingSize::B623 => (3., 10., 4.),
+            BearingSize::B625 => (5., 16., 5.),
+            BearingSize::B608 => (8., 22., 7.),
+        }
+    }
+}
+
+impl Hardware {
+    /// Return the diameter of the cylinder around the model. For a nut, it's
+    /// the distance across the corners.
+    pub fn diameter(self) -> f32 {
+        match self {
+            Hardware::Screw { size, .. } => size.dims().head_diameter,
+            Hardware::Nut(size) => size.dims().nut_width / cos_deg(30.),
+            Hardware::Washer(size) => size.dims().washer_outer_diameter,
+            Hardware::HeatSetInsert(size) => size.dims().insert_diameter,
+            Hardware::Bearing(size) => size.dims().1,
+        }
+    }
+
+    /// Return the length of the model along its axis.
+    pub fn height(self) -> f32 {
+        match self {
+            Hardware::Screw { size, length } => {
+                length + size.dims().head_height
+            }
+            Hardware::Nut(size) => size.dims().nut_height,
+            Hardware::Washer(size) => size.dims().washer_thickness,
+            Hardware::HeatSetInsert(size) => size.dims().insert_length,
+            Hardware::Bearing(size) => size.dims().2,
+        }
+    }
+
+    /// Return a model of the hardware, for checking fits and for
+    /// visualizing an assembly. Threads aren't modeled.
+    pub fn model(self, spec: HardwareSpec) -> Result<Tree, ScadDotsError> {
+        self.check()?;
+        let height = self.height();
+        let local = match self {
+            Hardware::Screw { size, length } => {
+                let dims = size.dims();
+                union![
+                    disc(dims.diameter, 0., length),
+                    disc(dims.head_diameter, length, dims.head_height),
+                ]
+            }
+            Hardware::Nut(size) => {
+                let dims = size.dims();
+                diff![
+                    hexagon(dims.nut_width, 0., height)?,
+                    through_hole(dims.diameter, height),
+                ]
+            }
+            Hardware::Washer(size) => {
+                let dims = size.dims();
+                diff![
+                    disc(dims.washer_outer_diameter, 0., height),
+                    through_hole(dims.washer_inner_diameter, height),
+                ]
+            }
+            Hardware::HeatSetInsert(size) => {
+                let dims = size.dims();
+                diff![
+                    disc(dims.insert_diameter, 0., height),
+                    through_hole(dims.diameter, height),
+                ]
+            }
+            Hardware::Bearing(size) => {
+                let (bore, outer, _) = size.dims();
+                diff![disc(outer, 0., height), through_hole(bore, height)]
+            }
+        };
+        self.place(spec, local)
+    }
+
+    /// Return the negative space to subtract from a part so the hardware
+    /// fits in it, at the same position as `model()`. It's `gap` larger than
+    /// the model all around, except that a screw's shaft gets a clearance
+    /// hole and an insert gets the hole it's melted into. Nuts and washers
+    /// get a solid hole with no bore, so they can be dropped in from the side
+    /// or above.
+    pub fn clearance(
+        self,
+        spec: HardwareSpec,
+        gap: f32,
+    ) -> Result<Tree, ScadDotsError> {
+        self.check()?;
+        check_finite("hardware gap", Some(gap))?;
+        if gap < 0. {
+            return Err(ScadDotsError::Dimension
+                .context("hardware clearance gap can't be negative"));
+        }
+        let (bottom, height) = (-gap, self.height() + 2. * gap);
+        let local = match self {
+            Hardware::Screw { size, length } => {
+                let dims = size.dims();
+                union![
+                    disc(
+                        dims.clearance_diameter + 2. * gap,
+                        bottom,
+                        length + gap
+                    ),
+                    disc(
+                        dims.head_diameter + 2. * gap,
+                        length,
+                        dims.head_height + gap,
+                    ),
+                ]
+            }
+            Hardware::Nut(size) => {
+                hexagon(size.dims().nut_width + 2. * gap, bottom, height)?
+            }
+            Hardware::HeatSetInsert(size) => disc(
+                size.dims().insert_hole_diameter + 2. * gap,
+                bottom,
+                height,
+            ),
+            Hardware::Washer(_) | Hardware::Bearing(_) => {
+                disc(self.diameter() + 2. * gap, bottom, height)
+            }
+        };
+        self.place(spec, local)
+    }
+
+    fn check(self) -> Result<(), ScadDotsError> {
+        if let Hardware::Screw { length, .. } = self {
+            check_finite("screw length", Some(length))?;
+            if length <= 0. {
+                return Err(ScadDotsError::Dimension
+                    .context("screw length must be positive"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Move a tree built around the z axis, with the model's bottom at z=0,
+    /// to where the spec puts the model.
+    fn place(
+        self,
+        spec: HardwareSpec,
+        local: Tree,
+    ) -> Result<Tree, ScadDotsError> {
+        check_finite("hardware position", spec.pos.coords.iter().cloned())?;
+        let bounding = Cylinder::new(CylinderSpec {
+            pos: spec.pos,
+            align: spec.align,
+            diameter: self.diameter(),
+            height: self.height(),
+            rot: spec.rot,
+        });
+        let offset: V3 = bounding.center_bot_pos - P3::origin();
+        Ok(Tree::translate(offset, Tree::rotate(spec.rot, local)))
+    }
+}
+
+/// Return a cylinder around the z axis.
+fn disc(diameter: f32, bottom_z: f32, height: f32) -> Tree {
+    Cylinder::new(CylinderSpec {
+        pos: P3::new(0., 0., bottom_z),
+        align: CylinderAlign::EndCenter(C1::P0),
+        diameter,
+        height,
+        rot: R3::identity(),
+    })
+    .into()
+}
+
+/// Return a hole through a part of the given height, which extends past its
+/// ends so it cuts cleanly.
+fn through_hole(diameter: f32, height: f32) -> Tree {
+    disc(diameter, -CUT_OVERLAP, height + 2. * CUT_OVERLAP)
+}
+
+/// Return a hexagonal prism around the z axis, with the given distance
+/// across the flats and a corner pointing along the x axis.
+fn hexagon(
+    width: f32,
+    bottom_z: f32,
+    height: f32,
+) -> Result<Tree, ScadDotsError> {
+    let radius = width / 2. / cos_deg(30.);
+    let points = (0..6)
+        .map(|i| {
+            let degrees = 60. * i as f32;
+            P2::new(radius * cos_deg(degrees), radius * sin_deg(degrees))
+        })
+        .collect();
+    let polygon = Polygon2D::new(points).context("invalid hexagon")?;
+    Ok(Extrusion::from_polygon(polygon, height, bottom_z)?.into())
+}
@@ -1,26 +1,187 @@
-use std::fs::File;
+use std::env;
+use std::fs::{self, File};
 use std::io::{self, BufReader, Read, Write};
+#[cfg(unix)]
 use std::os::unix::process::CommandExt;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
 
+#[cfg(unix)]
 use libc;
+use png;
 
+use core::utils::V3;
 use core::Tree;
 use errors::{ResultExt, ScadDotsError};
 use render::{to_code, RenderQuality};
 
-use parse::scad_relative_eq;
+use parse::{scad_bounds, scad_diff, scad_relative_eq, DiffEntry};
 
 // static RENDER_OPTIONS: RenderQuality = RenderQuality::Test;
 pub static MAX_RELATIVE: f32 = 0.00001;
 
+/// Set this environment variable to the path of the openscad binary, if it's
+/// not named `openscad` or not on the `PATH`.
+pub static OPENSCAD_BIN_VAR: &str = "OPENSCAD_BIN";
+
+/// Set this environment variable to also save a colored report of the
+/// differences next to each incorrect model, as `tests/bad_models/<name>.diff`.
+/// View it with something like `less -R`.
+pub static DIFF_FILE_VAR: &str = "SCAD_DOTS_DIFF_FILE";
+
+/// Environment variables that override the `HarnessConfig` defaults in
+/// `HarnessConfig::from_env()`.
+pub static MODEL_DIR_VAR: &str = "SCAD_DOTS_MODEL_DIR";
+pub static BAD_MODEL_DIR_VAR: &str = "SCAD_DOTS_BAD_MODEL_DIR";
+pub static TMP_DIR_VAR: &str = "SCAD_DOTS_TMP_DIR";
+pub static MAX_RELATIVE_VAR: &str = "SCAD_DOTS_MAX_RELATIVE";
+
+/// Set this environment variable to `1` to replace the expected models of
+/// tests that fail with the actual ones, instead of failing. Review the
+/// changes with `git diff` before committing them.
+pub static UPDATE_GOLDEN_VAR: &str = "UPDATE_GOLDEN";
+
+/// How much a color channel can change before a pixel counts as different,
+/// to ignore antialiasing noise between screenshots.
+const PIXEL_TOLERANCE: u8 = 16;
+
+/// Where the harness keeps its models, which program it views them with, and
+/// how closely they need to match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HarnessConfig {
+    /// Where the expected models are loaded from and created in.
+    pub model_dir: PathBuf,
+    /// Where models that don't match the expected ones are saved.
+    pub bad_model_dir: PathBuf,
+    /// Where models are saved for viewing.
+    pub tmp_dir: PathBuf,
+    /// The program used to view models, normally openscad.
+    pub viewer: PathBuf,
+    /// The maximum relative difference between numbers in matching models.
+    pub max_relative: f32,
+    /// Whether to replace expected models that don't match (or don't exist
+    /// yet) during `Action::Test`, instead of failing.
+    pub update: bool,
+    /// The maximum fraction of pixels that can differ between screenshots of
+    /// matching models, in `Action::Screenshot`.
+    pub max_image_difference: f32,
+}
+
+impl Default for HarnessConfig {
+    fn default() -> Self {
+        HarnessConfig {
+            model_dir: PathBuf::from("tests/good_models"),
+            bad_model_dir: PathBuf::from("tests/bad_models"),
+            tmp_dir: PathBuf::from("tests/tmp"),
+            viewer: PathBuf::from("openscad"),
+            max_relative: MAX_RELATIVE,
+            update: false,
+            max_image_difference: 0.001,
+        }
+    }
+}
+
+impl HarnessConfig {
+    /// Return the default config, with each field overridden by its
+    /// environment variable if it's set. The viewer uses `OPENSCAD_BIN`.
+    pub fn from_env() -> Result<Self, ScadDotsError> {
+        let default = HarnessConfig::default();
+        let dir = |var: &str, default: PathBuf| {
+            env::var_os(var).map(PathBuf::from).unwrap_or(default)
+        };
+        let mut config = HarnessConfig {
+            model_dir: dir(MODEL_DIR_VAR, default.model_dir),
+            bad_model_dir: dir(BAD_MODEL_DIR_VAR, default.bad_model_dir),
+            tmp_dir: dir(TMP_DIR_VAR, default.tmp_dir),
+            viewer: openscad_bin(),
+            max_relative: default.max_relative,
+            update: env::var(UPDATE_GOLDEN_VAR)
+                .map(|value| value == "1")
+                .unwrap_or(default.update),
+            max_image_difference: default.max_image_difference,
+        };
+        if let Ok(value) = env::var(MAX_RELATIVE_VAR) {
+            config.max_relative = value
+                .parse()
+                .ok()
+                .filter(|max: &f32| *max >= 0. && max.is_finite())
+                .ok_or_else(|| {
+                    ScadDotsError::Args.with_context(|| {
+                        format!("invalid {}: '{}'", MAX_RELATIVE_VAR, value)
+                    })
+                })?;
+        }
+        Ok(config)
+    }
+
+    pub fn with_model_dir<P>(mut self, dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.model_dir = dir.into();
+        self
+    }
+
+    pub fn with_bad_model_dir<P>(mut self, dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.bad_model_dir = dir.into();
+        self
+    }
+
+    pub fn with_tmp_dir<P>(mut self, dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.tmp_dir = dir.into();
+        self
+    }
+
+    pub fn with_viewer<P>(mut self, viewer: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.viewer = viewer.into();
+        self
+    }
+
+    pub fn with_max_relative(mut self, max_relative: f32) -> Self {
+        self.max_relative = max_relative;
+        self
+    }
+
+    pub fn with_update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    pub fn with_max_image_difference(mut self, max_difference: f32) -> Self {
+        self.max_image_difference = max_difference;
+        self
+    }
+
+    /// Return the path of the model with the given name.
+    fn model_path(&self, name: &str, status: GoodOrBad) -> PathBuf {
+        let dir = match status {
+            GoodOrBad::Good => &self.model_dir,
+            GoodOrBad::Bad => &self.bad_model_dir,
+        };
+        dir.join(format!("{}.scad", name))
+    }
+}
+
 /// What action to perform on this test case.
 /// Normally, only `Test` will be used. Others are for temporary use.
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 pub enum Action {
     Test,
+    /// Like `Test`, but compare screenshots of the actual and expected
+    /// models rendered by openscad, instead of their code. It's slow, but it
+    /// compares the final geometry, so it catches differences that the code
+    /// comparison can't see.
+    Screenshot,
     Create,
     ViewBoth,
     Preview,
@@ -28,6 +189,15 @@ pub enum Action {
     PrintHigh,
 }
 
+/// The result of performing an action on a single model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Passed,
+    Failed,
+    /// The expected model was replaced, in update mode.
+    Updated,
+}
+
 #[derive(Debug, Clone, Copy)]
 enum GoodOrBad {
     Good,
@@ -37,23 +207,75 @@ enum GoodOrBad {
 ////////////////////////////////////////////////////////////////////////////////
 
 pub fn preview_model(tree: &Tree) -> Result<(), ScadDotsError> {
+    let config = HarnessConfig::from_env()?;
     let scad = render_model(tree, RenderQuality::Low)?;
-    let path = save_temp_file("preview", "", &scad)?;
-    view_in_openscad(&[path])
+    let path = save_temp_file(&config, "preview", "", &scad)?;
+    view_in_openscad(&config, &[path])
 }
 
+/// Check the model against its expected model, using the config from
+/// `HarnessConfig::from_env()`.
 pub fn check_model<F>(name: &str, action: Action, f: F)
 where
     F: Fn() -> Result<Tree, ScadDotsError>,
 {
-    if let Err(e) = test_helper(name, action, &f) {
+    match HarnessConfig::from_env() {
+        Ok(config) => check_model_with(&config, name, action, f),
+        Err(e) => {
+            println!("error: {}", e);
+            panic!("invalid harness config")
+        }
+    }
+}
+
+/// Like `check_model()`, but with the given config.
+pub fn check_model_with<F>(
+    config: &HarnessConfig,
+    name: &str,
+    action: Action,
+    f: F,
+) where
+    F: Fn() -> Result<Tree, ScadDotsError>,
+{
+    if let Err(e) = test_helper(config, name, action, &f) {
+        println!("error: {}", e);
+        panic!("returned error")
+    }
+}
+
+/// Like `check_model()`, but for test cases that produce multiple named
+/// parts. Each part is compared against its own expected model, named
+/// `<name>_<part name>`.
+pub fn check_models<F>(name: &str, action: Action, f: F)
+where
+    F: Fn() -> Result<Vec<(String, Tree)>, ScadDotsError>,
+{
+    match HarnessConfig::from_env() {
+        Ok(config) => check_models_with(&config, name, action, f),
+        Err(e) => {
+            println!("error: {}", e);
+            panic!("invalid harness config")
+        }
+    }
+}
+
+/// Like `check_models()`, but with the given config.
+pub fn check_models_with<F>(
+    config: &HarnessConfig,
+    name: &str,
+    action: Action,
+    f: F,
+) where
+    F: Fn() -> Result<Vec<(String, Tree)>, ScadDotsError>,
+{
+    if let Err(e) = test_parts_helper(config, name, action, &f) {
         println!("error: {}", e);
         panic!("returned error")
     }
 }
 
-// TODO let lib user control paths, somehow
 fn test_helper<F>(
+    config: &HarnessConfig,
     name: &str,
     action: Action,
     model_creator: F,
@@ -63,52 +285,149 @@ where
 {
     let tree =
         model_creator().context("failed to construct test case's model")?;
+    match check_tree(config, name, action, &tree)? {
+        Outcome::Passed => (),
+        Outcome::Failed => panic!("Models don't match"),
+        Outcome::Updated => println!("Updated expected model: {}", name),
+    }
+    finish_action(action)
+}
+
+fn test_parts_helper<F>(
+    config: &HarnessConfig,
+    name: &str,
+    action: Action,
+    parts_creator: F,
+) -> Result<(), ScadDotsError>
+where
+    F: Fn() -> Result<Vec<(String, Tree)>, ScadDotsError>,
+{
+    let parts =
+        parts_creator().context("failed to construct test case's parts")?;
+    let mut mismatched = Vec::new();
+    let mut updated = Vec::new();
+    for (part_name, tree) in parts {
+        let full_name = format!("{}_{}", name, part_name);
+        match check_tree(config, &full_name, action, &tree)? {
+            Outcome::Passed => (),
+            Outcome::Failed => mismatched.push(full_name),
+            Outcome::Updated => updated.push(full_name),
+        }
+    }
+    if !updated.is_empty() {
+        println!("Updated expected models: {}", updated.join(", "));
+    }
+    if !mismatched.is_empty() {
+        panic!("Models don't match: {}", mismatched.join(", "))
+    }
+    finish_action(action)
+}
+
+/// Perform the action on a single model, and return whether it matched the
+/// expected model if it was tested.
+fn check_tree(
+    config: &HarnessConfig,
+    name: &str,
+    action: Action,
+    tree: &Tree,
+) -> Result<Outcome, ScadDotsError> {
     match action {
         Action::PrintMedium => {
-            let actual = render_model(&tree, RenderQuality::Medium)?;
-            let path = save_temp_file("print-medium", name, &actual)?;
-            view_in_openscad(&[path])?;
+            let actual = render_model(tree, RenderQuality::Medium)?;
+            let path = save_temp_file(config, "print-medium", name, &actual)?;
+            view_in_openscad(config, &[path])?;
         }
         Action::PrintHigh => {
-            let actual = render_model(&tree, RenderQuality::High)?;
-            let path = save_temp_file("print-high", name, &actual)?;
-            view_in_openscad(&[path])?;
+            let actual = render_model(tree, RenderQuality::High)?;
+            let path = save_temp_file(config, "print-high", name, &actual)?;
+            view_in_openscad(config, &[path])?;
         }
         Action::ViewBoth => {
-            let actual = render_model(&tree, RenderQuality::Low)?;
+            let actual = render_model(tree, RenderQuality::Low)?;
             let mut paths = Vec::new();
-            paths.push(save_temp_file("actual", name, &actual)?);
-            if let Ok(expected) = load_model(name) {
-                paths.push(save_temp_file("expected", name, &expected)?);
+            paths.push(save_temp_file(config, "actual", name, &actual)?);
+            if let Ok(expected) = load_model(config, name) {
+                paths
+                    .push(save_temp_file(config, "expected", name, &expected)?);
             }
-            view_in_openscad(&paths)?;
-            return Err(ScadDotsError::TestView);
+            view_in_openscad(config, &paths)?;
         }
         Action::Preview => {
-            let actual = render_model(&tree, RenderQuality::Low)?;
-            let path = save_temp_file("actual", name, &actual)?;
-            view_in_openscad(&[path])?;
+            let actual = render_model(tree, RenderQuality::Low)?;
+            let path = save_temp_file(config, "actual", name, &actual)?;
+            view_in_openscad(config, &[path])?;
             // Don't check if there's a matching expected model
         }
         Action::Create => {
-            let actual = render_model(&tree, RenderQuality::Low)?;
-            save_file(&name_to_path(name, GoodOrBad::Good), &actual)?;
-            return Err(ScadDotsError::TestCreate);
+            let actual = render_model(tree, RenderQuality::Low)?;
+            save_file(&config.model_path(name, GoodOrBad::Good), &actual)?;
         }
         Action::Test => {
-            let actual = render_model(&tree, RenderQuality::Low)?;
-            let expected = load_model(name)
+            let actual = render_model(tree, RenderQuality::Low)?;
+            let expected = match load_model(config, name) {
+                Err(_) if config.update => None,
+                expected => Some(
+                    expected.context("failed to load the expected model")?,
+                ),
+            };
+            if let Some(ref expected) = expected {
+                if scad_relative_eq(&actual, expected, config.max_relative)? {
+                    return Ok(Outcome::Passed);
+                }
+                report_diff(config, name, expected, &actual)?;
+            }
+            if config.update {
+                save_file(&config.model_path(name, GoodOrBad::Good), &actual)?;
+                return Ok(Outcome::Updated);
+            }
+            save_incorrect(config, name, &actual)?;
+            return Ok(Outcome::Failed);
+        }
+        Action::Screenshot => {
+            let actual = render_model(tree, RenderQuality::Low)?;
+            let expected = load_model(config, name)
                 .context("failed to load the expected model")?;
-            if !scad_relative_eq(&actual, &expected, MAX_RELATIVE)? {
-                save_incorrect(name, &actual)?;
-                panic!("Models don't match")
+            // Both use the same camera, so a model that moved or changed
+            // size looks different.
+            let camera = camera_args(&expected)?;
+            let actual_png =
+                screenshot(config, "actual", name, &actual, &camera)?;
+            let expected_png =
+                screenshot(config, "expected", name, &expected, &camera)?;
+            let difference = compare_images(&actual_png, &expected_png)?;
+            if difference > config.max_image_difference {
+                println!(
+                    "Screenshots of model '{}' differ in {:.2}% of pixels: {}, {}",
+                    name,
+                    difference * 100.,
+                    actual_png.display(),
+                    expected_png.display()
+                );
+                save_incorrect(config, name, &actual)?;
+                return Ok(Outcome::Failed);
             }
         }
     };
-    Ok(())
+    Ok(Outcome::Passed)
+}
+
+/// Some actions are only for temporary use, so they return an error after
+/// they've been performed on all models. That way they aren't accidentally
+/// left in place of `Action::Test`.
+fn finish_action(action: Action) -> Result<(), ScadDotsError> {
+    match action {
+        Action::ViewBoth => Err(ScadDotsError::TestView),
+        Action::Create => Err(ScadDotsError::TestCreate),
+        Action::Test
+        | Action::Screenshot
+        | Action::Preview
+        | Action::PrintMedium
+        | Action::PrintHigh => Ok(()),
+    }
 }
 
 /// This lets the child process (openscad) not get killed when the parent does.
+#[cfg(unix)]
 fn change_process_group() -> Result<(), io::Error> {
     // First zero means affect current process, second zero means change pgid to own pid.
     if 0 == unsafe { libc::setpgid(0, 0) } {
@@ -118,19 +437,217 @@ fn change_process_group() -> Result<(), io::Error> {
     }
 }
 
-fn view_in_openscad(paths: &[String]) -> Result<(), ScadDotsError> {
-    //  TODO only do before_exec for linux
-    // https://doc.rust-lang.org/reference/attributes.html#conditional-compilation
-    Command::new("openscad")
-        .args(paths)
-        .before_exec(change_process_group)
-        .spawn()
+/// Start the command in its own process group, so it keeps running after
+/// the tests finish.
+#[cfg(unix)]
+fn spawn_detached(command: &mut Command) -> Result<Child, io::Error> {
+    command.before_exec(change_process_group).spawn()
+}
+
+/// Start the command. Other platforms don't have process groups, so it may
+/// be killed when the tests finish.
+#[cfg(not(unix))]
+fn spawn_detached(command: &mut Command) -> Result<Child, io::Error> {
+    command.spawn()
+}
+
+/// Return the path of the openscad binary, from the `OPENSCAD_BIN`
+/// environment variable if it's set.
+pub fn openscad_bin() -> PathBuf {
+    env::var_os(OPENSCAD_BIN_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("openscad"))
+}
+
+/// Run `openscad --version` and return the version string it reports. Return
+/// a descriptive error if the binary can't be found or run.
+pub fn openscad_version() -> Result<String, ScadDotsError> {
+    openscad_version_at(&openscad_bin())
+}
+
+/// Like `openscad_version()`, but run the openscad binary at the given path
+/// instead.
+pub fn openscad_version_at(bin: &Path) -> Result<String, ScadDotsError> {
+    let output =
+        Command::new(bin)
+            .arg("--version")
+            .output()
+            .with_context(|| {
+                format!(
+                "failed to run openscad at '{}'. Is it installed? Set the {} \
+                 environment variable to use a different path.",
+                bin.display(),
+                OPENSCAD_BIN_VAR
+            )
+            })?;
+    if !output.status.success() {
+        return Err(ScadDotsError::Openscad(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )
+        .with_context(|| {
+            format!("openscad at '{}' failed to run", bin.display())
+        }));
+    }
+    // Openscad prints its version to stderr, but check stdout too in case
+    // that changes.
+    let text = if output.stdout.is_empty() {
+        output.stderr
+    } else {
+        output.stdout
+    };
+    Ok(String::from_utf8_lossy(&text).trim().to_owned())
+}
+
+/// Return true if there's probably no display to open the openscad viewer
+/// on, like in a CI environment.
+pub fn is_headless() -> bool {
+    if env::var_os("CI").is_some() {
+        return true;
+    }
+    if cfg!(target_os = "linux") {
+        return env::var_os("DISPLAY").is_none()
+            && env::var_os("WAYLAND_DISPLAY").is_none();
+    }
+    false
+}
+
+pub(crate) fn view_in_openscad(
+    config: &HarnessConfig,
+    paths: &[PathBuf],
+) -> Result<(), ScadDotsError> {
+    if is_headless() {
+        println!(
+            "Skipping openscad viewer in headless environment: {}",
+            paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        return Ok(());
+    }
+    println!("Using {}", openscad_version_at(&config.viewer)?);
+    spawn_detached(Command::new(&config.viewer).args(paths))
         .context("failed to run openscad viewer")?;
     Ok(())
 }
 
-fn load_model(name: &str) -> Result<String, ScadDotsError> {
-    let file = File::open(name_to_path(name, GoodOrBad::Good))
+/// Return the openscad arguments for a camera that looks at the center of
+/// the model's bounding box from the front right, far enough away to fit the
+/// whole box in view. If the model's bounds are unknown, like if it's all raw
+/// code, openscad zooms to fit each image instead.
+fn camera_args(code: &str) -> Result<Vec<String>, ScadDotsError> {
+    let bounds = match scad_bounds(code)
+        .context("failed to find the bounds of the model")?
+    {
+        Some(bounds) => bounds,
+        None => return Ok(vec!["--viewall".into(), "--autocenter".into()]),
+    };
+    let center = bounds.center();
+    // The field of view is 22.5 degrees, so this fits the box's diagonal.
+    let distance = 2.6 * bounds.size().norm().max(1.);
+    let eye = center + distance * V3::new(1., -1., 1.).normalize();
+    Ok(vec![format!(
+        "--camera={},{},{},{},{},{}",
+        eye.x, eye.y, eye.z, center.x, center.y, center.z
+    )])
+}
+
+/// Save the code in the temporary directory, render it to a PNG image with
+/// openscad, and return the image's path.
+fn screenshot(
+    config: &HarnessConfig,
+    id: &str,
+    test_name: &str,
+    code: &str,
+    camera: &[String],
+) -> Result<PathBuf, ScadDotsError> {
+    let scad_path = save_temp_file(config, id, test_name, code)?;
+    let png_path = scad_path.with_extension("png");
+    let output = Command::new(&config.viewer)
+        .args(&["--render", "--imgsize=800,600"])
+        .args(camera)
+        .arg("-o")
+        .arg(&png_path)
+        .arg(&scad_path)
+        .output()
+        .with_context(|| {
+            format!("failed to run openscad at '{}'", config.viewer.display())
+        })?;
+    if !output.status.success() {
+        return Err(ScadDotsError::Openscad(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )
+        .with_context(|| {
+            format!("failed to screenshot '{}'", scad_path.display())
+        }));
+    }
+    Ok(png_path)
+}
+
+/// Return the fraction of pixels that differ between two PNG images of the
+/// same size and color type. A pixel differs if any of its color channels
+/// differ by more than a small tolerance.
+pub fn compare_images(a: &Path, b: &Path) -> Result<f32, ScadDotsError> {
+    let (a_info, a_pixels) = load_png(a)?;
+    let (b_info, b_pixels) = load_png(b)?;
+    if (a_info.width, a_info.height, a_info.color_type)
+        != (b_info.width, b_info.height, b_info.color_type)
+    {
+        return Err(ScadDotsError::Dimension.with_context(|| {
+            format!(
+                "can't compare images with different sizes or color types: \
+                 '{}', '{}'",
+                a.display(),
+                b.display()
+            )
+        }));
+    }
+    let samples = a_info.color_type.samples();
+    let count = a_pixels.len() / samples;
+    if count == 0 {
+        return Ok(0.);
+    }
+    let different = a_pixels
+        .chunks(samples)
+        .zip(b_pixels.chunks(samples))
+        .filter(|&(a, b)| {
+            a.iter().zip(b).any(|(x, y)| {
+                (i16::from(*x) - i16::from(*y)).abs()
+                    > i16::from(PIXEL_TOLERANCE)
+            })
+        })
+        .count();
+    Ok(different as f32 / count as f32)
+}
+
+/// Load an 8-bit PNG image, and return its info and pixel data.
+fn load_png(path: &Path) -> Result<(png::OutputInfo, Vec<u8>), ScadDotsError> {
+    let decode = || -> Result<_, png::DecodingError> {
+        let decoder = png::Decoder::new(File::open(path)?);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels)?;
+        Ok((info, pixels))
+    };
+    let (info, pixels) = decode()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        .with_context(|| {
+            format!("failed to load image '{}'", path.display())
+        })?;
+    if info.bit_depth != png::BitDepth::Eight {
+        return Err(ScadDotsError::Args.with_context(|| {
+            format!("image '{}' isn't 8-bit", path.display())
+        }));
+    }
+    Ok((info, pixels))
+}
+
+fn load_model(
+    config: &HarnessConfig,
+    name: &str,
+) -> Result<String, ScadDotsError> {
+    let file = File::open(config.model_path(name, GoodOrBad::Good))
         .context("failed to open openscad file")?;
     let mut buf = BufReader::new(file);
     let mut s = String::new();
@@ -146,45 +663,80 @@ fn render_model(
     to_code(tree, render_options)
 }
 
-fn save_file(path: &str, data: &str) -> Result<(), ScadDotsError> {
-    println!("Writing to: {}", path);
+/// Save the file, creating its directory if it doesn't exist yet.
+fn save_file(path: &Path, data: &str) -> Result<(), ScadDotsError> {
+    println!("Writing to: {}", path.display());
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
     let mut f = File::create(path)?;
     f.write_all(data.as_bytes())?;
     Ok(())
 }
 
 fn save_temp_file(
+    config: &HarnessConfig,
     id: &str,
     test_name: &str,
     code: &str,
-) -> Result<(String), ScadDotsError> {
-    let path = format!("tests/tmp/{}_{}.scad", id, test_name);
+) -> Result<PathBuf, ScadDotsError> {
+    let path = config.tmp_dir.join(format!("{}_{}.scad", id, test_name));
     save_file(&path, code).context("failed to save temporary .scad file")?;
     Ok(path)
 }
 
-fn save_incorrect(name: &str, code: &str) -> Result<(), ScadDotsError> {
-    let path = name_to_path(name, GoodOrBad::Bad);
-    println!("Saving incorrect model as: '{}'", path);
+fn save_incorrect(
+    config: &HarnessConfig,
+    name: &str,
+    code: &str,
+) -> Result<(), ScadDotsError> {
+    let path = config.model_path(name, GoodOrBad::Bad);
+    println!("Saving incorrect model as: '{}'", path.display());
     println!(
         "****************************************************************"
     );
     save_file(&path, code)
 }
 
-fn name_to_path(name: &str, status: GoodOrBad) -> String {
-    let mut p = PathBuf::new();
-    p.push("tests");
-    p.push(format!("{}_models", &status.to_string()));
-    p.push(format!("{}.scad", name));
-    p.to_str().expect("failed to make path").to_owned()
+/// Print the differences between the expected and actual models, and save
+/// them to a file too if the `SCAD_DOTS_DIFF_FILE` environment variable is
+/// set.
+fn report_diff(
+    config: &HarnessConfig,
+    name: &str,
+    expected: &str,
+    actual: &str,
+) -> Result<(), ScadDotsError> {
+    let entries = scad_diff(expected, actual, config.max_relative)?;
+    println!(
+        "Model '{}' doesn't match the expected model (- expected, + actual):",
+        name
+    );
+    print!("{}", diff_report(&entries, false));
+    if env::var_os(DIFF_FILE_VAR).is_some() {
+        let path = config
+            .model_path(name, GoodOrBad::Bad)
+            .with_extension("diff");
+        save_file(&path, &diff_report(&entries, true))
+            .context("failed to save diff file")?;
+    }
+    Ok(())
 }
 
-impl GoodOrBad {
-    fn to_string(self) -> String {
-        match self {
-            GoodOrBad::Good => "good".into(),
-            GoodOrBad::Bad => "bad".into(),
-        }
+/// Format the differences with one block per node, optionally colored with
+/// terminal escape codes.
+fn diff_report(entries: &[DiffEntry], colored: bool) -> String {
+    let (red, green, reset) = if colored {
+        ("\x1b[31m", "\x1b[32m", "\x1b[0m")
+    } else {
+        ("", "", "")
+    };
+    let mut report = String::new();
+    for entry in entries {
+        report.push_str(&format!(
+            "  {} ({})\n    {}- {}{}\n    {}+ {}{}\n",
+            entry.path, entry.node, red, entry.a, reset, green, entry.b, reset
+        ));
     }
+    report
 }
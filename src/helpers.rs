@@ -0,0 +1,105 @@
+use core::utils::{rotation_from_axes, Axis, Corner1 as C1, P2, P3, R3, V3};
+use core::{
+    Cylinder, CylinderAlign, CylinderSpec, Dot, DotAlign, ExtrudeParams,
+    Extrusion, Polygon2D, Tree,
+};
+use errors::{ResultExt, ScadDotsError};
+
+/// Return an oblong slot with rounded ends, running between the centers of
+/// the 2 dots, to diff out of a plate. The ends are centered on the dots'
+/// centers, and the slot extends `depth / 2` to either side of them along
+/// `dot_a`'s local z axis, tilted to be perpendicular to the line between the
+/// dots. Use a depth larger than the plate's thickness to cut all the way
+/// through. It's an error if the dots are lined up along that axis.
+pub fn slot(
+    dot_a: &Dot,
+    dot_b: &Dot,
+    width: f32,
+    depth: f32,
+) -> Result<Tree, ScadDotsError> {
+    if !(width > 0.) || !(depth > 0.) {
+        return Err(ScadDotsError::Dimension
+            .context("slot width and depth must be positive"));
+    }
+    let direction =
+        dot_b.pos(DotAlign::centroid()) - dot_a.pos(DotAlign::centroid());
+    let axis = dot_a.dim_unit_vec(Axis::Z);
+    let rot = if direction == V3::zeros() {
+        dot_a.rot
+    } else {
+        // Make the axis perpendicular to the slot, so the ends aren't skewed.
+        let frame = rotation_from_axes(axis, direction)
+            .context("slot can't run along dot_a's z axis")?;
+        rotation_from_axes(direction, frame * V3::x())?
+    };
+    let end = |dot: &Dot| {
+        Cylinder::new(CylinderSpec {
+            pos: dot.pos(DotAlign::centroid()),
+            align: CylinderAlign::Centroid,
+            diameter: width,
+            height: depth,
+            rot,
+        })
+    };
+    Ok(hull![end(dot_a), end(dot_b)])
+}
+
+/// Return a flat plate with rounded corners, lying on the xy plane with its
+/// bottom at z=0 and its outer P000 corner at the origin. Use
+/// `Tree::translate()` and `Tree::rotate()` to place it. The corner radius
+/// can be anything from 0 (square corners) up to half the shorter side.
+pub fn rounded_plate(
+    x_length: f32,
+    y_length: f32,
+    thickness: f32,
+    corner_radius: f32,
+) -> Result<Tree, ScadDotsError> {
+    if !(x_length > 0.) || !(y_length > 0.) || !(thickness > 0.) {
+        return Err(ScadDotsError::Dimension
+            .context("plate lengths and thickness must be positive"));
+    }
+    if !(corner_radius >= 0.) || 2. * corner_radius > x_length.min(y_length) {
+        return Err(ScadDotsError::Dimension.with_context(|| {
+            format!(
+                "plate corner radius must be between 0 and half the shorter \
+                 side, but it's {}",
+                corner_radius
+            )
+        }));
+    }
+    if corner_radius == 0. {
+        return Ok(Extrusion {
+            perimeter: Polygon2D::new(vec![
+                P2::new(0., 0.),
+                P2::new(x_length, 0.),
+                P2::new(x_length, y_length),
+                P2::new(0., y_length),
+            ])?,
+            holes: Vec::new(),
+            bottom_z: 0.,
+            thickness,
+            params: ExtrudeParams::default(),
+        }
+        .into());
+    }
+    let corner = |x: f32, y: f32| {
+        Cylinder::new(CylinderSpec {
+            pos: P3::new(x, y, 0.),
+            align: CylinderAlign::EndCenter(C1::P0),
+            diameter: 2. * corner_radius,
+            height: thickness,
+            rot: R3::identity(),
+        })
+    };
+    let (near, far_x, far_y) = (
+        corner_radius,
+        x_length - corner_radius,
+        y_length - corner_radius,
+    );
+    Ok(hull![
+        corner(near, near),
+        corner(far_x, near),
+        corner(far_x, far_y),
+        corner(near, far_y),
+    ])
+}
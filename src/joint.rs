@@ -0,0 +1,202 @@
+use core::utils::{
+    check_finite, degrees_to_radians, rotation_from_axes, Axis, CubeFace,
+    CUT_OVERLAP, P2, P3,
+};
+use core::{Extrusion, OffsetJoin, Polygon2D, Tree};
+use cuboid::{Cuboid, CuboidAlign};
+use errors::{ResultExt, ScadDotsError};
+
+/// The two halves of a joint between two pieces that are flush against each
+/// other. Union `positive` with the first piece, so its tabs stick into the
+/// second piece, and subtract `negative` from the second piece.
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub positive: Tree,
+    pub negative: Tree,
+}
+
+/// Specify the tabs of a `Joint`.
+#[derive(Debug, Clone, Copy)]
+pub struct JointSpec {
+    pub style: JointStyle,
+    /// How many tabs stick out of the first piece. The edge is split into
+    /// `2 * tabs + 1` equal parts, and every other part is a tab, so the
+    /// second piece keeps both ends of the edge.
+    pub tabs: usize,
+    /// How far the tabs stick out.
+    pub depth: f32,
+    /// The gap between the tabs and the cutouts, on every side.
+    pub clearance: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum JointStyle {
+    /// Rectangular tabs, also called a box joint.
+    Finger,
+    /// Tabs that get wider as they go into the second piece, so the pieces
+    /// can only be slid apart through the thickness. The sides slope
+    /// outwards by the given angle in degrees. Around 10 degrees is typical.
+    Dovetail { degrees: f32 },
+}
+
+impl Joint {
+    /// Create a joint along an edge of the given length, in a plate of the
+    /// given thickness. The edge goes along the x axis from the origin, the
+    /// tabs stick out along the +y axis, and the plate goes from z=0 to
+    /// z=`thickness`.
+    pub fn new(
+        spec: JointSpec,
+        edge_length: f32,
+        thickness: f32,
+    ) -> Result<Self, ScadDotsError> {
+        spec.check(edge_length, thickness)?;
+        let width = edge_length / (2 * spec.tabs + 1) as f32;
+        let flare = match spec.style {
+            JointStyle::Finger => 0.,
+            JointStyle::Dovetail { degrees } => {
+                degrees_to_radians(degrees).tan()
+            }
+        };
+        let mut positives = Vec::new();
+        let mut negatives = Vec::new();
+        for i in 0..spec.tabs {
+            let center = (2 * i + 1) as f32 * width + width / 2.;
+            let (base, top) = (width / 2., width / 2. + spec.depth * flare);
+            let tab = Polygon2D::new(vec![
+                P2::new(center - base, -CUT_OVERLAP),
+                P2::new(center + base, -CUT_OVERLAP),
+                P2::new(center + base, 0.),
+                P2::new(center + top, spec.depth),
+                P2::new(center - top, spec.depth),
+                P2::new(center - base, 0.),
+            ])?;
+            let cutout = tab
+                .offset(spec.clearance, OffsetJoin::Miter)
+                .context("failed to add clearance around joint tab")?;
+            positives.push(Extrusion::from_polygon(tab, thickness, 0.)?);
+            negatives.push(Extrusion::from_polygon(
+                cutout,
+                thickness + 2. * CUT_OVERLAP,
+                -CUT_OVERLAP,
+            )?);
+        }
+        Ok(Self {
+            positive: Tree::union(positives),
+            negative: Tree::union(negatives),
+        })
+    }
+
+    /// Create a joint with tabs sticking out of the given face of the
+    /// cuboid. The joint runs along the face's longer edge, and goes through
+    /// its shorter edge, like a plate that's being split in two.
+    pub fn on_face(
+        spec: JointSpec,
+        cuboid: &Cuboid,
+        face: CubeFace,
+    ) -> Result<Self, ScadDotsError> {
+        let (a, b) = match face.axis() {
+            Axis::X => (Axis::Y, Axis::Z),
+            Axis::Y => (Axis::X, Axis::Z),
+            Axis::Z => (Axis::X, Axis::Y),
+        };
+        let (edge, through) = if cuboid.edge_length(a) >= cuboid.edge_length(b)
+        {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let edge_length = cuboid.edge_length(edge);
+        let thickness = cuboid.edge_length(through);
+        let joint = Self::new(spec, edge_length, thickness)
+            .context("failed to make joint on cuboid face")?;
+
+        // Map the x axis to the edge and the y axis to the face's normal. The
+        // z axis then goes through the thickness one way or the other.
+        let x = cuboid.edge_unit_vec(edge);
+        let y = cuboid.face_normal(face);
+        let z = x.cross(&y);
+        let rot = rotation_from_axes(x, z)?;
+        let corner = cuboid.pos(CuboidAlign::center_face(face))
+            - x * edge_length / 2.
+            - z * thickness / 2.;
+        let place = |tree: Tree| {
+            Tree::translate(corner - P3::origin(), Tree::rotate(rot, tree))
+        };
+        Ok(Self {
+            positive: place(joint.positive),
+            negative: place(joint.negative),
+        })
+    }
+
+    /// Create a joint between two cuboids that are flush against each other,
+    /// with tabs sticking out of `a`'s face into `b`. See `on_face()`.
+    pub fn between(
+        spec: JointSpec,
+        a: &Cuboid,
+        a_face: CubeFace,
+        b: &Cuboid,
+        b_face: CubeFace,
+    ) -> Result<Self, ScadDotsError> {
+        let a_plane = a.face_plane(a_face);
+        let b_plane = b.face_plane(b_face);
+        let gap = (b_plane.point - a_plane.point).dot(&a_plane.normal);
+        if a_plane.normal.dot(&b_plane.normal) > -1. + 1e-4 || gap.abs() > 1e-4
+        {
+            return Err(ScadDotsError::Args
+                .context("cuboid faces must be flush against each other"));
+        }
+        Self::on_face(spec, a, a_face)
+    }
+}
+
+impl JointSpec {
+    fn check(
+        &self,
+        edge_length: f32,
+        thickness: f32,
+    ) -> Result<(), ScadDotsError> {
+        check_finite(
+            "joint dimensions",
+            [self.depth, self.clearance, edge_length, thickness]
+                .iter()
+                .cloned(),
+        )?;
+        if self.tabs == 0 {
+            return Err(
+                ScadDotsError::Args.context("joint needs at least 1 tab")
+            );
+        }
+        if self.depth <= 0.
+            || self.clearance < 0.
+            || edge_length <= 0.
+            || thickness <= 0.
+        {
+            return Err(ScadDotsError::Dimension.context(
+                "joint depth, edge length, and thickness must be positive, \
+                 and clearance can't be negative",
+            ));
+        }
+        let width = edge_length / (2 * self.tabs + 1) as f32;
+        let flare = match self.style {
+            JointStyle::Finger => 0.,
+            JointStyle::Dovetail { degrees } => {
+                if !degrees.is_finite() || degrees <= 0. || degrees >= 45. {
+                    return Err(ScadDotsError::Dimension.context(
+                        "dovetail angle must be between 0 and 45 degrees",
+                    ));
+                }
+                degrees_to_radians(degrees).tan()
+            }
+        };
+        // The cutouts need some material between them at their widest.
+        if width - 2. * (self.depth * flare + self.clearance) <= 0. {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "joint tabs are too close together: {} tabs on a {} edge",
+                    self.tabs, edge_length
+                )
+            }));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+use core::utils::{
+    axis_degrees, check_finite, cos_deg, sin_deg, Axis, CubeFace, CUT_OVERLAP,
+    P2, P3, V2,
+};
+use core::Tree;
+use cuboid::{Cuboid, CuboidAlign, CuboidLink, CuboidShapes, CuboidSpec};
+use errors::{ResultExt, ScadDotsError};
+
+/// The distance between the centers of neighboring 1u keys.
+pub const KEY_UNIT: f32 = 19.05;
+/// The side length of the square hole that an MX-style switch clips into.
+pub const SWITCH_CUTOUT: f32 = 14.;
+/// The plate thickness that MX-style switch clips are made for.
+pub const PLATE_THICKNESS: f32 = 1.5;
+
+/// A single key in a `Layout`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Key {
+    /// The center of the key on the xy plane.
+    pub center: P2,
+    /// The width of the keycap in key units, like 2 for a 2u key.
+    pub width: f32,
+    /// The height of the keycap in key units.
+    pub height: f32,
+    /// How many degrees the key is rotated counterclockwise around its
+    /// center.
+    pub degrees: f32,
+}
+
+/// The positions of the keys on a keyboard. Keys can be added to `keys`
+/// directly, for layouts that don't fit in rows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layout {
+    pub keys: Vec<Key>,
+}
+
+/// Specify how a switch plate is made from a `Layout`.
+#[derive(Debug, Clone, Copy)]
+pub struct PlateSpec {
+    pub thickness: f32,
+    /// How far the plate extends past the edges of the keycaps.
+    pub margin: f32,
+    /// If it's given, chamfer the top edge of each cutout by this much, which
+    /// dampens the sound of the switches bottoming out. It must be less than
+    /// the thickness, so the switch clips still have something to grab.
+    pub chamfer: Option<f32>,
+}
+
+impl Key {
+    /// Create a 1u key with no rotation.
+    pub fn new(center: P2) -> Self {
+        Self {
+            center,
+            width: 1.,
+            height: 1.,
+            degrees: 0.,
+        }
+    }
+
+    pub fn with_size(mut self, width: f32, height: f32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.degrees = degrees;
+        self
+    }
+
+    fn check(&self) -> Result<(), ScadDotsError> {
+        check_finite(
+            "key",
+            [self.center.x, self.center.y, self.degrees].iter().cloned(),
+        )?;
+        if !self.width.is_finite()
+            || !self.height.is_finite()
+            || self.width * KEY_UNIT <= SWITCH_CUTOUT
+            || self.height * KEY_UNIT <= SWITCH_CUTOUT
+        {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "key at {} is {}u x {}u, which is too small for a switch",
+                    self.center, self.width, self.height
+                )
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl Layout {
+    /// Create a layout from rows of key widths, in key units, like a
+    /// simplified KLE layout. The first row is at the top, and each row
+    /// starts at the left. The top left corner of the first key is at the
+    /// origin, and the rows go down the -y axis.
+    pub fn from_rows<T>(rows: &[T]) -> Self
+    where
+        T: AsRef<[f32]>,
+    {
+        let mut keys = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            let mut left = 0.;
+            for &width in row.as_ref() {
+                let center = P2::new(
+                    (left + width / 2.) * KEY_UNIT,
+                    -(i as f32 + 0.5) * KEY_UNIT,
+                );
+                keys.push(Key::new(center).with_size(width, 1.));
+                left += width;
+            }
+        }
+        Self { keys }
+    }
+
+    /// Create a layout of 1u keys in a grid with the given number of rows and
+    /// columns, like an ortholinear keyboard.
+    pub fn grid(rows: usize, columns: usize) -> Self {
+        Self::from_rows(&vec![vec![1.; columns]; rows])
+    }
+
+    /// Rotate every key in the layout counterclockwise around the point, like
+    /// for one half of a split keyboard.
+    pub fn rotated(mut self, degrees: f32, about: P2) -> Self {
+        let (sin, cos) = (sin_deg(degrees), cos_deg(degrees));
+        for key in &mut self.keys {
+            let v = key.center - about;
+            key.center =
+                about + V2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y);
+            key.degrees += degrees;
+        }
+        self
+    }
+}
+
+impl Default for PlateSpec {
+    fn default() -> Self {
+        Self {
+            thickness: PLATE_THICKNESS,
+            margin: 0.,
+            chamfer: None,
+        }
+    }
+}
+
+/// Return a switch plate for the layout, lying on the xy plane with its
+/// bottom at z=0. The plate covers the area under each keycap, grown by the
+/// margin, and has a square cutout centered under each key for the switch.
+pub fn switch_plate(
+    layout: &Layout,
+    spec: PlateSpec,
+) -> Result<Tree, ScadDotsError> {
+    if layout.keys.is_empty() {
+        return Err(ScadDotsError::Args.context("layout has no keys"));
+    }
+    check_finite("plate spec", [spec.thickness, spec.margin].iter().cloned())?;
+    if spec.thickness <= 0. || spec.margin < 0. {
+        return Err(ScadDotsError::Dimension.context(
+            "plate thickness must be positive and margin can't be negative",
+        ));
+    }
+    if let Some(chamfer) = spec.chamfer {
+        if !chamfer.is_finite() || chamfer <= 0. || chamfer >= spec.thickness {
+            return Err(ScadDotsError::Dimension.context(
+                "plate chamfer must be positive and less than the thickness",
+            ));
+        }
+    }
+    let mut footprints = Vec::new();
+    let mut cutouts = Vec::new();
+    for key in &layout.keys {
+        key.check()?;
+        footprints.push(key_cuboid(
+            key,
+            0.,
+            key.width * KEY_UNIT + 2. * spec.margin,
+            key.height * KEY_UNIT + 2. * spec.margin,
+            spec.thickness,
+        )?);
+        cutouts.push(cutout(key, spec)?);
+    }
+    Ok(Tree::cut(Tree::union(footprints), cutouts))
+}
+
+/// Return the hole for the key's switch.
+fn cutout(key: &Key, spec: PlateSpec) -> Result<Tree, ScadDotsError> {
+    let hole = key_cuboid(
+        key,
+        -CUT_OVERLAP,
+        SWITCH_CUTOUT,
+        SWITCH_CUTOUT,
+        spec.thickness + 2. * CUT_OVERLAP,
+    )?;
+    Ok(match spec.chamfer {
+        None => hole,
+        Some(chamfer) => {
+            // Hull the hole's outline at the bottom of the chamfer with a
+            // larger outline above the plate, where the sides of the chamfer
+            // would meet.
+            let wide = SWITCH_CUTOUT + 2. * (chamfer + CUT_OVERLAP);
+            union![
+                hole,
+                hull![
+                    key_cuboid(
+                        key,
+                        spec.thickness - chamfer,
+                        SWITCH_CUTOUT,
+                        SWITCH_CUTOUT,
+                        chamfer,
+                    )?,
+                    key_cuboid(
+                        key,
+                        spec.thickness + CUT_OVERLAP,
+                        wide,
+                        wide,
+                        chamfer,
+                    )?,
+                ]
+            ]
+        }
+    })
+}
+
+/// Return a solid box centered under the key and rotated with it, with its
+/// bottom at the given z.
+fn key_cuboid(
+    key: &Key,
+    bottom_z: f32,
+    x_length: f32,
+    y_length: f32,
+    z_length: f32,
+) -> Result<Tree, ScadDotsError> {
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::new(key.center.x, key.center.y, bottom_z),
+        align: CuboidAlign::center_face(CubeFace::Z0),
+        x_length,
+        y_length,
+        z_length,
+        size: z_length.min(x_length).min(y_length),
+        rot: axis_degrees(Axis::Z, key.degrees),
+        shapes: CuboidShapes::Cube,
+    })
+    .with_context(|| {
+        format!("failed to make plate for key at {}", key.center)
+    })?;
+    cuboid.link(CuboidLink::Solid)
+}
@@ -0,0 +1,201 @@
+use nalgebra::{Matrix3, Rotation3};
+
+use core::utils::{
+    axis_degrees, degrees_to_radians, radians_to_degrees, Axis, Corner1 as C1,
+    Corner3 as C3, Fraction, R3, V3,
+};
+use core::{AnchorPoint, Cylinder, Dot, DotAlign, DotShape, DotSpec, Tree};
+use rect::{Rect, RectAlign};
+
+use errors::{ResultExt, ScadDotsError};
+
+/// Specify a grip texture made of small rotated cube dots, to union onto a
+/// surface (or diff out of it).
+#[derive(Debug, Clone, Copy)]
+pub struct KnurlSpec {
+    pub style: KnurlStyle,
+    /// The distance between neighboring diamonds or ridges.
+    pub pitch: f32,
+    /// How far the texture sticks out from the surface.
+    pub depth: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KnurlStyle {
+    /// A grid of diamonds, each made of a cube dot turned 45 degrees around
+    /// the surface normal. The depth can be at most the size of the cube,
+    /// which is `pitch / sqrt(2)`.
+    Diamond,
+    /// Straight ridges with a 90 degree peak, each made of the hull of 2 cube
+    /// dots turned 45 degrees around the ridge. On a Rect they run parallel
+    /// to its y axis, and on a Cylinder they run parallel to its axis. The
+    /// depth can be at most half the pitch.
+    Ridges,
+}
+
+/// Return a knurl texture covering the top (`C1::P1`) or bottom (`C1::P0`)
+/// outer face of the Rect. The texture is centered on the face, and as many
+/// whole diamonds or ridges as fit are used.
+pub fn knurl_rect(
+    rect: &Rect,
+    face: C1,
+    spec: KnurlSpec,
+) -> Result<Tree, ScadDotsError> {
+    let origin = rect.pos(RectAlign::outside(
+        C3::P000.copy_to(Axis::Z, face.is_high()),
+    ));
+    let unit_x = rect.edge_unit_vec(Axis::X);
+    let unit_y = rect.edge_unit_vec(Axis::Y);
+    let normal = face.sign() * unit_x.cross(&unit_y);
+    texture(
+        rect.edge_length(Axis::X),
+        rect.edge_length(Axis::Y),
+        spec,
+        |a, b| AnchorPoint {
+            pos: origin + a * unit_x + b * unit_y,
+            rot: frame(unit_y, normal),
+        },
+    )
+    .context("failed to knurl rect")
+}
+
+/// Return a knurl texture covering the part of the Cylinder's curved side
+/// surface between the 2 given angles, for its full height. The angles are in
+/// degrees, measured as in `Cylinder::pos_on_surface()`.
+pub fn knurl_cylinder(
+    cylinder: &Cylinder,
+    degrees: (f32, f32),
+    spec: KnurlSpec,
+) -> Result<Tree, ScadDotsError> {
+    if cylinder.is_cone() {
+        return Err(ScadDotsError::Args.context("can't knurl a cone"));
+    }
+    let span = degrees.1 - degrees.0;
+    if !(span > 0.) || span > 360. {
+        return Err(ScadDotsError::Args.context(
+            "knurl cylinder angles must increase by at most 360 degrees",
+        ));
+    }
+    let radius = cylinder.diameter / 2.;
+    let axis = cylinder.unit_axis();
+    let place = |a: f32, b: f32| {
+        let angle = degrees.0 + radians_to_degrees(a / radius);
+        let height = Fraction::new(b / cylinder.height)
+            .expect("knurl position is off the cylinder");
+        AnchorPoint {
+            pos: cylinder.pos_on_surface(angle, height),
+            rot: frame(axis, cylinder.unit_radial(angle)),
+        }
+    };
+    texture(
+        radius * degrees_to_radians(span),
+        cylinder.height,
+        spec,
+        place,
+    )
+    .context("failed to knurl cylinder")
+}
+
+impl KnurlSpec {
+    /// The size of each cube dot, chosen so neighbors just touch.
+    fn dot_size(&self) -> f32 {
+        self.pitch / 2_f32.sqrt()
+    }
+
+    fn validate(&self) -> Result<(), ScadDotsError> {
+        if !(self.pitch > 0.) || !(self.depth > 0.) {
+            return Err(ScadDotsError::Dimension
+                .context("knurl pitch and depth must be positive"));
+        }
+        let max_depth = match self.style {
+            KnurlStyle::Diamond => self.dot_size(),
+            KnurlStyle::Ridges => self.pitch / 2.,
+        };
+        if self.depth > max_depth {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "knurl depth {} is larger than the maximum of {} for \
+                     this pitch",
+                    self.depth, max_depth
+                )
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// Return the rotation whose local y axis is `along` and whose local z axis
+/// is `normal`. They must be perpendicular unit vectors.
+fn frame(along: V3, normal: V3) -> R3 {
+    let across = along.cross(&normal);
+    let matrix = Matrix3::from_columns(&[across, along, normal]);
+    R3::from_rotation_matrix(&Rotation3::from_matrix_unchecked(matrix))
+}
+
+/// Cover a flattened surface with the texture. The surface has coordinates
+/// from 0 to `u_length` across the ridges and 0 to `v_length` along them.
+/// `place(a, b)` returns the point on the surface at those coordinates, with
+/// a rotation whose local y axis points along the ridges and whose local z
+/// axis points out of the surface.
+fn texture<F>(
+    u_length: f32,
+    v_length: f32,
+    spec: KnurlSpec,
+    place: F,
+) -> Result<Tree, ScadDotsError>
+where
+    F: Fn(f32, f32) -> AnchorPoint,
+{
+    spec.validate()?;
+    let size = spec.dot_size();
+    let make_dot = |a: f32, b: f32, spin: R3| {
+        let point = place(a, b);
+        let normal = point.rot * V3::z();
+        // Sink the dot so its outermost point is `depth` above the surface.
+        let height = match spec.style {
+            KnurlStyle::Diamond => size / 2.,
+            KnurlStyle::Ridges => spec.pitch / 2.,
+        };
+        Dot::new(DotSpec {
+            pos: point.pos + (spec.depth - height) * normal,
+            align: DotAlign::centroid(),
+            size,
+            rot: point.rot * spin,
+            shape: DotShape::Cube,
+        })
+    };
+
+    let count_u = (u_length / spec.pitch).floor() as usize;
+    let margin_u = (u_length - count_u as f32 * spec.pitch) / 2.;
+    let center_u = |i: usize| margin_u + (i as f32 + 0.5) * spec.pitch;
+    let mut elements = Vec::new();
+    match spec.style {
+        KnurlStyle::Diamond => {
+            let count_v = (v_length / spec.pitch).floor() as usize;
+            let margin_v = (v_length - count_v as f32 * spec.pitch) / 2.;
+            let spin = axis_degrees(Axis::Z, 45.);
+            for i in 0..count_u {
+                for j in 0..count_v {
+                    let b = margin_v + (j as f32 + 0.5) * spec.pitch;
+                    elements.push(Tree::from(make_dot(center_u(i), b, spin)));
+                }
+            }
+        }
+        KnurlStyle::Ridges => {
+            if v_length >= size {
+                let spin = axis_degrees(Axis::Y, 45.);
+                for i in 0..count_u {
+                    elements.push(hull![
+                        make_dot(center_u(i), size / 2., spin),
+                        make_dot(center_u(i), v_length - size / 2., spin),
+                    ]);
+                }
+            }
+        }
+    }
+    if elements.is_empty() {
+        return Err(ScadDotsError::Dimension
+            .context("surface is too small to fit any knurl elements"));
+    }
+    Ok(Tree::union(elements))
+}
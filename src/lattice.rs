@@ -0,0 +1,144 @@
+use core::utils::{cos_deg, sin_deg, Axis, Corner3 as C3, P2, P3};
+use core::{ExtrudeParams, Extrusion, MinMaxCoord, Polygon2D, Tree};
+use errors::{ResultExt, ScadDotsError};
+use rect::{Rect, RectAlign};
+
+/// The area to fill with a lattice.
+#[derive(Debug, Clone)]
+pub enum LatticeRegion {
+    /// The whole outer face of a Rect, in the Rect's local xy plane, starting
+    /// at its outside bottom face.
+    Rect(Rect),
+    /// A polygon on the xy plane, starting at z=0. It must not intersect
+    /// itself.
+    Outline(Vec<P2>),
+}
+
+impl From<Rect> for LatticeRegion {
+    fn from(rect: Rect) -> Self {
+        LatticeRegion::Rect(rect)
+    }
+}
+
+impl From<Vec<P2>> for LatticeRegion {
+    fn from(outline: Vec<P2>) -> Self {
+        LatticeRegion::Outline(outline)
+    }
+}
+
+/// Return a honeycomb of hexagonal walls filling the region, extruded to the
+/// given thickness. The cells are regular hexagons with 2 sides parallel to
+/// the x axis, and `cell_size` is the distance between the centers of
+/// neighboring cells. The walls between cells are `wall_thickness` thick,
+/// and the cells are clipped to the region's outline without a border around
+/// it, so the result can be unioned into a frame or diffed out of a panel.
+pub fn honeycomb<T>(
+    region: T,
+    cell_size: f32,
+    wall_thickness: f32,
+    thickness: f32,
+) -> Result<Tree, ScadDotsError>
+where
+    T: Into<LatticeRegion>,
+{
+    if !(cell_size > 0.) || !(wall_thickness > 0.) || !(thickness > 0.) {
+        return Err(ScadDotsError::Dimension.context(
+            "honeycomb cell size, wall thickness, and thickness must be \
+             positive",
+        ));
+    }
+    if wall_thickness >= cell_size {
+        return Err(ScadDotsError::Dimension.context(
+            "honeycomb wall thickness must be less than the cell size",
+        ));
+    }
+    match region.into() {
+        LatticeRegion::Outline(outline) => {
+            flat_honeycomb(outline, cell_size, wall_thickness, thickness)
+        }
+        LatticeRegion::Rect(rect) => {
+            let x = rect.edge_length(Axis::X);
+            let y = rect.edge_length(Axis::Y);
+            let outline = vec![
+                P2::new(0., 0.),
+                P2::new(x, 0.),
+                P2::new(x, y),
+                P2::new(0., y),
+            ];
+            let flat =
+                flat_honeycomb(outline, cell_size, wall_thickness, thickness)?;
+            let origin = rect.pos(RectAlign::outside(C3::P000));
+            Ok(Tree::translate(
+                origin - P3::origin(),
+                Tree::rotate(rect.rot(), flat),
+            ))
+        }
+    }
+    .context("failed to make honeycomb")
+}
+
+/// Return a honeycomb filling the outline on the xy plane.
+fn flat_honeycomb(
+    outline: Vec<P2>,
+    cell_size: f32,
+    wall_thickness: f32,
+    thickness: f32,
+) -> Result<Tree, ScadDotsError> {
+    let perimeter = Polygon2D::new(outline.clone())
+        .context("honeycomb outline is invalid")?;
+    let x_min = outline.min_coord(Axis::X);
+    let x_max = outline.max_coord(Axis::X);
+    let y_min = outline.min_coord(Axis::Y);
+    let y_max = outline.max_coord(Axis::Y);
+
+    // Neighboring columns are offset by half a cell along y.
+    let column_spacing = cell_size * 3_f32.sqrt() / 2.;
+    let columns = ((x_max - x_min) / column_spacing).ceil() as usize + 2;
+    let rows = ((y_max - y_min) / cell_size).ceil() as usize + 2;
+    let hole_radius = (cell_size - wall_thickness) / 3_f32.sqrt();
+
+    let mut holes = Vec::new();
+    for i in 0..columns {
+        let shift = if i % 2 == 0 { 0. } else { cell_size / 2. };
+        for j in 0..rows {
+            let center = P2::new(
+                x_min + (i as f32 - 0.5) * column_spacing,
+                y_min + (j as f32 - 0.5) * cell_size + shift,
+            );
+            holes.push(hexagon(center, hole_radius, thickness)?);
+        }
+    }
+    let panel = Extrusion {
+        perimeter,
+        holes: Vec::new(),
+        bottom_z: 0.,
+        thickness,
+        params: ExtrudeParams::default(),
+    };
+    Ok(Tree::diff(vec![Tree::from(panel), Tree::union(holes)]))
+}
+
+/// Return a hexagonal hole with the given circumradius, extending past both
+/// faces of a panel with the given thickness.
+fn hexagon(
+    center: P2,
+    radius: f32,
+    thickness: f32,
+) -> Result<Extrusion, ScadDotsError> {
+    let perimeter = (0..6)
+        .map(|i| {
+            let degrees = i as f32 * 60.;
+            P2::new(
+                center.x + radius * cos_deg(degrees),
+                center.y + radius * sin_deg(degrees),
+            )
+        })
+        .collect();
+    Ok(Extrusion {
+        perimeter: Polygon2D::new(perimeter)?,
+        holes: Vec::new(),
+        bottom_z: -thickness,
+        thickness: 3. * thickness,
+        params: ExtrudeParams::default(),
+    })
+}
@@ -0,0 +1,46 @@
+use core::utils::{P3, V3};
+use core::{Bounds, Tree};
+use errors::ScadDotsError;
+
+/// Move the tree so it's centered on the origin in x and y, and its bottom
+/// is on the z=0 plane, ready for printing.
+pub fn center_on_origin(tree: Tree) -> Result<Tree, ScadDotsError> {
+    let bounds = bounds_of(&tree)?;
+    let center = bounds.center();
+    let offset = P3::origin() - P3::new(center.x, center.y, bounds.min.z);
+    Ok(Tree::translate(offset, tree))
+}
+
+/// Arrange the parts in a grid on the z=0 plane, like a print bed. Each part
+/// is centered in its own cell, with at least `spacing` between neighboring
+/// parts. The cells are all the same size, and the grid is as close to square
+/// as possible. The parts are placed row by row, starting from the origin
+/// and moving along the positive x and y axes.
+pub fn plate(parts: Vec<Tree>, spacing: f32) -> Result<Tree, ScadDotsError> {
+    if spacing < 0. {
+        return Err(
+            ScadDotsError::Dimension.context("plate spacing can't be negative")
+        );
+    }
+    let mut cell = V3::zeros();
+    for part in &parts {
+        let size = bounds_of(part)?.size();
+        cell.x = cell.x.max(size.x + spacing);
+        cell.y = cell.y.max(size.y + spacing);
+    }
+    let columns = (parts.len() as f32).sqrt().ceil() as usize;
+    let mut placed = Vec::new();
+    for (i, part) in parts.into_iter().enumerate() {
+        let (row, column) = (i / columns, i % columns);
+        let offset = V3::new(column as f32 * cell.x, row as f32 * cell.y, 0.);
+        placed.push(Tree::translate(offset, center_on_origin(part)?));
+    }
+    Ok(Tree::union(placed))
+}
+
+fn bounds_of(tree: &Tree) -> Result<Bounds, ScadDotsError> {
+    tree.bounds().ok_or_else(|| {
+        ScadDotsError::Args
+            .context("can't lay out a tree that's empty or has unknown bounds")
+    })
+}
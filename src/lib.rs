@@ -4,30 +4,67 @@ TODO document crate
 
 extern crate libc;
 extern crate nalgebra;
+extern crate png;
 
 #[macro_use]
 extern crate nom;
 
-extern crate scad;
-
 #[macro_use]
 extern crate approx;
 
 #[macro_use]
 extern crate scad_dots_derive;
 
+#[cfg(feature = "serialize")]
+extern crate serde;
+#[cfg(feature = "serialize")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "scene")]
+extern crate ron;
+#[cfg(feature = "scad")]
+extern crate scad;
+#[cfg(feature = "scene")]
+extern crate serde_json;
+
 pub use self::core::utils;
-pub use self::harness::{check_model, Action, MAX_RELATIVE};
+pub use self::harness::{
+    check_model, check_model_with, check_models, check_models_with, Action,
+    HarnessConfig, MAX_RELATIVE,
+};
 pub use self::parse::scad_relative_eq;
 
 #[macro_use]
 pub mod core;
+pub mod emit;
 pub mod errors;
+pub mod export;
 pub mod harness;
+pub mod params;
 pub mod parse;
+pub mod pattern;
+pub mod preview;
 pub mod render;
+#[cfg(feature = "scad")]
+pub mod scad_generator;
+#[cfg(feature = "scene")]
+pub mod scene;
 
+pub mod assembly;
 pub mod cuboid;
+pub mod enclosure;
+pub mod fastener;
+pub mod fillet;
+pub mod gear;
+pub mod generators;
+pub mod hardware;
+pub mod helpers;
+pub mod joint;
+pub mod keyboard;
+pub mod knurl;
+pub mod lattice;
+pub mod layout;
 pub mod post;
 pub mod rect;
+pub mod thread;
 pub mod triangle;
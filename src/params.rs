@@ -0,0 +1,323 @@
+use std::fmt;
+
+use core::MM_PER_INCH;
+use errors::ScadDotsError;
+
+/// The unit a parameter's value is written in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    /// Millimeters, like `core::Mm`.
+    Mm,
+    /// Inches, like `core::Inch`. They're converted to millimeters by
+    /// `ParamSet::get()`.
+    Inch,
+    Deg,
+    /// A plain number, like a count or a ratio.
+    Unitless,
+}
+
+/// A named, adjustable number used to build a model, like a wall thickness.
+/// Its default, value, and range are all in its own unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param<T> {
+    name: String,
+    description: Option<String>,
+    default: T,
+    value: T,
+    range: Option<(T, T)>,
+    step: Option<T>,
+    unit: Unit,
+}
+
+/// A collection of parameters, which parts read their dimensions from. The
+/// values can be changed before the parts are built, and the whole set can
+/// be written out as an OpenSCAD customizer header or a JSON schema.
+#[derive(Debug, Clone, Default)]
+pub struct ParamSet {
+    params: Vec<Param<f32>>,
+}
+
+/// Something that's built from a set of parameters.
+pub trait Parametric: Sized {
+    /// Return the parameters this reads, with their default values.
+    fn params() -> ParamSet;
+
+    /// Build it from the parameters, which may have been changed from their
+    /// defaults.
+    fn from_params(params: &ParamSet) -> Result<Self, ScadDotsError>;
+
+    /// Build it from the default parameters.
+    fn from_default_params() -> Result<Self, ScadDotsError> {
+        Self::from_params(&Self::params())
+    }
+}
+
+impl Unit {
+    /// Return how many millimeters (or degrees) are in one of this unit.
+    pub fn scale(self) -> f32 {
+        match self {
+            Unit::Inch => MM_PER_INCH,
+            Unit::Mm | Unit::Deg | Unit::Unitless => 1.,
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unit::Mm => write!(f, "mm"),
+            Unit::Inch => write!(f, "in"),
+            Unit::Deg => write!(f, "deg"),
+            Unit::Unitless => Ok(()),
+        }
+    }
+}
+
+impl Param<f32> {
+    /// Create a parameter in millimeters, with no range. The name must be a
+    /// valid OpenSCAD variable name.
+    pub fn new(name: &str, default: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            description: None,
+            default,
+            value: default,
+            range: None,
+            step: None,
+            unit: Unit::Mm,
+        }
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    /// Only allow values from min to max, inclusive.
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    /// How much the value changes per click of the OpenSCAD customizer's
+    /// spinbox or slider.
+    pub fn with_step(mut self, step: f32) -> Self {
+        self.step = Some(step);
+        self
+    }
+
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    pub fn default(&self) -> f32 {
+        self.default
+    }
+
+    /// Return the value in the parameter's own unit.
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Return the value converted from the parameter's unit to millimeters,
+    /// or degrees for angles.
+    pub fn converted(&self) -> f32 {
+        self.value * self.unit.scale()
+    }
+
+    fn check_value(&self, value: f32) -> Result<(), ScadDotsError> {
+        let in_range = match self.range {
+            Some((min, max)) => value >= min && value <= max,
+            None => true,
+        };
+        if !value.is_finite() || !in_range {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!(
+                    "parameter '{}' can't be {}{}",
+                    self.name,
+                    value,
+                    self.range_description()
+                )
+            }));
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), ScadDotsError> {
+        if !is_identifier(&self.name) {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!("invalid parameter name '{}'", self.name)
+            }));
+        }
+        if let Some((min, max)) = self.range {
+            if !min.is_finite() || !max.is_finite() || min > max {
+                return Err(ScadDotsError::Args.with_context(|| {
+                    format!("parameter '{}' has an invalid range", self.name)
+                }));
+            }
+        }
+        if let Some(step) = self.step {
+            if !step.is_finite() || step <= 0. {
+                return Err(ScadDotsError::Args.with_context(|| {
+                    format!("parameter '{}' has a non-positive step", self.name)
+                }));
+            }
+        }
+        self.check_value(self.value)
+    }
+
+    fn range_description(&self) -> String {
+        match self.range {
+            Some((min, max)) => format!(", it must be from {} to {}", min, max),
+            None => String::new(),
+        }
+    }
+
+    fn label(&self) -> String {
+        let description = self.description.as_ref().unwrap_or(&self.name);
+        match self.unit {
+            Unit::Unitless => description.to_owned(),
+            unit => format!("{} ({})", description, unit),
+        }
+    }
+}
+
+impl ParamSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a parameter with a unique name. Its default must be in its range.
+    pub fn add(&mut self, param: Param<f32>) -> Result<(), ScadDotsError> {
+        param.check()?;
+        if self.param(&param.name).is_some() {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!("already have a parameter named '{}'", param.name)
+            }));
+        }
+        self.params.push(param);
+        Ok(())
+    }
+
+    /// Change the value of a parameter, in its own unit.
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), ScadDotsError> {
+        let param = self
+            .params
+            .iter_mut()
+            .find(|param| param.name == name)
+            .ok_or_else(|| unknown_param(name))?;
+        param.check_value(value)?;
+        param.value = value;
+        Ok(())
+    }
+
+    /// Return the value of a parameter, converted to millimeters or degrees.
+    pub fn get(&self, name: &str) -> Result<f32, ScadDotsError> {
+        self.param(name)
+            .map(|param| param.converted())
+            .ok_or_else(|| unknown_param(name))
+    }
+
+    pub fn param(&self, name: &str) -> Option<&Param<f32>> {
+        self.params.iter().find(|param| param.name == name)
+    }
+
+    pub fn params(&self) -> &[Param<f32>] {
+        &self.params
+    }
+
+    /// Return OpenSCAD code declaring each parameter as a variable, with the
+    /// comments used by OpenSCAD's customizer panel to label them and limit
+    /// their ranges. The current values are used.
+    pub fn customizer_header(&self) -> String {
+        let mut code = String::from("/* [Parameters] */\n");
+        for param in &self.params {
+            code.push_str(&format!("// {}\n", param.label()));
+            code.push_str(&format!("{} = {};", param.name, param.value));
+            match (param.range, param.step) {
+                (Some((min, max)), Some(step)) => {
+                    code.push_str(&format!(" // [{}:{}:{}]", min, step, max))
+                }
+                (Some((min, max)), None) => {
+                    code.push_str(&format!(" // [{}:{}]", min, max))
+                }
+                (None, Some(step)) => code.push_str(&format!(" // {}", step)),
+                (None, None) => (),
+            }
+            code.push('\n');
+        }
+        code.push_str("/* [Hidden] */\n");
+        code
+    }
+
+    /// Return a JSON schema describing an object with a number for each
+    /// parameter, for editing the values with other tools. The defaults in
+    /// the schema are the current values.
+    pub fn json_schema(&self) -> String {
+        let properties: Vec<_> = self
+            .params
+            .iter()
+            .map(|param| {
+                let mut fields = vec![
+                    "\"type\": \"number\"".to_owned(),
+                    format!("\"description\": {}", json_string(&param.label())),
+                    format!("\"default\": {}", param.value),
+                ];
+                if let Some((min, max)) = param.range {
+                    fields.push(format!("\"minimum\": {}", min));
+                    fields.push(format!("\"maximum\": {}", max));
+                }
+                format!(
+                    "    {}: {{\n      {}\n    }}",
+                    json_string(&param.name),
+                    fields.join(",\n      ")
+                )
+            })
+            .collect();
+        format!(
+            "{{\n  \"$schema\": \"http://json-schema.org/draft-07/schema#\",\n  \
+             \"type\": \"object\",\n  \"properties\": {{\n{}\n  }}\n}}\n",
+            properties.join(",\n")
+        )
+    }
+}
+
+fn unknown_param(name: &str) -> ScadDotsError {
+    ScadDotsError::Args.context(&format!("no parameter named '{}'", name))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                quoted.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
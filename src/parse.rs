@@ -1,9 +1,17 @@
 use std;
+use std::fmt;
 
 use approx::{AbsDiffEq, RelativeEq};
+use core::utils::{axis_degrees, Corner3 as C3, P3, R3, V3};
+use core::Bounds;
+use emit::{RAW_END, RAW_START};
 use errors::ScadDotsError;
-use nom::{digit, float};
+use nom::{digit, ErrorKind, IResult, Needed};
 
+/// Return true if the two models have the same structure, and their numbers
+/// differ by at most `max_rel` times their size. Numbers that are within
+/// `max_rel` of each other also count as equal, since rounding noise around
+/// zero is huge relative to the numbers themselves.
 pub fn scad_relative_eq(
     a: &str,
     b: &str,
@@ -12,19 +20,98 @@ pub fn scad_relative_eq(
     Ok(relative_eq!(
         parse_scad(a)?,
         parse_scad(b)?,
+        epsilon = max_rel,
         max_relative = max_rel
     ))
 }
 
+/// Compare two models like `scad_relative_eq()`, but return every difference
+/// between them instead of a bool. It's empty if they're equal.
+pub fn scad_diff(
+    a: &str,
+    b: &str,
+    max_rel: f32,
+) -> Result<Vec<DiffEntry>, ScadDotsError> {
+    let method = EqMethod::Rel {
+        epsilon: max_rel,
+        max: max_rel,
+    };
+    let a = parse_scad(a)?;
+    let mut entries = Vec::new();
+    a.diff(&parse_scad(b)?, method, a.node_name(), &mut entries);
+    Ok(entries)
+}
+
+/// A single difference between two models, found by `scad_diff()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    /// The node types from the root down to the node that differs, with the
+    /// index of each one among its parent's children, like
+    /// `union/translate[1]/cube[0]`. Module calls are inlined first.
+    pub path: String,
+    /// The node type in the first model, like `cube`.
+    pub node: String,
+    /// The differing values in the first model.
+    pub a: String,
+    /// The differing values in the second model.
+    pub b: String,
+}
+
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} != {}", self.path, self.a, self.b)
+    }
+}
+
+/// Parse the OpenSCAD code and return the bounding box of the shapes it
+/// makes, or None if it's empty. Like `Tree::bounds()`, the box is
+/// conservative for differences and intersections, and raw code, imported
+/// files, and text are left out, since their sizes are unknown.
+pub fn scad_bounds(scad: &str) -> Result<Option<Bounds>, ScadDotsError> {
+    Ok(parse_scad(scad)?.bounds())
+}
+
 fn parse_scad(scad: &str) -> Result<ScadThing, ScadDotsError> {
-    let out = parser(scad.as_bytes());
-    if out.is_done() {
-        Ok(out.unwrap().1)
-    } else {
-        Err(ScadDotsError::Parse)
+    let (rest, items) = match parser(scad.as_bytes()) {
+        IResult::Done(rest, items) => (rest, items),
+        _ => return Err(ScadDotsError::Parse),
+    };
+    if !rest.iter().all(|c| (*c as char).is_whitespace()) {
+        return Err(ScadDotsError::Parse.with_context(|| {
+            format!(
+                "unexpected code: {}",
+                String::from_utf8_lossy(&rest[..rest.len().min(40)])
+            )
+        }));
+    }
+    let mut modules = Vec::new();
+    let mut body = Vec::new();
+    for item in items {
+        match item {
+            Item::Assignment => (),
+            Item::Module(name, statements) => {
+                modules.push((name, things(statements)?))
+            }
+            Item::Statement(statement) => body.push(thing(statement)?),
+        }
     }
+    // Several things at the top level are implicitly unioned.
+    let mut body = if body.len() == 1 {
+        body.remove(0)
+    } else {
+        ScadThing::Union(body)
+    };
+    // Replace module calls with the modules' bodies, so named parts compare
+    // equal to unnamed ones.
+    body.inline_modules(&modules, 0)?;
+    Ok(body)
 }
 
+type Module = (String, Vec<ScadThing>);
+
+/// How deeply module calls can be nested, to catch recursive modules.
+const MAX_CALL_DEPTH: usize = 100;
+
 type Double = (f32, f32);
 type Triple = (f32, f32, f32);
 
@@ -33,23 +120,63 @@ enum ScadThing {
     Difference(Vec<ScadThing>),
     Union(Vec<ScadThing>),
     Hull(Vec<ScadThing>),
+    Minkowski(Vec<ScadThing>),
+    Intersection(Vec<ScadThing>),
+    /// A modifier character like `%` or `#`, and the thing it's applied to
+    /// as the only child.
+    Modifier(char, Vec<ScadThing>),
     Translate(Triple, Vec<ScadThing>),
     Rotate(f32, Triple, Vec<ScadThing>),
     LinearExtrude {
         height: f32,
         center: bool,
-        convecity: f32, // misspelled in scad library!
+        // Older versions of this crate misspelled it as "convecity".
+        convecity: f32,
         twist: f32,
         slices: f32,
+        /// 1 if it isn't given.
+        scale: f32,
         children: Vec<ScadThing>, // can it actually have more than 1 child?
     },
-    Polygon(Vec<Double>, f32), // assume 'paths' is always 'undef'
-    // Color(Quad, Vec<ScadThing>),
-    Color(Triple, Vec<ScadThing>),
+    /// The angle, the convexity, and the children.
+    RotateExtrude(f32, f32, Vec<ScadThing>),
+    /// The points, the paths (empty if they're `undef`), and the convexity.
+    Polygon(Vec<Double>, Vec<Vec<f32>>, f32),
+    /// The rgb components, the alpha (1 if it isn't given), and the
+    /// children.
+    Color(Triple, f32, Vec<ScadThing>),
     Mirror(Triple, Vec<ScadThing>),
-    Cube(Triple),
-    Cylinder(f32, f32),
+    Scale(Triple, Vec<ScadThing>),
+    /// The size and whether it's centered.
+    Cube(Triple, bool),
+    /// The height, bottom diameter, top diameter, and whether it's centered.
+    Cylinder(f32, f32, f32, bool),
+    /// The diameter.
     Sphere(f32),
+    /// The radius.
+    Circle(f32),
+    Text {
+        size: f32,
+        spacing: f32,
+        /// The text, font, halign, valign, direction, language, and script.
+        strings: Vec<String>,
+    },
+    /// A call to the module with the given name.
+    Call(String),
+    /// Raw code, with its whitespace collapsed. It's only compared as text.
+    Raw(String),
+    /// The path of a file loaded with `import()`.
+    Import(String),
+    /// The path of a file added with `include <>`.
+    Include(String),
+    /// A `let()` that sets the curve detail variables `$fn`, `$fa`, and
+    /// `$fs`, if they're given.
+    Detail {
+        fn_: Option<f32>,
+        fa: Option<f32>,
+        fs: Option<f32>,
+        children: Vec<ScadThing>,
+    },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -69,41 +196,291 @@ impl EqMethod {
 
 impl ScadThing {
     fn map_eq(&self, other: &Self, method: EqMethod) -> bool {
+        let mut entries = Vec::new();
+        self.diff(other, method, String::new(), &mut entries);
+        entries.is_empty()
+    }
+
+    /// Add an entry for each difference between this thing and the other
+    /// one. The children are compared even if their counts differ.
+    fn diff(
+        &self,
+        other: &Self,
+        method: EqMethod,
+        path: String,
+        entries: &mut Vec<DiffEntry>,
+    ) {
+        let mut push = |a: String, b: String| {
+            entries.push(DiffEntry {
+                path: path.clone(),
+                node: self.node_name(),
+                a,
+                b,
+            })
+        };
+
         if !self.variant_eq(other) {
-            println!("\nNOT EQUAL: variants\n");
-            return false;
+            push(self.node_name(), other.node_name());
+            return;
         }
 
         if self.bools() != other.bools() {
-            return false;
+            push(
+                format!("{:?}", self.bools()),
+                format!("{:?}", other.bools()),
+            );
         }
 
-        if self.floats().len() != other.floats().len() {
-            return false;
+        if self.strings() != other.strings() {
+            push(
+                format!("{:?}", self.strings()),
+                format!("{:?}", other.strings()),
+            );
         }
 
-        for (a, b) in self.floats().into_iter().zip(other.floats().into_iter())
+        let (floats, other_floats) = (self.floats(), other.floats());
+        if floats.len() != other_floats.len()
+            || floats
+                .iter()
+                .zip(&other_floats)
+                .any(|(a, b)| !method.is_eq(*a, *b))
         {
-            if !method.is_eq(a, b) {
-                println!("\nNOT EQUAL: {}, {}\n", a, b);
-                return false;
-            }
+            push(format!("{:?}", floats), format!("{:?}", other_floats));
         }
 
-        if self.children().len() != other.children().len() {
-            return false;
+        let (children, other_children) = (self.children(), other.children());
+        if children.len() != other_children.len() {
+            push(
+                format!("{} children", children.len()),
+                format!("{} children", other_children.len()),
+            );
         }
 
-        for (c, d) in self
-            .children()
-            .into_iter()
-            .zip(other.children().into_iter())
-        {
-            if !c.map_eq(&d, method) {
-                return false;
+        for (i, (c, d)) in children.iter().zip(&other_children).enumerate() {
+            let child_path = format!("{}/{}[{}]", path, c.node_name(), i);
+            c.diff(d, method, child_path, entries);
+        }
+    }
+
+    /// The name of the OpenSCAD module or statement this thing came from.
+    fn node_name(&self) -> String {
+        let name = match *self {
+            ScadThing::Difference(_) => "difference",
+            ScadThing::Union(_) => "union",
+            ScadThing::Hull(_) => "hull",
+            ScadThing::Minkowski(_) => "minkowski",
+            ScadThing::Intersection(_) => "intersection",
+            ScadThing::Modifier(symbol, _) => return symbol.to_string(),
+            ScadThing::Translate(..) => "translate",
+            ScadThing::Rotate(..) => "rotate",
+            ScadThing::LinearExtrude { .. } => "linear_extrude",
+            ScadThing::RotateExtrude(..) => "rotate_extrude",
+            ScadThing::Polygon(..) => "polygon",
+            ScadThing::Color(..) => "color",
+            ScadThing::Mirror(..) => "mirror",
+            ScadThing::Scale(..) => "scale",
+            ScadThing::Cube(..) => "cube",
+            ScadThing::Cylinder(..) => "cylinder",
+            ScadThing::Sphere(_) => "sphere",
+            ScadThing::Circle(_) => "circle",
+            ScadThing::Text { .. } => "text",
+            ScadThing::Call(ref name) => return name.to_owned(),
+            ScadThing::Raw(_) => "raw scad",
+            ScadThing::Import(_) => "import",
+            ScadThing::Include(_) => "include",
+            ScadThing::Detail { .. } => "let",
+        };
+        name.to_owned()
+    }
+
+    /// Return the bounding box of this thing, or None if it's empty or
+    /// unknown. 2D shapes are flat boxes on the z=0 plane.
+    fn bounds(&self) -> Option<Bounds> {
+        let merge_all = |children: &[ScadThing]| {
+            children
+                .iter()
+                .filter_map(|child| child.bounds())
+                .fold(None, |acc, b| Some(Bounds::merge_option(acc, b)))
+        };
+        let v3 = |t: Triple| V3::new(t.0, t.1, t.2);
+        match *self {
+            ScadThing::Union(ref children)
+            | ScadThing::Hull(ref children)
+            | ScadThing::Color(_, _, ref children)
+            | ScadThing::Detail { ref children, .. } => merge_all(children),
+            ScadThing::Modifier(symbol, ref children) => match symbol {
+                // These don't appear in the final render.
+                '%' | '*' => None,
+                _ => merge_all(children),
+            },
+            ScadThing::Difference(ref children) => children.first()?.bounds(),
+            ScadThing::Intersection(ref children) => {
+                let mut boxes = children.iter().map(|child| child.bounds());
+                let first = boxes.next()??;
+                boxes.fold(Some(first), |acc, b| acc?.intersect(&b?))
+            }
+            ScadThing::Minkowski(ref children) => {
+                let mut boxes = children.iter().map(|child| child.bounds());
+                let first = boxes.next()??;
+                boxes.fold(Some(first), |acc, b| {
+                    let (acc, b) = (acc?, b?);
+                    Some(Bounds {
+                        min: acc.min + b.min.coords,
+                        max: acc.max + b.max.coords,
+                    })
+                })
+            }
+            ScadThing::Translate(offset, ref children) => {
+                let b = merge_all(children)?;
+                Some(Bounds {
+                    min: b.min + v3(offset),
+                    max: b.max + v3(offset),
+                })
+            }
+            ScadThing::Rotate(degrees, axis, ref children) => {
+                let rot = axis_degrees(v3(axis), degrees);
+                Some(merge_all(children)?.map_corners(|p| rot * p))
+            }
+            ScadThing::Mirror(normal, ref children) => {
+                let unit = v3(normal).normalize();
+                Some(merge_all(children)?.map_corners(|p| {
+                    p - 2. * (p - P3::origin()).dot(&unit) * unit
+                }))
+            }
+            ScadThing::Scale(factors, ref children) => {
+                Some(merge_all(children)?.map_corners(|p| {
+                    P3::from_coordinates(p.coords.component_mul(&v3(factors)))
+                }))
+            }
+            ScadThing::LinearExtrude {
+                height,
+                center,
+                twist,
+                scale,
+                ref children,
+                ..
+            } => {
+                let outline = merge_all(children)?;
+                let bottom_z = if center { -height / 2. } else { 0. };
+                let top_z = bottom_z + height;
+                if twist == 0. {
+                    let flat = |z: f32, scale: f32| {
+                        Bounds::from_points(vec![
+                            P3::new(
+                                scale * outline.min.x,
+                                scale * outline.min.y,
+                                z,
+                            ),
+                            P3::new(
+                                scale * outline.max.x,
+                                scale * outline.max.y,
+                                z,
+                            ),
+                        ])
+                        .expect("there are points")
+                    };
+                    Some(flat(bottom_z, 1.).merge(&flat(top_z, scale)))
+                } else {
+                    let radius = C3::all()
+                        .into_iter()
+                        .map(|corner| {
+                            let p = outline.corner(corner);
+                            p.x.hypot(p.y)
+                        })
+                        .fold(0., f32::max)
+                        * scale.max(1.);
+                    Some(Bounds {
+                        min: P3::new(-radius, -radius, bottom_z),
+                        max: P3::new(radius, radius, top_z),
+                    })
+                }
+            }
+            ScadThing::RotateExtrude(_, _, ref children) => {
+                let outline = merge_all(children)?;
+                let radius = outline.min.x.abs().max(outline.max.x.abs());
+                Some(Bounds {
+                    min: P3::new(-radius, -radius, outline.min.y),
+                    max: P3::new(radius, radius, outline.max.y),
+                })
+            }
+            ScadThing::Polygon(ref points, _, _) => Bounds::from_points(
+                points.iter().map(|&(x, y)| P3::new(x, y, 0.)),
+            ),
+            ScadThing::Cube(size, center) => {
+                let half = v3(size) / 2.;
+                let center = if center {
+                    P3::origin()
+                } else {
+                    P3::from_coordinates(half)
+                };
+                Some(Bounds::around(center, half))
+            }
+            ScadThing::Cylinder(height, bottom, top, center) => {
+                let radius = bottom.max(top) / 2.;
+                let bottom_z = if center { -height / 2. } else { 0. };
+                Some(Bounds {
+                    min: P3::new(-radius, -radius, bottom_z),
+                    max: P3::new(radius, radius, bottom_z + height),
+                })
+            }
+            ScadThing::Sphere(diameter) => {
+                let radius = diameter / 2.;
+                Some(Bounds::around(
+                    P3::origin(),
+                    V3::new(radius, radius, radius),
+                ))
+            }
+            ScadThing::Circle(radius) => {
+                Some(Bounds::around(P3::origin(), V3::new(radius, radius, 0.)))
+            }
+            ScadThing::Text { .. }
+            | ScadThing::Call(_)
+            | ScadThing::Raw(_)
+            | ScadThing::Import(_)
+            | ScadThing::Include(_) => None,
+        }
+    }
+
+    /// Replace every module call with the module's body, or a union if it
+    /// has more than 1 child.
+    fn inline_modules(
+        &mut self,
+        modules: &[Module],
+        depth: usize,
+    ) -> Result<(), ScadDotsError> {
+        if depth > MAX_CALL_DEPTH {
+            return Err(ScadDotsError::Parse
+                .with_context(|| "module calls are nested too deeply".into()));
+        }
+        let inlined = match *self {
+            ScadThing::Call(ref name) => {
+                let body = &modules
+                    .iter()
+                    .find(|module| module.0 == *name)
+                    .ok_or_else(|| {
+                        ScadDotsError::Parse.with_context(|| {
+                            format!("call to undefined module '{}'", name)
+                        })
+                    })?
+                    .1;
+                Some(if body.len() == 1 {
+                    body[0].clone()
+                } else {
+                    ScadThing::Union(body.clone())
+                })
+            }
+            _ => None,
+        };
+        if let Some(inlined) = inlined {
+            *self = inlined;
+            return self.inline_modules(modules, depth + 1);
+        }
+        if let Some(children) = self.children_mut() {
+            for child in children {
+                child.inline_modules(modules, depth)?;
             }
         }
-        true
+        Ok(())
     }
 
     fn variant_eq(&self, other: &Self) -> bool {
@@ -112,46 +489,95 @@ impl ScadThing {
 
     fn bools(&self) -> Vec<bool> {
         match *self {
-            ScadThing::LinearExtrude { center, .. } => vec![center],
+            ScadThing::LinearExtrude { center, .. }
+            | ScadThing::Cube(_, center)
+            | ScadThing::Cylinder(_, _, _, center) => vec![center],
+            ScadThing::Detail { fn_, fa, fs, .. } => {
+                vec![fn_.is_some(), fa.is_some(), fs.is_some()]
+            }
 
             ScadThing::Color(..)
             | ScadThing::Rotate(..)
             | ScadThing::Translate(..)
             | ScadThing::Union(..)
             | ScadThing::Hull(..)
+            | ScadThing::Minkowski(..)
+            | ScadThing::Intersection(..)
+            | ScadThing::Modifier(..)
             | ScadThing::Difference(..)
             | ScadThing::Mirror(..)
-            | ScadThing::Cube(..)
+            | ScadThing::Scale(..)
             | ScadThing::Sphere(..)
-            | ScadThing::Cylinder(..)
+            | ScadThing::Text { .. }
+            | ScadThing::Call(..)
+            | ScadThing::Raw(..)
+            | ScadThing::Import(..)
+            | ScadThing::Include(..)
+            | ScadThing::RotateExtrude(..)
+            | ScadThing::Circle(..)
             | ScadThing::Polygon(..) => Vec::new(),
         }
     }
 
+    fn strings(&self) -> Vec<String> {
+        match *self {
+            ScadThing::Text { ref strings, .. } => strings.to_owned(),
+            ScadThing::Modifier(symbol, _) => vec![symbol.to_string()],
+            ScadThing::Call(ref text)
+            | ScadThing::Raw(ref text)
+            | ScadThing::Import(ref text)
+            | ScadThing::Include(ref text) => vec![text.to_owned()],
+            _ => Vec::new(),
+        }
+    }
+
     fn floats(&self) -> Vec<f32> {
         match *self {
             ScadThing::Translate(v, _)
-            | ScadThing::Cube(v)
-            | ScadThing::Mirror(v, _) => vec![v.0, v.1, v.2],
+            | ScadThing::Cube(v, _)
+            | ScadThing::Mirror(v, _)
+            | ScadThing::Scale(v, _) => vec![v.0, v.1, v.2],
             ScadThing::Rotate(f, v, _) => vec![f, v.0, v.1, v.2],
-            ScadThing::Color(rgb, _) => vec![rgb.0, rgb.1, rgb.2],
-            ScadThing::Cylinder(f1, f2) => vec![f1, f2],
-            ScadThing::Sphere(f) => vec![f],
+            ScadThing::Color(rgb, alpha, _) => {
+                vec![rgb.0, rgb.1, rgb.2, alpha]
+            }
+            ScadThing::Cylinder(f1, f2, f3, _) => vec![f1, f2, f3],
+            ScadThing::Sphere(f) | ScadThing::Circle(f) => vec![f],
+            ScadThing::Text { size, spacing, .. } => vec![size, spacing],
+            ScadThing::RotateExtrude(angle, convexity, _) => {
+                vec![angle, convexity]
+            }
             ScadThing::LinearExtrude {
                 height,
                 convecity,
                 twist,
                 slices,
+                scale,
                 ..
-            } => vec![height, convecity, twist, slices],
-            ScadThing::Polygon(ref points, convexity) => {
+            } => vec![height, convecity, twist, slices, scale],
+            ScadThing::Polygon(ref points, ref paths, convexity) => {
                 let mut v = flatten(points);
+                for path in paths {
+                    // Include the length so different groupings don't match.
+                    v.push(path.len() as f32);
+                    v.extend(path);
+                }
                 v.push(convexity);
                 v
             }
+            ScadThing::Detail { fn_, fa, fs, .. } => {
+                fn_.into_iter().chain(fa).chain(fs).collect()
+            }
             ScadThing::Difference(_)
             | ScadThing::Union(_)
-            | ScadThing::Hull(_) => Vec::new(),
+            | ScadThing::Hull(_)
+            | ScadThing::Minkowski(_)
+            | ScadThing::Intersection(_)
+            | ScadThing::Modifier(..)
+            | ScadThing::Call(_)
+            | ScadThing::Raw(_)
+            | ScadThing::Import(_)
+            | ScadThing::Include(_) => Vec::new(),
         }
     }
 
@@ -159,18 +585,64 @@ impl ScadThing {
         match *self {
             ScadThing::Translate(_, ref children)
             | ScadThing::Rotate(_, _, ref children)
-            | ScadThing::Color(_, ref children)
+            | ScadThing::Color(_, _, ref children)
             | ScadThing::Mirror(_, ref children)
+            | ScadThing::Scale(_, ref children)
             | ScadThing::Hull(ref children)
+            | ScadThing::Minkowski(ref children)
+            | ScadThing::Intersection(ref children)
             | ScadThing::Difference(ref children)
             | ScadThing::LinearExtrude { ref children, .. }
+            | ScadThing::Detail { ref children, .. }
+            | ScadThing::RotateExtrude(_, _, ref children)
+            | ScadThing::Modifier(_, ref children)
             | ScadThing::Union(ref children) => children.to_owned(),
+
             ScadThing::Cube(..)
             | ScadThing::Sphere(..)
+            | ScadThing::Circle(..)
             | ScadThing::Cylinder(..)
+            | ScadThing::Text { .. }
+            | ScadThing::Call(..)
+            | ScadThing::Raw(..)
+            | ScadThing::Import(..)
+            | ScadThing::Include(..)
             | ScadThing::Polygon(..) => Vec::new(),
         }
     }
+
+    fn children_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match *self {
+            ScadThing::Translate(_, ref mut children)
+            | ScadThing::Rotate(_, _, ref mut children)
+            | ScadThing::Color(_, _, ref mut children)
+            | ScadThing::Mirror(_, ref mut children)
+            | ScadThing::Scale(_, ref mut children)
+            | ScadThing::Hull(ref mut children)
+            | ScadThing::Minkowski(ref mut children)
+            | ScadThing::Intersection(ref mut children)
+            | ScadThing::Difference(ref mut children)
+            | ScadThing::LinearExtrude {
+                ref mut children, ..
+            }
+            | ScadThing::Detail {
+                ref mut children, ..
+            }
+            | ScadThing::RotateExtrude(_, _, ref mut children)
+            | ScadThing::Modifier(_, ref mut children)
+            | ScadThing::Union(ref mut children) => Some(children),
+            ScadThing::Cube(..)
+            | ScadThing::Sphere(..)
+            | ScadThing::Circle(..)
+            | ScadThing::Cylinder(..)
+            | ScadThing::Text { .. }
+            | ScadThing::Call(..)
+            | ScadThing::Raw(..)
+            | ScadThing::Import(..)
+            | ScadThing::Include(..)
+            | ScadThing::Polygon(..) => None,
+        }
+    }
 }
 
 impl AbsDiffEq for ScadThing {
@@ -214,292 +686,666 @@ fn flatten(points: &[Double]) -> Vec<f32> {
     floats
 }
 
-named!(
-    parser<ScadThing>,
-    ws!(do_parse!(
-        // ignore the curve detail level in the header
-        _detail: opt!(detail) >> body: scad_thing >> (body)
-    ))
-);
+/// A value passed as an argument.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f32),
+    Bool(bool),
+    Str(String),
+    List(Vec<Value>),
+    Undef,
+}
 
-named!(
-    scad_thing<ScadThing>,
-    ws!(alt!(
-        cube | sphere
-            | cylinder
-            | union
-            | difference
-            | hull
-            | translate
-            | rotate
-            | color
-            | polygon
-            | linear_extrude
-            | mirror
-    ))
-);
+/// An argument, with its name if it was given by name.
+type Arg = (Option<String>, Value);
 
-named!(
-    detail<f32>,
-    ws!(do_parse!(
-        // TODO return i32 instead?
-        tag!("$fn=") >> detail: number >> tag!(";") >> (detail)
-    ))
-);
+/// A statement, before its arguments are checked.
+#[derive(Debug, Clone, PartialEq)]
+enum Statement {
+    /// The name, the arguments, and the children. The children are `None`
+    /// if the call ended with a semicolon.
+    Call(String, Vec<Arg>, Option<Vec<Statement>>),
+    Raw(String),
+    Include(String),
+    /// A modifier character like `%` or `#`, and the statement it's applied
+    /// to.
+    Modifier(char, Box<Statement>),
+}
+
+/// Something at the top level of a file.
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    /// A variable assignment like `$fn=5;`, which is ignored.
+    Assignment,
+    Module(String, Vec<Statement>),
+    Statement(Statement),
+}
+
+impl Value {
+    fn number(&self) -> Option<f32> {
+        match *self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Return the numbers in a list, if it only contains numbers.
+    fn numbers(&self) -> Option<Vec<f32>> {
+        match *self {
+            Value::List(ref items) => items.iter().map(Value::number).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// The arguments of a single call. Each one can be taken by name or by
+/// position, and any left over are an error.
+struct Args {
+    function: String,
+    args: Vec<(Option<String>, Option<Value>)>,
+}
+
+impl Args {
+    fn new(function: &str, args: Vec<Arg>) -> Args {
+        Args {
+            function: function.to_owned(),
+            args: args
+                .into_iter()
+                .map(|(name, value)| (name, Some(value)))
+                .collect(),
+        }
+    }
+
+    /// Take the argument with the given name, or else the unnamed argument
+    /// at the given position. An `undef` argument counts as missing.
+    fn take(&mut self, name: &str, position: Option<usize>) -> Option<Value> {
+        let index = self
+            .args
+            .iter()
+            .position(|arg| arg.0.as_ref().map(String::as_str) == Some(name))
+            .or_else(|| {
+                position.and_then(|position| {
+                    self.args
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, arg)| arg.0.is_none())
+                        .nth(position)
+                        .map(|(index, _)| index)
+                })
+            })?;
+        match self.args[index].1.take() {
+            Some(Value::Undef) => None,
+            value => value,
+        }
+    }
+
+    fn error(&self, message: &str) -> ScadDotsError {
+        ScadDotsError::Parse.with_context(|| {
+            format!("{} in call to {}()", message, self.function)
+        })
+    }
+
+    fn type_error(&self, name: &str, expected: &str) -> ScadDotsError {
+        self.error(&format!("argument '{}' isn't {}", name, expected))
+    }
+
+    fn number(
+        &mut self,
+        name: &str,
+        position: Option<usize>,
+    ) -> Result<Option<f32>, ScadDotsError> {
+        match self.take(name, position) {
+            None => Ok(None),
+            Some(Value::Number(n)) => Ok(Some(n)),
+            Some(_) => Err(self.type_error(name, "a number")),
+        }
+    }
+
+    fn boolean(
+        &mut self,
+        name: &str,
+        position: Option<usize>,
+    ) -> Result<Option<bool>, ScadDotsError> {
+        match self.take(name, position) {
+            None => Ok(None),
+            Some(Value::Bool(b)) => Ok(Some(b)),
+            Some(_) => Err(self.type_error(name, "a boolean")),
+        }
+    }
+
+    fn string(
+        &mut self,
+        name: &str,
+        position: Option<usize>,
+    ) -> Result<Option<String>, ScadDotsError> {
+        match self.take(name, position) {
+            None => Ok(None),
+            Some(Value::Str(s)) => Ok(Some(s)),
+            Some(_) => Err(self.type_error(name, "a string")),
+        }
+    }
+
+    /// Take a vector of 2 or 3 numbers. A missing z component is set to
+    /// `fill`. If `scalar` is true, a single number is used for all 3
+    /// components.
+    fn vector(
+        &mut self,
+        name: &str,
+        position: Option<usize>,
+        fill: f32,
+        scalar: bool,
+    ) -> Result<Option<Triple>, ScadDotsError> {
+        let value = match self.take(name, position) {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+        match (value.number(), value.numbers()) {
+            (Some(n), _) if scalar => Ok(Some((n, n, n))),
+            (_, Some(ref v)) if v.len() == 2 => Ok(Some((v[0], v[1], fill))),
+            (_, Some(ref v)) if v.len() == 3 => Ok(Some((v[0], v[1], v[2]))),
+            _ => Err(self.type_error(name, "a vector")),
+        }
+    }
+
+    /// Take the curve detail variables `$fn`, `$fa`, and `$fs`.
+    fn detail(
+        &mut self,
+    ) -> Result<(Option<f32>, Option<f32>, Option<f32>), ScadDotsError> {
+        Ok((
+            self.number("$fn", None)?,
+            self.number("$fa", None)?,
+            self.number("$fs", None)?,
+        ))
+    }
+
+    /// Check that every argument was used.
+    fn finish(&self) -> Result<(), ScadDotsError> {
+        let mut position = 0;
+        for arg in &self.args {
+            match *arg {
+                (Some(ref name), Some(_)) => {
+                    return Err(
+                        self.error(&format!("unexpected argument '{}'", name))
+                    );
+                }
+                (None, Some(_)) => {
+                    return Err(self.error(&format!(
+                        "unexpected argument at position {}",
+                        position
+                    )));
+                }
+                (None, None) => position += 1,
+                (Some(_), None) => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn things(statements: Vec<Statement>) -> Result<Vec<ScadThing>, ScadDotsError> {
+    statements.into_iter().map(thing).collect()
+}
+
+/// Check the arguments of a statement and convert it into a `ScadThing`.
+/// Arguments can be given in any order, by name or by position, and missing
+/// ones get OpenSCAD's defaults. Curve detail arguments like `$fn` are
+/// treated like a `let()` around the call.
+fn thing(statement: Statement) -> Result<ScadThing, ScadDotsError> {
+    let (name, args, children) = match statement {
+        Statement::Raw(code) => return Ok(ScadThing::Raw(code)),
+        Statement::Include(path) => return Ok(ScadThing::Include(path)),
+        Statement::Modifier(symbol, statement) => {
+            return Ok(ScadThing::Modifier(symbol, vec![thing(*statement)?]));
+        }
+        Statement::Call(name, args, children) => (name, args, children),
+    };
+    let is_call = args.is_empty() && children.is_none();
+    let children = things(children.unwrap_or_default())?;
+    let mut args = Args::new(&name, args);
+    let (fn_, fa, fs) = args.detail()?;
+
+    let thing = match name.as_str() {
+        "let" => {
+            args.finish()?;
+            return Ok(ScadThing::Detail {
+                fn_,
+                fa,
+                fs,
+                children,
+            });
+        }
+        "union" => ScadThing::Union(children),
+        "difference" => ScadThing::Difference(children),
+        "hull" => ScadThing::Hull(children),
+        "minkowski" => ScadThing::Minkowski(children),
+        "intersection" => ScadThing::Intersection(children),
+        "translate" => ScadThing::Translate(
+            args.vector("v", Some(0), 0., false)?
+                .ok_or_else(|| args.error("missing vector"))?,
+            children,
+        ),
+        "mirror" => ScadThing::Mirror(
+            args.vector("v", Some(0), 0., false)?
+                .ok_or_else(|| args.error("missing vector"))?,
+            children,
+        ),
+        "scale" => ScadThing::Scale(
+            args.vector("v", Some(0), 1., true)?
+                .ok_or_else(|| args.error("missing vector"))?,
+            children,
+        ),
+        "rotate" => {
+            let (angle, axis) = rotation(&mut args)?;
+            ScadThing::Rotate(angle, axis, children)
+        }
+        "color" => {
+            let rgba = match args.take("c", Some(0)) {
+                None => vec![1., 1., 1.],
+                Some(Value::Str(_)) => {
+                    return Err(args.error("named colors aren't supported"))
+                }
+                Some(value) => value
+                    .numbers()
+                    .filter(|v| v.len() == 3 || v.len() == 4)
+                    .ok_or_else(|| args.type_error("c", "a color vector"))?,
+            };
+            let alpha = args
+                .number("alpha", Some(1))?
+                .or_else(|| rgba.get(3).cloned())
+                .unwrap_or(1.);
+            ScadThing::Color((rgba[0], rgba[1], rgba[2]), alpha, children)
+        }
+        "linear_extrude" => {
+            let height = args.number("height", Some(0))?.unwrap_or(100.);
+            let center = args.boolean("center", Some(1))?.unwrap_or(false);
+            let convecity = match args.number("convexity", Some(2))? {
+                Some(convexity) => Some(convexity),
+                None => args.number("convecity", None)?,
+            };
+            let twist = args.number("twist", Some(3))?.unwrap_or(0.);
+            let slices = args.number("slices", Some(4))?.unwrap_or(1.);
+            let scale = match args.vector("scale", Some(5), 1., true)? {
+                None => 1.,
+                Some((x, y, _)) if x == y => x,
+                Some(_) => {
+                    return Err(args.error("unequal scale factors"));
+                }
+            };
+            ScadThing::LinearExtrude {
+                height,
+                center,
+                convecity: convecity.unwrap_or(1.),
+                twist,
+                slices,
+                scale,
+                children,
+            }
+        }
+        "rotate_extrude" => ScadThing::RotateExtrude(
+            args.number("angle", None)?.unwrap_or(360.),
+            args.number("convexity", None)?.unwrap_or(1.),
+            children,
+        ),
+        "cube" => ScadThing::Cube(
+            args.vector("size", Some(0), 1., true)?
+                .unwrap_or((1., 1., 1.)),
+            args.boolean("center", Some(1))?.unwrap_or(false),
+        ),
+        "sphere" => {
+            let radius = args.number("r", Some(0))?;
+            let diameter = args.number("d", None)?;
+            ScadThing::Sphere(
+                diameter.or_else(|| radius.map(|r| r * 2.)).unwrap_or(2.),
+            )
+        }
+        "circle" => {
+            let radius = args.number("r", Some(0))?;
+            let diameter = args.number("d", None)?;
+            ScadThing::Circle(
+                radius.or_else(|| diameter.map(|d| d / 2.)).unwrap_or(1.),
+            )
+        }
+        "cylinder" => {
+            let height = args.number("h", Some(0))?.unwrap_or(1.);
+            let r1 = args.number("r1", Some(1))?;
+            let r2 = args.number("r2", Some(2))?;
+            let center = args.boolean("center", Some(3))?.unwrap_or(false);
+            let r = args.number("r", None)?;
+            let d = args.number("d", None)?;
+            let d1 = args.number("d1", None)?;
+            let d2 = args.number("d2", None)?;
+            let both = d.or_else(|| r.map(|r| r * 2.)).unwrap_or(2.);
+            ScadThing::Cylinder(
+                height,
+                d1.or_else(|| r1.map(|r| r * 2.)).unwrap_or(both),
+                d2.or_else(|| r2.map(|r| r * 2.)).unwrap_or(both),
+                center,
+            )
+        }
+        "polygon" => {
+            let points = args
+                .take("points", Some(0))
+                .ok_or_else(|| args.error("missing points"))?;
+            let points = match points {
+                Value::List(ref points) => points
+                    .iter()
+                    .map(|p| match p.numbers() {
+                        Some(ref v) if v.len() == 2 => Some((v[0], v[1])),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>(),
+                _ => None,
+            }
+            .ok_or_else(|| args.type_error("points", "a list of 2d points"))?;
+            let paths = match args.take("paths", Some(1)) {
+                None => Vec::new(),
+                Some(Value::List(ref paths)) => paths
+                    .iter()
+                    .map(Value::numbers)
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| {
+                        args.type_error("paths", "a list of paths")
+                    })?,
+                Some(_) => return Err(args.type_error("paths", "a list")),
+            };
+            let convexity = args.number("convexity", Some(2))?.unwrap_or(1.);
+            ScadThing::Polygon(points, paths, convexity)
+        }
+        "text" => {
+            let text = args
+                .string("text", Some(0))?
+                .ok_or_else(|| args.error("missing text"))?;
+            let size = args.number("size", Some(1))?.unwrap_or(10.);
+            let mut strings = vec![text];
+            for &(name, default) in &[
+                ("font", ""),
+                ("halign", "left"),
+                ("valign", "baseline"),
+                ("direction", "ltr"),
+                ("language", "en"),
+                ("script", "latin"),
+            ] {
+                let position = if name == "font" { Some(2) } else { None };
+                strings.push(
+                    args.string(name, position)?
+                        .unwrap_or_else(|| default.to_owned()),
+                );
+            }
+            let spacing = args.number("spacing", None)?.unwrap_or(1.);
+            ScadThing::Text {
+                size,
+                spacing,
+                strings,
+            }
+        }
+        "import" => {
+            let path = args
+                .string("file", Some(0))?
+                .ok_or_else(|| args.error("missing file"))?;
+            // The convexity only affects the preview.
+            args.number("convexity", None)?;
+            ScadThing::Import(path)
+        }
+        _ if is_call => ScadThing::Call(name.clone()),
+        _ => return Err(args.error("unknown module")),
+    };
+    args.finish()?;
+    if fn_.is_none() && fa.is_none() && fs.is_none() {
+        Ok(thing)
+    } else {
+        Ok(ScadThing::Detail {
+            fn_,
+            fa,
+            fs,
+            children: vec![thing],
+        })
+    }
+}
+
+/// Take the arguments of a `rotate()`, as an angle in degrees and an axis.
+/// A vector of Euler angles is converted to the same form.
+fn rotation(args: &mut Args) -> Result<(f32, Triple), ScadDotsError> {
+    let angles = match args.take("a", Some(0)) {
+        None => return Ok((0., (0., 0., 1.))),
+        Some(Value::Number(angle)) => {
+            let axis = args.vector("v", Some(1), 0., false)?;
+            return Ok((angle, axis.unwrap_or((0., 0., 1.))));
+        }
+        Some(value) => value
+            .numbers()
+            .filter(|v| v.len() == 3)
+            .ok_or_else(|| args.type_error("a", "an angle"))?,
+    };
+    let rot = R3::from_euler_angles(
+        angles[0].to_radians(),
+        angles[1].to_radians(),
+        angles[2].to_radians(),
+    );
+    Ok(match rot.axis_angle() {
+        Some((axis, angle)) => (angle.to_degrees(), (axis.x, axis.y, axis.z)),
+        None => (0., (0., 0., 1.)),
+    })
+}
 
 named!(
-    union<ScadThing>,
-    ws!(do_parse!(
-        tag!("union")
-            >> tag!("()")
-            >> tag!("{")
-            >> children: many1!(scad_thing)
-            >> tag!("}")
-            >> (ScadThing::Union(children))
-    ))
+    parser<Vec<Item>>,
+    ws!(many1!(complete!(alt!(assignment | module | top_statement))))
 );
 
+named!(top_statement<Item>, map!(statement, Item::Statement));
+
 named!(
-    difference<ScadThing>,
+    assignment<Item>,
     ws!(do_parse!(
-        tag!("difference")
-            >> tag!("()")
-            >> tag!("{")
-            >> children: many1!(scad_thing)
-            >> tag!("}")
-            >> (ScadThing::Difference(children))
+        argument_name >> tag!("=") >> value >> tag!(";") >> (Item::Assignment)
     ))
 );
 
 named!(
-    color<ScadThing>,
+    module<Item>,
     ws!(do_parse!(
-        tag!("color")
+        tag!("module")
+            >> name: identifier
             >> tag!("(")
-            >> rgb: rgb
             >> tag!(")")
             >> tag!("{")
-            >> children: many1!(scad_thing)
+            >> body: many0!(statement)
             >> tag!("}")
-            >> (ScadThing::Color(rgb, children))
+            >> (Item::Module(name, body))
     ))
 );
 
 named!(
-    mirror<ScadThing>,
-    ws!(do_parse!(
-        tag!("mirror")
-            >> tag!("(")
-            >> vector: triple
-            >> tag!(")")
-            >> tag!("{")
-            >> children: many1!(scad_thing)
-            >> tag!("}")
-            >> (ScadThing::Mirror(vector, children))
-    ))
+    statement<Statement>,
+    ws!(alt!(raw | include | modifier | call))
 );
 
 named!(
-    translate<ScadThing>,
+    modifier<Statement>,
     ws!(do_parse!(
-        tag!("translate")
-            >> tag!("(")
-            >> vector: triple
-            >> tag!(")")
-            >> tag!("{")
-            >> children: many1!(scad_thing)
-            >> tag!("}")
-            >> (ScadThing::Translate(vector, children))
+        symbol: one_of!("%#!*")
+            >> statement: statement
+            >> (Statement::Modifier(symbol, Box::new(statement)))
     ))
 );
 
 named!(
-    polygon<ScadThing>,
+    call<Statement>,
     ws!(do_parse!(
-        tag!("polygon")
+        name: identifier
             >> tag!("(")
-            >> tag!("points")
-            >> tag!("=")
-            >> tag!("[")
-            >> point_vec: many1!(double_trailing_comma)
-            >> tag!("]")
-            >> tag!(",")
-            >> tag!("paths")
-            >> tag!("=")
-            >> tag!("undef")
-            >> tag!(",")
-            >> tag!("convexity")
-            >> tag!("=")
-            >> convexity: number
+            >> args: arguments
             >> tag!(")")
-            >> tag!(";")
-            >> (ScadThing::Polygon(point_vec, convexity))
+            >> children: alt!(
+                do_parse!(tag!(";") >> (None))
+                    | do_parse!(
+                        tag!("{")
+                            >> children: many0!(statement)
+                            >> tag!("}")
+                            >> (Some(children))
+                    )
+                    | map!(statement, |child| Some(vec![child]))
+            )
+            >> (Statement::Call(name, args, children))
     ))
 );
 
 named!(
-    linear_extrude<ScadThing>,
+    raw<Statement>,
     ws!(do_parse!(
-        tag!("linear_extrude")
-            >> tag!("(")
-            >> tag!("height")
-            >> tag!("=")
-            >> height: number
-            >> tag!(",")
-            >> tag!("center")
-            >> tag!("=")
-            >> center: boolean
-            >> tag!(",")
-            >> tag!("convecity")
-            >> tag!("=")
-            >> convecity: number
-            >> tag!(",")
-            >> tag!("twist")
-            >> tag!("=")
-            >> twist: number
-            >> tag!(",")
-            >> tag!("slices")
-            >> tag!("=")
-            >> slices: number
-            >> tag!(")")
-            >> tag!("{")
-            >> children: many1!(scad_thing)
-            >> tag!("}")
-            >> (ScadThing::LinearExtrude {
-                height,
-                center,
-                convecity,
-                twist,
-                slices,
-                children,
-            })
+        tag!(RAW_START)
+            >> code: map_res!(take_until_and_consume!(RAW_END), std::str::from_utf8)
+            >> (Statement::Raw(code.split_whitespace().collect::<Vec<_>>().join(" ")))
     ))
 );
 
 named!(
-    rotate<ScadThing>,
+    include<Statement>,
     ws!(do_parse!(
-        tag!("rotate")
-            >> tag!("(")
-            >> angle: number
-            >> tag!(",")
-            >> axis: triple
-            >> tag!(")")
-            >> tag!("{")
-            >> children: many1!(scad_thing)
-            >> tag!("}")
-            >> (ScadThing::Rotate(angle, axis, children))
+        tag!("include")
+            >> tag!("<")
+            >> path: map_res!(is_not!(">"), std::str::from_utf8)
+            >> tag!(">")
+            >> (Statement::Include(path.to_owned()))
     ))
 );
 
 named!(
-    hull<ScadThing>,
+    arguments<Vec<Arg>>,
     ws!(do_parse!(
-        tag!("hull")
-            >> tag!("()")
-            >> tag!("{")
-            >> children: many1!(scad_thing)
-            >> tag!("}")
-            >> (ScadThing::Hull(children))
+        args: separated_list!(tag!(","), argument)
+            >> opt!(tag!(","))
+            >> (args)
     ))
 );
 
 named!(
-    cube<ScadThing>,
+    argument<Arg>,
     ws!(do_parse!(
-        tag!("cube")
-            >> tag!("(")
-            >> dims: triple
-            >> tag!(")")
-            >> tag!(";")
-            >> (ScadThing::Cube(dims))
+        name: opt!(argument_name_equals) >> value: value >> ((name, value))
     ))
 );
 
 named!(
-    sphere<ScadThing>,
-    ws!(do_parse!(
-        tag!("sphere")
-            >> tag!("(d=")
-            >> diameter: number
-            >> tag!(")")
-            >> tag!(";")
-            >> (ScadThing::Sphere(diameter))
-    ))
+    argument_name_equals<String>,
+    ws!(do_parse!(name: argument_name >> tag!("=") >> (name)))
 );
 
 named!(
-    cylinder<ScadThing>,
-    ws!(do_parse!(
-        tag!("cylinder")
-            >> tag!("(")
-            >> tag!("h=")
-            >> height: number
-            >> tag!(",")
-            >> tag!("d=")
-            >> diameter: number
-            >> tag!(")")
-            >> tag!(";")
-            >> (ScadThing::Cylinder(height, diameter))
+    value<Value>,
+    ws!(alt!(
+        map!(number, Value::Number)
+            | do_parse!(tag!("true") >> (Value::Bool(true)))
+            | do_parse!(tag!("false") >> (Value::Bool(false)))
+            | do_parse!(tag!("undef") >> (Value::Undef))
+            | map!(string, Value::Str)
+            | list
     ))
 );
 
 named!(
-    rgb<Triple>,
+    list<Value>,
     ws!(do_parse!(
-        tag!("[") >>
-        r: number >>
-        tag!(",") >>
-        g: number >>
-        tag!(",") >>
-        b: number >>
-    // tag!(",") >>
-    // a: number >>
-        tag!("]") >>
-        // (r,g,b,a)
-        (r,g,b)
+        tag!("[")
+            >> items: separated_list!(tag!(","), value)
+            >> opt!(tag!(","))
+            >> tag!("]")
+            >> (Value::List(items))
     ))
 );
 
 named!(
-    double_trailing_comma<Double>,
-    ws!(do_parse!(p: double >> tag!(",") >> (p)))
-);
-
-named!(
-    double<Double>,
-    ws!(do_parse!(
-        tag!("[") >> x: number >> tag!(",") >> y: number >> tag!("]") >> (x, y)
-    ))
+    identifier<String>,
+    map!(
+        map_res!(take_while1!(is_identifier_char), std::str::from_utf8),
+        str::to_owned
+    )
 );
 
+// Like an identifier, but it can be a special variable like `$fn`.
 named!(
-    triple<Triple>,
-    ws!(do_parse!(
-        tag!("[")
-            >> x: number
-            >> tag!(",")
-            >> y: number
-            >> tag!(",")
-            >> z: number
-            >> tag!("]")
-            >> (x, y, z)
-    ))
+    argument_name<String>,
+    map!(
+        map_res!(
+            recognize!(pair!(
+                opt!(tag!("$")),
+                take_while1!(is_identifier_char)
+            )),
+            std::str::from_utf8
+        ),
+        str::to_owned
+    )
 );
 
-named!(boolean<bool>, alt!(true_string | false_string));
-named!(true_string<bool>, ws!(do_parse!(tag!("true") >> (true))));
-named!(false_string<bool>, ws!(do_parse!(tag!("false") >> (false))));
-
-named!(number<f32>, alt!(float | integer));
+/// A string. The escapes `\"`, `\\`, `\n`, `\t`, and `\r` are replaced by
+/// the characters they stand for, and any other escaped character stands
+/// for itself.
+fn string(input: &[u8]) -> IResult<&[u8], String> {
+    if input.is_empty() {
+        return IResult::Incomplete(Needed::Size(1));
+    }
+    if input[0] != b'"' {
+        return IResult::Error(error_position!(ErrorKind::Tag, input));
+    }
+    let mut contents = Vec::new();
+    let mut i = 1;
+    while i < input.len() {
+        match input[i] {
+            b'"' => {
+                return match String::from_utf8(contents) {
+                    Ok(s) => IResult::Done(&input[i + 1..], s),
+                    Err(_) => IResult::Error(error_position!(
+                        ErrorKind::MapRes,
+                        input
+                    )),
+                };
+            }
+            b'\\' if i + 1 < input.len() => {
+                contents.push(match input[i + 1] {
+                    b'n' => b'\n',
+                    b't' => b'\t',
+                    b'r' => b'\r',
+                    c => c,
+                });
+                i += 2;
+            }
+            c => {
+                contents.push(c);
+                i += 1;
+            }
+        }
+    }
+    IResult::Incomplete(Needed::Unknown)
+}
 
+// A number, which may have a sign, a fractional part, and an exponent.
 named!(
-    integer<f32>,
-    do_parse!(
-        sign: opt!(tag!("-"))
-            >> num: map_res!(numeric_string, std::str::FromStr::from_str)
-            >> (if sign.is_none() { num } else { num * -1. })
+    number<f32>,
+    map_res!(
+        map_res!(
+            recognize!(tuple!(
+                opt!(alt!(tag!("-") | tag!("+"))),
+                alt!(
+                    recognize!(pair!(
+                        digit,
+                        opt!(complete!(pair!(
+                            tag!("."),
+                            opt!(complete!(digit))
+                        )))
+                    )) | recognize!(pair!(tag!("."), digit))
+                ),
+                opt!(complete!(tuple!(
+                    alt!(tag!("e") | tag!("E")),
+                    opt!(alt!(tag!("-") | tag!("+"))),
+                    digit
+                )))
+            )),
+            std::str::from_utf8
+        ),
+        std::str::FromStr::from_str
     )
 );
 
-named!(numeric_string<&str>, map_res!(digit, std::str::from_utf8));
+fn is_identifier_char(c: u8) -> bool {
+    c == b'_' || (c as char).is_ascii_alphanumeric()
+}
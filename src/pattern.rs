@@ -0,0 +1,121 @@
+use std::f32::consts::PI;
+
+use core::utils::{axis_radians, P3, R3, V3};
+use core::{MapDots, Tree};
+use errors::{ResultExt, ScadDotsError};
+
+/// Anything that can be copied into a pattern. This is implemented for every
+/// struct that implements MapDots, and for Trees.
+pub trait Arrange: Sized {
+    fn arrange_translate(&self, offset: V3) -> Self;
+
+    /// Rotate around the origin.
+    fn arrange_rotate(&self, rot: R3) -> Self;
+}
+
+impl<T> Arrange for T
+where
+    T: MapDots,
+{
+    fn arrange_translate(&self, offset: V3) -> Self {
+        self.map_translate(offset)
+    }
+
+    fn arrange_rotate(&self, rot: R3) -> Self {
+        self.map_rotate(rot)
+    }
+}
+
+impl Arrange for Tree {
+    fn arrange_translate(&self, offset: V3) -> Self {
+        Tree::translate(offset, self)
+    }
+
+    fn arrange_rotate(&self, rot: R3) -> Self {
+        Tree::rotate(rot, self)
+    }
+}
+
+/// Return `count` copies in a row, each moved by `spacing` from the one
+/// before it. The first copy is in the original position.
+pub fn linear_array<T>(thing: &T, count: usize, spacing: V3) -> Vec<T>
+where
+    T: Arrange,
+{
+    (0..count)
+        .map(|i| thing.arrange_translate(i as f32 * spacing))
+        .collect()
+}
+
+/// Return copies arranged in a grid, with `counts.0` copies spaced by
+/// `spacing.0` in one direction and `counts.1` copies spaced by `spacing.1`
+/// in the other. The copies are ordered row by row, so the copy at `(i, j)` is
+/// at index `i * counts.1 + j`.
+pub fn grid_array<T>(
+    thing: &T,
+    counts: (usize, usize),
+    spacing: (V3, V3),
+) -> Vec<T>
+where
+    T: Arrange,
+{
+    let mut copies = Vec::new();
+    for i in 0..counts.0 {
+        for j in 0..counts.1 {
+            let offset = i as f32 * spacing.0 + j as f32 * spacing.1;
+            copies.push(thing.arrange_translate(offset));
+        }
+    }
+    copies
+}
+
+/// Return `count` copies spaced evenly around the given axis line that passes
+/// through `pivot`. Each copy is rotated about the axis, so it faces the same
+/// way relative to the axis. The first copy is in the original position.
+pub fn polar_array<T>(thing: &T, count: usize, pivot: P3, axis: V3) -> Vec<T>
+where
+    T: Arrange,
+{
+    let to_pivot = pivot - P3::origin();
+    (0..count)
+        .map(|i| {
+            let radians = (i as f32) / (count as f32) * 2. * PI;
+            thing
+                .arrange_translate(-to_pivot)
+                .arrange_rotate(axis_radians(axis, radians))
+                .arrange_translate(to_pivot)
+        })
+        .collect()
+}
+
+/// Sweep a profile along a path. A copy of the profile is rotated by each
+/// waypoint's rotation (around the origin) and then moved to the waypoint's
+/// position, so the profile should be built around the origin. Each copy is
+/// linked into a Tree with the given function, like
+/// `|r: &Rect| r.link(RectLink::Dots)`, and consecutive copies are hulled
+/// together.
+pub fn sweep<T, F>(
+    profile: &T,
+    waypoints: &[(P3, R3)],
+    link: F,
+) -> Result<Tree, ScadDotsError>
+where
+    T: MapDots,
+    F: Fn(&T) -> Result<Tree, ScadDotsError>,
+{
+    if waypoints.len() < 2 {
+        return Err(
+            ScadDotsError::Args.context("sweep needs at least 2 waypoints")
+        );
+    }
+    let mut copies = Vec::new();
+    for &(pos, rot) in waypoints {
+        let copy = profile.map_rotate(rot).map_translate(pos - P3::origin());
+        copies.push(link(&copy).context("failed to link swept profile")?);
+    }
+    let mut segments = Vec::new();
+    for pair in copies.windows(2) {
+        segments.push(Tree::hull(pair.to_vec()));
+    }
+    Ok(Tree::union(segments))
+}
@@ -1,10 +1,15 @@
-use core::utils::{midpoint, Axis, Corner1 as C1, Corner3 as C3, P3, R3, V3};
+use std::fmt;
+
+use core::utils::{
+    midpoint, rotation_from_axes, Axis, Corner1 as C1, Corner2 as C2,
+    Corner3 as C3, DimensionMode, P3, R3, V3,
+};
 use core::{
-    chain, chain_loop, Dot, DotShape, DotSpec, MapDots, MinMaxCoord, Snake,
-    Tree,
+    chain, chain_loop, drop_solid, smooth_joints, Anchor, AnchorName,
+    AnchorPoint, Dot, DotShape, DotSpec, MapDots, MinMaxCoord, Snake, Tree,
 };
 
-use errors::ScadDotsError;
+use errors::{ResultExt, ScadDotsError};
 
 #[derive(Debug, Clone, Copy, MapDots, MinMaxCoord, Default)]
 pub struct Post {
@@ -13,20 +18,66 @@ pub struct Post {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct PostSpec {
     pub pos: P3,
     pub align: PostAlign,
     pub len: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
     pub rot: R3,
     pub size: f32,
     pub shapes: PostShapes,
 }
 
+/// Specify a Post by the direction of its axis, instead of a rotation.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PostSpecAxis {
+    pub pos: P3,
+    pub align: PostAlign,
+    pub len: f32,
+    /// The direction from the bottom of the post to the top.
+    pub axis: V3,
+    /// A direction for the post's local x axis. Only the part that's
+    /// perpendicular to `axis` is used.
+    pub x_axis_handle: V3,
+    pub size: f32,
+    pub shapes: PostShapes,
+}
+
+/// Specify a Post that runs between 2 points. The given corner of the bottom
+/// face of the bottom dot is placed at `bot`, and the same corner of the top
+/// face of the top dot is placed at `top`.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PostSpecEnds {
+    pub bot: P3,
+    pub top: P3,
+    pub size: f32,
+    pub align_bot_face: C2,
+    /// A point that the post's local x axis points towards, roughly. Only
+    /// the direction perpendicular to the post's axis is used.
+    pub x_axis_handle: P3,
+    pub shapes: PostShapes,
+}
+
 pub trait PostSpecTrait: Copy {
     fn to_dot(&self, upper_or_lower: C1) -> Result<Dot, ScadDotsError>;
+
+    /// In strict mode, return an error if the post is shorter than its dot
+    /// size. In clamp mode, return a copy lengthened to the dot size instead.
+    /// See `RectSpecTrait::check_dimensions()`.
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError>;
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum PostAlign {
     Corner {
         post: C1,
@@ -41,6 +92,7 @@ pub enum PostAlign {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum PostShapes {
     Cube,
     Sphere,
@@ -50,37 +102,60 @@ pub enum PostShapes {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum PostLink {
     Solid,
     Dots,
 }
 
-#[derive(Debug, Clone, Copy, MapDots, MinMaxCoord)]
+#[derive(Debug, Clone, MapDots, MinMaxCoord)]
 pub struct PostSnake {
-    posts: [Post; 4],
+    posts: Vec<Post>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum PostSnakeLink {
     Chain,
     Posts,
+    /// Like `Chain`, but with the elbows rounded off to the given radius.
+    /// See `Post::chain_smooth()`.
+    Smooth {
+        radius: f32,
+    },
+    /// A continuous wall along the path of the snake. It's the same solid as
+    /// `Chain`, which already hulls each pair of consecutive posts into a
+    /// panel, and it's only a clearer name when the snake is used as a wall.
+    Wall,
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
 impl Post {
-    /// Create a new Post from the given specification.
+    /// Create a new Post from the given specification. If it fails, the error
+    /// includes the spec.
     pub fn new<T>(spec: T) -> Result<Self, ScadDotsError>
+    where
+        T: PostSpecTrait + fmt::Debug,
+    {
+        Self::from_spec(spec).map_err(|e| e.in_part("post", None, &spec))
+    }
+
+    /// Like `new()`, but include the name in the error, to tell which part
+    /// of a model failed.
+    pub fn new_named<T>(name: &str, spec: T) -> Result<Self, ScadDotsError>
+    where
+        T: PostSpecTrait + fmt::Debug,
+    {
+        Self::from_spec(spec).map_err(|e| e.in_part("post", Some(name), &spec))
+    }
+
+    fn from_spec<T>(spec: T) -> Result<Self, ScadDotsError>
     where
         T: PostSpecTrait,
     {
+        let spec = spec.check_dimensions(DimensionMode::Strict)?;
         let bot = spec.to_dot(C1::P0)?;
         let top = spec.to_dot(C1::P1)?;
-
-        // TODO fix dimension checking, also dist() changed
-        // if bot.dist(top) < size {
-        //     bail!("post is too short, top and bottom dot overlap");
-        // }
         Ok(Self { top, bot })
     }
 
@@ -150,6 +225,14 @@ impl Post {
         })
     }
 
+    /// Return the hull of the Post's dots and copies of them dropped down to
+    /// `bottom_z`. See `Dot::drop()`.
+    pub fn drop_solid(&self, bottom_z: f32, shape: Option<DotShape>) -> Tree {
+        drop_solid(&[self.bot, self.top], bottom_z, shape)
+    }
+
+    /// Return a snake of posts between this post and the other one, moving
+    /// along each axis once in the given order. See `Snake::new()`.
     pub fn snake(
         &self,
         other: Self,
@@ -157,24 +240,19 @@ impl Post {
     ) -> Result<PostSnake, ScadDotsError> {
         let tops = Snake::new(self.top, other.top, order)?.dots;
         let bots = Snake::new(self.bot, other.bot, order)?.dots;
-        let mut posts = [Self::default(); 4];
-        posts[0] = Self {
-            top: tops[0],
-            bot: bots[0],
-        };
-        posts[1] = Self {
-            top: tops[1],
-            bot: bots[1],
-        };
-        posts[2] = Self {
-            top: tops[2],
-            bot: bots[2],
-        };
-        posts[3] = Self {
-            top: tops[3],
-            bot: bots[3],
-        };
-        Ok(PostSnake { posts })
+        Ok(PostSnake::from_dots(&tops, &bots))
+    }
+
+    /// Return a snake of posts between this post and the other one, with one
+    /// segment for each axis. See `Snake::with_waypoints()`.
+    pub fn snake_with_waypoints(
+        &self,
+        other: Self,
+        axes: &[Axis],
+    ) -> Result<PostSnake, ScadDotsError> {
+        let tops = Snake::with_waypoints(self.top, other.top, axes)?.dots;
+        let bots = Snake::with_waypoints(self.bot, other.bot, axes)?.dots;
+        Ok(PostSnake::from_dots(&tops, &bots))
     }
 
     pub fn chain(posts: &[Self]) -> Result<Tree, ScadDotsError> {
@@ -183,6 +261,22 @@ impl Post {
         chain(&post_trees)
     }
 
+    /// Like `Post::chain()`, but round off the elbows at the posts between
+    /// the first and last one. See `chain_smooth()`.
+    pub fn chain_smooth(
+        posts: &[Self],
+        radius: f32,
+    ) -> Result<Tree, ScadDotsError> {
+        let bots: Vec<_> = posts.iter().map(|post| post.bot).collect();
+        let tops: Vec<_> = posts.iter().map(|post| post.top).collect();
+        let post_trees: Vec<_> = smooth_joints(&bots, radius)?
+            .into_iter()
+            .zip(smooth_joints(&tops, radius)?)
+            .map(|(bot, top)| hull![bot, top])
+            .collect();
+        chain(&post_trees).context("failed to smooth post chain")
+    }
+
     pub fn chain_loop(posts: &[Self]) -> Result<Tree, ScadDotsError> {
         let post_trees: Vec<_> =
             posts.into_iter().map(|p| p.link(PostLink::Solid)).collect();
@@ -197,7 +291,37 @@ impl Post {
     }
 }
 
+impl Anchor for Post {
+    type Align = PostAlign;
+
+    fn anchor_at(&self, align: PostAlign) -> AnchorPoint {
+        AnchorPoint {
+            pos: self.pos(align),
+            rot: self.top.rot,
+        }
+    }
+
+    fn align_from_name(name: AnchorName) -> Result<PostAlign, ScadDotsError> {
+        name.require_outside()?;
+        let (a, b) = name.corners();
+        Ok(if name.is_corner() {
+            PostAlign::outside(a)
+        } else {
+            PostAlign::outside_midpoint(a, b)
+        })
+    }
+}
+
 impl PostSpecTrait for PostSpec {
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError> {
+        let mut spec = *self;
+        mode.fit_lengths(spec.size, &mut [("len", &mut spec.len)])?;
+        Ok(spec)
+    }
+
     fn to_dot(&self, upper_or_lower: C1) -> Result<Dot, ScadDotsError> {
         let origin =
             self.pos
@@ -212,7 +336,70 @@ impl PostSpecTrait for PostSpec {
             rot: self.rot,
             shape: self.shapes.get(upper_or_lower),
         };
-        Ok(Dot::new(spec))
+        Dot::try_new(spec)
+    }
+}
+
+impl PostSpecTrait for PostSpecAxis {
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError> {
+        let mut spec = *self;
+        mode.fit_lengths(spec.size, &mut [("len", &mut spec.len)])?;
+        Ok(spec)
+    }
+
+    fn to_dot(&self, upper_or_lower: C1) -> Result<Dot, ScadDotsError> {
+        let rot = rotation_from_axes(self.x_axis_handle, self.axis)
+            .context("failed to orient post along axis")?;
+        PostSpec {
+            pos: self.pos,
+            align: self.align,
+            len: self.len,
+            rot,
+            size: self.size,
+            shapes: self.shapes,
+        }
+        .to_dot(upper_or_lower)
+    }
+}
+
+impl PostSpecTrait for PostSpecEnds {
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError> {
+        let axis = self.top - self.bot;
+        let mut len = axis.norm();
+        mode.fit_lengths(self.size, &mut [("len", &mut len)])?;
+        if len == axis.norm() {
+            return Ok(*self);
+        }
+        // Move the top away from the bottom, along the post's axis.
+        if axis.norm() == 0. {
+            return Err(ScadDotsError::Dimension.context(
+                "can't lengthen a post whose ends are at the same point",
+            ));
+        }
+        Ok(Self {
+            top: self.bot + axis.normalize() * len,
+            ..*self
+        })
+    }
+
+    fn to_dot(&self, upper_or_lower: C1) -> Result<Dot, ScadDotsError> {
+        let axis = self.top - self.bot;
+        PostSpecAxis {
+            pos: self.bot,
+            align: PostAlign::outside(self.align_bot_face.into()),
+            len: axis.norm(),
+            axis,
+            x_axis_handle: self.x_axis_handle - self.bot,
+            size: self.size,
+            shapes: self.shapes,
+        }
+        .to_dot(upper_or_lower)
     }
 }
 
@@ -292,22 +479,25 @@ impl PostShapes {
 }
 
 impl PostSnake {
-    pub fn bottoms(&self) -> [Dot; 4] {
-        [
-            self.posts[0].bot,
-            self.posts[1].bot,
-            self.posts[2].bot,
-            self.posts[3].bot,
-        ]
+    fn from_dots(tops: &[Dot], bots: &[Dot]) -> Self {
+        let posts = tops
+            .iter()
+            .zip(bots)
+            .map(|(&top, &bot)| Post { top, bot })
+            .collect();
+        PostSnake { posts }
+    }
+
+    pub fn bottoms(&self) -> Vec<Dot> {
+        self.posts.iter().map(|post| post.bot).collect()
     }
 
-    pub fn all(&self) -> [Post; 4] {
-        self.posts
+    pub fn all(&self) -> Vec<Post> {
+        self.posts.clone()
     }
 
     pub fn as_vec(&self) -> Vec<Post> {
-        let v: Vec<_> = self.posts.to_vec();
-        v
+        self.posts.clone()
     }
 
     pub fn get(&self, index: usize) -> Post {
@@ -320,7 +510,12 @@ impl PostSnake {
 
     pub fn link(&self, style: PostSnakeLink) -> Result<Tree, ScadDotsError> {
         match style {
-            PostSnakeLink::Chain => Post::chain(&self.posts),
+            PostSnakeLink::Chain | PostSnakeLink::Wall => {
+                Post::chain(&self.posts)
+            }
+            PostSnakeLink::Smooth { radius } => {
+                Post::chain_smooth(&self.posts, radius)
+            }
             PostSnakeLink::Posts => {
                 // Ok(union![self.posts[0], self.posts[1], self.posts[2], self.posts[2]])
                 let v: Vec<_> = self
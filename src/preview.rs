@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use core::Tree;
+use errors::{ResultExt, ScadDotsError};
+use harness::{view_in_openscad, HarnessConfig};
+use render::{to_code, RenderOptions, RenderQuality};
+
+/// Shows the model returned by a closure in a single OpenSCAD window, and
+/// updates it every time it's refreshed. The model is always saved to the
+/// same file, so turn on "Automatic Reload and Preview" in OpenSCAD's Design
+/// menu to see the changes.
+pub struct Watcher<F> {
+    model: F,
+    name: String,
+    options: RenderOptions,
+    config: HarnessConfig,
+    open_viewer: bool,
+    last_code: Option<String>,
+}
+
+/// Return a watcher for the model returned by the closure. Call `refresh()`
+/// on it in a loop or from a file watcher's callback, or call `run()`. The
+/// model is saved as `watch_<name>.scad` in the harness's temporary
+/// directory.
+pub fn watch<F>(name: &str, model: F) -> Result<Watcher<F>, ScadDotsError>
+where
+    F: FnMut() -> Result<Tree, ScadDotsError>,
+{
+    Ok(Watcher {
+        model,
+        name: name.to_owned(),
+        options: RenderQuality::Low.into(),
+        config: HarnessConfig::from_env()?,
+        open_viewer: true,
+        last_code: None,
+    })
+}
+
+impl<F> Watcher<F>
+where
+    F: FnMut() -> Result<Tree, ScadDotsError>,
+{
+    pub fn with_options<O>(mut self, options: O) -> Self
+    where
+        O: Into<RenderOptions>,
+    {
+        self.options = options.into();
+        self
+    }
+
+    /// Use a different harness config, for its temporary directory and
+    /// viewer.
+    pub fn with_config(mut self, config: HarnessConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Whether to open the viewer on the first refresh. It's true by
+    /// default.
+    pub fn with_viewer(mut self, open_viewer: bool) -> Self {
+        self.open_viewer = open_viewer;
+        self
+    }
+
+    /// The file the model is saved to.
+    pub fn path(&self) -> PathBuf {
+        self.config
+            .tmp_dir
+            .join(format!("watch_{}.scad", self.name))
+    }
+
+    /// Call the closure and save its model, and open the viewer the first
+    /// time. The file is only rewritten if the code changed, so the viewer
+    /// doesn't reload needlessly. Return true if it was rewritten.
+    pub fn refresh(&mut self) -> Result<bool, ScadDotsError> {
+        let tree = (self.model)().context("failed to construct model")?;
+        let code = to_code(&tree, self.options)?;
+        if self.last_code.as_ref() == Some(&code) {
+            return Ok(false);
+        }
+        let path = self.path();
+        fs::create_dir_all(&self.config.tmp_dir)?;
+        fs::write(&path, &code).with_context(|| {
+            format!("failed to write preview file '{}'", path.display())
+        })?;
+        let first = self.last_code.is_none();
+        self.last_code = Some(code);
+        if first && self.open_viewer {
+            view_in_openscad(&self.config, &[path])?;
+        }
+        Ok(true)
+    }
+
+    /// Refresh forever, waiting for the interval between refreshes. Errors
+    /// are printed instead of returned, so the previous model stays visible
+    /// while it's being fixed. This is useful when the closure reads its
+    /// model's parameters from a file.
+    pub fn run(&mut self, interval: Duration) -> ! {
+        loop {
+            match self.refresh() {
+                Ok(true) => println!("Updated {}", self.path().display()),
+                Ok(false) => (),
+                Err(e) => println!("error: {}", e),
+            }
+            thread::sleep(interval);
+        }
+    }
+}
@@ -1,15 +1,18 @@
+use std::fmt;
+
 use core::utils::{
-    midpoint, Axis, Corner2 as C2, Corner3 as C3, CubeFace, P3, R3, V3,
+    midpoint, rotate, Axis, ColorSpec, Corner2 as C2, Corner3 as C3, CubeFace,
+    DimensionMode, Plane, P3, R3, V3,
 };
 use core::{
-    chain_loop, drop_solid, mark, Dot, DotShape, DotSpec, MapDots, MinMaxCoord,
-    Tree,
+    chain_loop, drop_solid, mark, Anchor, AnchorName, AnchorPoint, Dot,
+    DotShape, DotSpec, MapDots, MinMaxCoord, Tree,
 };
 use cuboid::{Cuboid, CuboidLink};
 
 use errors::{ResultExt, ScadDotsError};
 
-#[derive(Debug, Clone, Copy, MinMaxCoord, MapDots)]
+#[derive(Debug, Clone, Copy, MinMaxCoord)]
 pub struct Rect {
     pub p00: Dot,
     pub p01: Dot,
@@ -18,17 +21,23 @@ pub struct Rect {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct RectSpec {
     pub pos: P3,
     pub align: RectAlign,
     pub x_length: f32,
     pub y_length: f32,
     pub size: f32,
+    #[cfg_attr(
+        feature = "serialize",
+        serde(with = "::core::serde_utils::rot")
+    )]
     pub rot: R3,
     pub shapes: RectShapes,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum RectAlign {
     Corner {
         rect: C2,
@@ -43,6 +52,7 @@ pub enum RectAlign {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum RectShapes {
     Cube,
     Sphere,
@@ -56,28 +66,75 @@ pub enum RectShapes {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum RectLink {
     Solid,
     Frame,
     Dots,
     YPosts,
     Chamfer,
+    /// Both diagonals, each hulled between opposite corner dots. Union this
+    /// with `Frame` to make a braced frame.
+    CrossBrace,
+}
+
+/// The overall dimensions of a Rect or Cuboid, relative to its default
+/// orientation. Use this for clearance checks instead of re-deriving the
+/// lengths from the dot size.
+#[derive(Debug, Clone, Copy)]
+pub struct Silhouette {
+    /// The outer lengths along each axis, including the dots on both ends.
+    pub outer: V3,
+    /// The lengths of the empty space enclosed by the dots along each axis.
+    /// For a Rect, the z length is the same as the outer z length.
+    pub inner: V3,
+    /// The radius of the corners, if the dots are round. It's 0 unless all
+    /// of the corner dots are spheres or cylinders.
+    pub corner_radius: f32,
 }
 
 /// Any struct implementing this trait can be used to construct a Rect, by by
 /// constructing each of the 4 dots that form the corners of the Rect.
 pub trait RectSpecTrait: Copy {
     fn to_dot(&self, corner: C2) -> Result<Dot, ScadDotsError>;
+
+    /// In strict mode, return an error if any of the lengths are smaller than
+    /// the dot size. In clamp mode, return a copy with those lengths raised to
+    /// the dot size instead. Constructors always use strict mode, so to clamp
+    /// a spec, pass it to the constructor after calling this.
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError>;
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
 impl Rect {
+    /// Create a new Rect. If it fails, the error includes the spec.
     pub fn new<T>(spec: T) -> Result<Self, ScadDotsError>
+    where
+        T: RectSpecTrait + fmt::Debug,
+    {
+        Self::from_spec(spec).map_err(|e| e.in_part("rect", None, &spec))
+    }
+
+    /// Like `new()`, but include the name in the error, to tell which part
+    /// of a model failed.
+    pub fn new_named<T>(name: &str, spec: T) -> Result<Self, ScadDotsError>
+    where
+        T: RectSpecTrait + fmt::Debug,
+    {
+        Self::from_spec(spec).map_err(|e| e.in_part("rect", Some(name), &spec))
+    }
+
+    /// Create a new Rect without adding the spec to errors, for bigger parts
+    /// that add their own.
+    pub(crate) fn from_spec<T>(spec: T) -> Result<Self, ScadDotsError>
     where
         T: RectSpecTrait,
     {
-        // TODO check dimensions, ensure positive
+        let spec = spec.check_dimensions(DimensionMode::Strict)?;
         Ok(Self {
             p00: spec.to_dot(C2::P00)?,
             p01: spec.to_dot(C2::P01)?,
@@ -145,6 +202,38 @@ impl Rect {
         self.edge(axis).norm()
     }
 
+    /// Return the unit vector pointing out of the given face of the
+    /// Rect, in its actual rotated orientation.
+    pub fn face_normal(&self, face: CubeFace) -> V3 {
+        rotate(self.rot(), face.normal())
+    }
+
+    /// Return the plane of the given outer face of the Rect, with its
+    /// normal pointing out of the Rect. Use it to place other parts flush
+    /// against the face.
+    pub fn face_plane(&self, face: CubeFace) -> Plane {
+        Plane::new(
+            self.pos(RectAlign::center_face(face)),
+            self.face_normal(face),
+        )
+    }
+
+    /// Return the outer and inner dimensions of the Rect.
+    pub fn silhouette(&self) -> Silhouette {
+        Silhouette::new(
+            V3::new(
+                self.edge_length(Axis::X),
+                self.edge_length(Axis::Y),
+                self.size(),
+            ),
+            self.size(),
+            false,
+            self.dots().into_iter().map(|dot| dot.shape),
+        )
+    }
+
+    /// Return the hull of the Rect's dots and copies of them dropped down to
+    /// `bottom_z`. See `Dot::drop()`.
     pub fn drop_solid(&self, bottom_z: f32, shape: Option<DotShape>) -> Tree {
         drop_solid(&self.dots(), bottom_z, shape)
     }
@@ -160,6 +249,26 @@ impl Rect {
         Tree::union(marks)
     }
 
+    /// For debugging. Return the union of 12 small red spheres placed at the
+    /// midpoint of each outer edge of the Rect.
+    pub fn mark_edges(&self) -> Tree {
+        let mut marks = Vec::new();
+        for (a, b) in C3::all_edges() {
+            marks.push(mark(self.pos(RectAlign::outside_midpoint(a, b)), 1.));
+        }
+        Tree::color(ColorSpec::Red, Tree::union(marks))
+    }
+
+    /// For debugging. Return the union of 6 small green spheres placed at the
+    /// center of each outer face of the Rect.
+    pub fn mark_faces(&self) -> Tree {
+        let mut marks = Vec::new();
+        for face in CubeFace::all() {
+            marks.push(mark(self.pos(RectAlign::center_face(face)), 1.));
+        }
+        Tree::color(ColorSpec::Green, Tree::union(marks))
+    }
+
     pub fn link(&self, style: RectLink) -> Result<Tree, ScadDotsError> {
         let dots = self.dots();
         Ok(match style {
@@ -178,6 +287,10 @@ impl Rect {
             RectLink::Chamfer => self
                 .chamfer()
                 .context("failed to link Rect in Chamfer style")?,
+            RectLink::CrossBrace => union![
+                hull![self.dot(C2::P00), self.dot(C2::P11)],
+                hull![self.dot(C2::P01), self.dot(C2::P10)],
+            ],
         })
     }
 
@@ -214,14 +327,65 @@ impl Rect {
     }
 }
 
+impl MapDots for Rect {
+    fn map(&self, f: &Fn(&Dot) -> Dot) -> Self {
+        Self {
+            p00: f(&self.p00),
+            p01: f(&self.p01),
+            p10: f(&self.p10),
+            p11: f(&self.p11),
+        }
+    }
+
+    /// Mirroring flips each dot's local y axis, so swap the corners across
+    /// it to keep `p00` at the Rect's origin.
+    fn map_mirror(&self, normal: V3) -> Self {
+        Self {
+            p00: self.p01.mirror(normal),
+            p01: self.p00.mirror(normal),
+            p10: self.p11.mirror(normal),
+            p11: self.p10.mirror(normal),
+        }
+    }
+}
+
+impl Anchor for Rect {
+    type Align = RectAlign;
+
+    fn anchor_at(&self, align: RectAlign) -> AnchorPoint {
+        AnchorPoint {
+            pos: self.pos(align),
+            rot: self.rot(),
+        }
+    }
+
+    fn align_from_name(name: AnchorName) -> Result<RectAlign, ScadDotsError> {
+        let (a, b) = name.corners();
+        Ok(match (name.is_corner(), name.inside) {
+            (true, false) => RectAlign::outside(a),
+            (true, true) => RectAlign::inside(a),
+            (false, false) => RectAlign::outside_midpoint(a, b),
+            (false, true) => RectAlign::inside_midpoint(a, b),
+        })
+    }
+}
+
 impl RectSpec {
     /// The length of the Rect's inner edge along the given axis (relative to the default orientation).
     pub fn inner_length(&self, axis: Axis) -> f32 {
-        match axis {
-            Axis::X => self.x_length - 2. * self.size,
-            Axis::Y => self.y_length - 2. * self.size,
-            Axis::Z => self.size,
-        }
+        self.silhouette().inner[axis.index()]
+    }
+
+    /// The outer and inner dimensions of the Rect that this spec describes.
+    pub fn silhouette(&self) -> Silhouette {
+        Silhouette::new(
+            V3::new(self.x_length, self.y_length, self.size),
+            self.size,
+            false,
+            C2::all_clockwise()
+                .into_iter()
+                .map(|corner| self.shapes.get(corner)),
+        )
     }
 
     /// Make a copy with a new position
@@ -274,7 +438,43 @@ impl RectSpec {
     }
 }
 
+impl Silhouette {
+    /// Compute the inner lengths by removing a dot from each end of the outer
+    /// lengths. The z axis is only treated that way if `hollow_z` is true.
+    /// The shapes are the shapes of the corner dots.
+    pub fn new<I>(outer: V3, size: f32, hollow_z: bool, shapes: I) -> Self
+    where
+        I: IntoIterator<Item = DotShape>,
+    {
+        let z_walls = if hollow_z { 2. * size } else { 0. };
+        let round = shapes.into_iter().all(|shape| match shape {
+            DotShape::Sphere | DotShape::Cylinder => true,
+            _ => false,
+        });
+        Self {
+            outer,
+            inner: outer - V3::new(2. * size, 2. * size, z_walls),
+            corner_radius: if round { size / 2. } else { 0. },
+        }
+    }
+}
+
 impl RectSpecTrait for RectSpec {
+    fn check_dimensions(
+        &self,
+        mode: DimensionMode,
+    ) -> Result<Self, ScadDotsError> {
+        let mut spec = *self;
+        mode.fit_lengths(
+            spec.size,
+            &mut [
+                ("x_length", &mut spec.x_length),
+                ("y_length", &mut spec.y_length),
+            ],
+        )?;
+        Ok(spec)
+    }
+
     fn to_dot(&self, corner: C2) -> Result<Dot, ScadDotsError> {
         let dot_dimensions = V3::new(self.size, self.size, self.size);
         let rect_dimensions =
@@ -291,7 +491,7 @@ impl RectSpecTrait for RectSpec {
             size: self.size,
             shape: self.shapes.get(corner),
         };
-        Ok(Dot::new(spec))
+        Dot::try_new(spec)
     }
 }
 
@@ -416,6 +616,14 @@ impl From<DotShape> for RectShapes {
             DotShape::Cube => RectShapes::Cube,
             DotShape::Cylinder => RectShapes::Cylinder,
             DotShape::Sphere => RectShapes::Sphere,
+            DotShape::ChamferedCube { .. } | DotShape::Prism { .. } => {
+                RectShapes::Custom {
+                    p00: shape,
+                    p01: shape,
+                    p10: shape,
+                    p11: shape,
+                }
+            }
         }
     }
 }
@@ -1,18 +1,168 @@
-use scad::*;
+use std::env;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use core::utils::{
-    radians_to_degrees, rotate, unwrap_rot_axis, Corner3 as C3, P2, P3, V2, V3,
+    rotate, snap_rotation, Axis, ColorSpec, Corner3 as C3, Fraction, P2, P3,
+    R3, V3,
 };
 use core::{
-    Cylinder, Dot, DotShape, Extrusion, Tree, TreeObject, TreeOperator,
+    Cylinder, Dot, DotShape, ExtrudeParams, Extrusion, ImportKind,
+    ScadModifier, Text, Torus, Tree, TreeObject, TreeOperator,
 };
+use emit::{file_code, ScadBackend, ScadNode};
 use errors::{ResultExt, ScadDotsError};
+use harness::openscad_bin;
 
 pub trait Render {
+    /// Build the output for the given backend.
+    fn render_with<B>(
+        &self,
+        backend: &B,
+        options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend;
+
+    /// Build the OpenSCAD code.
     fn render(
         &self,
-        options: RenderQuality,
-    ) -> Result<ScadObject, ScadDotsError>;
+        options: RenderOptions,
+    ) -> Result<ScadNode, ScadDotsError> {
+        self.render_with(&ScadBackend::new(options.precision), options)
+    }
+}
+
+/// The target that models are rendered to. Each method builds the output for
+/// a single kind of shape or operation, so a new output format only needs to
+/// implement this trait instead of touching every primitive. Shapes are built
+/// at the origin and then moved into place with `translate()` and
+/// `rotate()`. Methods return an error for values they can't write, like NaN
+/// or infinite numbers.
+pub trait Backend {
+    /// The output for a shape or operation, including its children.
+    type Node;
+
+    /// A box with one corner on the origin, extending along the positive
+    /// axes.
+    fn cube(&self, lengths: V3) -> Result<Self::Node, ScadDotsError>;
+
+    /// A sphere centered on the origin.
+    fn sphere(&self, diameter: f32) -> Result<Self::Node, ScadDotsError>;
+
+    /// A cylinder or cone along the z axis, with its bottom face centered on
+    /// the origin.
+    fn cylinder(
+        &self,
+        height: f32,
+        bottom_diameter: f32,
+        top_diameter: f32,
+    ) -> Result<Self::Node, ScadDotsError>;
+
+    /// A 2D circle centered on the origin.
+    fn circle(&self, radius: f32) -> Result<Self::Node, ScadDotsError>;
+
+    /// A 2D polygon. The first contour is the outside, and the rest are
+    /// holes.
+    fn polygon(&self, contours: &[&[P2]]) -> Result<Self::Node, ScadDotsError>;
+
+    /// A line of 2D text, centered on the origin. Backends that can't draw
+    /// text can return an error.
+    fn text(&self, text: &str, size: f32) -> Result<Self::Node, ScadDotsError>;
+
+    /// OpenSCAD code to include as is. Other backends can return an error.
+    fn raw_scad(&self, code: &str) -> Result<Self::Node, ScadDotsError>;
+
+    /// A shape loaded from another file.
+    fn import(
+        &self,
+        path: &str,
+        kind: ImportKind,
+    ) -> Result<Self::Node, ScadDotsError>;
+
+    /// Extrude the 2D children upwards from the xy plane. Backends that
+    /// can't use some of the parameters can return an error.
+    fn linear_extrude(
+        &self,
+        height: f32,
+        params: ExtrudeParams,
+        children: Vec<Self::Node>,
+    ) -> Result<Self::Node, ScadDotsError>;
+
+    /// Sweep the 2D children in a full circle around the z axis, treating
+    /// their y axis as the z axis.
+    fn rotate_extrude(&self, children: Vec<Self::Node>) -> Self::Node;
+
+    fn combine(&self, op: Combine, children: Vec<Self::Node>) -> Self::Node;
+
+    fn color(
+        &self,
+        color: ColorSpec,
+        children: Vec<Self::Node>,
+    ) -> Result<Self::Node, ScadDotsError>;
+
+    /// Mirror the children across the plane through the origin with the
+    /// given normal.
+    fn mirror(
+        &self,
+        normal: V3,
+        children: Vec<Self::Node>,
+    ) -> Result<Self::Node, ScadDotsError>;
+
+    fn scale(
+        &self,
+        factors: V3,
+        children: Vec<Self::Node>,
+    ) -> Result<Self::Node, ScadDotsError>;
+
+    fn translate(
+        &self,
+        offset: V3,
+        children: Vec<Self::Node>,
+    ) -> Result<Self::Node, ScadDotsError>;
+
+    /// Rotate the children around the origin.
+    fn rotate(
+        &self,
+        rot: R3,
+        children: Vec<Self::Node>,
+    ) -> Result<Self::Node, ScadDotsError>;
+
+    /// Change how the children are shown. Backends that don't have a preview
+    /// can apply the modifier's effect on the final render instead.
+    fn modifier(
+        &self,
+        modifier: ScadModifier,
+        children: Vec<Self::Node>,
+    ) -> Self::Node;
+
+    /// Mark the children as a named part. Backends that can't name things
+    /// can return them unchanged, as a union.
+    fn named(&self, name: &str, children: Vec<Self::Node>) -> Self::Node;
+
+    /// Set how finely the children's curves are split into segments. See
+    /// `TreeOperator::Detail`.
+    fn detail(
+        &self,
+        fn_: Option<i32>,
+        fa: Option<f32>,
+        fs: Option<f32>,
+        children: Vec<Self::Node>,
+    ) -> Result<Self::Node, ScadDotsError>;
+}
+
+/// Ways of combining several shapes into one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combine {
+    Union,
+    Hull,
+    /// Subtract all the following shapes from the first.
+    Diff,
+    Intersect,
+    Minkowski,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -23,6 +173,26 @@ pub enum RenderQuality {
     High,
 }
 
+/// Options that control how a model is rendered. A plain `RenderQuality` can
+/// be used anywhere these are expected, to get the default for everything
+/// else.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    pub quality: RenderQuality,
+    /// If set, clean up every rotation with `snap_rotation()` and this
+    /// tolerance before rendering it.
+    pub snap_tolerance: Option<f32>,
+    /// If set, round every number in the code to this many decimal places.
+    /// Tiny floating point differences then don't change the code, so it
+    /// diffs cleanly.
+    pub precision: Option<usize>,
+    /// If true, write subtrees that appear more than once as shared modules.
+    /// This helps with patterns of trees from the `pattern` module, where
+    /// every copy is the same subtree inside a `translate()` or `rotate()`.
+    /// See `ScadNode::deduplicated()`.
+    pub dedupe: bool,
+}
+
 impl RenderQuality {
     pub fn detail(self) -> i32 {
         match self {
@@ -33,134 +203,265 @@ impl RenderQuality {
     }
 }
 
-pub fn to_file<T>(
+impl RenderOptions {
+    pub fn detail(self) -> i32 {
+        self.quality.detail()
+    }
+
+    /// Return the rotation to pass to the backend.
+    fn snap(self, rot: R3) -> R3 {
+        match self.snap_tolerance {
+            Some(tolerance) => snap_rotation(rot, tolerance),
+            None => rot,
+        }
+    }
+}
+
+impl From<RenderQuality> for RenderOptions {
+    fn from(quality: RenderQuality) -> Self {
+        Self {
+            quality,
+            snap_tolerance: None,
+            precision: None,
+            dedupe: false,
+        }
+    }
+}
+
+pub fn to_file<T, O>(
     thing: &T,
     path: String,
-    options: RenderQuality,
+    options: O,
 ) -> Result<(), ScadDotsError>
 where
     T: Render,
+    O: Into<RenderOptions>,
 {
-    let scad_file = make_scad_file(thing, options)?;
-    scad_file.write_to_file(path);
-    Ok(())
+    let code = to_code(thing, options)?;
+    fs::write(&path, code)
+        .with_context(|| format!("failed to write scad file '{}'", path))
 }
 
-pub fn to_code<T>(
+/// Counts the temporary files made by `compile_to_stl()`, so that threads
+/// compiling files with the same name don't overwrite each other's code.
+static TEMP_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Compile the thing into a mesh file with openscad, which must be
+/// installed. The format depends on the path's extension: `stl`, `3mf`,
+/// `off`, or `amf`. The openscad binary can be changed with the
+/// `OPENSCAD_BIN` environment variable.
+pub fn compile_to_stl<T, O>(
     thing: &T,
-    options: RenderQuality,
-) -> Result<String, ScadDotsError>
+    path: String,
+    options: O,
+) -> Result<(), ScadDotsError>
 where
     T: Render,
+    O: Into<RenderOptions>,
 {
-    let scad_file = make_scad_file(thing, options)?;
-    Ok(scad_file.get_code())
+    let extension = Path::new(&path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+    match extension.as_ref().map(String::as_str) {
+        Some("stl") | Some("3mf") | Some("off") | Some("amf") => (),
+        _ => {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!("unsupported mesh file extension: '{}'", path)
+            }))
+        }
+    }
+
+    let scad_path = env::temp_dir().join(format!(
+        "scad-dots-{}-{}-{}.scad",
+        process::id(),
+        Path::new(&path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        TEMP_FILE_COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&scad_path, to_code(thing, options)?).with_context(|| {
+        format!("failed to write scad file '{}'", scad_path.display())
+    })?;
+    let output = Command::new(openscad_bin())
+        .arg("-o")
+        .arg(&path)
+        .arg(&scad_path)
+        .output();
+    // It's only a temporary file, so it doesn't matter if it can't be removed.
+    let _ = fs::remove_file(&scad_path);
+    let output = output.context("failed to run openscad")?;
+    if !output.status.success() {
+        return Err(ScadDotsError::Openscad(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )
+        .with_context(|| format!("failed to compile '{}'", path)));
+    }
+    Ok(())
 }
 
-fn make_scad_file<T>(
-    thing: &T,
-    options: RenderQuality,
-) -> Result<ScadFile, ScadDotsError>
+pub fn to_code<T, O>(thing: &T, options: O) -> Result<String, ScadDotsError>
 where
     T: Render,
+    O: Into<RenderOptions>,
 {
-    let mut scad_file = ScadFile::new();
+    let options = options.into();
+    let mut node = thing.render(options).context("failed to render to scad")?;
+    if options.dedupe {
+        node = node.deduplicated();
+    }
     // detail controls resolution of curves
-    scad_file.set_detail(options.detail());
-    scad_file
-        .add_object(thing.render(options).context("failed to render to scad")?);
-    Ok(scad_file)
+    Ok(file_code(options.detail(), &node))
 }
 
-impl TreeOperator {
-    fn operation(&self) -> ScadObject {
-        match self {
-            TreeOperator::Union(_) => scad!(Union),
-            TreeOperator::Hull(_) => scad!(Hull),
-            TreeOperator::Diff(_) => scad!(Difference),
-            TreeOperator::Intersect(_) => scad!(Intersection),
-            TreeOperator::Color(color, _) => scad!(Color(color.rgb())),
-            TreeOperator::Mirror(normal, _) => scad!(Mirror(*normal)),
-        }
-    }
-
-    fn children(&self) -> Vec<Tree> {
-        // TODO return refs?
-        match self {
-            TreeOperator::Union(ref v)
-            | TreeOperator::Hull(ref v)
-            | TreeOperator::Diff(ref v)
-            | TreeOperator::Intersect(ref v) => v.clone(),
-
-            TreeOperator::Color(_, ref tree)
-            | TreeOperator::Mirror(_, ref tree) => vec![*tree.to_owned()],
-        }
-    }
+/// Return a translation containing a rotation, for placing an object that's
+/// been built at the origin.
+fn placed<B>(
+    backend: &B,
+    offset: V3,
+    rot: R3,
+    options: RenderOptions,
+    child: B::Node,
+) -> Result<B::Node, ScadDotsError>
+where
+    B: Backend,
+{
+    let rotated = backend.rotate(options.snap(rot), vec![child])?;
+    backend.translate(offset, vec![rotated])
 }
 
 impl Render for TreeObject {
-    fn render(
+    fn render_with<B>(
         &self,
-        options: RenderQuality,
-    ) -> Result<ScadObject, ScadDotsError> {
+        backend: &B,
+        options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
         match self {
-            TreeObject::Dot(ref dot) => dot.render(options),
-            TreeObject::Cylinder(ref cylinder) => cylinder.render(options),
-            TreeObject::Extrusion(ref extrusion) => extrusion.render(options),
+            TreeObject::Dot(ref dot) => dot.render_with(backend, options),
+            TreeObject::Cylinder(ref cylinder) => {
+                cylinder.render_with(backend, options)
+            }
+            TreeObject::Extrusion(ref extrusion) => {
+                extrusion.render_with(backend, options)
+            }
+            TreeObject::Torus(ref torus) => torus.render_with(backend, options),
+            TreeObject::Text(ref text) => text.render_with(backend, options),
+            TreeObject::RawScad(ref code) => backend.raw_scad(code),
+            TreeObject::Import { ref path, kind } => {
+                backend.import(path, *kind)
+            }
         }
     }
 }
 
 impl Render for TreeOperator {
-    fn render(
+    fn render_with<B>(
         &self,
-        options: RenderQuality,
-    ) -> Result<ScadObject, ScadDotsError> {
-        let mut operation = self.operation();
+        backend: &B,
+        options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        let mut children = Vec::new();
         for child in self.children() {
-            operation.add_child(
+            children.push(
                 child
-                    .render(options)
+                    .render_with(backend, options)
                     .context("failed to render child of operator")?,
             );
         }
-        Ok(operation)
+        Ok(match self {
+            TreeOperator::Union(_) => backend.combine(Combine::Union, children),
+            TreeOperator::Hull(_) => backend.combine(Combine::Hull, children),
+            TreeOperator::Diff(_) => backend.combine(Combine::Diff, children),
+            TreeOperator::Intersect(_) => {
+                backend.combine(Combine::Intersect, children)
+            }
+            TreeOperator::Minkowski(_) => {
+                backend.combine(Combine::Minkowski, children)
+            }
+            TreeOperator::Color(color, _) => backend.color(*color, children)?,
+            TreeOperator::Mirror(normal, _) => {
+                backend.mirror(*normal, children)?
+            }
+            TreeOperator::Scale(factors, _) => {
+                backend.scale(*factors, children)?
+            }
+            TreeOperator::Translate(offset, _) => {
+                backend.translate(*offset, children)?
+            }
+            TreeOperator::Rotate(rot, _) => {
+                backend.rotate(options.snap(*rot), children)?
+            }
+            TreeOperator::Modifier(modifier, _) => {
+                backend.modifier(*modifier, children)
+            }
+            TreeOperator::Named(name, _) => backend.named(name, children),
+            TreeOperator::Detail { fn_, fa, fs, .. } => {
+                check_detail(*fn_, *fa, *fs)?;
+                backend.detail(*fn_, *fa, *fs, children)?
+            }
+        })
+    }
+}
+
+fn check_detail(
+    fn_: Option<i32>,
+    fa: Option<f32>,
+    fs: Option<f32>,
+) -> Result<(), ScadDotsError> {
+    if fn_.map_or(false, |n| n < 0) {
+        return Err(
+            ScadDotsError::Args.context("number of segments can't be negative")
+        );
     }
+    let positive = |x: Option<f32>| x.map_or(true, |x| x > 0. && x.is_finite());
+    if !positive(fa) || !positive(fs) {
+        return Err(ScadDotsError::Args
+            .context("segment angle and length must be positive"));
+    }
+    Ok(())
 }
 
 impl Render for Tree {
-    fn render(
+    fn render_with<B>(
         &self,
-        options: RenderQuality,
-    ) -> Result<ScadObject, ScadDotsError> {
+        backend: &B,
+        options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
         match self {
-            Tree::Object(ref object) => object.render(options),
-            Tree::Operator(ref operator) => operator.render(options),
+            Tree::Object(ref object) => object.render_with(backend, options),
+            Tree::Operator(ref operator) => {
+                operator.render_with(backend, options)
+            }
         }
     }
 }
 
 impl Render for Cylinder {
-    fn render(
+    fn render_with<B>(
         &self,
-        _options: RenderQuality,
-    ) -> Result<ScadObject, ScadDotsError> {
-        let obj = scad!(
-                Translate(self.scad_translation());{
-                    scad!(
-                        Rotate(
-                            self.rot_degs(),
-                            self.rot_axis()?
-                        );{
-                            // Make cylinder w/ bottom face centered on origin
-                            scad!(
-                                Cylinder(self.height, Diameter(self.diameter))
-                            )
-                        }
-                    )
-                }
-        );
-        Ok(obj)
+        backend: &B,
+        options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        // Make cylinder w/ bottom face centered on origin
+        let shape = backend.cylinder(
+            self.height,
+            self.diameter,
+            self.top_diameter(),
+        )?;
+        placed(backend, self.scad_translation(), self.rot, options, shape)
     }
 }
 
@@ -168,31 +469,62 @@ impl Cylinder {
     fn scad_translation(&self) -> V3 {
         self.center_bot_pos - P3::origin()
     }
+}
 
-    fn rot_degs(&self) -> f32 {
-        radians_to_degrees(self.rot.angle())
+impl Render for Torus {
+    fn render_with<B>(
+        &self,
+        backend: &B,
+        options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        let tube_offset = V3::new(self.major_radius, 0., 0.);
+        // Sweep a circle around the z axis
+        let circle = backend.circle(self.minor_radius)?;
+        let shape = backend.rotate_extrude(vec![
+            backend.translate(tube_offset, vec![circle])?
+        ]);
+        placed(
+            backend,
+            self.center - P3::origin(),
+            self.rot,
+            options,
+            shape,
+        )
     }
+}
 
-    fn rot_axis(&self) -> Result<V3, ScadDotsError> {
-        unwrap_rot_axis(self.rot)
+impl Render for Text {
+    fn render_with<B>(
+        &self,
+        backend: &B,
+        options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        let shape = backend.linear_extrude(
+            self.thickness,
+            ExtrudeParams::default(),
+            vec![backend.text(&self.text, self.font_size)?],
+        )?;
+        placed(backend, self.pos - P3::origin(), self.rot, options, shape)
     }
 }
 
 impl Render for Dot {
-    fn render(
+    fn render_with<B>(
         &self,
-        _options: RenderQuality,
-    ) -> Result<ScadObject, ScadDotsError> {
-        let obj = scad!(
-            Translate(self.scad_translation());{
-                scad!(
-                    Rotate(self.rot_degs(), self.rot_axis()?);{
-                        self.render_shape()
-                    }
-                )
-            }
-        );
-        Ok(obj)
+        backend: &B,
+        options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        let shape = self.render_shape(backend)?;
+        placed(backend, self.scad_translation(), self.rot, options, shape)
     }
 }
 
@@ -204,31 +536,104 @@ impl Dot {
     fn scad_to_p000(&self) -> V3 {
         let half = self.size / 2.;
         let v = match self.shape {
-            DotShape::Cube => V3::new(0., 0., 0.),
+            DotShape::Cube | DotShape::ChamferedCube { .. } => {
+                V3::new(0., 0., 0.)
+            }
             DotShape::Sphere => V3::new(half, half, half),
-            DotShape::Cylinder => V3::new(half, half, 0.),
+            DotShape::Cylinder | DotShape::Prism { .. } => {
+                V3::new(half, half, 0.)
+            }
         };
         rotate(self.rot, v)
     }
 
-    pub fn render_shape(&self) -> ScadObject {
-        match self.shape {
-            DotShape::Cube =>
+    /// Build the dot's shape at the origin, before it's moved into place.
+    pub fn render_shape<B>(&self, backend: &B) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        let size = self.size;
+        Ok(match self.shape {
             // Make cube, with bottom face centered on the origin
-            {
-                scad!(Cube(V3::new(self.size, self.size, self.size)))
-            }
-            DotShape::Sphere =>
+            DotShape::Cube => backend.cube(V3::new(size, size, size))?,
             // Make sphere, with bottom surface touching the origin
-            {
-                scad!(Sphere(Diameter(self.size)))
-            }
-            DotShape::Cylinder =>
+            DotShape::Sphere => backend.sphere(size)?,
             // Make cylinder, with bottom face centered on the origin
-            {
-                scad!(Cylinder(self.size, Diameter(self.size)))
+            DotShape::Cylinder => backend.cylinder(size, size, size)?,
+            DotShape::ChamferedCube { chamfer } => {
+                self.render_chamfered_cube(backend, chamfer)?
             }
+            DotShape::Prism { sides } => self.render_prism(backend, sides)?,
+        })
+    }
+
+    /// Make a prism, with its bottom face centered on the origin like a
+    /// cylinder.
+    fn render_prism<B>(
+        &self,
+        backend: &B,
+        sides: u32,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        if sides < 3 {
+            return Err(ScadDotsError::Args.with_context(|| {
+                format!("prism dot has {} sides, but needs at least 3", sides)
+            }));
+        }
+        let radius = self.size / 2.;
+        let points: Vec<P2> = (0..sides)
+            .map(|i| {
+                let radians = i as f32 / sides as f32 * 2. * PI;
+                P2::new(radius * radians.cos(), radius * radians.sin())
+            })
+            .collect();
+        backend.linear_extrude(
+            self.size,
+            ExtrudeParams::default(),
+            vec![backend.polygon(&[&points])?],
+        )
+    }
+
+    /// Make a chamfered cube, with its corner on the origin like a plain
+    /// cube. It's the hull of 3 slabs, each one full size along 1 axis and
+    /// shrunk by the chamfer on both ends along the other 2 axes.
+    fn render_chamfered_cube<B>(
+        &self,
+        backend: &B,
+        chamfer: Fraction,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        let cut = chamfer.unwrap() * self.size / 2.;
+        let inner = self.size - 2. * cut;
+        let mut slabs = Vec::new();
+        for axis in &[Axis::X, Axis::Y, Axis::Z] {
+            // Use the first length along the slab's full axis, and the
+            // second along the other axes.
+            let pick = |a: Axis, full: f32, shrunk: f32| {
+                if a == *axis {
+                    full
+                } else {
+                    shrunk
+                }
+            };
+            let offset = V3::new(
+                pick(Axis::X, 0., cut),
+                pick(Axis::Y, 0., cut),
+                pick(Axis::Z, 0., cut),
+            );
+            let lengths = V3::new(
+                pick(Axis::X, self.size, inner),
+                pick(Axis::Y, self.size, inner),
+                pick(Axis::Z, self.size, inner),
+            );
+            slabs
+                .push(backend.translate(offset, vec![backend.cube(lengths)?])?);
         }
+        Ok(backend.combine(Combine::Hull, slabs))
     }
 }
 
@@ -239,18 +644,21 @@ impl Extrusion {
 }
 
 impl Render for Extrusion {
-    fn render(
+    fn render_with<B>(
         &self,
-        _options: RenderQuality,
-    ) -> Result<ScadObject, ScadDotsError> {
-        let points: Vec<V2> =
-            self.perimeter.iter().map(|p| p - P2::origin()).collect();
-        let mut params = LinExtrudeParams::default();
-        params.height = self.thickness;
-        Ok(scad!(
-        Translate(self.scad_translation());{
-            scad!(LinearExtrude(params);{
-                scad!( Polygon(PolygonParameters::new(points)))
-            })}))
+        backend: &B,
+        _options: RenderOptions,
+    ) -> Result<B::Node, ScadDotsError>
+    where
+        B: Backend,
+    {
+        let mut contours = vec![self.perimeter.points()];
+        contours.extend(self.holes.iter().map(|hole| hole.points()));
+        let shape = backend.linear_extrude(
+            self.thickness,
+            self.params,
+            vec![backend.polygon(&contours)?],
+        )?;
+        backend.translate(self.scad_translation(), vec![shape])
     }
 }
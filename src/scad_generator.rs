@@ -0,0 +1,258 @@
+//! Render models with the `scad` crate instead of the internal emitter. This
+//! is only built with the `scad` feature, for code that already works with
+//! `ScadObject`s. The scad crate can't express everything the internal
+//! emitter can, so rendering fails for text, raw code, included scad files,
+//! and scaled extrusions. Colors lose their alpha, and per-subtree detail and
+//! part names are left out.
+
+use scad::{
+    CircleType, LinExtrudeParams, PolygonParameters, RotateExtrudeParams,
+    ScadElement, ScadFile, ScadObject,
+};
+
+use core::utils::{radians_to_degrees, unwrap_rot_axis, ColorSpec, P2, R3, V3};
+use core::{ExtrudeParams, ImportKind, ScadModifier};
+use errors::{ResultExt, ScadDotsError};
+use render::{Backend, Combine, Render, RenderOptions};
+
+/// A backend that builds `ScadObject`s from the scad crate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScadGeneratorBackend;
+
+/// Render the thing into a `ScadFile`, with the detail set by the options.
+pub fn to_scad_file<T, O>(
+    thing: &T,
+    options: O,
+) -> Result<ScadFile, ScadDotsError>
+where
+    T: Render,
+    O: Into<RenderOptions>,
+{
+    let options = options.into();
+    let mut scad_file = ScadFile::new();
+    // detail controls resolution of curves
+    scad_file.set_detail(options.detail());
+    scad_file.add_object(
+        thing
+            .render_with(&ScadGeneratorBackend, options)
+            .context("failed to render to scad")?,
+    );
+    Ok(scad_file)
+}
+
+/// Like `render::to_code()`, but using the scad crate.
+pub fn to_code<T, O>(thing: &T, options: O) -> Result<String, ScadDotsError>
+where
+    T: Render,
+    O: Into<RenderOptions>,
+{
+    Ok(to_scad_file(thing, options)?.get_code())
+}
+
+fn object(element: ScadElement, children: Vec<ScadObject>) -> ScadObject {
+    let mut object = ScadObject::new(element);
+    for child in children {
+        object.add_child(child);
+    }
+    object
+}
+
+fn unsupported(what: &str) -> ScadDotsError {
+    ScadDotsError::Args
+        .context(&format!("the scad crate backend doesn't support {}", what))
+}
+
+impl Backend for ScadGeneratorBackend {
+    type Node = ScadObject;
+
+    fn cube(&self, lengths: V3) -> Result<ScadObject, ScadDotsError> {
+        Ok(ScadObject::new(ScadElement::Cube(lengths)))
+    }
+
+    fn sphere(&self, diameter: f32) -> Result<ScadObject, ScadDotsError> {
+        Ok(ScadObject::new(ScadElement::Sphere(CircleType::Diameter(
+            diameter,
+        ))))
+    }
+
+    fn cylinder(
+        &self,
+        height: f32,
+        bottom_diameter: f32,
+        top_diameter: f32,
+    ) -> Result<ScadObject, ScadDotsError> {
+        Ok(ScadObject::new(if bottom_diameter == top_diameter {
+            ScadElement::Cylinder(height, CircleType::Diameter(bottom_diameter))
+        } else {
+            ScadElement::Cone(
+                height,
+                CircleType::Diameter(bottom_diameter),
+                CircleType::Diameter(top_diameter),
+            )
+        }))
+    }
+
+    fn circle(&self, radius: f32) -> Result<ScadObject, ScadDotsError> {
+        Ok(ScadObject::new(ScadElement::Circle(CircleType::Radius(
+            radius,
+        ))))
+    }
+
+    fn polygon(&self, contours: &[&[P2]]) -> Result<ScadObject, ScadDotsError> {
+        let mut points = Vec::new();
+        let mut paths = Vec::new();
+        for contour in contours {
+            let start = points.len();
+            points.extend(contour.iter().map(|p| p.coords));
+            paths.push((start..points.len()).collect());
+        }
+        let params = PolygonParameters::new(points);
+        Ok(ScadObject::new(ScadElement::Polygon(
+            if contours.len() == 1 {
+                params
+            } else {
+                params.multi_vector_path(paths)
+            },
+        )))
+    }
+
+    fn text(
+        &self,
+        _text: &str,
+        _size: f32,
+    ) -> Result<ScadObject, ScadDotsError> {
+        Err(unsupported("text"))
+    }
+
+    fn raw_scad(&self, _code: &str) -> Result<ScadObject, ScadDotsError> {
+        Err(unsupported("raw scad code"))
+    }
+
+    fn import(
+        &self,
+        path: &str,
+        kind: ImportKind,
+    ) -> Result<ScadObject, ScadDotsError> {
+        match kind {
+            ImportKind::Stl | ImportKind::Dxf => {
+                Ok(ScadObject::new(ScadElement::Import(path.to_owned())))
+            }
+            ImportKind::Scad => Err(unsupported("including scad files")),
+        }
+    }
+
+    fn linear_extrude(
+        &self,
+        height: f32,
+        params: ExtrudeParams,
+        children: Vec<ScadObject>,
+    ) -> Result<ScadObject, ScadDotsError> {
+        if params.scale != 1. {
+            return Err(unsupported("scaled extrusions"));
+        }
+        let params = LinExtrudeParams {
+            height,
+            center: params.center,
+            twist: params.twist,
+            slices: params.slices as i32,
+            ..LinExtrudeParams::default()
+        };
+        Ok(object(ScadElement::LinearExtrude(params), children))
+    }
+
+    fn rotate_extrude(&self, children: Vec<ScadObject>) -> ScadObject {
+        object(
+            ScadElement::RotateExtrude(RotateExtrudeParams::default()),
+            children,
+        )
+    }
+
+    fn combine(&self, op: Combine, children: Vec<ScadObject>) -> ScadObject {
+        let element = match op {
+            Combine::Union => ScadElement::Union,
+            Combine::Hull => ScadElement::Hull,
+            Combine::Diff => ScadElement::Difference,
+            Combine::Intersect => ScadElement::Intersection,
+            Combine::Minkowski => ScadElement::Minkowski,
+        };
+        object(element, children)
+    }
+
+    /// The scad crate has no alpha, so every color is opaque.
+    fn color(
+        &self,
+        color: ColorSpec,
+        children: Vec<ScadObject>,
+    ) -> Result<ScadObject, ScadDotsError> {
+        Ok(object(ScadElement::Color(color.rgb()), children))
+    }
+
+    fn mirror(
+        &self,
+        normal: V3,
+        children: Vec<ScadObject>,
+    ) -> Result<ScadObject, ScadDotsError> {
+        Ok(object(ScadElement::Mirror(normal), children))
+    }
+
+    fn scale(
+        &self,
+        factors: V3,
+        children: Vec<ScadObject>,
+    ) -> Result<ScadObject, ScadDotsError> {
+        Ok(object(ScadElement::Scale(factors), children))
+    }
+
+    fn translate(
+        &self,
+        offset: V3,
+        children: Vec<ScadObject>,
+    ) -> Result<ScadObject, ScadDotsError> {
+        Ok(object(ScadElement::Translate(offset), children))
+    }
+
+    fn rotate(
+        &self,
+        rot: R3,
+        children: Vec<ScadObject>,
+    ) -> Result<ScadObject, ScadDotsError> {
+        let degrees = radians_to_degrees(rot.angle());
+        let axis = unwrap_rot_axis(rot)?;
+        Ok(object(ScadElement::Rotate(degrees, axis), children))
+    }
+
+    /// The scad crate only has the `!` modifier, so the others are applied
+    /// the way they affect the final render: `%` and `*` leave the children
+    /// out, and `#` keeps them unchanged.
+    fn modifier(
+        &self,
+        modifier: ScadModifier,
+        children: Vec<ScadObject>,
+    ) -> ScadObject {
+        match modifier {
+            ScadModifier::Root => {
+                object(ScadElement::Union, children).important()
+            }
+            ScadModifier::Debug => object(ScadElement::Union, children),
+            ScadModifier::Background | ScadModifier::Disable => {
+                ScadObject::new(ScadElement::Union)
+            }
+        }
+    }
+
+    fn named(&self, _name: &str, children: Vec<ScadObject>) -> ScadObject {
+        object(ScadElement::Union, children)
+    }
+
+    /// The scad crate can only set the detail for the whole file, so the
+    /// children use that.
+    fn detail(
+        &self,
+        _fn: Option<i32>,
+        _fa: Option<f32>,
+        _fs: Option<f32>,
+        children: Vec<ScadObject>,
+    ) -> Result<ScadObject, ScadDotsError> {
+        Ok(object(ScadElement::Union, children))
+    }
+}
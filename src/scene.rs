@@ -0,0 +1,214 @@
+//! Load models from declarative scene files, so their parameters can be
+//! tweaked without recompiling. A scene is a single `SceneNode`, written in
+//! RON or JSON. For example, in RON:
+//!
+//! ```text
+//! Diff([
+//!     Cylinder((
+//!         pos: [0, 0, 0],
+//!         align: EndCenter(P0),
+//!         diameter: 20,
+//!         height: 5,
+//!         rot: (w: 1, i: 0, j: 0, k: 0),
+//!     )),
+//!     Translate(offset: [0, 0, -1], child: Cylinder((
+//!         pos: [0, 0, 0],
+//!         align: EndCenter(P0),
+//!         diameter: 8,
+//!         height: 7,
+//!         rot: (w: 1, i: 0, j: 0, k: 0),
+//!     ))),
+//! ])
+//! ```
+//!
+//! Primitives are made from the same spec structs used in Rust code, and
+//! Rects, Cuboids, and Posts also take the style to link them with.
+//! Rotations are written as quaternions there, but the `Rotate` node takes an
+//! axis and an angle in degrees, which is easier to edit by hand.
+
+use std::fs;
+use std::path::Path;
+
+use ron;
+use serde_json;
+
+use core::utils::{axis_degrees, ColorSpec, V3};
+use core::{
+    Cylinder, CylinderSpec, Dot, DotSpec, Extrusion, ImportKind, ScadModifier,
+    Text, Torus, TorusSpec, Tree,
+};
+use cuboid::{Cuboid, CuboidLink, CuboidSpec};
+use errors::{ResultExt, ScadDotsError};
+use post::{Post, PostLink, PostSpec};
+use rect::{Rect, RectLink, RectSpec};
+
+/// A node in a scene file. It's converted to a `Tree` by `to_tree()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SceneNode {
+    Dot(DotSpec),
+    Cylinder(CylinderSpec),
+    Torus(TorusSpec),
+    Extrusion(Extrusion),
+    Text(Text),
+    /// A Rect, linked in the given style. See `Rect::link()`.
+    Rect {
+        spec: RectSpec,
+        link: RectLink,
+    },
+    /// A Cuboid, linked in the given style. See `Cuboid::link()`.
+    Cuboid {
+        spec: CuboidSpec,
+        link: CuboidLink,
+    },
+    /// A Post, linked in the given style. See `Post::link()`.
+    Post {
+        spec: PostSpec,
+        link: PostLink,
+    },
+    /// OpenSCAD code that's written out exactly as given. See
+    /// `TreeObject::RawScad`.
+    RawScad(String),
+    Import {
+        path: String,
+        kind: ImportKind,
+    },
+    Union(Vec<SceneNode>),
+    Hull(Vec<SceneNode>),
+    /// Subtract all following nodes from the first.
+    Diff(Vec<SceneNode>),
+    Intersect(Vec<SceneNode>),
+    Minkowski(Vec<SceneNode>),
+    Translate {
+        offset: V3,
+        child: Box<SceneNode>,
+    },
+    /// Rotate the child around the axis through the origin, by the angle in
+    /// degrees.
+    Rotate {
+        axis: V3,
+        degrees: f32,
+        child: Box<SceneNode>,
+    },
+    Scale {
+        factors: V3,
+        child: Box<SceneNode>,
+    },
+    /// Mirror the child across the plane with the given normal vector.
+    Mirror {
+        normal: V3,
+        child: Box<SceneNode>,
+    },
+    Color {
+        color: ColorSpec,
+        child: Box<SceneNode>,
+    },
+    Modifier {
+        modifier: ScadModifier,
+        child: Box<SceneNode>,
+    },
+    /// Render the child as a separate OpenSCAD module. See `Tree::named()`.
+    Named {
+        name: String,
+        child: Box<SceneNode>,
+    },
+    /// A tree in its own serialized form, as written by serializing a `Tree`.
+    Tree(Tree),
+}
+
+impl SceneNode {
+    /// Build the tree described by this node and its children.
+    pub fn to_tree(&self) -> Result<Tree, ScadDotsError> {
+        Ok(match self {
+            SceneNode::Dot(spec) => Dot::try_new(*spec)?.into(),
+            SceneNode::Cylinder(spec) => Cylinder::try_new(*spec)?.into(),
+            SceneNode::Torus(spec) => Torus::new(*spec)?.into(),
+            SceneNode::Extrusion(extrusion) => extrusion.clone().into(),
+            SceneNode::Text(text) => text.clone().into(),
+            SceneNode::Rect { spec, link } => Rect::new(*spec)?.link(*link)?,
+            SceneNode::Cuboid { spec, link } => {
+                Cuboid::new(*spec)?.link(*link)?
+            }
+            SceneNode::Post { spec, link } => Post::new(*spec)?.link(*link),
+            SceneNode::RawScad(code) => Tree::raw_scad(code.as_str()),
+            SceneNode::Import { path, kind } => {
+                Tree::import(path.as_str(), *kind)
+            }
+            SceneNode::Union(children) => Tree::union(to_trees(children)?),
+            SceneNode::Hull(children) => Tree::hull(to_trees(children)?),
+            SceneNode::Diff(children) => Tree::diff(to_trees(children)?),
+            SceneNode::Intersect(children) => {
+                Tree::intersect(to_trees(children)?)
+            }
+            SceneNode::Minkowski(children) => {
+                Tree::minkowski(to_trees(children)?)
+            }
+            SceneNode::Translate { offset, child } => {
+                Tree::translate(*offset, child.to_tree()?)
+            }
+            SceneNode::Rotate {
+                axis,
+                degrees,
+                child,
+            } => {
+                if axis.norm() == 0. {
+                    return Err(ScadDotsError::Args
+                        .context("rotation axis must not be zero"));
+                }
+                Tree::rotate(axis_degrees(*axis, *degrees), child.to_tree()?)
+            }
+            SceneNode::Scale { factors, child } => {
+                Tree::scale(*factors, child.to_tree()?)
+            }
+            SceneNode::Mirror { normal, child } => {
+                Tree::mirror(*normal, child.to_tree()?)
+            }
+            SceneNode::Color { color, child } => {
+                Tree::color(*color, child.to_tree()?)
+            }
+            SceneNode::Modifier { modifier, child } => {
+                Tree::modifier(*modifier, child.to_tree()?)
+            }
+            SceneNode::Named { name, child } => {
+                Tree::named(name.as_str(), child.to_tree()?)
+            }
+            SceneNode::Tree(tree) => tree.clone(),
+        })
+    }
+}
+
+fn to_trees(nodes: &[SceneNode]) -> Result<Vec<Tree>, ScadDotsError> {
+    nodes.iter().map(|node| node.to_tree()).collect()
+}
+
+/// Build the tree described by a scene written in RON.
+pub fn from_ron(text: &str) -> Result<Tree, ScadDotsError> {
+    let node: SceneNode = ron::de::from_str(text)
+        .map_err(|e| ScadDotsError::Scene(e.to_string()))?;
+    node.to_tree()
+}
+
+/// Build the tree described by a scene written in JSON.
+pub fn from_json(text: &str) -> Result<Tree, ScadDotsError> {
+    let node: SceneNode = serde_json::from_str(text)
+        .map_err(|e| ScadDotsError::Scene(e.to_string()))?;
+    node.to_tree()
+}
+
+/// Load a scene file and build its tree. The format is chosen by the file's
+/// extension, which must be `ron` or `json`.
+pub fn load_scene<P>(path: P) -> Result<Tree, ScadDotsError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let context = || format!("failed to load scene '{}'", path.display());
+    let text = fs::read_to_string(path).with_context(context)?;
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    match extension {
+        Some("ron") => from_ron(&text),
+        Some("json") => from_json(&text),
+        _ => Err(ScadDotsError::Args
+            .context("scene file extension must be 'ron' or 'json'")),
+    }
+    .map_err(|e| e.with_context(context))
+}
@@ -0,0 +1,174 @@
+use std::f32::consts::PI;
+
+use core::utils::{
+    axis_degrees, axis_radians, Axis, Corner1 as C1, P2, P3, R3, V3,
+};
+use core::{
+    Cylinder, CylinderAlign, CylinderSpec, ExtrudeParams, Extrusion, Polygon2D,
+    Tree,
+};
+use errors::ScadDotsError;
+
+/// The default number of segments used to approximate each turn of a thread.
+pub const DEFAULT_SEGMENTS_PER_TURN: usize = 24;
+
+/// A screw thread with the ISO metric basic profile (60 degree flanks), along
+/// the z axis in its default orientation. It's approximated by hulls between
+/// thin slices of the tooth profile, placed at steps around the helix.
+#[derive(Debug, Clone, Copy)]
+pub struct Thread {
+    spec: ThreadSpec,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadSpec {
+    /// The center of the bottom end of the thread.
+    pub pos: P3,
+    /// The nominal major diameter, like 3 for an M3 thread.
+    pub diameter: f32,
+    /// The distance between teeth, like 0.5 for an M3 thread.
+    pub pitch: f32,
+    pub length: f32,
+    /// How much smaller an external thread is (or how much larger an
+    /// internal thread is) on each side, so that printed parts fit
+    /// together.
+    pub clearance: f32,
+    pub kind: ThreadKind,
+    /// The number of segments used to approximate each turn. See
+    /// `DEFAULT_SEGMENTS_PER_TURN`.
+    pub segments_per_turn: usize,
+    pub rot: R3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThreadKind {
+    /// A solid threaded rod, like a bolt.
+    External,
+    /// The negative space of a threaded hole, like in a nut, to be
+    /// subtracted from a solid.
+    Internal,
+}
+
+impl Thread {
+    /// Create a new thread.
+    pub fn new(spec: ThreadSpec) -> Result<Self, ScadDotsError> {
+        if !(spec.diameter > 0.) || !(spec.pitch > 0.) || !(spec.length > 0.) {
+            return Err(ScadDotsError::Dimension.context(
+                "Thread diameter, pitch, and length must be positive",
+            ));
+        }
+        if !(spec.clearance >= 0.) {
+            return Err(ScadDotsError::Dimension
+                .context("Thread clearance can't be negative"));
+        }
+        if spec.segments_per_turn < 3 {
+            return Err(ScadDotsError::Args
+                .context("Thread needs at least 3 segments per turn"));
+        }
+        let thread = Self { spec };
+        if !(thread.minor_radius() > 0.) {
+            return Err(ScadDotsError::Dimension
+                .context("Thread pitch is too coarse for its diameter"));
+        }
+        Ok(thread)
+    }
+
+    /// Return the height of the fundamental triangle of the ISO profile.
+    fn triangle_height(&self) -> f32 {
+        3_f32.sqrt() / 2. * self.spec.pitch
+    }
+
+    /// Return the radius of the crests of the teeth, including clearance.
+    pub fn major_radius(&self) -> f32 {
+        self.spec.diameter / 2. + self.clearance_offset()
+    }
+
+    /// Return the radius of the roots between the teeth, including
+    /// clearance.
+    pub fn minor_radius(&self) -> f32 {
+        self.major_radius() - 5. / 8. * self.triangle_height()
+    }
+
+    fn clearance_offset(&self) -> f32 {
+        match self.spec.kind {
+            ThreadKind::External => -self.spec.clearance,
+            ThreadKind::Internal => self.spec.clearance,
+        }
+    }
+
+    /// Return a thin slice of one tooth, in the plane containing the z axis
+    /// and the direction at the given angle around it. The tooth is centered
+    /// at the given height.
+    fn tooth_slice(&self, radians: f32, height: f32) -> Tree {
+        let pitch = self.spec.pitch;
+        // Start the tooth a little inside the core so they overlap.
+        let root = self.minor_radius() - pitch / 16.;
+        let crest = self.major_radius();
+        // The ISO profile has a flat of width P/8 at the crest and P/4 at
+        // the root.
+        let perimeter = Polygon2D::new(vec![
+            P2::new(root, -3. / 8. * pitch),
+            P2::new(crest, -pitch / 16.),
+            P2::new(crest, pitch / 16.),
+            P2::new(root, 3. / 8. * pitch),
+        ])
+        .expect("thread tooth profile is always a trapezoid");
+        let thickness = pitch / 100.;
+        let slice = Extrusion {
+            perimeter,
+            holes: Vec::new(),
+            bottom_z: -thickness / 2.,
+            thickness,
+            params: ExtrudeParams::default(),
+        };
+        // Stand the profile up in the xz plane, then turn it around the axis.
+        let upright = Tree::rotate(axis_degrees(Axis::X, 90.), slice);
+        Tree::translate(
+            V3::new(0., 0., height),
+            Tree::rotate(axis_radians(Axis::Z, radians), upright),
+        )
+    }
+
+    /// Return the teeth, extending a full turn past both ends.
+    fn teeth(&self) -> Tree {
+        let pitch = self.spec.pitch;
+        let steps_per_turn = self.spec.segments_per_turn;
+        let turns = (self.spec.length / pitch).ceil() as usize + 2;
+        let slices: Vec<_> = (0..(turns * steps_per_turn + 1))
+            .map(|i| {
+                let turn = i as f32 / steps_per_turn as f32;
+                self.tooth_slice(turn * 2. * PI, (turn - 1.) * pitch)
+            })
+            .collect();
+        Tree::union(
+            slices
+                .windows(2)
+                .map(|pair| Tree::hull(pair.to_vec()))
+                .collect(),
+        )
+    }
+
+    /// Return a cylinder along the thread's axis, in its default position.
+    fn cylinder(&self, radius: f32) -> Cylinder {
+        Cylinder::new(CylinderSpec {
+            pos: P3::origin(),
+            align: CylinderAlign::EndCenter(C1::P0),
+            diameter: 2. * radius,
+            height: self.spec.length,
+            rot: R3::identity(),
+        })
+    }
+}
+
+impl From<Thread> for Tree {
+    fn from(thread: Thread) -> Tree {
+        let core = thread.cylinder(thread.minor_radius());
+        // Trim the teeth to the length of the thread.
+        let trim = thread.cylinder(2. * thread.major_radius());
+        let solid = intersect![union![core, thread.teeth()], trim];
+        Tree::translate(
+            thread.spec.pos - P3::origin(),
+            Tree::rotate(thread.spec.rot, solid),
+        )
+    }
+}
@@ -1,16 +1,22 @@
 use core::utils::{
-    axis_degrees, rotate, rotation_between, sin_deg, Axis, CubeFace, P3, R3, V3,
+    axis_degrees, cos_deg, midpoint, radians_to_degrees, rotate,
+    rotation_between, sin_deg, Axis, Corner1 as C1, CubeFace, P3, R3, V3,
 };
 use core::{
-    mark, Dot, DotAlign, DotShape, DotSpec, MapDots, MinMaxCoord, Tree,
+    drop_solid, mark, Anchor, AnchorName, AnchorPoint, Cylinder, CylinderAlign,
+    CylinderSpec, Dot, DotAlign, DotShape, DotSpec, MapDots, MinMaxCoord, Tree,
 };
 use errors::ScadDotsError;
 
-#[derive(Debug, Clone, Copy, MapDots, MinMaxCoord)]
+#[derive(Debug, Clone, Copy)]
 pub struct Triangle {
     pub a: Dot,
     pub b: Dot,
     pub c: Dot,
+    /// If set, the corners are rounded to this radius when the Triangle is
+    /// linked, instead of taking the shape of the dots. See
+    /// `TriangleSpec::corner_radius`.
+    pub corner_radius: Option<f32>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -18,9 +24,18 @@ pub struct TriangleSpec {
     pub deg_b: f32,
     pub len_bc: f32,
     pub deg_c: f32,
+    /// The size of the corner dots, which is also the Triangle's thickness.
     pub size: f32,
     pub point_b: P3,
     pub rot: R3,
+    /// The shape of the corner dots. Use `DotShape::Cylinder` for corners
+    /// rounded to half the dot size, or `DotShape::Cube` for sharp corners.
+    pub shape: DotShape,
+    /// Round the corners to this radius when linking, independent of the
+    /// dot size. It must be positive and smaller than the radius of the
+    /// triangle's inscribed circle. If it's None, the corners take the shape
+    /// of the dots.
+    pub corner_radius: Option<f32>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -30,25 +45,46 @@ pub enum TriCorner {
     C,
 }
 
+/// A triangular prism is made of 2 Triangles, one above the other.
+#[derive(Debug, Clone, Copy, MapDots, MinMaxCoord)]
+pub struct Prism {
+    pub top: Triangle,
+    pub bot: Triangle,
+}
+
+/// Specify an alignment point on a Prism.
+#[derive(Debug, Clone, Copy)]
+pub enum PrismAlign {
+    /// The midpoint between the centers of the top and bottom faces.
+    Centroid,
+    /// The center of the bottom (C1::P0) or top (C1::P1) triangular face. This
+    /// is the centroid of the triangle formed by the corner dots' centers.
+    FaceCenter(C1),
+    /// The center of the rectangular side face between the 2 given corners.
+    SideCenter(TriCorner, TriCorner),
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 impl Triangle {
     pub fn new(spec: TriangleSpec) -> Result<Self, ScadDotsError> {
+        spec.validate()?;
         let a_spec = DotSpec {
             pos: spec.center(TriCorner::A),
             align: DotAlign::center_face(CubeFace::Z0),
             size: spec.size,
             rot: spec.rot,
-            shape: DotShape::Cylinder,
+            shape: spec.shape,
         };
 
         let b_spec = a_spec.with_pos(spec.center(TriCorner::B));
         let c_spec = a_spec.with_pos(spec.center(TriCorner::C));
 
         Ok(Self {
-            a: Dot::new(a_spec),
-            b: Dot::new(b_spec),
-            c: Dot::new(c_spec),
+            a: Dot::try_new(a_spec)?,
+            b: Dot::try_new(b_spec)?,
+            c: Dot::try_new(c_spec)?,
+            corner_radius: spec.corner_radius,
         })
     }
 
@@ -61,11 +97,340 @@ impl Triangle {
     }
 
     pub fn link(&self) -> Result<Tree, ScadDotsError> {
-        Ok(hull![self.a, self.b, self.c])
+        Ok(Tree::hull(self.corners()))
+    }
+
+    /// Return a solid triangular prism with the given total height. See
+    /// `Prism::new()`.
+    pub fn link_prism(&self, height: f32) -> Result<Tree, ScadDotsError> {
+        Prism::new(*self, height)?.link()
+    }
+
+    /// Return the shapes to hull together at the 3 corners: either the dots
+    /// themselves, or cylinders with the corner radius that fill the same
+    /// thickness.
+    fn corners(&self) -> Vec<Tree> {
+        let radius = match self.corner_radius {
+            Some(radius) => radius,
+            None => return vec![self.a.into(), self.b.into(), self.c.into()],
+        };
+        let all = [TriCorner::A, TriCorner::B, TriCorner::C];
+        all.iter()
+            .map(|&corner| {
+                let (other1, other2) = others(corner);
+                let center = |c| self.dot(c).pos(DotAlign::centroid());
+                let to_1 = (center(other1) - center(corner)).normalize();
+                let to_2 = (center(other2) - center(corner)).normalize();
+                // The inset triangle of dot centers has the same angles and
+                // bisectors as the outer triangle, so move along the bisector
+                // until the cylinder touches both sides.
+                let bisector = (to_1 + to_2).normalize();
+                let half_angle_sin = (to_1.angle(&to_2) / 2.).sin();
+                let dot = self.dot(corner);
+                let pos = dot.pos(DotAlign::center_face(CubeFace::Z0))
+                    + bisector * (radius - dot.size / 2.) / half_angle_sin;
+                Cylinder::new(CylinderSpec {
+                    pos,
+                    align: CylinderAlign::EndCenter(C1::P0),
+                    diameter: 2. * radius,
+                    height: dot.size,
+                    rot: dot.rot,
+                })
+                .into()
+            })
+            .collect()
+    }
+
+    /// Return the hull of the Triangle's dots and copies of them dropped down
+    /// to `bottom_z`. See `Dot::drop()`.
+    pub fn drop_solid(&self, bottom_z: f32, shape: Option<DotShape>) -> Tree {
+        drop_solid(&[self.a, self.b, self.c], bottom_z, shape)
+    }
+
+    /// Return a copy of the dot at the given corner.
+    pub fn dot(&self, corner: TriCorner) -> Dot {
+        match corner {
+            TriCorner::A => self.a,
+            TriCorner::B => self.b,
+            TriCorner::C => self.c,
+        }
+    }
+
+    /// Return the size of the Triangle's dots, which is also its thickness.
+    pub fn size(&self) -> f32 {
+        self.a.size
+    }
+
+    /// Return a unit vector perpendicular to the plane of the Triangle,
+    /// pointing from its bottom face to its top face.
+    pub fn unit_normal(&self) -> V3 {
+        rotate(self.a.rot, Axis::Z)
+    }
+
+    /// Return the average of the given alignment point on each of the 3 dots.
+    pub fn centroid(&self, align: DotAlign) -> P3 {
+        let sum = (self.a.pos(align) - P3::origin())
+            + (self.b.pos(align) - P3::origin())
+            + (self.c.pos(align) - P3::origin());
+        P3::origin() + sum / 3.
+    }
+}
+
+impl MapDots for Triangle {
+    fn map(&self, f: &Fn(&Dot) -> Dot) -> Self {
+        Self {
+            a: f(&self.a),
+            b: f(&self.b),
+            c: f(&self.c),
+            corner_radius: self.corner_radius,
+        }
+    }
+}
+
+impl MinMaxCoord for Triangle {
+    fn all_coords(&self, axis: Axis) -> Vec<f32> {
+        [self.a, self.b, self.c].all_coords(axis)
+    }
+}
+
+/// The anchor points of a Triangle are the averages of the same anchor points on
+/// its 3 dots. See `Triangle::centroid()`.
+impl Anchor for Triangle {
+    type Align = DotAlign;
+
+    fn anchor_at(&self, align: DotAlign) -> AnchorPoint {
+        AnchorPoint {
+            pos: self.centroid(align),
+            rot: self.a.rot,
+        }
+    }
+
+    fn align_from_name(name: AnchorName) -> Result<DotAlign, ScadDotsError> {
+        Dot::align_from_name(name)
+    }
+}
+
+impl Prism {
+    /// Create a Prism by extruding the Triangle along its normal, so that the
+    /// Prism has the given total height. The height includes the thickness of
+    /// the Triangle itself, so it can't be less than the Triangle's dot size.
+    pub fn new(triangle: Triangle, height: f32) -> Result<Self, ScadDotsError> {
+        if height < triangle.size() {
+            return Err(ScadDotsError::Dimension.context(
+                "Prism height can't be less than the size of its dots",
+            ));
+        }
+        let offset = (height - triangle.size()) * triangle.unit_normal();
+        Ok(Self {
+            bot: triangle,
+            top: triangle.map_translate(offset),
+        })
+    }
+
+    /// Return the Triangle at the bottom (C1::P0) or top (C1::P1) of the Prism.
+    pub fn triangle(&self, upper_or_lower: C1) -> Triangle {
+        match upper_or_lower {
+            C1::P0 => self.bot,
+            C1::P1 => self.top,
+        }
+    }
+
+    /// Return the absolute position of the given alignment point on the Prism.
+    pub fn pos(&self, align: PrismAlign) -> P3 {
+        match align {
+            PrismAlign::Centroid => midpoint(
+                self.pos(PrismAlign::FaceCenter(C1::P0)),
+                self.pos(PrismAlign::FaceCenter(C1::P1)),
+            ),
+            PrismAlign::FaceCenter(C1::P0) => {
+                self.bot.centroid(DotAlign::center_face(CubeFace::Z0))
+            }
+            PrismAlign::FaceCenter(C1::P1) => {
+                self.top.centroid(DotAlign::center_face(CubeFace::Z1))
+            }
+            PrismAlign::SideCenter(c1, c2) => {
+                let center = |corner| {
+                    midpoint(self.bot_center(corner), self.top_center(corner))
+                };
+                let edge_midpoint = midpoint(center(c1), center(c2));
+
+                // Push the point out from the dot centers to the surface of the
+                // dots, away from the 3rd corner.
+                let edge = center(c2) - center(c1);
+                let mut outward =
+                    edge.cross(&self.bot.unit_normal()).normalize();
+                let to_third = center(opposite(c1, c2)) - edge_midpoint;
+                if outward.dot(&to_third) > 0. {
+                    outward = -outward;
+                }
+                edge_midpoint + outward * self.bot.size() / 2.
+            }
+        }
+    }
+
+    pub fn link(&self) -> Result<Tree, ScadDotsError> {
+        let mut corners = self.bot.corners();
+        corners.extend(self.top.corners());
+        Ok(Tree::hull(corners))
+    }
+
+    fn bot_center(&self, corner: TriCorner) -> P3 {
+        self.bot.dot(corner).pos(DotAlign::centroid())
+    }
+
+    fn top_center(&self, corner: TriCorner) -> P3 {
+        self.top.dot(corner).pos(DotAlign::centroid())
     }
 }
 
 impl TriangleSpec {
+    /// Specify a triangle by the lengths of its 3 sides. They must satisfy
+    /// the triangle inequality. Side B-C lies along the local x axis starting
+    /// at `point_b`, like with the angle-side-angle fields. The dots are
+    /// cylinders, and the corners aren't rounded further.
+    pub fn sss(
+        len_ab: f32,
+        len_bc: f32,
+        len_ca: f32,
+        size: f32,
+        point_b: P3,
+        rot: R3,
+    ) -> Result<Self, ScadDotsError> {
+        if !(len_ab > 0. && len_bc > 0. && len_ca > 0.) {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "Triangle sides must be positive, got {}, {}, and {}",
+                    len_ab, len_bc, len_ca
+                )
+            }));
+        }
+        if !(len_ab < len_bc + len_ca
+            && len_bc < len_ca + len_ab
+            && len_ca < len_ab + len_bc)
+        {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "Triangle sides {}, {}, and {} don't satisfy the \
+                     triangle inequality",
+                    len_ab, len_bc, len_ca
+                )
+            }));
+        }
+        // Use the law of cosines to find the angles at the ends of B-C.
+        let angle = |adjacent1: f32, adjacent2: f32, opposite: f32| {
+            let cos = (adjacent1.powi(2) + adjacent2.powi(2)
+                - opposite.powi(2))
+                / (2. * adjacent1 * adjacent2);
+            radians_to_degrees(cos.max(-1.).min(1.).acos())
+        };
+        let spec = Self {
+            deg_b: angle(len_ab, len_bc, len_ca),
+            len_bc,
+            deg_c: angle(len_bc, len_ca, len_ab),
+            size,
+            point_b,
+            rot,
+            shape: DotShape::Cylinder,
+            corner_radius: None,
+        };
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    /// Specify a triangle by 2 sides and the angle between them, at corner
+    /// B. See `TriangleSpec::sss()`.
+    pub fn sas(
+        len_ab: f32,
+        deg_b: f32,
+        len_bc: f32,
+        size: f32,
+        point_b: P3,
+        rot: R3,
+    ) -> Result<Self, ScadDotsError> {
+        if !(deg_b > 0. && deg_b < 180.) {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "Triangle angle must be between 0 and 180 degrees, got {}",
+                    deg_b
+                )
+            }));
+        }
+        let len_ca = (len_ab.powi(2) + len_bc.powi(2)
+            - 2. * len_ab * len_bc * cos_deg(deg_b))
+        .sqrt();
+        Self::sss(len_ab, len_bc, len_ca, size, point_b, rot)
+    }
+
+    /// Specify a right triangle by the lengths of its 2 legs, with the right
+    /// angle at corner B. See `TriangleSpec::sss()`.
+    pub fn right(
+        leg_ab: f32,
+        leg_bc: f32,
+        size: f32,
+        point_b: P3,
+        rot: R3,
+    ) -> Result<Self, ScadDotsError> {
+        Self::sas(leg_ab, 90., leg_bc, size, point_b, rot)
+    }
+
+    /// Check that the spec describes a real, non-degenerate triangle. All
+    /// angles must be positive, and so must the side length and dot size.
+    pub fn validate(&self) -> Result<(), ScadDotsError> {
+        // Written as negations so that NaN values are rejected too.
+        if !(self.deg_b > 0. && self.deg_c > 0.) {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "Triangle angles must be positive, got deg_b={} and \
+                     deg_c={}",
+                    self.deg_b, self.deg_c
+                )
+            }));
+        }
+        if !(self.deg_b + self.deg_c < 180.) {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!(
+                    "Triangle angles must sum to less than 180 degrees, got \
+                     deg_b={} and deg_c={}",
+                    self.deg_b, self.deg_c
+                )
+            }));
+        }
+        if !(self.len_bc > 0.) {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!("Triangle len_bc must be positive, got {}", self.len_bc)
+            }));
+        }
+        if !(self.size > 0.) {
+            return Err(ScadDotsError::Dimension.with_context(|| {
+                format!("Triangle dot size must be positive, got {}", self.size)
+            }));
+        }
+        if let Some(radius) = self.corner_radius {
+            if !(radius > 0. && radius < self.inradius()) {
+                return Err(ScadDotsError::Dimension.with_context(|| {
+                    format!(
+                        "Triangle corner radius must be between 0 and the \
+                         inscribed radius {}, got {}",
+                        self.inradius(),
+                        radius
+                    )
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the radius of the largest circle that fits inside the
+    /// triangle.
+    pub fn inradius(&self) -> f32 {
+        let a = self.len(TriCorner::B, TriCorner::C);
+        let b = self.len(TriCorner::A, TriCorner::C);
+        let c = self.len(TriCorner::A, TriCorner::B);
+        let semiperimeter = (a + b + c) / 2.;
+        let area = a * c * sin_deg(self.deg_b) / 2.;
+        area / semiperimeter
+    }
+
     fn center(&self, v: TriCorner) -> P3 {
         self.point(v) + self.unit_to_center(v) * self.dist_to_center(v)
     }
@@ -151,6 +516,15 @@ impl TriangleSpec {
     }
 }
 
+/// Return the 2 corners other than the given one.
+fn others(corner: TriCorner) -> (TriCorner, TriCorner) {
+    match corner {
+        TriCorner::A => (TriCorner::B, TriCorner::C),
+        TriCorner::B => (TriCorner::C, TriCorner::A),
+        TriCorner::C => (TriCorner::A, TriCorner::B),
+    }
+}
+
 fn opposite(v1: TriCorner, v2: TriCorner) -> TriCorner {
     match (v1, v2) {
         (TriCorner::A, TriCorner::B) | (TriCorner::B, TriCorner::A) => {
@@ -2,18 +2,69 @@
 extern crate approx;
 extern crate nalgebra;
 extern crate nom;
+extern crate png;
+#[cfg(feature = "serialize")]
+extern crate serde;
+#[cfg(feature = "serialize")]
+extern crate serde_json;
 
-#[macro_use(union, hull, mirror, red)]
+#[macro_use(
+    union,
+    hull,
+    diff,
+    cut,
+    intersect,
+    minkowski,
+    mirror,
+    mirror_copy,
+    red,
+    scale,
+    translate,
+    rotate,
+    ghost,
+    highlight
+)]
 extern crate scad_dots;
 
-use scad_dots::harness::{check_model, Action, MAX_RELATIVE};
-use scad_dots::parse::scad_relative_eq;
+use scad_dots::emit::precise_num;
+use scad_dots::errors::ScadDotsError;
+use scad_dots::export::{outline_code, OutlineFormat};
+use scad_dots::harness::{
+    check_model, check_model_with, check_models, compare_images,
+    openscad_version_at, Action, HarnessConfig, MAX_RELATIVE,
+};
+use scad_dots::params::{Param, ParamSet, Parametric, Unit};
+use scad_dots::parse::{scad_bounds, scad_diff, scad_relative_eq};
+use scad_dots::preview::watch;
+use scad_dots::render::{
+    compile_to_stl, to_code, Backend, Combine, Render, RenderOptions,
+    RenderQuality,
+};
+#[cfg(feature = "scad")]
+use scad_dots::scad_generator;
+#[cfg(feature = "scene")]
+use scad_dots::scene;
 
+use scad_dots::assembly::*;
 use scad_dots::core::*;
 use scad_dots::core::{Corner1 as C1, Corner2 as C2, Corner3 as C3};
 use scad_dots::cuboid::*;
+use scad_dots::enclosure::*;
+use scad_dots::fastener::*;
+use scad_dots::fillet::*;
+use scad_dots::gear::*;
+use scad_dots::generators::*;
+use scad_dots::hardware::*;
+use scad_dots::helpers::*;
+use scad_dots::joint::*;
+use scad_dots::keyboard::*;
+use scad_dots::knurl::*;
+use scad_dots::lattice::*;
+use scad_dots::layout::*;
+use scad_dots::pattern::*;
 use scad_dots::post::*;
 use scad_dots::rect::*;
+use scad_dots::thread::*;
 use scad_dots::triangle::*;
 
 use std::f32::consts::PI;
@@ -21,19 +72,314 @@ use std::f32::consts::PI;
 fn extrude1() {
     check_model("extrude1", Action::Test, || {
         let extrusion = Extrusion {
-            perimeter: vec![
+            perimeter: Polygon2D::new(vec![
                 P2::new(-5., -5.),
                 P2::new(0., 10.),
                 P2::new(20., 10.),
-            ],
+            ])
+            .unwrap(),
+            holes: Vec::new(),
             thickness: 1.,
             bottom_z: -5.,
+            params: ExtrudeParams::default(),
         };
         // change!
         Ok(extrusion.into())
     })
 }
 
+#[test]
+fn extrusion_transforms() {
+    let extrusion = Extrusion {
+        perimeter: Polygon2D::new(vec![
+            P2::new(0., 0.),
+            P2::new(4., 0.),
+            P2::new(4., 2.),
+        ])
+        .unwrap(),
+        holes: Vec::new(),
+        thickness: 3.,
+        bottom_z: 1.,
+        params: ExtrudeParams::default(),
+    };
+    assert_eq!(extrusion.max_coord(Axis::X), 4.);
+    assert_eq!(extrusion.max_coord(Axis::Z), 4.);
+
+    let moved = extrusion.translate(V3::new(1., 2., -1.));
+    assert_eq!(moved.min_coord(Axis::X), 1.);
+    assert_eq!(moved.max_coord(Axis::Y), 4.);
+    assert_eq!(moved.min_coord(Axis::Z), 0.);
+
+    let rotated = extrusion.rotate_z(90.);
+    assert_relative_eq!(rotated.min_coord(Axis::X), -2., epsilon = 0.0001);
+    assert_relative_eq!(rotated.max_coord(Axis::Y), 4., epsilon = 0.0001);
+}
+
+#[test]
+fn polygon_2d() {
+    // An L shape, going clockwise.
+    let points = vec![
+        P2::new(0., 0.),
+        P2::new(0., 10.),
+        P2::new(4., 10.),
+        P2::new(4., 4.),
+        P2::new(10., 4.),
+        P2::new(10., 0.),
+    ];
+    let polygon = Polygon2D::new(points.clone()).unwrap();
+    assert!(!polygon.is_counterclockwise());
+    assert_relative_eq!(polygon.signed_area(), -64.);
+    assert_relative_eq!(polygon.reversed().area(), 64.);
+    let centroid = polygon.centroid();
+    assert_relative_eq!(centroid.x, 3.875, epsilon = 1e-5);
+    assert_relative_eq!(centroid.y, 3.875, epsilon = 1e-5);
+    assert!(polygon.contains(P2::new(2., 8.)));
+    assert!(polygon.contains(P2::new(8., 2.)));
+    assert!(!polygon.contains(P2::new(8., 8.)));
+    assert!(!polygon.contains(P2::new(-1., 2.)));
+
+    let triangles = polygon.triangulate().unwrap();
+    assert_eq!(triangles.len(), points.len() - 2);
+    let mut total = 0.;
+    for triangle in &triangles {
+        let area = Polygon2D::new(triangle.to_vec()).unwrap().signed_area();
+        assert!(area > 0.);
+        total += area;
+    }
+    assert_relative_eq!(total, 64., epsilon = 1e-4);
+
+    let grown = polygon.offset(1., OffsetJoin::Miter).unwrap();
+    assert_relative_eq!(grown.area(), 12. * 6. + 6. * 6., epsilon = 1e-4);
+    let shrunk = polygon.offset(-1., OffsetJoin::Miter).unwrap();
+    assert_relative_eq!(shrunk.area(), 2. * 8. + 6. * 2., epsilon = 1e-4);
+    assert!(polygon.offset(-3., OffsetJoin::Miter).is_err());
+
+    let bowtie = vec![
+        P2::new(0., 0.),
+        P2::new(10., 10.),
+        P2::new(10., 0.),
+        P2::new(0., 10.),
+    ];
+    assert!(Polygon2D::new(bowtie).is_err());
+    assert!(Polygon2D::new(points[..2].to_vec()).is_err());
+    let folded = vec![P2::new(0., 0.), P2::new(10., 0.), P2::new(5., 0.)];
+    assert!(Polygon2D::new(folded).is_err());
+
+    let extrusion = Extrusion::from_polygon(polygon.clone(), 2., 1.).unwrap();
+    assert_eq!(extrusion.perimeter, polygon);
+}
+
+#[test]
+fn convex_hull() {
+    let points = vec![
+        P2::new(2., 2.),
+        P2::new(0., 0.),
+        P2::new(4., 0.),
+        P2::new(2., 0.),
+        P2::new(4., 4.),
+        P2::new(1., 3.),
+        P2::new(0., 4.),
+        P2::new(4., 4.),
+    ];
+    assert_eq!(
+        convex_hull_2d(&points),
+        vec![
+            P2::new(0., 0.),
+            P2::new(4., 0.),
+            P2::new(4., 4.),
+            P2::new(0., 4.),
+        ]
+    );
+    let line = [P2::new(0., 0.), P2::new(2., 2.), P2::new(1., 1.)];
+    assert_eq!(
+        convex_hull_2d(&line),
+        vec![P2::new(0., 0.), P2::new(2., 2.)]
+    );
+
+    let dot = |x, y, z| {
+        Dot::new(DotSpec {
+            pos: P3::new(x, y, z),
+            align: DotAlign::centroid(),
+            size: 1.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let dots = [
+        dot(0., 0., 5.),
+        dot(10., 0., 0.),
+        dot(5., 2., 3.),
+        dot(5., 8., 0.),
+    ];
+    let extrusion = Extrusion::convex_hull_of_dots(&dots, 2., 0.).unwrap();
+    assert_eq!(
+        extrusion.perimeter.points(),
+        &[P2::new(0., 0.), P2::new(10., 0.), P2::new(5., 8.)]
+    );
+    assert!(Extrusion::convex_hull_of_dots(&dots[..2], 2., 0.).is_err());
+}
+
+#[test]
+fn extrusion_offset() {
+    let square = Extrusion {
+        perimeter: Polygon2D::new(vec![
+            P2::new(0., 0.),
+            P2::new(10., 0.),
+            P2::new(10., 10.),
+            P2::new(0., 10.),
+        ])
+        .unwrap(),
+        holes: Vec::new(),
+        thickness: 1.,
+        bottom_z: 0.,
+        params: ExtrudeParams::default(),
+    };
+    let mut clockwise = square.clone();
+    clockwise.perimeter = clockwise.perimeter.reversed();
+    for extrusion in &[square.clone(), clockwise] {
+        let outset = extrusion.offset(1., OffsetJoin::Miter).unwrap();
+        assert_eq!(outset.perimeter.points().len(), 4);
+        assert_relative_eq!(outset.min_coord(Axis::X), -1.);
+        assert_relative_eq!(outset.max_coord(Axis::Y), 11.);
+
+        let inset = extrusion.offset(-2., OffsetJoin::Miter).unwrap();
+        assert_relative_eq!(inset.min_coord(Axis::X), 2.);
+        assert_relative_eq!(inset.max_coord(Axis::Y), 8.);
+
+        let join = OffsetJoin::Round { segments: 16 };
+        let rounded = extrusion.offset(1., join).unwrap();
+        assert_eq!(rounded.perimeter.points().len(), 4 * 5);
+        assert_relative_eq!(rounded.max_coord(Axis::X), 11.);
+        // Inward offsets don't round the corners of a convex perimeter.
+        assert_eq!(
+            extrusion
+                .offset(-1., join)
+                .unwrap()
+                .perimeter
+                .points()
+                .len(),
+            4
+        );
+    }
+
+    assert!(square.offset(std::f32::NAN, OffsetJoin::Miter).is_err());
+}
+
+#[test]
+fn extrusion_holes() {
+    let plate = Extrusion {
+        perimeter: Polygon2D::new(vec![
+            P2::new(0., 0.),
+            P2::new(10., 0.),
+            P2::new(10., 10.),
+            P2::new(0., 10.),
+        ])
+        .unwrap(),
+        holes: vec![Polygon2D::new(vec![
+            P2::new(4., 4.),
+            P2::new(6., 4.),
+            P2::new(5., 6.),
+        ])
+        .unwrap()],
+        thickness: 2.,
+        bottom_z: 0.,
+        params: ExtrudeParams::default(),
+    };
+    let code = to_code(&plate, RenderQuality::Low).unwrap();
+    let expected = "translate([0,0,0]) { linear_extrude(height=2,\
+                    center=false,convexity=10,twist=0,slices=1) { \
+                    polygon(points=[[0,0],[10,0],[10,10],[0,10],[4,4],\
+                    [6,4],[5,6],],paths=[[0,1,2,3,],[4,5,6,],],\
+                    convexity=10); } }";
+    assert!(scad_relative_eq(&code, expected, MAX_RELATIVE).unwrap());
+
+    let solid = Extrusion {
+        holes: Vec::new(),
+        ..plate.clone()
+    };
+    let solid_code = to_code(&solid, RenderQuality::Low).unwrap();
+    assert!(!scad_relative_eq(&code, &solid_code, MAX_RELATIVE).unwrap());
+
+    let thicker_walls = plate.offset(0.1, OffsetJoin::Miter).unwrap();
+    assert_relative_eq!(thicker_walls.holes[0].points()[0].y, 4.1);
+    assert_relative_eq!(thicker_walls.max_coord(Axis::X), 10.1);
+    let moved = plate.translate(V3::new(1., 0., 0.));
+    assert_relative_eq!(moved.holes[0].points()[0].x, 5.);
+}
+
+#[test]
+fn extrusion_params() {
+    let square = Extrusion {
+        perimeter: Polygon2D::new(vec![
+            P2::new(-1., -1.),
+            P2::new(1., -1.),
+            P2::new(1., 1.),
+            P2::new(-1., 1.),
+        ])
+        .unwrap(),
+        holes: Vec::new(),
+        thickness: 4.,
+        bottom_z: 0.,
+        params: ExtrudeParams {
+            twist: 90.,
+            scale: 2.,
+            slices: 10,
+            center: true,
+        },
+    };
+    let code = to_code(&square, RenderQuality::Low).unwrap();
+    let expected = "translate([0,0,0]) { linear_extrude(height=4,\
+                    center=true,convexity=10,twist=90,slices=10,scale=2) { \
+                    polygon(points=[[-1,-1],[1,-1],[1,1],[-1,1],],\
+                    paths=undef,convexity=10); } }";
+    assert!(scad_relative_eq(&code, expected, MAX_RELATIVE).unwrap());
+
+    let unscaled = Extrusion {
+        params: ExtrudeParams {
+            scale: 1.,
+            ..square.params
+        },
+        ..square.clone()
+    };
+    let unscaled_code = to_code(&unscaled, RenderQuality::Low).unwrap();
+    assert!(!scad_relative_eq(&code, &unscaled_code, MAX_RELATIVE).unwrap());
+
+    assert_relative_eq!(square.min_coord(Axis::Z), -2.);
+    assert_relative_eq!(square.max_coord(Axis::Z), 2.);
+    assert_relative_eq!(square.max_coord(Axis::X), 2. * 2_f32.sqrt());
+}
+
+#[test]
+fn export_outline() {
+    let plate = Extrusion {
+        perimeter: Polygon2D::new(vec![
+            P2::new(0., 0.),
+            P2::new(10., 0.),
+            P2::new(10., 5.),
+        ])
+        .unwrap(),
+        holes: vec![Polygon2D::new(vec![
+            P2::new(7., 1.),
+            P2::new(9., 1.),
+            P2::new(9., 2.),
+        ])
+        .unwrap()],
+        thickness: 2.,
+        bottom_z: 0.,
+        params: ExtrudeParams::default(),
+    };
+    let svg = outline_code(&plate, OutlineFormat::Svg).unwrap();
+    assert!(svg.contains("viewBox=\"0 0 10 5\""));
+    assert!(svg.contains("d=\"M0,5 L10,5 L10,0 Z M7,4 L9,4 L9,3 Z\""));
+
+    let dxf = outline_code(&plate, OutlineFormat::Dxf).unwrap();
+    assert_eq!(dxf.matches("POLYLINE").count(), 2);
+    assert_eq!(dxf.matches("VERTEX").count(), 6);
+    assert!(dxf.starts_with("0\nSECTION\n2\nHEADER\n9\n$ACADVER\n"));
+    assert!(dxf.contains("POLYLINE\n8\n0\n66\n1\n10\n0\n20\n0\n30\n0\n"));
+    assert!(dxf.ends_with("0\nEOF\n"));
+}
+
 #[test]
 fn cylinder_spec() {
     check_model("cylinder_spec", Action::Test, || {
@@ -62,6 +408,55 @@ fn cylinder_spec2() {
     })
 }
 
+#[test]
+fn cylinder_tangent_plane() {
+    let cyl = Cylinder::new(CylinderSpec {
+        pos: P3::new(1., 2., 3.),
+        align: CylinderAlign::EndCenter(C1::P0),
+        diameter: 10.,
+        height: 4.,
+        rot: axis_degrees(Axis::Z, 90.),
+    });
+    let top = Fraction::new(1.).expect("bad fraction");
+    assert_relative_eq!(
+        cyl.pos_on_surface(0., top),
+        P3::new(1., 7., 7.),
+        epsilon = 0.0001
+    );
+
+    let plane = cyl.tangent_plane(90.);
+    assert_relative_eq!(plane.normal, V3::new(-1., 0., 0.), epsilon = 0.0001);
+    assert_relative_eq!(plane.point, P3::new(-4., 2., 5.), epsilon = 0.0001);
+    assert_relative_eq!(
+        plane.signed_distance(cyl.pos(CylinderAlign::Centroid)),
+        -5.,
+        epsilon = 0.0001
+    );
+}
+
+#[test]
+fn cylinder_parts() {
+    // Compares against the same expected models as cylinder_spec and
+    // cylinder_spec2.
+    check_models("cylinder", Action::Test, || {
+        let spec = CylinderSpec {
+            pos: P3::origin(),
+            align: CylinderAlign::EndCenter(C1::P0),
+            diameter: 10.,
+            height: 3.,
+            rot: R3::identity(),
+        };
+        let spec2 = CylinderSpec {
+            align: CylinderAlign::EndCenter(C1::P1),
+            ..spec
+        };
+        Ok(vec![
+            ("spec".to_owned(), Cylinder::new(spec).into()),
+            ("spec2".to_owned(), Cylinder::new(spec2).into()),
+        ])
+    })
+}
+
 #[test]
 fn cylinder_spec3() {
     check_model("cylinder_spec3", Action::Test, || {
@@ -99,6 +494,39 @@ fn cylinder_spec4() {
     })
 }
 
+#[test]
+fn torus_align() {
+    let torus = Torus::new(TorusSpec {
+        pos: P3::new(0., 0., 10.),
+        align: TorusAlign::FaceCenter(C1::P0),
+        major_radius: 8.,
+        minor_radius: 2.,
+        rot: axis_degrees(Axis::X, 90.),
+    })
+    .expect("failed to make torus");
+    assert_relative_eq!(
+        torus.pos(TorusAlign::Centroid),
+        P3::new(0., -2., 10.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        torus.pos(TorusAlign::FaceCenter(C1::P1)),
+        P3::new(0., -4., 10.),
+        epsilon = 0.0001
+    );
+    assert_eq!(torus.outer_diameter(), 20.);
+    assert_eq!(torus.inner_diameter(), 12.);
+
+    assert!(Torus::new(TorusSpec {
+        pos: P3::origin(),
+        align: TorusAlign::Centroid,
+        major_radius: 1.,
+        minor_radius: 2.,
+        rot: R3::identity(),
+    })
+    .is_err());
+}
+
 #[test]
 fn explode_radially() {
     check_model("explode_radially", Action::Test, || {
@@ -142,6 +570,110 @@ fn explode_radially2() {
     })
 }
 
+#[test]
+fn dot_array_with() {
+    let dot = Dot::new(DotSpec {
+        pos: P3::new(10., 0., 0.),
+        align: DotAlign::centroid(),
+        size: 1.0,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    // A spiral staircase: rotate 90 degrees and rise 2 units per step
+    let dots = dot.array_with(3, |i| {
+        Iso3::new(
+            V3::new(0., 0., 2. * i as f32),
+            V3::new(0., 0., i as f32 * PI / 2.),
+        )
+    });
+    assert_eq!(dots.len(), 3);
+    assert_relative_eq!(
+        dots[0].pos(DotAlign::centroid()),
+        P3::new(10., 0., 0.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        dots[1].pos(DotAlign::centroid()),
+        P3::new(0., 10., 2.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        dots[2].pos(DotAlign::centroid()),
+        P3::new(-10., 0., 4.),
+        epsilon = 0.0001
+    );
+}
+
+#[test]
+fn map_dots_collections() {
+    let dot = Dot::new(DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::centroid(),
+        size: 2.0,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    let offset = V3::new(1., 2., 3.);
+
+    let moved = vec![dot, dot].map_translate(offset);
+    assert_relative_eq!(
+        moved[1].pos(DotAlign::centroid()),
+        P3::new(1., 2., 3.)
+    );
+
+    let moved = Some(dot).map_translate(offset).unwrap();
+    assert_relative_eq!(moved.pos(DotAlign::centroid()), P3::new(1., 2., 3.));
+
+    let moved = Box::new(dot).map_translate(offset);
+    assert_relative_eq!(moved.pos(DotAlign::centroid()), P3::new(1., 2., 3.));
+
+    let moved = [dot, dot, dot, dot, dot, dot].map_translate(offset);
+    assert_relative_eq!(
+        moved[5].pos(DotAlign::centroid()),
+        P3::new(1., 2., 3.)
+    );
+
+    let things = (Some(moved[0]), None::<Dot>);
+    assert_relative_eq!(things.max_coord(Axis::Z), 4.);
+    assert_relative_eq!(Box::new([dot, moved[0]]).min_coord(Axis::X), -1.);
+}
+
+#[test]
+fn explode_radially_about() {
+    let post = Post::new(PostSpec {
+        pos: P3::new(10., 0., 0.),
+        align: PostAlign::outside_midpoint(C3::P000, C3::P110),
+        len: 4.,
+        rot: R3::identity(),
+        size: 2.,
+        shapes: PostShapes::Cube,
+    })
+    .expect("failed to make post");
+
+    let copies =
+        post.explode_radially_about(P3::origin(), Axis::Z.into(), 4, true);
+    assert_eq!(copies.len(), 4);
+    assert_relative_eq!(
+        copies[1].midpoint3(),
+        P3::new(0., 10., 2.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        copies[2].midpoint3(),
+        P3::new(-10., 0., 2.),
+        epsilon = 0.0001
+    );
+
+    let unrotated =
+        post.explode_radially_about(P3::origin(), Axis::Z.into(), 4, false);
+    assert_relative_eq!(
+        unrotated[3].midpoint3(),
+        P3::new(0., -10., 2.),
+        epsilon = 0.0001
+    );
+    assert_eq!(unrotated[3].top.rot.angle(), 0.);
+}
+
 #[test]
 fn mirror() {
     check_model("mirror", Action::Test, || {
@@ -169,142 +701,2479 @@ fn mirror() {
 }
 
 #[test]
-fn rect_cut_corners() {
-    check_model("rect_cut_corners", Action::Test, || {
-        let r = Rect::new(RectSpec {
-            pos: P3::origin(),
-            align: RectAlign::origin(),
-            y_length: 2.0,
-            x_length: 4.0,
-            size: 1.0,
-            rot: axis_degrees(Axis::X, 45.),
-            shapes: RectShapes::Cube,
-        })?;
-        r.link(RectLink::Chamfer)
+fn mirror_prism() {
+    check_model("mirror_prism", Action::Test, || {
+        let dot = Dot::new(DotSpec {
+            pos: P3::new(5., 2., 0.),
+            align: DotAlign::centroid(),
+            size: 4.,
+            rot: axis_degrees(Axis::Z, 20.),
+            shape: DotShape::Prism { sides: 3 },
+        });
+        let mirrored = dot.mirror(V3::x());
+        // The corner that pointed away from the mirror now points back
+        // across it.
+        let corner = mirrored.rot * V3::x();
+        assert_relative_eq!(
+            corner,
+            V3::new(-cos_deg(20.), sin_deg(20.), 0.),
+            epsilon = 0.0001
+        );
+        Ok(union![dot, mirrored])
     })
 }
 
 #[test]
-fn cuboid_chamfer_hole() {
-    check_model("cuboid_chamfer_hole", Action::Test, || {
-        // note that the z dimension is too thick!
-        let hole_spec = CuboidSpecChamferZHole {
-            pos: P3::origin(),
-            align: CuboidAlign::center_face(CubeFace::Z0),
-            x_length: 4.,
-            y_length: 3.,
-            z_length: 1.,
-            chamfer: Fraction::new(1.)?,
-            rot: R3::identity(),
-            shapes: CuboidShapes::Cube,
-        };
-        let hole = Cuboid::new(hole_spec)?;
-        hole.link(CuboidLink::ChamferZ)
+fn mirror_copies() {
+    let dot = Dot::new(DotSpec {
+        pos: P3::new(2., 0., 0.),
+        align: DotAlign::origin(),
+        size: 1.,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    assert_eq!(
+        to_code(&mirror_copy![Axis::X, dot], RenderQuality::Low)
+            .expect("failed to render"),
+        to_code(&union![dot, mirror![Axis::X, dot]], RenderQuality::Low)
+            .expect("failed to render")
+    );
+
+    let mirrored = dot.map_mirror(V3::x());
+    assert_relative_eq!(
+        mirrored.pos(DotAlign::centroid()),
+        P3::new(-2.5, 0.5, 0.5)
+    );
+    assert_relative_eq!(mirrored.min_coord(Axis::X), -3., epsilon = 0.0001);
+    assert_relative_eq!(mirrored.max_coord(Axis::X), -2., epsilon = 0.0001);
+
+    let rect_spec = RectSpec {
+        pos: P3::new(1., 2., 3.),
+        align: RectAlign::origin(),
+        x_length: 5.,
+        y_length: 10.,
+        size: 1.,
+        rot: axis_radians(V3::new(1., 0., -2.), PI / 3.),
+        shapes: RectShapes::Cube,
+    };
+    let rect = Rect::new(rect_spec).expect("failed to make rect");
+    let normal = V3::new(1., 1., 0.);
+    let mirrored = rect.map_mirror(normal);
+    let rebuilt = Rect::new(RectSpec {
+        pos: mirrored.pos(RectAlign::origin()),
+        rot: mirrored.p00.rot,
+        ..rect_spec
     })
+    .expect("failed to make rect");
+    for corner in C2::all_clockwise() {
+        assert_relative_eq!(
+            mirrored.dot(corner).p000,
+            rebuilt.dot(corner).p000,
+            epsilon = 0.0001
+        );
+    }
+    let twice = mirrored.map_mirror(normal);
+    for corner in C2::all_clockwise() {
+        assert_relative_eq!(
+            twice.dot(corner).pos(DotAlign::centroid()),
+            rect.dot(corner).pos(DotAlign::centroid()),
+            epsilon = 0.0001
+        );
+    }
 }
 
 #[test]
-fn triangle_simple() {
-    check_model("triangle_simple", Action::Test, || {
-        let spec = TriangleSpec {
-            deg_b: 90.,
-            len_bc: f32::sqrt(3.) * 10.,
-            deg_c: 30.,
-            size: 3.,
-            point_b: P3::new(0., -9., 0.),
-            rot: axis_degrees(Axis::Z, 30.),
-        };
-        let tri = Triangle::new(spec)?;
-        Ok(union![tri.mark(spec)?, tri.link()?])
+fn mirror_corners() {
+    // Mirroring across the yz plane turns the Rect around, so the dots that
+    // were at the far y corners become its p00 and p10.
+    let rect = Rect::new(RectSpec {
+        pos: P3::new(1., 2., 3.),
+        align: RectAlign::origin(),
+        x_length: 5.,
+        y_length: 10.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: RectShapes::Cube,
+    })
+    .expect("failed to make rect");
+    let mirrored = rect.map_mirror(V3::x());
+    let center = |r: &Rect, corner| r.dot(corner).pos(DotAlign::centroid());
+    assert_relative_eq!(
+        center(&mirrored, C2::P00),
+        P3::new(-1.5, 11.5, 3.5),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        center(&mirrored, C2::P10),
+        P3::new(-5.5, 11.5, 3.5),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        center(&mirrored, C2::P01),
+        P3::new(-1.5, 2.5, 3.5),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        mirrored.pos(RectAlign::origin()),
+        P3::new(-1., 12., 3.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(mirrored.edge_length(Axis::X), 5., epsilon = 0.0001);
+    assert_relative_eq!(mirrored.edge_length(Axis::Y), 10., epsilon = 0.0001);
+
+    // Arrays mirror each element the same way, instead of dot by dot.
+    let rects = [rect.clone(), rect.map_translate(V3::new(0., 0., 7.))];
+    let mirrored_rects = rects.map_mirror(V3::x());
+    for (before, after) in rects.iter().zip(mirrored_rects.iter()) {
+        let expected = before.map_mirror(V3::x());
+        for corner in C2::all_clockwise() {
+            assert_eq!(after.dot(corner).p000, expected.dot(corner).p000);
+        }
+    }
+
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::new(1., 2., 3.),
+        align: CuboidAlign::origin(),
+        x_length: 5.,
+        y_length: 10.,
+        z_length: 4.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Cube,
     })
+    .expect("failed to make cuboid");
+    let mirrored = cuboid.map_mirror(V3::z());
+    assert_relative_eq!(
+        mirrored.pos(CuboidAlign::origin()),
+        P3::new(1., 12., -3.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        mirrored.dot(C3::P111).pos(DotAlign::centroid()),
+        P3::new(5.5, 2.5, -6.5),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(mirrored.edge_length(Axis::Z), 4., epsilon = 0.0001);
 }
 
 #[test]
-fn map_cuboid() {
-    check_model("map_cuboid", Action::Test, || {
-        let p1 = Cuboid::new(CuboidSpec {
-            pos: P3::origin(),
-            align: CuboidAlign::outside(C3::P111),
-            x_length: 10.,
-            y_length: 7.,
-            z_length: 4.,
+fn normalize_tree() {
+    let dot = |x| {
+        Dot::new(DotSpec {
+            pos: P3::new(x, 0., 0.),
+            align: DotAlign::origin(),
             size: 1.,
             rot: R3::identity(),
-            shapes: DotShape::Cube.into(),
-        })?;
-        let p2 = p1.map_translate(V3::new(0., -10., 0.));
-        let p3 = p1.map_rotate(axis_radians(Axis::Z, -PI / 8.));
-        let p4 = p1.map(&|d: &Dot| {
-            d.translate(V3::new(20., 0., 0.))
-                .rotate(axis_radians(Axis::Z, -PI / 8.))
-        });
-        Ok(union![
-            p1.link(CuboidLink::Solid)?,
-            p2.link(CuboidLink::Solid)?,
-            p3.link(CuboidLink::Solid)?,
-            p4.link(CuboidLink::Solid)?,
-        ])
-    })
+            shape: DotShape::Cube,
+        })
+    };
+    let empty: Vec<Tree> = Vec::new();
+    let nested = union![
+        union![dot(1.), Tree::union(empty.clone())],
+        union![dot(2.), union![dot(3.)]],
+        hull![hull![dot(1.)], dot(2.)],
+        Tree::color(ColorSpec::Red, union![dot(4.), dot(5.)]),
+        diff![Tree::hull(empty.clone()), dot(6.)],
+    ];
+    let expected = union![
+        dot(1.),
+        dot(2.),
+        dot(3.),
+        hull![dot(1.), dot(2.)],
+        red![dot(4.)],
+        red![dot(5.)],
+    ];
+    let render = |tree: &Tree| {
+        to_code(tree, RenderQuality::Low).expect("failed to render")
+    };
+    assert!(scad_relative_eq(
+        &render(&nested.clone().normalize(true)),
+        &render(&expected),
+        MAX_RELATIVE
+    )
+    .expect("failed to parse"));
+
+    let uncolored = nested.normalize(false);
+    assert!(render(&uncolored).contains("color"));
+    assert!(!scad_relative_eq(
+        &render(&uncolored),
+        &render(&expected),
+        MAX_RELATIVE
+    )
+    .expect("failed to parse"));
+
+    // An empty operand makes the whole intersection or Minkowski sum empty.
+    let emptied = union![
+        dot(7.),
+        intersect![dot(1.), Tree::union(empty.clone())],
+        minkowski![dot(1.), union![Tree::union(empty.clone())]],
+    ];
+    assert!(scad_relative_eq(
+        &render(&emptied.normalize(true)),
+        &render(&dot(7.).into()),
+        MAX_RELATIVE
+    )
+    .expect("failed to parse"));
+    let kept = intersect![dot(1.), union![dot(2.), Tree::union(empty)]];
+    assert!(scad_relative_eq(
+        &render(&kept.normalize(true)),
+        &render(&intersect![dot(1.), dot(2.)]),
+        MAX_RELATIVE
+    )
+    .expect("failed to parse"));
 }
 
 #[test]
-fn map_post() {
-    check_model("map_post", Action::Test, || {
-        let p1 = Post::new(PostSpec {
-            pos: P3::new(4., 0., 10.),
-            align: PostAlign::outside(C3::P111),
-            len: 12.,
-            size: 2.,
-            rot: axis_radians(Axis::X, PI / 8.)
-                * axis_radians(Axis::Y, PI / 2.),
-            shapes: PostShapes::Cube,
-        })?;
-        let p2 = p1.map_translate(V3::new(0., 0., 4.));
-        let axis =
-            p1.pos(PostAlign::outside(C3::P001)) - p1.pos(PostAlign::origin());
-        let p3 = p1.map_rotate(axis_radians(axis, PI / 8.));
-        let p4 = p2.map_rotate(axis_radians(axis, PI / 8.));
-        Ok(union![
-            p1.link(PostLink::Dots),
-            p2.link(PostLink::Dots),
-            p3.link(PostLink::Dots),
-            p4.link(PostLink::Dots),
-        ])
-    })
+fn tree_transforms() {
+    let dot = Dot::new(DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::origin(),
+        size: 2.,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    let render = |tree: &Tree| {
+        to_code(tree, RenderQuality::Low).expect("failed to render")
+    };
+    let offset = V3::new(1., 2., 3.);
+    let moved = translate![offset, dot];
+    assert!(scad_relative_eq(
+        &render(&moved),
+        // Skip the curve detail header of the dot's code.
+        &format!(
+            "translate([1,2,3]) {{ {} }}",
+            render(&Tree::from(dot)).splitn(2, ';').nth(1).unwrap()
+        ),
+        MAX_RELATIVE
+    )
+    .expect("failed to parse"));
+    let turned = rotate![axis_degrees(Axis::Z, 90.), moved.clone()];
+    assert!(render(&turned).contains("rotate"));
+
+    let copies = linear_array(&moved, 3, V3::new(5., 0., 0.));
+    assert_eq!(copies.len(), 3);
+    assert!(scad_relative_eq(
+        &render(&copies[1]),
+        &render(&translate![V3::new(5., 0., 0.), moved.clone()]),
+        MAX_RELATIVE
+    )
+    .expect("failed to parse"));
 }
 
 #[test]
-fn map_rect() {
-    check_model("map_rect", Action::Test, || {
-        let r1 = Rect::new(RectSpec {
-            pos: P3::origin(),
-            align: RectAlign::origin(),
-            x_length: 10.,
-            y_length: 5.,
+fn tree_bounds() {
+    let dot = |pos, shape| {
+        Dot::new(DotSpec {
+            pos,
+            align: DotAlign::centroid(),
             size: 2.,
-            rot: axis_radians(Axis::X, PI / 4.),
-            shapes: DotShape::Cube.into(),
-        })?;
-        let r2 = r1.map_translate(V3::new(3., 0., 5.));
-        let r3 = r1.map_rotate(axis_radians(V3::new(1., 1., 0.), PI / 2.));
-        Ok(union![
-            r1.link(RectLink::Dots)?,
-            r2.link(RectLink::Dots)?,
-            r3.link(RectLink::Dots)?,
-        ])
-    })
+            rot: R3::identity(),
+            shape,
+        })
+    };
+    let sphere = dot(P3::origin(), DotShape::Sphere);
+    let cube = dot(P3::new(10., 0., 0.), DotShape::Cube);
+    let tree = union![sphere, cube];
+    let bounds = tree.bounds().expect("tree should have bounds");
+    assert_relative_eq!(bounds.min, P3::new(-1., -1., -1.));
+    assert_relative_eq!(bounds.max, P3::new(11., 1., 1.));
+    assert_relative_eq!(tree.bound_length(Axis::X), 12.);
+
+    let moved = translate![V3::new(0., 0., 5.), tree.clone()];
+    assert_relative_eq!(moved.min_coord(Axis::Z), 4.);
+
+    // A difference is no bigger than its base.
+    let cut = diff![sphere, cube];
+    assert_eq!(cut.bounds(), Some(sphere.bounds()));
+
+    let disjoint = intersect![sphere, cube];
+    assert_eq!(disjoint.bounds(), None);
+    assert_eq!(Tree::union(Vec::<Tree>::new()).bounds(), None);
+
+    let cylinder = Cylinder::new(CylinderSpec {
+        pos: P3::origin(),
+        align: CylinderAlign::EndCenter(C1::P0),
+        diameter: 4.,
+        height: 10.,
+        rot: axis_degrees(Axis::X, 90.),
+    });
+    let bounds = Tree::from(cylinder).bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(-2., -10., -2.), epsilon = 0.0001);
+    assert_relative_eq!(bounds.max, P3::new(2., 0., 2.), epsilon = 0.0001);
 }
 
 #[test]
-fn scad_equality() {
-    let scad1 = "
-				union()
-				{
-								cylinder(h=1,d=2);
-								}
-";
+fn layout() {
+    let dot = |pos, size| {
+        Dot::new(DotSpec {
+            pos,
+            align: DotAlign::origin(),
+            size,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let centered = center_on_origin(Tree::from(dot(P3::new(5., 6., 7.), 2.)))
+        .expect("failed to center");
+    let bounds = centered.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(-1., -1., 0.));
+    assert_relative_eq!(bounds.max, P3::new(1., 1., 2.));
 
-    let scad2 = "
+    let parts: Vec<Tree> = (0..5)
+        .map(|i| Tree::from(dot(P3::new(0., 0., i as f32), 2.)))
+        .collect();
+    let plated = plate(parts, 1.).expect("failed to plate");
+    let bounds = plated.bounds().unwrap();
+    // 3 columns and 2 rows of 3mm cells.
+    assert_relative_eq!(bounds.min, P3::new(-1., -1., 0.));
+    assert_relative_eq!(bounds.max, P3::new(7., 4., 2.));
+
+    assert!(center_on_origin(Tree::union(Vec::<Tree>::new())).is_err());
+    assert!(plate(vec![centered], -1.).is_err());
+}
+
+#[test]
+fn chamfered_cube() {
+    let shape = DotShape::ChamferedCube {
+        chamfer: Fraction::new(0.5).unwrap(),
+    };
+    let dot = Dot::new(DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::origin(),
+        size: 2.,
+        rot: axis_degrees(Axis::Z, 30.),
+        shape,
+    });
+    let code = to_code(&Tree::from(dot), RenderQuality::Low)
+        .expect("failed to render");
+    assert!(code.contains("hull"));
+    assert_eq!(
+        Tree::from(dot).bounds(),
+        Tree::from(dot.with_shape(DotShape::Cube)).bounds()
+    );
+
+    let rect = Rect::new(RectSpec {
+        pos: P3::origin(),
+        align: RectAlign::origin(),
+        x_length: 5.,
+        y_length: 10.,
+        size: 2.,
+        rot: R3::identity(),
+        shapes: shape.into(),
+    })
+    .expect("failed to make rect");
+    assert_eq!(rect.dot(C2::P10).shape, shape);
+    match CuboidShapes::from(shape) {
+        CuboidShapes::Custom { p111, .. } => assert_eq!(p111, shape),
+        _ => panic!("expected custom cuboid shapes"),
+    }
+}
+
+#[test]
+fn prism_dot() {
+    let dot = Dot::new(DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::centroid(),
+        size: prism_size(6, 10.),
+        rot: R3::identity(),
+        shape: DotShape::Prism { sides: 6 },
+    });
+    assert_relative_eq!(dot.size, 20. / 3_f32.sqrt());
+    let code = to_code(&Tree::from(dot), RenderQuality::Low)
+        .expect("failed to render");
+    assert!(code.contains("polygon"));
+
+    let degenerate = dot.with_shape(DotShape::Prism { sides: 2 });
+    assert!(to_code(&Tree::from(degenerate), RenderQuality::Low).is_err());
+
+    let hexagon = "cylinder(h=1,d=2,$fn=6);";
+    assert!(scad_relative_eq(hexagon, hexagon, MAX_RELATIVE).unwrap());
+    assert!(!scad_relative_eq(
+        hexagon,
+        "cylinder(h=1,d=2,$fn=8);",
+        MAX_RELATIVE
+    )
+    .unwrap());
+    assert!(
+        !scad_relative_eq(hexagon, "cylinder(h=1,d=2);", MAX_RELATIVE).unwrap()
+    );
+}
+
+#[test]
+fn text_label() {
+    let label = Text {
+        text: "v1.2".to_owned(),
+        font_size: 5.,
+        thickness: 1.,
+        pos: P3::new(0., 0., 10.),
+        rot: R3::identity(),
+    };
+    let render = |tree: &Tree| {
+        to_code(tree, RenderQuality::Low).expect("failed to render")
+    };
+    let code = render(&label.clone().into());
+    let expected = "translate([0,0,10]) { rotate(0,[0,0,1]) { \
+                    linear_extrude(height=1,center=false,convexity=10,\
+                    twist=0,slices=1) { text(\"v1.2\",size=5,\
+                    halign=\"center\",valign=\"center\"); } } }";
+    assert!(scad_relative_eq(&code, expected, MAX_RELATIVE).unwrap());
+
+    let other = Text {
+        text: "v1.3".to_owned(),
+        ..label.clone()
+    };
+    assert!(
+        !scad_relative_eq(&code, &render(&other.into()), MAX_RELATIVE).unwrap()
+    );
+
+    let quoted = Text {
+        text: "say \"hi\"\\\nbye".to_owned(),
+        ..label.clone()
+    };
+    let quoted_code = render(&quoted.into());
+    assert!(quoted_code.contains(r#"text("say \"hi\"\\\nbye""#));
+    let with_newline = quoted_code.replace(r"\n", "\n");
+    assert!(
+        scad_relative_eq(&quoted_code, &with_newline, MAX_RELATIVE).unwrap()
+    );
+    let unquoted = quoted_code.replace(r#"\""#, "'");
+    assert!(!scad_relative_eq(&quoted_code, &unquoted, MAX_RELATIVE).unwrap());
+
+    let bounds = Tree::from(label).bounds().unwrap();
+    assert_relative_eq!(bounds.min.z, 10.);
+    assert_relative_eq!(bounds.max.z, 11.);
+}
+
+#[test]
+fn thread() {
+    let spec = ThreadSpec {
+        pos: P3::new(1., 2., 3.),
+        diameter: 3.,
+        pitch: 0.5,
+        length: 4.,
+        clearance: 0.1,
+        kind: ThreadKind::External,
+        segments_per_turn: DEFAULT_SEGMENTS_PER_TURN,
+        rot: R3::identity(),
+    };
+    let bolt = Thread::new(spec).expect("failed to make thread");
+    let nut = Thread::new(ThreadSpec {
+        kind: ThreadKind::Internal,
+        ..spec
+    })
+    .expect("failed to make thread");
+    assert_relative_eq!(bolt.major_radius(), 1.4);
+    assert_relative_eq!(nut.major_radius(), 1.6);
+    assert!(bolt.minor_radius() < bolt.major_radius());
+
+    let tree = Tree::from(bolt);
+    to_code(&tree, RenderQuality::Low).expect("failed to render thread");
+    let bounds = tree.bounds().unwrap();
+    assert_relative_eq!(bounds.min.z, 3.);
+    assert_relative_eq!(bounds.max.z, 7.);
+
+    assert!(Thread::new(ThreadSpec { pitch: 0., ..spec }).is_err());
+    assert!(Thread::new(ThreadSpec { pitch: 5., ..spec }).is_err());
+    assert!(Thread::new(ThreadSpec {
+        clearance: -0.1,
+        ..spec
+    })
+    .is_err());
+    assert!(Thread::new(ThreadSpec {
+        segments_per_turn: 2,
+        ..spec
+    })
+    .is_err());
+}
+
+#[test]
+fn honeycomb_panel() {
+    let rect = Rect::new(RectSpec {
+        pos: P3::new(5., 0., 0.),
+        align: RectAlign::origin(),
+        x_length: 30.,
+        y_length: 20.,
+        size: 2.,
+        rot: axis_degrees(Axis::Z, 90.),
+        shapes: RectShapes::Cube,
+    })
+    .expect("failed to make rect");
+    let panel = honeycomb(rect, 6., 1., 2.).expect("failed to make honeycomb");
+    to_code(&panel, RenderQuality::Low).expect("failed to render honeycomb");
+    let bounds = panel.bounds().unwrap();
+    assert_relative_eq!(bounds.min.x, -15., max_relative = MAX_RELATIVE);
+    assert_relative_eq!(bounds.max.y, 30., max_relative = MAX_RELATIVE);
+
+    let triangle = vec![P2::new(0., 0.), P2::new(20., 0.), P2::new(0., 20.)];
+    honeycomb(triangle.clone(), 5., 0.5, 1.).expect("failed to make honeycomb");
+    assert!(honeycomb(triangle.clone(), 5., 5., 1.).is_err());
+    assert!(honeycomb(triangle, 0., 0.5, 1.).is_err());
+    assert!(honeycomb(vec![P2::origin()], 5., 0.5, 1.).is_err());
+}
+
+#[test]
+fn color_alpha() {
+    let render = |tree: &Tree| {
+        to_code(tree, RenderQuality::Low).expect("failed to render")
+    };
+    let cube = || {
+        Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size: 1.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let faint = ColorSpec::Red.with_alpha(0.2);
+    assert_eq!(faint.rgba(), V4::new(1., 0., 0., 0.2));
+    assert_eq!(ColorSpec::Green.alpha(), 1.);
+
+    let code = render(&Tree::color(faint, cube()));
+    assert!(code.contains("color([1,0,0],0.2)"));
+    let opaque = render(&Tree::color(ColorSpec::Red, cube()));
+    assert!(opaque.contains("color([1,0,0])"));
+    assert!(!scad_relative_eq(&code, &opaque, MAX_RELATIVE).unwrap());
+    let custom = ColorSpec::Custom {
+        rgb: V3::new(1., 0., 0.),
+        alpha: 1.,
+    };
+    assert!(scad_relative_eq(
+        &render(&Tree::color(custom, cube())),
+        &opaque,
+        MAX_RELATIVE
+    )
+    .unwrap());
+}
+
+#[test]
+fn modifiers() {
+    let render = |tree: &Tree| {
+        to_code(tree, RenderQuality::Low).expect("failed to render")
+    };
+    let cube = |x| {
+        Dot::new(DotSpec {
+            pos: P3::new(x, 0., 0.),
+            align: DotAlign::origin(),
+            size: 1.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let tree = union![cube(0.), ghost![cube(5.)], highlight![cube(-5.)]];
+    let code = render(&tree);
+    assert!(code.contains("%union()"));
+    assert!(code.contains("#union()"));
+    let disabled = Tree::modifier(ScadModifier::Disable, cube(0.));
+    assert!(render(&disabled).contains("*union()"));
+
+    // The ghost isn't part of the final shape.
+    let bounds = tree.bounds().unwrap();
+    assert_relative_eq!(bounds.min.x, -5.);
+    assert_relative_eq!(bounds.max.x, 1.);
+
+    let normalized =
+        union![ghost![union![cube(0.), union![cube(1.)]]]].normalize(false);
+    match normalized {
+        Tree::Operator(TreeOperator::Modifier(ScadModifier::Background, _)) => {
+            ()
+        }
+        _ => panic!("expected the modifier to be kept"),
+    }
+}
+
+#[test]
+fn cone() {
+    let cylinder = Cylinder::new(CylinderSpec {
+        pos: P3::origin(),
+        align: CylinderAlign::EndCenter(C1::P0),
+        diameter: 4.,
+        height: 2.,
+        rot: R3::identity(),
+    });
+    let cone = cylinder.with_diameter_top(8.);
+    assert!(cone.is_cone());
+    assert!(!cylinder.is_cone());
+    assert!(!cylinder.with_diameter_top(4.).is_cone());
+
+    let render = |tree: Tree| {
+        to_code(&tree, RenderQuality::Low).expect("failed to render")
+    };
+    let code = render(cone.into());
+    assert!(code.contains("cylinder(h=2,d1=4,d2=8)"));
+    assert!(
+        !scad_relative_eq(&code, &render(cylinder.into()), MAX_RELATIVE)
+            .unwrap()
+    );
+
+    let top = Fraction::new(1.).unwrap();
+    assert_relative_eq!(cone.pos_on_surface(0., top), P3::new(4., 0., 2.));
+    let bounds = Tree::from(cone).bounds().unwrap();
+    assert_relative_eq!(bounds.max.x, 4.);
+    assert_relative_eq!(bounds.min.y, -4.);
+
+    // The side slopes outwards at 45 degrees.
+    let plane = cone.tangent_plane(0.);
+    assert_relative_eq!(
+        plane.normal,
+        V3::new(1., 0., -1.).normalize(),
+        max_relative = MAX_RELATIVE
+    );
+}
+
+#[test]
+fn slot_between_dots() {
+    let dot = |x, y| {
+        Dot::new(DotSpec {
+            pos: P3::new(x, y, 0.),
+            align: DotAlign::centroid(),
+            size: 2.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let (a, b) = (dot(0., 0.), dot(10., 10.));
+    let cutout = slot(&a, &b, 3., 4.).expect("failed to make slot");
+    let bounds = cutout.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(-1.5, -1.5, -2.));
+    assert_relative_eq!(bounds.max, P3::new(11.5, 11.5, 2.));
+    to_code(
+        &diff![union![a.clone(), b.clone()], cutout],
+        RenderQuality::Low,
+    )
+    .expect("failed to render slot");
+    assert!(slot(&a, &b, 0., 4.).is_err());
+
+    // The ends tilt to stay square to a slot that climbs.
+    let raised = a.translate(V3::new(10., 0., 10.));
+    let bounds = slot(&a, &raised, 3., 4.).unwrap().bounds().unwrap();
+    let (axial, radial) = (2. / 2_f32.sqrt(), 1.5 / 2_f32.sqrt());
+    assert_relative_eq!(
+        bounds.min,
+        P3::new(-axial - radial, -1.5, -axial - radial),
+        epsilon = 1e-4
+    );
+    let above = a.translate(V3::new(0., 0., 10.));
+    assert!(slot(&a, &above, 3., 4.).is_err());
+}
+
+#[test]
+fn snake_waypoints() {
+    let dot = |pos| {
+        Dot::new(DotSpec {
+            pos,
+            align: C3::P000.into(),
+            size: 2.0,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let start = dot(P3::origin());
+    let end = dot(P3::new(-6., -10., 7.));
+    let axes = [Axis::X, Axis::Z, Axis::X, Axis::Y, Axis::Z];
+    let snake = Snake::with_waypoints(start, end, &axes).unwrap();
+    assert_eq!(snake.dots.len(), 6);
+    assert_relative_eq!(snake.dots[1].p000, P3::new(-3., 0., 0.));
+    assert_relative_eq!(snake.dots[2].p000, P3::new(-3., 0., 3.5));
+    assert_relative_eq!(snake.dots[3].p000, P3::new(-6., 0., 3.5));
+    assert_relative_eq!(snake.dots[5].p000, end.p000);
+
+    assert!(Snake::with_waypoints(start, end, &[Axis::X, Axis::X]).is_err());
+    assert!(Snake::with_waypoints(start, end, &[]).is_err());
+
+    let order = [Axis::Y, Axis::X, Axis::Z];
+    let old = Snake::new(start, end, order).unwrap();
+    let new = Snake::with_waypoints(start, end, &order).unwrap();
+    for (a, b) in old.dots.iter().zip(new.dots.iter()) {
+        assert_relative_eq!(a.p000, b.p000);
+    }
+
+    let post = Post {
+        bot: start,
+        top: start.translate(V3::new(0., 0., 4.)),
+    };
+    let other = Post {
+        bot: end,
+        top: end.translate(V3::new(0., 0., 4.)),
+    };
+    let posts = post.snake_with_waypoints(other, &axes).unwrap();
+    assert_eq!(posts.as_vec().len(), 6);
+    to_code(
+        &posts.link(PostSnakeLink::Chain).unwrap(),
+        RenderQuality::Low,
+    )
+    .expect("failed to render");
+}
+
+#[test]
+fn smooth_chain() {
+    let dot = |pos| {
+        Dot::new(DotSpec {
+            pos,
+            align: C3::P000.into(),
+            size: 2.0,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let snake = Snake::new(
+        dot(P3::origin()),
+        dot(P3::new(5., 10., -7.)),
+        [Axis::Z, Axis::X, Axis::Y],
+    )
+    .unwrap();
+    let code = |tree: Tree| to_code(&tree, RenderQuality::Low).unwrap();
+    let smooth = code(snake.link(SnakeLink::Smooth { radius: 0.5 }).unwrap());
+    assert!(!scad_relative_eq(
+        &smooth,
+        &code(snake.link(SnakeLink::Chain).unwrap()),
+        MAX_RELATIVE
+    )
+    .unwrap());
+    assert!(snake.link(SnakeLink::Smooth { radius: 1.5 }).is_err());
+    assert!(snake.link(SnakeLink::Smooth { radius: 0. }).is_err());
+
+    let rounded = snake.dots[1].rounded(1.).unwrap();
+    let bounds = rounded.bounds().unwrap();
+    assert_relative_eq!(bounds.min, snake.dots[1].p000);
+    assert_relative_eq!(bounds.max, snake.dots[1].p000 + V3::new(2., 2., 2.));
+
+    let post = Post {
+        bot: dot(P3::origin()),
+        top: dot(P3::new(0., 0., 4.)),
+    };
+    let other = Post {
+        bot: dot(P3::new(8., 6., 0.)),
+        top: dot(P3::new(8., 6., 4.)),
+    };
+    let posts = post.snake(other, [Axis::X, Axis::Y, Axis::Z]).unwrap();
+    code(posts.link(PostSnakeLink::Smooth { radius: 0.9 }).unwrap());
+}
+
+#[test]
+fn snake_wall() {
+    let dot = |pos| {
+        Dot::new(DotSpec {
+            pos,
+            align: C3::P000.into(),
+            size: 1.0,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let post = Post {
+        bot: dot(P3::origin()),
+        top: dot(P3::new(0., 0., 5.)),
+    };
+    let other = Post {
+        bot: dot(P3::new(8., 6., 0.)),
+        top: dot(P3::new(8., 6., 5.)),
+    };
+    let posts = post
+        .snake_with_waypoints(other, &[Axis::X, Axis::Y, Axis::X])
+        .unwrap();
+    let wall = posts.link(PostSnakeLink::Wall).unwrap();
+    let bounds = wall.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::origin());
+    assert_relative_eq!(bounds.max, P3::new(9., 7., 6.));
+    // Chain already hulls whole posts together, so it's the same wall.
+    assert_eq!(
+        to_code(&wall, RenderQuality::Low).unwrap(),
+        to_code(
+            &posts.link(PostSnakeLink::Chain).unwrap(),
+            RenderQuality::Low
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn cuboid_from_corners() {
+    let spec = CuboidSpecCorners {
+        p000: P3::new(1., 2., 3.),
+        p111: P3::new(11., 7., 5.),
+        size: 1.,
+        shapes: CuboidShapes::Cube,
+    };
+    let cuboid = Cuboid::new(spec).expect("failed to make cuboid");
+    assert_relative_eq!(cuboid.pos(CuboidAlign::origin()), spec.p000);
+    assert_relative_eq!(cuboid.pos(CuboidAlign::outside(C3::P111)), spec.p111);
+    assert_relative_eq!(cuboid.edge_length(Axis::Y), 5.);
+
+    let too_thin = CuboidSpecCorners {
+        p111: P3::new(11., 7., 3.5),
+        ..spec
+    };
+    assert!(Cuboid::new(too_thin).is_err());
+    let backwards = CuboidSpecCorners {
+        p000: spec.p111,
+        p111: spec.p000,
+        ..spec
+    };
+    assert!(Cuboid::new(backwards).is_err());
+}
+
+#[test]
+fn cuboid_bounding() {
+    let dot = |x, y, z| {
+        Dot::new(DotSpec {
+            pos: P3::new(x, y, z),
+            align: DotAlign::origin(),
+            size: 1.,
+            rot: R3::identity(),
+            shape: DotShape::Sphere,
+        })
+    };
+    let dots = vec![dot(0., 0., 0.), dot(9., 4., 2.)];
+    let cuboid =
+        Cuboid::bounding(&dots, 1., 0.5, R3::identity(), CuboidShapes::Cube)
+            .expect("failed to make cuboid");
+    assert_relative_eq!(
+        cuboid.pos(CuboidAlign::origin()),
+        P3::new(-0.5, -0.5, -0.5)
+    );
+    assert_relative_eq!(
+        cuboid.pos(CuboidAlign::outside(C3::P111)),
+        P3::new(10.5, 5.5, 3.5)
+    );
+
+    let rot = axis_degrees(Axis::Z, 30.);
+    let rotated = Cuboid::bounding(&dots, 1., 0., rot, CuboidShapes::Cube)
+        .expect("failed to make cuboid");
+    assert_relative_eq!(
+        (rotated.rot().inverse() * rot).angle(),
+        0.,
+        epsilon = 0.0001
+    );
+    // Every corner of the original bounding box is inside the rotated one.
+    let inverse = rot.inverse();
+    let (min, max) = (
+        inverse * rotated.pos(CuboidAlign::origin()),
+        inverse * rotated.pos(CuboidAlign::outside(C3::P111)),
+    );
+    for &x in &[0., 10.] {
+        for &y in &[0., 5.] {
+            for &z in &[0., 3.] {
+                let p = inverse * P3::new(x, y, z);
+                assert!(p.x >= min.x - 1e-4 && p.x <= max.x + 1e-4);
+                assert!(p.y >= min.y - 1e-4 && p.y <= max.y + 1e-4);
+                assert!(p.z >= min.z - 1e-4 && p.z <= max.z + 1e-4);
+            }
+        }
+    }
+    assert!(rotated.edge_length(Axis::X) >= 10.);
+
+    let empty: Vec<Dot> = Vec::new();
+    assert!(Cuboid::bounding(
+        &empty,
+        1.,
+        0.,
+        R3::identity(),
+        CuboidShapes::Cube
+    )
+    .is_err());
+}
+
+#[test]
+fn cuboid_shell() {
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::origin(),
+        align: CuboidAlign::origin(),
+        x_length: 10.,
+        y_length: 8.,
+        z_length: 6.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+    let closed = cuboid.link(CuboidLink::Shell { wall: 1. }).unwrap();
+    let tray = cuboid.shell(1., &[CubeFace::Z1]).unwrap();
+    let render = |tree: &Tree| {
+        to_code(tree, RenderQuality::Low).expect("failed to render")
+    };
+    assert!(
+        !scad_relative_eq(&render(&closed), &render(&tray), MAX_RELATIVE)
+            .unwrap()
+    );
+    let bounds = tray.bounds().unwrap();
+    assert_relative_eq!(bounds.max, P3::new(10., 8., 6.));
+
+    assert!(cuboid.shell(0., &[]).is_err());
+    assert!(cuboid.shell(3., &[]).is_err());
+    cuboid
+        .shell(2.9, &[CubeFace::Z0, CubeFace::Z1])
+        .expect("failed to make tube");
+}
+
+#[test]
+fn rounded_plates() {
+    let plate = rounded_plate(20., 10., 2., 3.).expect("failed to make plate");
+    let bounds = plate.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::origin());
+    assert_relative_eq!(bounds.max, P3::new(20., 10., 2.));
+    assert!(to_code(&plate, RenderQuality::Low)
+        .unwrap()
+        .contains("cylinder(h=2,d=6)"));
+
+    let square = rounded_plate(20., 10., 2., 0.).expect("failed to make plate");
+    assert_relative_eq!(square.bounds().unwrap().max, P3::new(20., 10., 2.));
+    rounded_plate(20., 10., 2., 5.).expect("failed to make plate");
+    assert!(rounded_plate(20., 10., 2., 5.1).is_err());
+    assert!(rounded_plate(20., 10., 0., 1.).is_err());
+}
+
+#[test]
+fn cross_brace() {
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::origin(),
+        align: CuboidAlign::origin(),
+        x_length: 10.,
+        y_length: 8.,
+        z_length: 6.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+    let braced = union![
+        cuboid.link(CuboidLink::Frame).unwrap(),
+        cuboid.link(CuboidLink::CrossBrace(CubeFace::Z1)).unwrap(),
+    ];
+    to_code(&braced, RenderQuality::Low).expect("failed to render");
+
+    let brace = cuboid.link(CuboidLink::CrossBrace(CubeFace::X0)).unwrap();
+    let bounds = brace.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::origin());
+    assert_relative_eq!(bounds.max, P3::new(1., 8., 6.));
+
+    let rect = cuboid.rect(CubeFace::Z0);
+    let code =
+        |link| to_code(&rect.link(link).unwrap(), RenderQuality::Low).unwrap();
+    assert!(!scad_relative_eq(
+        &code(RectLink::CrossBrace),
+        &code(RectLink::Solid),
+        MAX_RELATIVE
+    )
+    .unwrap());
+}
+
+#[test]
+fn cuboid_edge_posts() {
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::new(1., 2., 3.),
+        align: CuboidAlign::origin(),
+        x_length: 10.,
+        y_length: 8.,
+        z_length: 6.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+    let code = |tree: Tree| to_code(&tree, RenderQuality::Low).unwrap();
+    assert!(scad_relative_eq(
+        &code(cuboid.edge_post(CuboidEdge::Z10).link(PostLink::Solid)),
+        &code(cuboid.vertical_post(C2::P10).link(PostLink::Solid)),
+        MAX_RELATIVE
+    )
+    .unwrap());
+
+    let edges = CuboidEdge::all();
+    assert_eq!(edges.len(), 12);
+    for edge in edges {
+        let post = cuboid.edge_post(edge);
+        let (low, high) = edge.corners();
+        assert_relative_eq!(post.bot.p000, cuboid.dot(low).p000);
+        assert_relative_eq!(post.top.p000, cuboid.dot(high).p000);
+        let length = cuboid.edge_length(edge.axis()) - 1.;
+        assert_relative_eq!((post.top.p000 - post.bot.p000).norm(), length);
+    }
+}
+
+#[test]
+fn face_planes() {
+    let rot = axis_degrees(Axis::Z, 30.) * axis_degrees(Axis::X, 45.);
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::new(1., 2., 3.),
+        align: CuboidAlign::centroid(),
+        x_length: 10.,
+        y_length: 8.,
+        z_length: 6.,
+        size: 1.,
+        rot,
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+    assert_relative_eq!(
+        cuboid.face_normal(CubeFace::Y1),
+        rot * V3::new(0., 1., 0.)
+    );
+    let plane = cuboid.face_plane(CubeFace::X0);
+    assert_relative_eq!(plane.normal, rot * V3::new(-1., 0., 0.));
+    assert_relative_eq!(
+        plane.signed_distance(cuboid.pos(CuboidAlign::centroid())),
+        -5.
+    );
+    assert_relative_eq!(
+        plane.signed_distance(cuboid.pos(CuboidAlign::outside(C3::P011))),
+        0.,
+        epsilon = 1e-5
+    );
+
+    let rect = cuboid.rect(CubeFace::Z1);
+    let plane = rect.face_plane(CubeFace::Z1);
+    assert_relative_eq!(plane.normal, rot * V3::new(0., 0., 1.));
+    assert_relative_eq!(
+        plane.signed_distance(rect.pos(RectAlign::outside(C3::P000))),
+        -1.,
+        epsilon = 1e-5
+    );
+}
+
+#[test]
+fn plane_constructors() {
+    let slope = Plane::from_slopes(0.5, -1., 2.);
+    assert_relative_eq!(slope.z(2., 3.).unwrap(), 0.);
+    let points = Plane::from_points(
+        P3::new(0., 0., 2.),
+        P3::new(2., 0., 3.),
+        P3::new(0., 1., 1.),
+    )
+    .unwrap();
+    assert_relative_eq!(points.normal, slope.normal, epsilon = 1e-6);
+    assert_relative_eq!(points.signed_distance(P3::new(2., 3., 0.)), 0.);
+    assert!(Plane::from_points(
+        P3::origin(),
+        P3::new(1., 1., 1.),
+        P3::new(2., 2., 2.)
+    )
+    .is_err());
+    assert!(Plane::from_normal_point(V3::zeros(), P3::origin()).is_err());
+
+    // A vertical plane, which the slope form can't describe.
+    let wall =
+        Plane::from_normal_point(V3::new(1., 0., 0.), P3::new(3., 0., 0.))
+            .unwrap();
+    assert!(wall.z(0., 0.).is_err());
+    assert_relative_eq!(
+        wall.intersect_line(P3::new(0., 1., 2.), V3::new(2., 0., 1.))
+            .unwrap(),
+        P3::new(3., 1., 3.5)
+    );
+    assert!(wall
+        .intersect_line(P3::origin(), V3::new(0., 1., 1.))
+        .is_none());
+
+    let floor = Plane::from_slopes(0., 0., 1.);
+    let (point, direction) = wall.intersect_plane(&floor).unwrap();
+    assert_relative_eq!(wall.signed_distance(point), 0., epsilon = 1e-6);
+    assert_relative_eq!(floor.signed_distance(point), 0., epsilon = 1e-6);
+    assert_relative_eq!(direction.dot(&V3::new(0., 1., 0.)).abs(), 1.);
+    assert!(floor
+        .intersect_plane(&Plane::from_slopes(0., 0., 5.))
+        .is_none());
+}
+
+#[test]
+fn project_onto_plane() {
+    let plane = Plane::from_slopes(0.2, 0., 1.);
+    let dots: Vec<_> = (0..5)
+        .map(|i| {
+            let degrees = i as f32 * 20.;
+            Dot::new(DotSpec {
+                pos: P3::new(
+                    10. * cos_deg(degrees),
+                    0.,
+                    10. * sin_deg(degrees),
+                ),
+                align: DotAlign::centroid(),
+                size: 2.,
+                rot: axis_degrees(Axis::Y, -degrees),
+                shape: DotShape::Cube,
+            })
+        })
+        .collect();
+    let bottom = DotAlign::center_face(CubeFace::Z0);
+
+    let flat = dots.map_project_onto(plane, bottom, true);
+    for (dot, original) in flat.iter().zip(dots.iter()) {
+        assert_relative_eq!(
+            plane.signed_distance(dot.pos(bottom)),
+            0.,
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(
+            dot.dim_unit_vec(Axis::Z),
+            plane.normal,
+            epsilon = 1e-5
+        );
+        // It only moves along the normal.
+        let moved = dot.pos(bottom) - original.pos(bottom);
+        assert_relative_eq!(
+            moved.cross(&plane.normal).norm(),
+            0.,
+            epsilon = 1e-4
+        );
+    }
+
+    let kept = dots.map_project_onto(plane, bottom, false);
+    for (dot, original) in kept.iter().zip(dots.iter()) {
+        assert_relative_eq!(
+            plane.signed_distance(dot.pos(bottom)),
+            0.,
+            epsilon = 1e-5
+        );
+        assert_relative_eq!((dot.rot.inverse() * original.rot).angle(), 0.);
+    }
+
+    // A dot facing away from the plane's normal gets flipped over.
+    let upside_down = dots[0].rotate_to(axis_degrees(Axis::X, 180.));
+    let flipped =
+        upside_down.project_onto(Plane::from_slopes(0., 0., 0.), bottom, true);
+    assert_relative_eq!(
+        flipped.dim_unit_vec(Axis::Z),
+        V3::new(0., 0., 1.),
+        epsilon = 1e-5
+    );
+    assert_relative_eq!(flipped.pos(bottom).z, 0., epsilon = 1e-5);
+}
+
+#[test]
+fn lines_and_segments() {
+    let x_line = Line3::new(P3::new(0., 1., 0.), V3::new(3., 0., 0.)).unwrap();
+    assert_relative_eq!(x_line.direction, V3::new(1., 0., 0.));
+    assert_relative_eq!(x_line.distance_to(P3::new(5., 1., 4.)), 4.);
+    assert!(Line3::through(P3::origin(), P3::origin()).is_err());
+
+    let z_line =
+        Line3::through(P3::new(2., 5., 0.), P3::new(2., 5., 1.)).unwrap();
+    let (on_x, on_z) = x_line.closest_points(&z_line).unwrap();
+    assert_relative_eq!(on_x, P3::new(2., 1., 0.));
+    assert_relative_eq!(on_z, P3::new(2., 5., 0.));
+    assert!(x_line.closest_points(&x_line).is_none());
+
+    let floor = Plane::from_slopes(0., 0., 3.);
+    assert_relative_eq!(
+        z_line.intersect_plane(&floor).unwrap(),
+        P3::new(2., 5., 3.)
+    );
+    assert!(x_line.intersect_plane(&floor).is_none());
+
+    let segment = Segment3::new(P3::origin(), P3::new(4., 0., 0.));
+    assert_relative_eq!(segment.length(), 4.);
+    assert_relative_eq!(segment.distance_to(P3::new(2., 3., 0.)), 3.);
+    assert_relative_eq!(segment.distance_to(P3::new(7., 4., 0.)), 5.);
+    assert!(segment.intersect_plane(&floor).is_none());
+    let riser = Segment3::new(P3::new(6., 2., -1.), P3::new(6., 2., 5.));
+    assert_relative_eq!(
+        riser.intersect_plane(&floor).unwrap(),
+        P3::new(6., 2., 3.)
+    );
+    let (a, b) = segment.closest_points(&riser);
+    assert_relative_eq!(a, P3::new(4., 0., 0.));
+    assert_relative_eq!(b, P3::new(6., 2., 0.));
+    let parallel = Segment3::new(P3::new(1., 2., 0.), P3::new(3., 2., 0.));
+    let (a, b) = segment.closest_points(&parallel);
+    assert_relative_eq!((a - b).norm(), 2.);
+
+    let dot = Dot::new(DotSpec {
+        pos: P3::new(5., 7., 3.),
+        align: DotAlign::centroid(),
+        size: 2.,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    let moved = dot.translate_to_line(&x_line, DotAlign::centroid());
+    assert_relative_eq!(moved.pos(DotAlign::centroid()), P3::new(5., 1., 0.));
+}
+
+#[test]
+fn circle_and_arc_of_dots() {
+    let spec = DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::centroid(),
+        size: 1.,
+        rot: R3::identity(),
+        shape: DotShape::Cylinder,
+    };
+    let center = P3::new(1., 2., 3.);
+    let circle =
+        circle_of_dots(center, 5., V3::new(0., 0., 2.), 8, spec).unwrap();
+    assert_eq!(circle.len(), 8);
+    let centroid = |dot: &Dot| dot.pos(DotAlign::centroid());
+    assert_relative_eq!(
+        centroid(&circle[0]),
+        P3::new(6., 2., 3.),
+        epsilon = 1e-5
+    );
+    assert_relative_eq!(
+        centroid(&circle[2]),
+        P3::new(1., 7., 3.),
+        epsilon = 1e-5
+    );
+    for dot in &circle {
+        assert_relative_eq!(
+            (centroid(dot) - center).norm(),
+            5.,
+            epsilon = 1e-5
+        );
+    }
+    // Each dot faces the same way relative to the center.
+    assert_relative_eq!(
+        circle[2].dim_unit_vec(Axis::X),
+        V3::new(0., 1., 0.),
+        epsilon = 1e-5
+    );
+    to_code(&chain_loop(&circle).unwrap(), RenderQuality::Low)
+        .expect("failed to render");
+
+    let arc =
+        arc_of_dots(P3::origin(), 2., V3::new(1., 0., 0.), 0., 90., 4, spec)
+            .unwrap();
+    assert_eq!(arc.len(), 4);
+    assert_relative_eq!(centroid(&arc[0]), P3::new(0., 2., 0.), epsilon = 1e-5);
+    assert_relative_eq!(centroid(&arc[3]), P3::new(0., 0., 2.), epsilon = 1e-5);
+
+    assert!(circle_of_dots(center, 5., V3::zeros(), 8, spec).is_err());
+    assert!(circle_of_dots(center, 0., V3::z(), 8, spec).is_err());
+    assert!(arc_of_dots(center, 5., V3::z(), 0., 90., 1, spec).is_err());
+}
+
+#[test]
+fn helix_dots() {
+    let spec = DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::centroid(),
+        size: 1.,
+        rot: R3::identity(),
+        shape: DotShape::Sphere,
+    };
+    let dots =
+        helix_of_dots(P3::origin(), V3::z(), 4., 2., 2.5, 8, spec).unwrap();
+    assert_eq!(dots.len(), 21);
+    let centroid = |dot: &Dot| dot.pos(DotAlign::centroid());
+    assert_relative_eq!(
+        centroid(&dots[0]),
+        P3::new(4., 0., 0.),
+        epsilon = 1e-5
+    );
+    assert_relative_eq!(
+        centroid(&dots[2]),
+        P3::new(0., 4., 0.5),
+        epsilon = 1e-5
+    );
+    assert_relative_eq!(
+        centroid(&dots[8]),
+        P3::new(4., 0., 2.),
+        epsilon = 1e-5
+    );
+    assert_relative_eq!(
+        centroid(&dots[20]),
+        P3::new(-4., 0., 5.),
+        epsilon = 1e-5
+    );
+
+    let spring = helix(P3::origin(), V3::z(), 4., 2., 2.5, 8, spec).unwrap();
+    let bounds = spring.bounds().unwrap();
+    assert_relative_eq!(bounds.min.z, -0.5, epsilon = 1e-5);
+    assert_relative_eq!(bounds.max.z, 5.5, epsilon = 1e-5);
+
+    assert!(helix_of_dots(P3::origin(), V3::z(), 4., 2., 0., 8, spec).is_err());
+    assert!(helix_of_dots(P3::origin(), V3::z(), 4., 2., 1., 2, spec).is_err());
+}
+
+#[test]
+fn map_transform_and_scale() {
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::new(1., 2., 3.),
+        align: CuboidAlign::origin(),
+        x_length: 10.,
+        y_length: 8.,
+        z_length: 6.,
+        size: 2.,
+        rot: axis_degrees(Axis::Z, 20.),
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+
+    let shift = V3::new(-4., 5., 9.);
+    let isometry =
+        Iso3::new(shift, V3::new(1., 2., 3.).normalize() * 70_f32.to_radians());
+    let rot = isometry.rotation;
+    let transformed = cuboid.map_transform(isometry);
+    let manual = cuboid.map_rotate(rot).map_translate(shift);
+    for corner in C3::all() {
+        assert_relative_eq!(
+            transformed.pos(CuboidAlign::outside(corner)),
+            manual.pos(CuboidAlign::outside(corner)),
+            epsilon = 1e-4
+        );
+    }
+
+    let pivot = cuboid.pos(CuboidAlign::outside(C3::P000));
+    let half = cuboid.map_scale_about(pivot, 0.5).unwrap();
+    assert_relative_eq!(
+        half.pos(CuboidAlign::outside(C3::P000)),
+        pivot,
+        epsilon = 1e-5
+    );
+    assert_relative_eq!(half.edge_length(Axis::X), 5., epsilon = 1e-5);
+    assert_relative_eq!(half.edge_length(Axis::Z), 3., epsilon = 1e-5);
+    assert_relative_eq!(half.size(), 1.);
+    assert!(cuboid.map_scale_about(pivot, 0.).is_err());
+    assert!(cuboid.map_scale_about(pivot, -2.).is_err());
+}
+
+#[test]
+fn rotate_about_pivot() {
+    let dot = Dot::new(DotSpec {
+        pos: P3::new(5., 0., 1.),
+        align: DotAlign::centroid(),
+        size: 2.,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    let pivot = P3::new(3., 0., 0.);
+    let rot = axis_degrees(Axis::Z, 90.);
+    let turned = dot.rotate_about(pivot, rot);
+    assert_relative_eq!(
+        turned.pos(DotAlign::centroid()),
+        P3::new(3., 2., 1.),
+        epsilon = 1e-5
+    );
+    assert_relative_eq!(
+        turned.dim_unit_vec(Axis::X),
+        V3::new(0., 1., 0.),
+        epsilon = 1e-5
+    );
+
+    let rect = Rect::new(RectSpec {
+        pos: P3::new(5., 0., 0.),
+        align: RectAlign::origin(),
+        x_length: 4.,
+        y_length: 2.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: RectShapes::Cube,
+    })
+    .unwrap();
+    let turned = rect.map_rotate_about(pivot, rot);
+    assert_relative_eq!(
+        turned.pos(RectAlign::origin()),
+        P3::new(3., 2., 0.),
+        epsilon = 1e-5
+    );
+    let manual = rect
+        .map_translate(P3::origin() - pivot)
+        .map_rotate(rot)
+        .map_translate(pivot - P3::origin());
+    assert_relative_eq!(
+        turned.pos(RectAlign::outside(C3::P111)),
+        manual.pos(RectAlign::outside(C3::P111)),
+        epsilon = 1e-5
+    );
+}
+
+#[test]
+fn euler_angles() {
+    let same = |a: R3, b: R3| (a.inverse() * b).angle() < 1e-4;
+    let rot = euler_deg(30., -20., 10.);
+    let manual = axis_degrees(Axis::Z, 30.)
+        * axis_degrees(Axis::Y, -20.)
+        * axis_degrees(Axis::X, 10.);
+    assert!(same(rot, manual));
+
+    let (yaw, pitch, roll) = r3_to_euler_deg(rot);
+    assert_relative_eq!(yaw, 30., epsilon = 1e-3);
+    assert_relative_eq!(pitch, -20., epsilon = 1e-3);
+    assert_relative_eq!(roll, 10., epsilon = 1e-3);
+
+    // Like OpenSCAD's rotate([10, -20, 30]).
+    let built = Rot::new().about_x(10.).about_y(-20.).about_z(30.);
+    assert!(same(built.r3(), rot));
+    assert!(same(R3::from(Rot::default()), R3::identity()));
+    let other_order = Rot::new().about_z(30.).about_y(-20.).about_x(10.);
+    assert!(!same(other_order.into(), rot));
+    assert!(same(
+        Rot::new().about(V3::new(1., 1., 0.), 45.).then(manual).r3(),
+        manual * axis_degrees(V3::new(1., 1., 0.), 45.)
+    ));
+}
+
+#[test]
+fn coord_statistics() {
+    let dot = |x| {
+        Dot::new(DotSpec {
+            pos: P3::new(x, 1., 2.),
+            align: DotAlign::centroid(),
+            size: 2.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let dots = vec![dot(0.), dot(1.), dot(2.), dot(13.)];
+    assert_relative_eq!(dots.centroid_of_dots(), P3::new(4., 1., 2.));
+    assert_relative_eq!(dots.mean_coord(Axis::X), 4.);
+    // The midpoint of the bounds is pulled towards the outlier.
+    assert_relative_eq!(dots.midpoint(Axis::X), 6.5);
+
+    // 32 corner coordinates: 4 each at -1, 0, 2, 3, 12, and 14, and 8 at 1.
+    assert_relative_eq!(dots.coord_percentile(Axis::X, 0.).unwrap(), -1.);
+    assert_relative_eq!(dots.coord_percentile(Axis::X, 100.).unwrap(), 14.);
+    assert_relative_eq!(dots.coord_percentile(Axis::X, 50.).unwrap(), 1.5);
+    assert_relative_eq!(dots.coord_percentile(Axis::X, 75.).unwrap(), 5.25);
+    assert_relative_eq!(dots.coord_percentile(Axis::Y, 10.).unwrap(), 0.);
+    assert!(dots.coord_percentile(Axis::X, 101.).is_err());
+    assert!(Vec::<Dot>::new().coord_percentile(Axis::X, 50.).is_err());
+}
+
+/// A backend that lists the shapes and operations by name, to check that
+/// rendering only goes through the `Backend` trait.
+struct NameBackend;
+
+impl NameBackend {
+    fn op(&self, name: &str, children: Vec<String>) -> String {
+        format!("{}({})", name, children.join(","))
+    }
+}
+
+impl Backend for NameBackend {
+    type Node = String;
+
+    fn cube(&self, _lengths: V3) -> Result<String, ScadDotsError> {
+        Ok("cube".into())
+    }
+    fn sphere(&self, _diameter: f32) -> Result<String, ScadDotsError> {
+        Ok("sphere".into())
+    }
+    fn cylinder(
+        &self,
+        _height: f32,
+        bottom: f32,
+        top: f32,
+    ) -> Result<String, ScadDotsError> {
+        Ok(if bottom == top {
+            "cylinder".into()
+        } else {
+            "cone".into()
+        })
+    }
+    fn circle(&self, _radius: f32) -> Result<String, ScadDotsError> {
+        Ok("circle".into())
+    }
+    fn polygon(&self, contours: &[&[P2]]) -> Result<String, ScadDotsError> {
+        Ok(format!("polygon{}", contours.len()))
+    }
+    fn text(&self, text: &str, _size: f32) -> Result<String, ScadDotsError> {
+        Ok(format!("text:{}", text))
+    }
+    fn raw_scad(&self, _code: &str) -> Result<String, ScadDotsError> {
+        Err(ScadDotsError::Args)
+    }
+    fn import(
+        &self,
+        path: &str,
+        _kind: ImportKind,
+    ) -> Result<String, ScadDotsError> {
+        Ok(format!("import:{}", path))
+    }
+    fn linear_extrude(
+        &self,
+        _height: f32,
+        _params: ExtrudeParams,
+        children: Vec<String>,
+    ) -> Result<String, ScadDotsError> {
+        Ok(self.op("extrude", children))
+    }
+    fn rotate_extrude(&self, children: Vec<String>) -> String {
+        self.op("sweep", children)
+    }
+    fn combine(&self, op: Combine, children: Vec<String>) -> String {
+        self.op(&format!("{:?}", op), children)
+    }
+    fn color(
+        &self,
+        _color: ColorSpec,
+        children: Vec<String>,
+    ) -> Result<String, ScadDotsError> {
+        Ok(self.op("color", children))
+    }
+    fn mirror(
+        &self,
+        _normal: V3,
+        children: Vec<String>,
+    ) -> Result<String, ScadDotsError> {
+        Ok(self.op("mirror", children))
+    }
+    fn scale(
+        &self,
+        _factors: V3,
+        children: Vec<String>,
+    ) -> Result<String, ScadDotsError> {
+        Ok(self.op("scale", children))
+    }
+    fn translate(
+        &self,
+        _offset: V3,
+        children: Vec<String>,
+    ) -> Result<String, ScadDotsError> {
+        Ok(self.op("move", children))
+    }
+    fn rotate(
+        &self,
+        _rot: R3,
+        children: Vec<String>,
+    ) -> Result<String, ScadDotsError> {
+        Ok(self.op("turn", children))
+    }
+    fn modifier(
+        &self,
+        modifier: ScadModifier,
+        children: Vec<String>,
+    ) -> String {
+        self.op(&format!("{:?}", modifier), children)
+    }
+    fn named(&self, name: &str, children: Vec<String>) -> String {
+        self.op(name, children)
+    }
+    fn detail(
+        &self,
+        _fn: Option<i32>,
+        _fa: Option<f32>,
+        _fs: Option<f32>,
+        children: Vec<String>,
+    ) -> Result<String, ScadDotsError> {
+        Ok(self.op("detail", children))
+    }
+}
+
+#[test]
+fn custom_backend() {
+    let dot = |shape| {
+        Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size: 2.,
+            rot: R3::identity(),
+            shape,
+        })
+    };
+    let tree = diff![
+        hull![dot(DotShape::Cube), dot(DotShape::Sphere)],
+        ghost![red![dot(DotShape::Cylinder)]],
+    ];
+    let options = RenderOptions::from(RenderQuality::Low);
+    assert_eq!(
+        tree.render_with(&NameBackend, options).unwrap(),
+        "Diff(Hull(move(turn(cube)),move(turn(sphere))),\
+         Background(color(move(turn(cylinder)))))"
+    );
+    // The default render still goes to OpenSCAD code.
+    assert!(tree
+        .render(options)
+        .unwrap()
+        .code()
+        .contains("difference()"));
+}
+
+#[test]
+fn named_modules() {
+    let render = |tree: &Tree| {
+        to_code(tree, RenderQuality::Low).expect("failed to render")
+    };
+    let dot = |x: f32| {
+        Dot::new(DotSpec {
+            pos: P3::new(x, 0., 0.),
+            align: DotAlign::origin(),
+            size: 1.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let unnamed = union![hull![dot(0.), dot(2.)], dot(5.), dot(5.)];
+    let named = union![
+        Tree::named("lid", hull![Tree::named("peg", dot(0.)), dot(2.)]),
+        Tree::named("peg", dot(5.)),
+        // The same name and shape share a module.
+        Tree::named("peg", dot(5.)),
+    ];
+    let code = render(&named);
+    assert!(code.contains("module part_lid()"));
+    assert!(code.contains("module part_peg_2()"));
+    assert_eq!(code.matches("module part_peg").count(), 2);
+    // Inner modules are defined before the ones that call them.
+    assert!(code.find("module part_peg()") < code.find("module part_lid()"));
+    assert!(code.contains("part_lid();"));
+    assert!(render(&Tree::named("2 pegs!", dot(0.))).contains("part_2_pegs_()"));
+
+    assert!(scad_relative_eq(&code, &render(&unnamed), MAX_RELATIVE).unwrap());
+    assert_eq!(named.bounds(), unnamed.bounds());
+}
+
+#[test]
+fn detail_per_subtree() {
+    let render = |tree: &Tree| to_code(tree, RenderQuality::Low);
+    let hole = || {
+        Tree::from(Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::centroid(),
+            size: 2.,
+            rot: R3::identity(),
+            shape: DotShape::Cylinder,
+        }))
+    };
+    let fine = Tree::detail(Some(40), None, None, hole());
+    let code = render(&fine).unwrap();
+    assert!(code.starts_with("$fn=5;"));
+    assert!(code.contains("let($fn=40)"));
+    assert!(render(&Tree::detail(None, Some(5.), Some(0.5), hole()))
+        .unwrap()
+        .contains("let($fa=5,$fs=0.5)"));
+    assert_eq!(fine.bounds(), hole().bounds());
+
+    let same = |a: &Tree, b: &Tree| {
+        scad_relative_eq(&render(a).unwrap(), &render(b).unwrap(), MAX_RELATIVE)
+            .unwrap()
+    };
+    assert!(same(&fine, &fine.clone()));
+    assert!(!same(&fine, &Tree::detail(Some(41), None, None, hole())));
+    assert!(!same(&fine, &Tree::detail(None, Some(40.), None, hole())));
+    assert!(!same(&fine, &hole()));
+
+    assert!(render(&Tree::detail(Some(-1), None, None, hole())).is_err());
+    assert!(render(&Tree::detail(None, Some(0.), None, hole())).is_err());
+    assert!(render(&Tree::detail(None, None, Some(-1.), hole())).is_err());
+}
+
+#[test]
+fn render_precision() {
+    assert_eq!(precise_num(1.23456, Some(3)).unwrap(), "1.235");
+    assert_eq!(precise_num(2.5001, Some(2)).unwrap(), "2.5");
+    assert_eq!(precise_num(10., Some(4)).unwrap(), "10");
+    assert_eq!(precise_num(-0.00001, Some(4)).unwrap(), "0");
+    assert_eq!(precise_num(-0., None).unwrap(), "0");
+    assert_eq!(precise_num(7.6, Some(0)).unwrap(), "8");
+    assert_eq!(precise_num(0.1, None).unwrap(), "0.1");
+    assert!(precise_num(std::f32::NAN, Some(4)).is_err());
+    assert!(precise_num(std::f32::INFINITY, None).is_err());
+    assert!(precise_num(-std::f32::INFINITY, Some(2)).is_err());
+
+    let dot = |x: f32| {
+        Tree::from(Dot::new(DotSpec {
+            pos: P3::new(x, 1. / 3., 0.),
+            align: DotAlign::origin(),
+            size: 1.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        }))
+    };
+    let options = RenderOptions {
+        quality: RenderQuality::Low,
+        snap_tolerance: None,
+        precision: Some(4),
+        dedupe: false,
+    };
+    let code = to_code(&dot(1.00001), options).unwrap();
+    assert!(code.contains("translate([1,0.3333,0])"));
+    assert_eq!(code, to_code(&dot(0.99999), options).unwrap());
+    assert_ne!(
+        to_code(&dot(1.00001), RenderQuality::Low).unwrap(),
+        to_code(&dot(0.99999), RenderQuality::Low).unwrap()
+    );
+    let poisoned = Tree::translate(V3::new(std::f32::NAN, 0., 0.), dot(1.));
+    assert!(to_code(&poisoned, options).is_err());
+    assert!(to_code(&poisoned, RenderQuality::Low).is_err());
+}
+
+#[test]
+fn parse_every_object() {
+    let torus = |minor_radius| {
+        Torus::new(TorusSpec {
+            pos: P3::new(0., 0., 10.),
+            align: TorusAlign::Centroid,
+            major_radius: 8.,
+            minor_radius,
+            rot: axis_degrees(Axis::X, 90.),
+        })
+        .expect("failed to make torus")
+    };
+    let dot = |shape| {
+        Tree::from(Dot::new(DotSpec {
+            pos: P3::new(1., 2., 3.),
+            align: DotAlign::centroid(),
+            size: 2.,
+            rot: axis_degrees(Axis::Y, 30.),
+            shape,
+        }))
+    };
+    let cone = Cylinder::new(CylinderSpec {
+        pos: P3::origin(),
+        align: CylinderAlign::Centroid,
+        diameter: 4.,
+        height: 6.,
+        rot: R3::identity(),
+    })
+    .with_diameter_top(1.);
+    let extrusion = Extrusion {
+        perimeter: Polygon2D::new(vec![
+            P2::new(0., 0.),
+            P2::new(10., 0.),
+            P2::new(0., 10.),
+        ])
+        .unwrap(),
+        holes: vec![Polygon2D::new(vec![
+            P2::new(1., 1.),
+            P2::new(2., 1.),
+            P2::new(1., 2.),
+        ])
+        .unwrap()],
+        thickness: 1.,
+        bottom_z: -2.,
+        params: ExtrudeParams::default(),
+    };
+    let label = Text {
+        text: "abc".into(),
+        font_size: 3.,
+        thickness: 0.5,
+        pos: P3::origin(),
+        rot: R3::identity(),
+    };
+    let model = |minor_radius| -> Tree {
+        union![
+            Tree::named(
+                "dots",
+                hull![
+                    dot(DotShape::Cube),
+                    dot(DotShape::Sphere),
+                    dot(DotShape::Cylinder),
+                    dot(DotShape::ChamferedCube {
+                        chamfer: Fraction::new(0.5).unwrap()
+                    }),
+                    dot(DotShape::Prism { sides: 6 }),
+                ]
+            ),
+            diff![cone, extrusion.clone()],
+            minkowski![torus(minor_radius), red![label.clone()]],
+            mirror![V3::x(), scale![V3::new(1., 2., 3.), extrusion.clone()]],
+            Tree::detail(Some(30), None, None, torus(minor_radius)),
+        ]
+    };
+    let render = |tree: &Tree| {
+        to_code(tree, RenderQuality::Low).expect("failed to render")
+    };
+    let code = render(&model(2.));
+    assert!(scad_relative_eq(&code, &code, MAX_RELATIVE).unwrap());
+    assert!(
+        !scad_relative_eq(&code, &render(&model(2.5)), MAX_RELATIVE).unwrap()
+    );
+}
+
+#[test]
+fn raw_scad_and_import() {
+    let render = |tree: &Tree| to_code(tree, RenderQuality::Low);
+    let same = |a: &Tree, b: &Tree| {
+        scad_relative_eq(&render(a).unwrap(), &render(b).unwrap(), MAX_RELATIVE)
+            .unwrap()
+    };
+    let switch = Tree::import("vendor/switch.scad", ImportKind::Scad);
+    let footprint = translate![
+        V3::new(1., 2., 3.),
+        union![
+            switch.clone(),
+            Tree::raw_scad("switch_footprint(size=14);"),
+            Tree::import("keycap.stl", ImportKind::Stl),
+        ]
+    ];
+    let code = render(&footprint).unwrap();
+    assert!(code.contains("include <vendor/switch.scad>\n"));
+    assert!(code.contains("switch_footprint(size=14);\n"));
+    assert!(code.contains("import(\"keycap.stl\");"));
+    assert!(same(&footprint, &footprint.clone()));
+    assert!(footprint.bounds().is_none());
+
+    // Raw code is compared as text, ignoring whitespace.
+    let raw = |code| Tree::raw_scad(code);
+    assert!(same(&raw("cube( [1,2,3] );"), &raw("cube(  [1,2,3]\n);")));
+    assert!(!same(&raw("cube([1,2,3]);"), &raw("cube([1,2,3.0001]);")));
+    assert!(!same(
+        &Tree::import("a.stl", ImportKind::Stl),
+        &Tree::import("b.stl", ImportKind::Stl)
+    ));
+    assert!(!same(
+        &switch,
+        &Tree::import("vendor/switch.scad", ImportKind::Stl)
+    ));
+    assert!(render(&Tree::import("a>b.scad", ImportKind::Scad)).is_err());
+    assert!(render(&raw("cube(1); /* end raw scad */ sphere(1);")).is_err());
+
+    let cube = Dot::new(DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::origin(),
+        size: 2.,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    assert_eq!(union![cube, switch.clone()].bounds(), Some(cube.bounds()));
+    // Raw code as the base of a difference hides the whole difference.
+    let hidden = diff![raw("cube(4);"), cube];
+    assert_eq!(hidden.bounds(), None);
+    assert_eq!(intersect![switch, cube].bounds(), None);
+    assert!(center_on_origin(hidden).is_err());
+    assert_eq!(diff![cube, raw("cube(4);")].bounds(), Some(cube.bounds()));
+}
+
+#[test]
+fn parse_any_argument_order() {
+    let eq = |a: &str, b: &str| scad_relative_eq(a, b, MAX_RELATIVE).unwrap();
+    let model = "$fn=5;\nunion()\n{\n\tcylinder(h=2,d=4);\n\tsphere(d=3);\n\ttranslate([1,0,0])\n\t{\n\t\tcube([1,2,3]);\n\t}\n}\n";
+    // Reordered and positional arguments, radii, explicit defaults,
+    // scientific notation, and a child without braces.
+    let other = "union() {\n  cylinder(r=2, center=false, h=2e0);\n  sphere(1.5);\n  translate(v=[1, 0, 0]) cube(size=[1, 2, 3], center=false);\n}\n";
+    assert!(eq(model, other));
+    assert!(!eq(
+        model,
+        &other.replace("center=false, h", "center=true, h")
+    ));
+    assert!(!eq(model, &other.replace("2e0", "2e1")));
+
+    // Curve detail arguments are the same as a surrounding let().
+    assert!(eq(
+        "sphere(d=2, $fn=20);",
+        "let($fn=20)\n{\n\tsphere(d=2);\n}\n"
+    ));
+    assert!(eq(
+        "rotate([0, 0, 90]) cube(1);",
+        "rotate(90,[0,0,1]) cube(1);"
+    ));
+    assert!(eq(
+        "cylinder(h=1, r1=1, r2=0.5);",
+        "cylinder(1, d1=2, d2=1);"
+    ));
+
+    let error = |code: &str| scad_relative_eq(code, code, MAX_RELATIVE);
+    assert!(error("cube([1,2,3], color=1);").is_err());
+    assert!(error("sphere(d=\"big\");").is_err());
+    assert!(error("frobnicate(1);").is_err());
+}
+
+#[test]
+fn parse_intersection_scale_and_modifiers() {
+    let render = |tree: &Tree| to_code(tree, RenderQuality::Low).unwrap();
+    let same = |a: &Tree, b: &Tree| {
+        scad_relative_eq(&render(a), &render(b), MAX_RELATIVE).unwrap()
+    };
+    let cube = |size| {
+        Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let tree = intersect![
+        scale![V3::new(1., 2., 3.), cube(2.)],
+        ghost![cube(1.)],
+        highlight![cube(1.5)],
+    ];
+    assert!(same(&tree, &tree.clone()));
+    assert!(!same(
+        &tree,
+        &union![
+            scale![V3::new(1., 2., 3.), cube(2.)],
+            ghost![cube(1.)],
+            highlight![cube(1.5)],
+        ]
+    ));
+    assert!(!same(
+        &tree,
+        &intersect![
+            scale![V3::new(1., 2., 4.), cube(2.)],
+            ghost![cube(1.)],
+            highlight![cube(1.5)],
+        ]
+    ));
+    assert!(!same(
+        &tree,
+        &intersect![
+            scale![V3::new(1., 2., 3.), cube(2.)],
+            highlight![cube(1.)],
+            highlight![cube(1.5)],
+        ]
+    ));
+    assert!(!same(
+        &tree,
+        &intersect![
+            scale![V3::new(1., 2., 3.), cube(2.)],
+            cube(1.),
+            highlight![cube(1.5)],
+        ]
+    ));
+}
+
+#[test]
+fn structural_diff() {
+    let a = "union()\n{\n\tcube([1,2,3]);\n\ttranslate([1,0,0])\n\t{\n\t\tsphere(d=2);\n\t}\n}\n";
+    assert!(scad_diff(a, a, MAX_RELATIVE).unwrap().is_empty());
+    // Rounding noise around zero isn't a difference.
+    let noisy = a.replace("[1,0,0]", "[1,0.0000014,-0.0000005]");
+    assert!(scad_diff(a, &noisy, MAX_RELATIVE).unwrap().is_empty());
+    assert!(scad_relative_eq(a, &noisy, MAX_RELATIVE).unwrap());
+
+    let b = a
+        .replace("d=2", "d=2.5")
+        .replace("[1,2,3]", "[1,2,3],center=true");
+    let entries = scad_diff(a, &b, MAX_RELATIVE).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].path, "union/cube[0]");
+    assert_eq!(entries[0].node, "cube");
+    assert_eq!(entries[0].b, "[true]");
+    assert_eq!(entries[1].path, "union/translate[1]/sphere[0]");
+    assert_eq!(entries[1].a, "[2.0]");
+    assert_eq!(entries[1].b, "[2.5]");
+
+    let c = a.replace("sphere(d=2)", "cube(2)");
+    let entries = scad_diff(a, &c, MAX_RELATIVE).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].to_string(),
+        "union/translate[1]/sphere[0]: sphere != cube"
+    );
+}
+
+#[test]
+fn bounds_of_scad_code() {
+    let dot = |pos, shape| {
+        Dot::new(DotSpec {
+            pos,
+            align: DotAlign::centroid(),
+            size: 2.,
+            rot: axis_degrees(Axis::Z, 90.),
+            shape,
+        })
+    };
+    let extrusion = Extrusion {
+        perimeter: Polygon2D::new(vec![
+            P2::new(0., 0.),
+            P2::new(4., 0.),
+            P2::new(4., 2.),
+        ])
+        .unwrap(),
+        holes: Vec::new(),
+        thickness: 3.,
+        bottom_z: 1.,
+        params: ExtrudeParams::default(),
+    };
+    let tree = union![
+        dot(P3::new(1., 2., 3.), DotShape::Cube),
+        translate![V3::new(0., 0., -5.), dot(P3::origin(), DotShape::Sphere)],
+        mirror![V3::x(), dot(P3::new(6., 0., 0.), DotShape::Cylinder)],
+        extrusion,
+    ];
+    let code = to_code(&tree, RenderQuality::Low).unwrap();
+    let expected = tree.bounds().unwrap();
+    let bounds = scad_bounds(&code).unwrap().unwrap();
+    assert_relative_eq!(bounds.min, expected.min, epsilon = 0.0001);
+    assert_relative_eq!(bounds.max, expected.max, epsilon = 0.0001);
+
+    let code = to_code(&Tree::raw_scad("cube(1);"), RenderQuality::Low);
+    assert_eq!(scad_bounds(&code.unwrap()).unwrap(), None);
+}
+
+#[test]
+fn dedupe_repeated_subtrees() {
+    let dot = |x| {
+        Dot::new(DotSpec {
+            pos: P3::new(x, 0., 0.),
+            align: DotAlign::origin(),
+            size: 1.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let cutout = hull![dot(0.), dot(2.)];
+    let tree = diff![
+        dot(0.),
+        translate![V3::new(0., 5., 0.), cutout.clone()],
+        translate![V3::new(0., 10., 0.), cutout.clone()],
+        translate![V3::new(0., 15., 0.), cutout.clone()],
+    ];
+    let options = RenderOptions {
+        quality: RenderQuality::Low,
+        snap_tolerance: None,
+        precision: None,
+        dedupe: true,
+    };
+    let deduped = to_code(&tree, options).unwrap();
+    let plain = to_code(&tree, RenderQuality::Low).unwrap();
+    // The cutout is shared, and so is the dot inside it, because the dot is
+    // also used outside of the cutouts.
+    assert_eq!(deduped.matches("module part_shared").count(), 2);
+    assert_eq!(deduped.matches("part_shared_2();").count(), 3);
+    assert_eq!(deduped.matches("part_shared();").count(), 2);
+    assert_eq!(deduped.matches("hull()").count(), 1);
+    assert!(deduped.len() < plain.len());
+    assert!(scad_relative_eq(&deduped, &plain, MAX_RELATIVE).unwrap());
+
+    // Nothing is shared if nothing repeats.
+    let unique = diff![dot(-3.), cutout];
+    assert_eq!(
+        to_code(&unique, options).unwrap(),
+        to_code(&unique, RenderQuality::Low).unwrap()
+    );
+}
+
+#[test]
+fn scale() {
+    check_model("scale", Action::Test, || {
+        let dot = Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size: 2.0,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        });
+        Ok(scale![V3::new(2., 1., 0.5), dot])
+    })
+}
+
+#[test]
+fn minkowski() {
+    check_model("minkowski", Action::Test, || {
+        let cube = Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size: 2.0,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        });
+        let sphere = Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::centroid(),
+            size: 1.0,
+            rot: R3::identity(),
+            shape: DotShape::Sphere,
+        });
+        Ok(minkowski![cube, sphere])
+    })
+}
+
+#[test]
+fn cut() {
+    let dot = |size| {
+        Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::centroid(),
+            size,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let cut = cut![dot(3.); dot(2.), dot(1.)];
+    let diff = diff![dot(3.), dot(2.), dot(1.)];
+    assert_eq!(
+        to_code(&cut, RenderQuality::Low).expect("failed to render cut"),
+        to_code(&diff, RenderQuality::Low).expect("failed to render diff")
+    );
+}
+
+#[test]
+fn fillet() {
+    let spec = FilletSpec {
+        start: P3::origin(),
+        end: P3::new(0., 10., 0.),
+        dirs: (V3::x(), V3::z()),
+        radius: 2.,
+    };
+    let fillet = Fillet::new(spec).expect("failed to make fillet");
+    assert_eq!(fillet.length(), 10.);
+
+    // The order of the surface directions doesn't matter
+    let swapped = Fillet::new(FilletSpec {
+        dirs: (V3::z(), V3::x()),
+        ..spec
+    })
+    .expect("failed to make swapped fillet");
+    assert_eq!(
+        to_code(&Tree::from(fillet), RenderQuality::Low)
+            .expect("failed to render"),
+        to_code(&Tree::from(swapped), RenderQuality::Low)
+            .expect("failed to render")
+    );
+
+    assert!(Fillet::new(FilletSpec { radius: 0., ..spec }).is_err());
+    assert!(Fillet::new(FilletSpec {
+        radius: 20.,
+        ..spec
+    })
+    .is_err());
+    assert!(Fillet::new(FilletSpec {
+        dirs: (V3::x(), V3::y()),
+        ..spec
+    })
+    .is_err());
+    assert!(Fillet::new(FilletSpec {
+        dirs: (V3::x(), V3::zeros()),
+        ..spec
+    })
+    .is_err());
+    assert!(Fillet::new(FilletSpec {
+        radius: std::f32::NAN,
+        ..spec
+    })
+    .is_err());
+    assert!(Fillet::new(FilletSpec {
+        start: P3::new(std::f32::NAN, 0., 0.),
+        ..spec
+    })
+    .is_err());
+}
+
+#[test]
+fn screw_hole() {
+    let spec = ScrewHoleSpec {
+        pos: P3::new(0., 0., 10.),
+        align: CylinderAlign::EndCenter(C1::P1),
+        diameter: 3.,
+        length: 10.,
+        head: Some(HoleHead::Countersink { diameter: 6. }),
+        rot: R3::identity(),
+    };
+    let hole = ScrewHole::new(spec).expect("failed to make screw hole");
+    assert_relative_eq!(
+        hole.pos(CylinderAlign::EndCenter(C1::P0)),
+        P3::origin()
+    );
+    assert_relative_eq!(hole.head.unwrap().depth(hole.diameter), 1.5);
+
+    let plain = ScrewHole::new(ScrewHoleSpec { head: None, ..spec })
+        .expect("failed to make plain screw hole");
+    let shaft = Cylinder::new(CylinderSpec {
+        pos: P3::origin(),
+        align: CylinderAlign::EndCenter(C1::P0),
+        diameter: 3.,
+        height: 10.,
+        rot: R3::identity(),
+    });
+    assert_eq!(
+        to_code(&Tree::from(plain), RenderQuality::Low)
+            .expect("failed to render"),
+        to_code(&Tree::from(shaft), RenderQuality::Low)
+            .expect("failed to render")
+    );
+
+    let too_narrow = HoleHead::Counterbore {
+        diameter: 2.,
+        depth: 3.,
+    };
+    let too_deep = HoleHead::Counterbore {
+        diameter: 6.,
+        depth: 11.,
+    };
+    assert!(ScrewHole::new(ScrewHoleSpec {
+        head: Some(too_narrow),
+        ..spec
+    })
+    .is_err());
+    assert!(ScrewHole::new(ScrewHoleSpec {
+        head: Some(too_deep),
+        ..spec
+    })
+    .is_err());
+}
+
+#[test]
+fn rect_cut_corners() {
+    check_model("rect_cut_corners", Action::Test, || {
+        let r = Rect::new(RectSpec {
+            pos: P3::origin(),
+            align: RectAlign::origin(),
+            y_length: 2.0,
+            x_length: 4.0,
+            size: 1.0,
+            rot: axis_degrees(Axis::X, 45.),
+            shapes: RectShapes::Cube,
+        })?;
+        r.link(RectLink::Chamfer)
+    })
+}
+
+#[test]
+fn cuboid_chamfer_hole() {
+    check_model("cuboid_chamfer_hole", Action::Test, || {
+        // Note that the z dimension is thinner than the dot size, so it has
+        // to be clamped.
+        let hole_spec = CuboidSpecChamferZHole {
+            pos: P3::origin(),
+            align: CuboidAlign::center_face(CubeFace::Z0),
+            x_length: 4.,
+            y_length: 3.,
+            z_length: 1.,
+            chamfer: Fraction::new(1.)?,
+            rot: R3::identity(),
+            shapes: CuboidShapes::Cube,
+        };
+        assert!(Cuboid::new(hole_spec).is_err());
+        let hole =
+            Cuboid::new(hole_spec.check_dimensions(DimensionMode::Clamp)?)?;
+        hole.link(CuboidLink::ChamferZ)
+    })
+}
+
+#[test]
+fn triangle_simple() {
+    check_model("triangle_simple", Action::Test, || {
+        let spec = TriangleSpec {
+            deg_b: 90.,
+            len_bc: f32::sqrt(3.) * 10.,
+            deg_c: 30.,
+            size: 3.,
+            point_b: P3::new(0., -9., 0.),
+            rot: axis_degrees(Axis::Z, 30.),
+            shape: DotShape::Cylinder,
+            corner_radius: None,
+        };
+        let tri = Triangle::new(spec)?;
+        Ok(union![tri.mark(spec)?, tri.link()?])
+    })
+}
+
+#[test]
+fn triangle_validation() {
+    let spec = TriangleSpec {
+        deg_b: 90.,
+        len_bc: 10.,
+        deg_c: 30.,
+        size: 1.,
+        point_b: P3::origin(),
+        rot: R3::identity(),
+        shape: DotShape::Cylinder,
+        corner_radius: None,
+    };
+    assert!(Triangle::new(spec).is_ok());
+    assert!(Triangle::new(TriangleSpec { deg_c: 90., ..spec }).is_err());
+    assert!(Triangle::new(TriangleSpec {
+        deg_b: -10.,
+        ..spec
+    })
+    .is_err());
+    assert!(Triangle::new(TriangleSpec { len_bc: 0., ..spec }).is_err());
+    assert!(Triangle::new(TriangleSpec {
+        size: std::f32::NAN,
+        ..spec
+    })
+    .is_err());
+    assert!(Triangle::new(TriangleSpec {
+        corner_radius: Some(0.),
+        ..spec
+    })
+    .is_err());
+    assert!(Triangle::new(TriangleSpec {
+        corner_radius: Some(spec.inradius()),
+        ..spec
+    })
+    .is_err());
+}
+
+#[test]
+fn triangle_corners() {
+    let spec = TriangleSpec {
+        deg_b: 90.,
+        len_bc: 10.,
+        deg_c: 45.,
+        size: 2.,
+        point_b: P3::origin(),
+        rot: R3::identity(),
+        shape: DotShape::Cylinder,
+        corner_radius: None,
+    };
+    assert_relative_eq!(spec.inradius(), 10. - 50_f32.sqrt(), epsilon = 1e-5);
+
+    let bounds = |spec| {
+        let tri = Triangle::new(spec).expect("bad triangle");
+        tri.link().unwrap().bounds().unwrap()
+    };
+    // Rounding to half the dot size fills the same space as cylinder dots.
+    let plain = bounds(spec);
+    let rounded = bounds(TriangleSpec {
+        corner_radius: Some(1.),
+        ..spec
+    });
+    assert_relative_eq!(plain.min, rounded.min, epsilon = 1e-5);
+    assert_relative_eq!(plain.max, rounded.max, epsilon = 1e-5);
+    // A larger radius pulls the rounded corners in from the sharp corners.
+    let rounder = bounds(TriangleSpec {
+        corner_radius: Some(2.),
+        ..spec
+    });
+    assert_relative_eq!(rounder.min, plain.min, epsilon = 1e-5);
+    assert!(rounder.max.x < plain.max.x);
+    assert!(rounder.max.y < plain.max.y);
+
+    let sharp = Triangle::new(TriangleSpec {
+        shape: DotShape::Cube,
+        ..spec
+    })
+    .unwrap();
+    assert_eq!(sharp.a.shape, DotShape::Cube);
+    let prism = sharp.link_prism(6.).unwrap();
+    assert_relative_eq!(prism.bounds().unwrap().max.z, 6.);
+    assert!(sharp.link_prism(1.).is_err());
+}
+
+#[test]
+fn triangle_constructors() {
+    let right = TriangleSpec::right(3., 4., 1., P3::origin(), R3::identity())
+        .expect("bad right triangle");
+    assert_relative_eq!(right.deg_b, 90.);
+    assert_relative_eq!(right.len_bc, 4.);
+    assert_relative_eq!(right.len(TriCorner::A, TriCorner::C), 5.);
+    assert_relative_eq!(
+        right.point(TriCorner::A),
+        P3::new(0., 3., 0.),
+        epsilon = 1e-5
+    );
+
+    let sss = TriangleSpec::sss(3., 4., 5., 1., P3::origin(), R3::identity())
+        .expect("bad sss triangle");
+    assert_relative_eq!(sss.deg_b, right.deg_b);
+    assert_relative_eq!(sss.deg_c, right.deg_c);
+
+    let sas = TriangleSpec::sas(5., 60., 5., 1., P3::origin(), R3::identity())
+        .expect("bad sas triangle");
+    assert_relative_eq!(sas.deg_c, 60., epsilon = 1e-3);
+    assert_relative_eq!(
+        sas.len(TriCorner::A, TriCorner::C),
+        5.,
+        epsilon = 1e-4
+    );
+
+    let make =
+        |a, b, c| TriangleSpec::sss(a, b, c, 1., P3::origin(), R3::identity());
+    assert!(make(1., 2., 3.).is_err());
+    assert!(make(1., 2., 10.).is_err());
+    assert!(make(-3., 4., 5.).is_err());
+    assert!(
+        TriangleSpec::sas(5., 180., 5., 1., P3::origin(), R3::identity())
+            .is_err()
+    );
+}
+
+#[test]
+fn prism() {
+    let spec = TriangleSpec {
+        deg_b: 90.,
+        len_bc: 10.,
+        deg_c: 45.,
+        size: 2.,
+        point_b: P3::new(0., 0., 5.),
+        rot: R3::identity(),
+        shape: DotShape::Cylinder,
+        corner_radius: None,
+    };
+    let prism = Prism::new(Triangle::new(spec).expect("bad triangle"), 10.)
+        .expect("failed to make prism");
+    assert_relative_eq!(prism.min_coord(Axis::Z), 5.);
+    assert_relative_eq!(prism.max_coord(Axis::Z), 15.);
+    assert_relative_eq!(prism.pos(PrismAlign::FaceCenter(C1::P0)).z, 5.);
+    assert_relative_eq!(prism.pos(PrismAlign::FaceCenter(C1::P1)).z, 15.);
+    assert_relative_eq!(prism.pos(PrismAlign::Centroid).z, 10.);
+
+    // Side B-C lies along the x axis, and the 3rd corner is in the +y direction
+    let side = prism.pos(PrismAlign::SideCenter(TriCorner::B, TriCorner::C));
+    assert_relative_eq!(side.y, 0., epsilon = 0.0001);
+    assert_relative_eq!(side.z, 10.);
+
+    assert!(Prism::new(prism.bot, 1.).is_err());
+}
+
+#[test]
+fn map_cuboid() {
+    check_model("map_cuboid", Action::Test, || {
+        let p1 = Cuboid::new(CuboidSpec {
+            pos: P3::origin(),
+            align: CuboidAlign::outside(C3::P111),
+            x_length: 10.,
+            y_length: 7.,
+            z_length: 4.,
+            size: 1.,
+            rot: R3::identity(),
+            shapes: DotShape::Cube.into(),
+        })?;
+        let p2 = p1.map_translate(V3::new(0., -10., 0.));
+        let p3 = p1.map_rotate(axis_radians(Axis::Z, -PI / 8.));
+        let p4 = p1.map(&|d: &Dot| {
+            d.translate(V3::new(20., 0., 0.))
+                .rotate(axis_radians(Axis::Z, -PI / 8.))
+        });
+        Ok(union![
+            p1.link(CuboidLink::Solid)?,
+            p2.link(CuboidLink::Solid)?,
+            p3.link(CuboidLink::Solid)?,
+            p4.link(CuboidLink::Solid)?,
+        ])
+    })
+}
+
+#[test]
+fn map_post() {
+    check_model("map_post", Action::Test, || {
+        let p1 = Post::new(PostSpec {
+            pos: P3::new(4., 0., 10.),
+            align: PostAlign::outside(C3::P111),
+            len: 12.,
+            size: 2.,
+            rot: axis_radians(Axis::X, PI / 8.)
+                * axis_radians(Axis::Y, PI / 2.),
+            shapes: PostShapes::Cube,
+        })?;
+        let p2 = p1.map_translate(V3::new(0., 0., 4.));
+        let axis =
+            p1.pos(PostAlign::outside(C3::P001)) - p1.pos(PostAlign::origin());
+        let p3 = p1.map_rotate(axis_radians(axis, PI / 8.));
+        let p4 = p2.map_rotate(axis_radians(axis, PI / 8.));
+        Ok(union![
+            p1.link(PostLink::Dots),
+            p2.link(PostLink::Dots),
+            p3.link(PostLink::Dots),
+            p4.link(PostLink::Dots),
+        ])
+    })
+}
+
+#[test]
+fn map_rect() {
+    check_model("map_rect", Action::Test, || {
+        let r1 = Rect::new(RectSpec {
+            pos: P3::origin(),
+            align: RectAlign::origin(),
+            x_length: 10.,
+            y_length: 5.,
+            size: 2.,
+            rot: axis_radians(Axis::X, PI / 4.),
+            shapes: DotShape::Cube.into(),
+        })?;
+        let r2 = r1.map_translate(V3::new(3., 0., 5.));
+        let r3 = r1.map_rotate(axis_radians(V3::new(1., 1., 0.), PI / 2.));
+        Ok(union![
+            r1.link(RectLink::Dots)?,
+            r2.link(RectLink::Dots)?,
+            r3.link(RectLink::Dots)?,
+        ])
+    })
+}
+
+#[test]
+fn scad_equality() {
+    let scad1 = "
+				union()
+				{
+								cylinder(h=1,d=2);
+								}
+";
+
+    let scad2 = "
 				union()
 				{
                  cylinder(h=1,d=2);
@@ -492,6 +3361,66 @@ fn simple_rect() {
     })
 }
 
+#[test]
+fn silhouette() {
+    let spec = CuboidSpec {
+        pos: P3::new(3., 2., 1.),
+        align: CuboidAlign::origin(),
+        x_length: 10.,
+        y_length: 7.,
+        z_length: 4.,
+        size: 1.,
+        rot: axis_degrees(Axis::Z, 30.),
+        shapes: CuboidShapes::Sphere,
+    };
+    let cuboid = Cuboid::new(spec).expect("failed to make cuboid");
+    let s = cuboid.silhouette();
+    assert_relative_eq!(s.outer, V3::new(10., 7., 4.), max_relative = 0.0001);
+    assert_relative_eq!(s.inner, V3::new(8., 5., 2.), max_relative = 0.0001);
+    assert_eq!(s.corner_radius, 0.5);
+
+    let rect = cuboid.bot.silhouette();
+    assert_relative_eq!(
+        rect.outer,
+        V3::new(10., 7., 1.),
+        max_relative = 0.0001
+    );
+    assert_relative_eq!(rect.inner, V3::new(8., 5., 1.), max_relative = 0.0001);
+    assert_eq!(rect.corner_radius, 0.5);
+
+    let cube = Cuboid::new(CuboidSpec {
+        shapes: CuboidShapes::Cube,
+        ..spec
+    })
+    .expect("failed to make cuboid");
+    assert_eq!(cube.silhouette().corner_radius, 0.);
+    assert_eq!(cube.bot.silhouette().corner_radius, 0.);
+    let rect_spec = RectSpec {
+        pos: P3::origin(),
+        align: RectAlign::origin(),
+        x_length: 10.,
+        y_length: 7.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: RectShapes::Cube,
+    };
+    assert_eq!(rect_spec.silhouette().corner_radius, 0.);
+    let mixed = RectShapes::Custom {
+        p00: DotShape::Cube,
+        p01: DotShape::Cylinder,
+        p10: DotShape::Cylinder,
+        p11: DotShape::Cylinder,
+    };
+    assert_eq!(rect_spec.with_shapes(mixed).silhouette().corner_radius, 0.);
+    assert_eq!(
+        rect_spec
+            .with_shapes(RectShapes::Cylinder)
+            .silhouette()
+            .corner_radius,
+        0.5
+    );
+}
+
 #[test]
 fn rect2() {
     // Not carefully checked
@@ -513,34 +3442,70 @@ fn rect2() {
     })
 }
 
-// #[test]
-// fn post_ends() {
-//     check_model("post_ends", Action::Preview, || {
-//         let p = Post::new(
-//             PostShapes::Cube,
-//             PostSpecEnds {
-//                 // bot: P3::origin(),
-//                 // top: P3::new(10., 0., 0.),
-//                 // align_bot_face: C2::P00,
-//                 // size: 2.,
-//                 // point_on_x_axis: P3::new(0., -2., 5.),
-
-//                 // bot: P3::origin(),
-//                 // top: P3::new(0., 0., 10.),
-//                 // align_bot_face: C2::P00,
-//                 // size: 2.,
-//                 // x_axis_handle: P3::new(2., 1., 0.),
-
-//                 bot: P3::origin(),
-//                 top: P3::new(7., 0., 10.),
-//                 align_bot_face: C2::P00,
-//                 size: 2.,
-//                 x_axis_handle: P3::new(2., 1., 0.),
-//             },
-//         )?;
-//         Ok(p.link(PostLink::Solid))
-//     })
-// }
+#[test]
+fn post_ends() {
+    let p = Post::new(PostSpecEnds {
+        bot: P3::origin(),
+        top: P3::new(0., 0., 10.),
+        align_bot_face: C2::P00,
+        size: 2.,
+        x_axis_handle: P3::new(2., 1., 0.),
+        shapes: PostShapes::Cube,
+    })
+    .expect("failed to make post");
+    assert_relative_eq!(p.pos(PostAlign::origin()), P3::origin());
+    assert_relative_eq!(
+        p.pos(PostAlign::outside(C3::P001)),
+        P3::new(0., 0., 10.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        p.edge_unit_vec(Axis::X),
+        V3::new(2., 1., 0.).normalize(),
+        epsilon = 0.0001
+    );
+
+    let slanted = PostSpecEnds {
+        bot: P3::new(1., 1., 0.),
+        top: P3::new(7., 0., 10.),
+        align_bot_face: C2::P11,
+        size: 2.,
+        x_axis_handle: P3::new(2., 1., 0.),
+        shapes: PostShapes::Round,
+    };
+    let p = Post::new(slanted).expect("failed to make post");
+    assert_relative_eq!(
+        p.pos(PostAlign::outside(C3::P111)),
+        slanted.top,
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        p.pos(PostAlign::outside(C3::P110)),
+        slanted.bot,
+        epsilon = 0.0001
+    );
+
+    let axis_spec = PostSpecAxis {
+        pos: P3::origin(),
+        align: PostAlign::origin(),
+        len: 5.,
+        axis: V3::new(1., 0., 0.),
+        x_axis_handle: V3::new(0., 1., 0.),
+        size: 1.,
+        shapes: PostShapes::Cube,
+    };
+    let p = Post::new(axis_spec).expect("failed to make post");
+    assert_relative_eq!(
+        p.pos(PostAlign::outside(C3::P001)),
+        P3::new(5., 0., 0.),
+        epsilon = 0.0001
+    );
+    let parallel = PostSpecAxis {
+        x_axis_handle: V3::new(2., 0., 0.),
+        ..axis_spec
+    };
+    assert!(Post::new(parallel).is_err());
+}
 
 #[test]
 fn simple_post() {
@@ -690,49 +3655,463 @@ fn spiral_cuboid() {
 }
 
 #[test]
-fn snake() {
-    check_model("snake", Action::Test, || {
-        let start = Dot::new(DotSpec {
-            pos: P3::origin(),
-            align: C3::P111.into(),
-            size: 2.0,
-            rot: R3::identity(),
-            shape: DotShape::Cube,
-        });
+fn drop_solid_parity() {
+    let post = Post::new(PostSpec {
+        pos: P3::new(0., 0., 10.),
+        align: PostAlign::origin(),
+        len: 5.,
+        rot: axis_degrees(Axis::Y, 30.),
+        size: 1.,
+        shapes: PostShapes::Cube,
+    })
+    .expect("failed to make post");
+    let manual = drop_solid(&[post.bot, post.top], -2., None);
+    assert_eq!(
+        to_code(&post.drop_solid(-2., None), RenderQuality::Low)
+            .expect("failed to render"),
+        to_code(&manual, RenderQuality::Low).expect("failed to render")
+    );
+}
+
+#[test]
+fn snake() {
+    check_model("snake", Action::Test, || {
+        let start = Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: C3::P111.into(),
+            size: 2.0,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        });
+
+        let end = Dot::new(DotSpec {
+            pos: P3::new(-5., -10., 7.),
+            align: C3::P111.into(),
+            size: 2.0,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        });
+
+        let snake = Snake::new(start, end, [Axis::X, Axis::Z, Axis::Y])?;
+
+        Ok(snake.link(SnakeLink::Chain)?)
+    })
+}
+
+#[test]
+fn cuboid_corners() {
+    check_model("cuboid_corners", Action::Test, || {
+        let r = Cuboid::new(CuboidSpec {
+            pos: P3::new(4., 6., 8.),
+            align: CuboidAlign::Corner {
+                dot: C3::P100,
+                cuboid: C3::P010,
+            },
+            y_length: 15.0,
+            x_length: 10.0,
+            z_length: 5.0,
+            size: 2.,
+            rot: axis_radians(V3::x_axis().into_inner(), PI / 8.),
+            shapes: CuboidShapes::Cube,
+        })?;
+
+        Ok(union![r.link(CuboidLink::Frame)?, r.mark_corners()])
+    })
+}
+
+#[test]
+fn cube_edges() {
+    let edges = C3::all_edges();
+    assert_eq!(edges.len(), 12);
+    for (a, b) in edges {
+        let differences = [Axis::X, Axis::Y, Axis::Z]
+            .iter()
+            .filter(|&&axis| a.is_high(axis) != b.is_high(axis))
+            .count();
+        assert_eq!(differences, 1);
+    }
+}
+
+#[test]
+fn cuboid_mark_edges_faces() {
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::origin(),
+        align: CuboidAlign::origin(),
+        x_length: 10.0,
+        y_length: 15.0,
+        z_length: 5.0,
+        size: 2.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+    for tree in vec![cuboid.mark_edges(), cuboid.mark_faces()] {
+        match tree {
+            Tree::Operator(TreeOperator::Color(_, _)) => (),
+            _ => panic!("expected colored marks"),
+        }
+    }
+}
+
+#[test]
+fn anchors() {
+    let cuboid = Cuboid::new(CuboidSpec {
+        pos: P3::origin(),
+        align: CuboidAlign::origin(),
+        x_length: 10.0,
+        y_length: 20.0,
+        z_length: 5.0,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+    let anchor = cuboid
+        .anchor("top_left_inside")
+        .expect("failed to get anchor");
+    assert_relative_eq!(
+        anchor.pos,
+        cuboid.pos(CuboidAlign::inside_midpoint(C3::P001, C3::P011))
+    );
+    assert_relative_eq!(anchor.pos, P3::new(1., 10., 4.));
+    assert!(cuboid.anchor("top_bottom").is_err());
+    assert!(cuboid.anchor("upper_left").is_err());
+
+    let dot = Dot::new(DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::centroid(),
+        size: 2.,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    assert!(dot.anchor("top_inside").is_err());
+
+    // Stand the dot on top of the cuboid, with matching rotation
+    let target = AnchorPoint {
+        pos: cuboid.anchor("top").expect("failed to get anchor").pos,
+        rot: axis_degrees(Axis::Z, 90.),
+    };
+    let placed = dot.place_at("bottom", target).expect("failed to place");
+    assert_relative_eq!(
+        placed.pos(DotAlign::center_face(CubeFace::Z0)),
+        P3::new(5., 10., 5.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(placed.rot.angle(), PI / 2., epsilon = 0.0001);
+}
+
+#[test]
+fn assembly() {
+    let base = Cuboid::new(CuboidSpec {
+        pos: P3::origin(),
+        align: CuboidAlign::origin(),
+        x_length: 10.0,
+        y_length: 20.0,
+        z_length: 5.0,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+    let peg = Post::new(PostSpec {
+        pos: P3::new(-50., 3., 7.),
+        align: PostAlign::origin(),
+        len: 4.,
+        rot: axis_degrees(Axis::X, 90.),
+        size: 2.,
+        shapes: PostShapes::Cube,
+    })
+    .expect("failed to make post");
+
+    let mut assembly = Assembly::new();
+    assembly
+        .add_dots("base", base, |c: &Cuboid| c.link(CuboidLink::Solid))
+        .expect("failed to add base");
+    assembly
+        .add_dots("peg", peg, |p: &Post| Ok(p.link(PostLink::Solid)))
+        .expect("failed to add peg");
+    assembly
+        .join(
+            ("peg", "bottom"),
+            ("base", "top"),
+            JointKind::Mate {
+                offset: V3::new(0., 0., 1.),
+            },
+        )
+        .expect("failed to join");
+    assert!(assembly
+        .join(
+            ("peg", "top"),
+            ("base", "top"),
+            JointKind::Mate {
+                offset: V3::zeros()
+            }
+        )
+        .is_err());
+    assert!(assembly
+        .add_dots("base", base, |c: &Cuboid| c.link(CuboidLink::Solid))
+        .is_err());
+
+    let placements = assembly.solve().expect("failed to solve");
+    let placed_peg = peg.map(&|dot| dot.transform(placements["peg"]));
+    assert_relative_eq!(
+        placed_peg.anchor("bottom").expect("bad anchor").pos,
+        P3::new(5., 10., 6.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        placed_peg.anchor("bottom").expect("bad anchor").rot.angle(),
+        0.,
+        epsilon = 0.0001
+    );
+    assembly.link().expect("failed to link assembly");
+}
+
+#[test]
+fn snap_rotations() {
+    let tolerance = 0.001;
+    let nearly_none = axis_radians(V3::new(1., 2., 3.), 0.0001);
+    assert_eq!(snap_rotation(nearly_none, tolerance).angle(), 0.);
+
+    let nearly_z = axis_degrees(V3::new(0.0001, 0., -1.), 30.);
+    let snapped = snap_rotation(nearly_z, tolerance);
+    assert_eq!(snapped.axis().unwrap().into_inner(), V3::new(0., 0., -1.));
+    assert_relative_eq!(snapped.angle(), nearly_z.angle());
+
+    let skewed = axis_degrees(V3::new(1., 1., 0.), 30.);
+    assert_eq!(snap_rotation(skewed, tolerance), skewed);
+
+    let dot = |rot| {
+        Tree::from(Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size: 1.0,
+            rot,
+            shape: DotShape::Cube,
+        }))
+    };
+    let options = RenderOptions {
+        quality: RenderQuality::Low,
+        snap_tolerance: Some(tolerance),
+        precision: None,
+        dedupe: false,
+    };
+    assert_eq!(
+        to_code(&dot(nearly_z), options).expect("failed to render"),
+        to_code(&dot(snapped), options).expect("failed to render")
+    );
+}
+
+#[test]
+fn cuboid_ribs() {
+    let wall = Cuboid::new(CuboidSpec {
+        pos: P3::origin(),
+        align: CuboidAlign::origin(),
+        x_length: 10.0,
+        y_length: 20.0,
+        z_length: 5.0,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Cube,
+    })
+    .expect("failed to make cuboid");
+    let spec = RibSpec {
+        face: CubeFace::Y1,
+        along: Axis::Z,
+        count: 3,
+        thickness: 1.,
+        depth: 2.,
+    };
+    let ribs = wall.ribs(spec).expect("failed to make ribs");
+    assert_eq!(ribs.len(), 3);
+    assert_relative_eq!(
+        ribs[0].pos(CuboidAlign::origin()),
+        P3::new(2., 20., 0.)
+    );
+    assert_relative_eq!(
+        ribs[2].pos(CuboidAlign::outside(C3::P111)),
+        P3::new(8., 22., 5.)
+    );
+
+    assert!(wall
+        .ribs(RibSpec {
+            along: Axis::Y,
+            ..spec
+        })
+        .is_err());
+    assert!(wall.ribs(RibSpec { count: 11, ..spec }).is_err());
+}
+
+#[test]
+fn patterns() {
+    let dot = Dot::new(DotSpec {
+        pos: P3::new(10., 0., 0.),
+        align: DotAlign::centroid(),
+        size: 1.0,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    let center = |d: &Dot| d.pos(DotAlign::centroid());
 
-        let end = Dot::new(DotSpec {
-            pos: P3::new(-5., -10., 7.),
-            align: C3::P111.into(),
-            size: 2.0,
+    let row = linear_array(&dot, 4, V3::new(0., 2., 0.));
+    assert_eq!(row.len(), 4);
+    assert_relative_eq!(center(&row[3]), P3::new(10., 6., 0.));
+
+    let grid = grid_array(&dot, (2, 3), (V3::x(), V3::y()));
+    assert_eq!(grid.len(), 6);
+    assert_relative_eq!(center(&grid[5]), P3::new(11., 2., 0.));
+
+    let ring = polar_array(&dot, 4, P3::new(5., 0., 0.), V3::z());
+    assert_eq!(ring.len(), 4);
+    assert_relative_eq!(
+        center(&ring[1]),
+        P3::new(5., 5., 0.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(ring[1].rot.angle(), PI / 2., epsilon = 0.0001);
+}
+
+#[test]
+fn paths() {
+    let dot = |pos| {
+        Dot::new(DotSpec {
+            pos,
+            align: DotAlign::centroid(),
+            size: 1.0,
             rot: R3::identity(),
-            shape: DotShape::Cube,
-        });
+            shape: DotShape::Sphere,
+        })
+    };
+    let center = |d: &Dot| d.pos(DotAlign::centroid());
+    let start = dot(P3::new(10., 0., 0.));
+    let end = dot(P3::new(0., 10., 0.));
 
-        let snake = Snake::new(start, end, [Axis::X, Axis::Z, Axis::Y])?;
+    let arc = Path::new(
+        start,
+        end,
+        PathShape::Arc {
+            center: P3::origin(),
+            axis: V3::z(),
+        },
+        2,
+    )
+    .expect("failed to make arc");
+    assert_eq!(arc.dots.len(), 3);
+    let half = 10. / 2_f32.sqrt();
+    assert_relative_eq!(
+        center(&arc.dots[1]),
+        P3::new(half, half, 0.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        center(&arc.dots[2]),
+        P3::new(0., 10., 0.),
+        epsilon = 0.0001
+    );
 
-        Ok(snake.link(SnakeLink::Chain)?)
-    })
+    // Control points on the straight line make a straight curve
+    let bezier = Path::new(
+        start,
+        end,
+        PathShape::Bezier {
+            control_a: P3::new(10., 0., 0.),
+            control_b: P3::new(0., 10., 0.),
+        },
+        4,
+    )
+    .expect("failed to make bezier");
+    assert_relative_eq!(
+        center(&bezier.dots[2]),
+        P3::new(5., 5., 0.),
+        epsilon = 0.0001
+    );
+    bezier.link().expect("failed to link path");
+
+    assert!(Path::new(start, end, PathShape::Line, 0).is_err());
 }
 
 #[test]
-fn cuboid_corners() {
-    check_model("cuboid_corners", Action::Test, || {
-        let r = Cuboid::new(CuboidSpec {
-            pos: P3::new(4., 6., 8.),
-            align: CuboidAlign::Corner {
-                dot: C3::P100,
-                cuboid: C3::P010,
-            },
-            y_length: 15.0,
-            x_length: 10.0,
-            z_length: 5.0,
-            size: 2.,
-            rot: axis_radians(V3::x_axis().into_inner(), PI / 8.),
-            shapes: CuboidShapes::Cube,
-        })?;
+fn knurl() {
+    let rect = Rect::new(RectSpec {
+        pos: P3::origin(),
+        align: RectAlign::origin(),
+        x_length: 10.,
+        y_length: 10.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: RectShapes::Cube,
+    })
+    .expect("failed to make rect");
+    let cylinder = Cylinder::new(CylinderSpec {
+        pos: P3::origin(),
+        align: CylinderAlign::EndCenter(C1::P0),
+        diameter: 10.,
+        height: 5.,
+        rot: R3::identity(),
+    });
+    let spec = KnurlSpec {
+        style: KnurlStyle::Diamond,
+        pitch: 2.,
+        depth: 0.5,
+    };
+    knurl_rect(&rect, C1::P1, spec).expect("failed to knurl rect");
+    knurl_cylinder(&cylinder, (0., 360.), spec)
+        .expect("failed to knurl cylinder");
+    let ridges = KnurlSpec {
+        style: KnurlStyle::Ridges,
+        ..spec
+    };
+    knurl_rect(&rect, C1::P0, ridges).expect("failed to knurl rect");
+    knurl_cylinder(&cylinder, (0., 90.), ridges)
+        .expect("failed to knurl cylinder");
 
-        Ok(union![r.link(CuboidLink::Frame)?, r.mark_corners()])
+    let too_deep = KnurlSpec { depth: 1.5, ..spec };
+    assert!(knurl_rect(&rect, C1::P1, too_deep).is_err());
+    let too_coarse = KnurlSpec { pitch: 20., ..spec };
+    assert!(knurl_rect(&rect, C1::P1, too_coarse).is_err());
+    assert!(knurl_cylinder(&cylinder, (90., 0.), spec).is_err());
+}
+
+#[test]
+fn sweep_rect() {
+    let rect = Rect::new(RectSpec {
+        pos: P3::origin(),
+        align: RectAlign::centroid(),
+        x_length: 4.,
+        y_length: 2.,
+        size: 0.5,
+        rot: R3::identity(),
+        shapes: RectShapes::Cube,
     })
+    .expect("failed to make rect");
+    let link = |r: &Rect| r.link(RectLink::Dots);
+    let offset = V3::new(0., 0., 10.);
+    let swept = sweep(
+        &rect,
+        &[
+            (P3::origin(), R3::identity()),
+            (P3::origin() + offset, R3::identity()),
+        ],
+        link,
+    )
+    .expect("failed to sweep");
+    let expected = union![hull![
+        link(&rect).unwrap(),
+        link(&rect.map_translate(offset)).unwrap(),
+    ]];
+    assert_eq!(
+        to_code(&swept, RenderQuality::Low).expect("failed to render"),
+        to_code(&expected, RenderQuality::Low).expect("failed to render")
+    );
+
+    let waypoints: Vec<_> = (0..5)
+        .map(|i| {
+            let rot = axis_degrees(V3::z(), 20. * i as f32);
+            (P3::new(0., 0., 5. * i as f32), rot)
+        })
+        .collect();
+    sweep(&rect, &waypoints, link).expect("failed to sweep with rotation");
+    assert!(sweep(&rect, &waypoints[..1], link).is_err());
 }
 
 #[test]
@@ -806,3 +4185,1106 @@ fn dot_fancy_translation2() {
         Ok(union![a, b, c])
     })
 }
+
+#[test]
+fn compile_unsupported_format() {
+    let tree = Tree::from(Dot::new(DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::origin(),
+        size: 1.,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    }));
+    for path in &["part.scad", "part.png", "part"] {
+        assert!(compile_to_stl(&tree, path.to_string(), RenderQuality::Low)
+            .is_err());
+    }
+}
+
+#[test]
+fn missing_openscad_bin() {
+    let fake = std::path::Path::new("/nonexistent/path/to/openscad");
+    let err = openscad_version_at(fake).unwrap_err();
+    assert!(err.to_string().contains("/nonexistent/path/to/openscad"));
+
+    // A binary that starts but exits with an error isn't a version.
+    let failing = std::path::Path::new("/bin/false");
+    if failing.exists() {
+        let err = openscad_version_at(failing).unwrap_err();
+        assert!(err.to_string().contains("/bin/false"));
+    }
+}
+
+#[test]
+fn harness_config() {
+    let dir = std::env::temp_dir().join("scad_dots_harness_config");
+    let config = HarnessConfig::default()
+        .with_model_dir(dir.join("good"))
+        .with_bad_model_dir(dir.join("bad"))
+        .with_tmp_dir(dir.join("tmp"))
+        .with_max_relative(0.01);
+    assert_eq!(config.viewer, std::path::PathBuf::from("openscad"));
+
+    let model = || {
+        Ok(Tree::from(Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size: 1.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })))
+    };
+    std::fs::create_dir_all(dir.join("good")).unwrap();
+    let code = to_code(&model().unwrap(), RenderQuality::Low).unwrap();
+    std::fs::write(
+        dir.join("good/cube.scad"),
+        code.replace("[1,1,1]", "[1.001,1,1]"),
+    )
+    .unwrap();
+    // It's within the configured tolerance, but not the default one.
+    check_model_with(&config, "cube", Action::Test, model);
+    let strict = config.clone().with_max_relative(MAX_RELATIVE);
+    let result = std::panic::catch_unwind(|| {
+        check_model_with(&strict, "cube", Action::Test, model)
+    });
+    assert!(result.is_err());
+    assert!(dir.join("bad/cube.scad").exists());
+}
+
+#[test]
+fn update_golden() {
+    let dir = std::env::temp_dir().join("scad_dots_update_golden");
+    let _ = std::fs::remove_dir_all(&dir);
+    let config = HarnessConfig::default()
+        .with_model_dir(dir.join("good"))
+        .with_bad_model_dir(dir.join("bad"))
+        .with_update(true);
+    let model = |size| {
+        move || {
+            Ok(Tree::from(Dot::new(DotSpec {
+                pos: P3::origin(),
+                align: DotAlign::origin(),
+                size,
+                rot: R3::identity(),
+                shape: DotShape::Cube,
+            })))
+        }
+    };
+    // Missing and mismatched models are replaced instead of failing.
+    check_model_with(&config, "cube", Action::Test, model(1.));
+    check_model_with(&config, "cube", Action::Test, model(2.));
+    let strict = config.clone().with_update(false);
+    check_model_with(&strict, "cube", Action::Test, model(2.));
+    assert!(!dir.join("bad/cube.scad").exists());
+}
+
+#[test]
+fn screenshot_comparison() {
+    use png::HasParameters;
+
+    let dir = std::env::temp_dir().join("scad_dots_screenshot_comparison");
+    std::fs::create_dir_all(&dir).unwrap();
+    let save = |name: &str, width: u32, pixels: &[u8]| {
+        let path = dir.join(name);
+        let file = std::fs::File::create(&path).unwrap();
+        let mut encoder = png::Encoder::new(file, width, 1);
+        encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(pixels).unwrap();
+        path
+    };
+    let a = save("a.png", 4, &[0, 0, 0, 50, 50, 50, 100, 100, 100, 9, 9, 9]);
+    // One pixel changed a lot, and one only slightly.
+    let b = save("b.png", 4, &[0, 0, 0, 55, 50, 50, 100, 200, 100, 9, 9, 9]);
+    assert_relative_eq!(compare_images(&a, &a).unwrap(), 0.);
+    assert_relative_eq!(compare_images(&a, &b).unwrap(), 0.25);
+    let small = save("small.png", 1, &[0, 0, 0]);
+    assert!(compare_images(&a, &small).is_err());
+    assert!(compare_images(&a, &dir.join("missing.png")).is_err());
+}
+
+#[test]
+fn watch_preview() {
+    let dir = std::env::temp_dir().join("scad_dots_watch_preview");
+    let size = std::cell::Cell::new(1.);
+    let mut watcher = watch("cube", || {
+        Ok(Tree::from(Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size: size.get(),
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })))
+    })
+    .unwrap()
+    .with_config(HarnessConfig::default().with_tmp_dir(&dir))
+    .with_viewer(false);
+    assert_eq!(watcher.path(), dir.join("watch_cube.scad"));
+
+    assert!(watcher.refresh().unwrap());
+    assert!(!watcher.refresh().unwrap());
+    size.set(2.);
+    assert!(watcher.refresh().unwrap());
+    let code = std::fs::read_to_string(watcher.path()).unwrap();
+    assert!(code.contains("cube([2,2,2])"));
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn serde_round_trip() {
+    let tree = union![
+        Dot::new(DotSpec {
+            pos: P3::new(1., 2., 3.),
+            align: DotAlign::centroid(),
+            size: 2.,
+            rot: axis_radians(Axis::Z, PI / 3.),
+            shape: DotShape::Sphere,
+        }),
+        rotate!(
+            axis_degrees(Axis::Y, 45.),
+            Cylinder::new(CylinderSpec {
+                pos: P3::origin(),
+                align: CylinderAlign::EndCenter(C1::P0),
+                diameter: 2.,
+                height: 5.,
+                rot: R3::identity(),
+            })
+        )
+    ];
+    let json = serde_json::to_string(&tree).unwrap();
+    let back: Tree = serde_json::from_str(&json).unwrap();
+    let options = RenderQuality::Low;
+    assert_eq!(
+        to_code(&tree, options).unwrap(),
+        to_code(&back, options).unwrap()
+    );
+
+    // Hand-written rotations don't have to be normalized.
+    let dot: Dot = serde_json::from_str(
+        r#"{"shape": "Cube", "p000": [0, 0, 0], "size": 1,
+            "rot": {"w": 2, "i": 0, "j": 0, "k": 0}}"#,
+    )
+    .unwrap();
+    assert_eq!(dot.rot, R3::identity());
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn serde_specs() {
+    fn round_trip<T>(spec: &T) -> T
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        serde_json::from_str(&serde_json::to_string(spec).unwrap()).unwrap()
+    }
+    let rect = RectSpec {
+        pos: P3::new(1., 2., 3.),
+        align: RectAlign::Midpoint {
+            rect_a: C2::P00,
+            dot_a: C3::P000,
+            rect_b: C2::P11,
+            dot_b: C3::P111,
+        },
+        x_length: 5.,
+        y_length: 4.,
+        size: 1.,
+        rot: axis_degrees(Axis::Z, 30.),
+        shapes: RectShapes::Sphere,
+    };
+    let back = round_trip(&rect);
+    assert_eq!(back.shapes, rect.shapes);
+    assert_relative_eq!(
+        Rect::new(back).unwrap().p11.p000,
+        Rect::new(rect).unwrap().p11.p000
+    );
+
+    let cuboid = CuboidSpec {
+        pos: P3::origin(),
+        align: CuboidAlign::outside(C3::P000),
+        x_length: 5.,
+        y_length: 4.,
+        z_length: 3.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: CuboidShapes::Custom {
+            p000: DotShape::Cube,
+            p100: DotShape::Sphere,
+            p110: DotShape::Cylinder,
+            p010: DotShape::Cube,
+            p001: DotShape::Cube,
+            p101: DotShape::Cube,
+            p111: DotShape::Cube,
+            p011: DotShape::Cube,
+        },
+    };
+    assert_eq!(round_trip(&cuboid).shapes, cuboid.shapes);
+
+    let post = PostSpecEnds {
+        bot: P3::origin(),
+        top: P3::new(0., 0., 10.),
+        size: 2.,
+        align_bot_face: C2::P00,
+        x_axis_handle: P3::new(1., 0., 0.),
+        shapes: PostShapes::Round,
+    };
+    let back = round_trip(&post);
+    assert_eq!(back.shapes, post.shapes);
+    assert_eq!(back.top, post.top);
+}
+
+#[cfg(feature = "scene")]
+#[test]
+fn scene_files() {
+    let ron = r#"
+        Diff([
+            Cylinder((
+                pos: [0, 0, 0],
+                align: EndCenter(P0),
+                diameter: 20,
+                height: 5,
+                rot: (w: 1, i: 0, j: 0, k: 0),
+            )),
+            Rotate(axis: [1, 0, 0], degrees: 90, child: Translate(
+                offset: [0, 0, -1],
+                child: Cylinder((
+                    pos: [0, 0, 0],
+                    align: EndCenter(P0),
+                    diameter: 8,
+                    height: 7,
+                    rot: (w: 1, i: 0, j: 0, k: 0),
+                )),
+            )),
+        ])
+    "#;
+    let cylinder = |diameter, height| {
+        Cylinder::new(CylinderSpec {
+            pos: P3::origin(),
+            align: CylinderAlign::EndCenter(C1::P0),
+            diameter,
+            height,
+            rot: R3::identity(),
+        })
+    };
+    let expected = diff![
+        cylinder(20., 5.),
+        rotate!(
+            axis_degrees(Axis::X, 90.),
+            translate!([0., 0., -1.], cylinder(8., 7.))
+        )
+    ];
+    let options = RenderQuality::Low;
+    assert_eq!(
+        to_code(&scene::from_ron(ron).unwrap(), options).unwrap(),
+        to_code(&expected, options).unwrap()
+    );
+
+    let json = r#"{"Translate": {
+        "offset": [1, 2, 3],
+        "child": {"RawScad": "cube(1);"}
+    }}"#;
+    assert_eq!(
+        to_code(&scene::from_json(json).unwrap(), options).unwrap(),
+        to_code(
+            &translate!([1., 2., 3.], Tree::raw_scad("cube(1);")),
+            options
+        )
+        .unwrap()
+    );
+
+    let ron = r#"
+        Union([
+            Rect(
+                spec: (
+                    pos: [0, 0, 0],
+                    align: Corner(rect: P00, dot: P000),
+                    x_length: 10,
+                    y_length: 5,
+                    size: 1,
+                    rot: (w: 1, i: 0, j: 0, k: 0),
+                    shapes: Cube,
+                ),
+                link: Frame,
+            ),
+            Post(
+                spec: (
+                    pos: [0, 0, 0],
+                    align: Corner(post: P0, dot: P000),
+                    len: 8,
+                    rot: (w: 1, i: 0, j: 0, k: 0),
+                    size: 1,
+                    shapes: Round,
+                ),
+                link: Solid,
+            ),
+        ])
+    "#;
+    let rect = Rect::new(RectSpec {
+        pos: P3::origin(),
+        align: RectAlign::Corner {
+            rect: C2::P00,
+            dot: C3::P000,
+        },
+        x_length: 10.,
+        y_length: 5.,
+        size: 1.,
+        rot: R3::identity(),
+        shapes: RectShapes::Cube,
+    })
+    .unwrap();
+    let post = Post::new(PostSpec {
+        pos: P3::origin(),
+        align: PostAlign::Corner {
+            post: C1::P0,
+            dot: C3::P000,
+        },
+        len: 8.,
+        rot: R3::identity(),
+        size: 1.,
+        shapes: PostShapes::Round,
+    })
+    .unwrap();
+    assert_eq!(
+        to_code(&scene::from_ron(ron).unwrap(), options).unwrap(),
+        to_code(
+            &union![
+                rect.link(RectLink::Frame).unwrap(),
+                post.link(PostLink::Solid)
+            ],
+            options
+        )
+        .unwrap()
+    );
+
+    match scene::from_json(r#"{"Sphere": 3}"#) {
+        Err(ScadDotsError::Scene(_)) => (),
+        other => panic!("expected a scene error, got {:?}", other),
+    }
+    assert!(scene::load_scene("model.txt").is_err());
+}
+
+#[test]
+fn param_set() {
+    struct Plate {
+        width: f32,
+        thickness: f32,
+    }
+
+    impl Parametric for Plate {
+        fn params() -> ParamSet {
+            let mut params = ParamSet::new();
+            params
+                .add(
+                    Param::new("width", 2.)
+                        .with_unit(Unit::Inch)
+                        .with_range(1., 4.)
+                        .with_step(0.5)
+                        .with_description("Plate width"),
+                )
+                .unwrap();
+            params
+                .add(Param::new("thickness", 3.).with_range(1., 10.))
+                .unwrap();
+            params
+                .add(Param::new("holes", 4.).with_unit(Unit::Unitless))
+                .unwrap();
+            params
+        }
+
+        fn from_params(params: &ParamSet) -> Result<Self, ScadDotsError> {
+            Ok(Self {
+                width: params.get("width")?,
+                thickness: params.get("thickness")?,
+            })
+        }
+    }
+
+    let plate = Plate::from_default_params().unwrap();
+    assert_relative_eq!(plate.width, 50.8);
+    assert_relative_eq!(plate.thickness, 3.);
+
+    let mut params = Plate::params();
+    params.set("thickness", 4.5).unwrap();
+    assert!(params.set("thickness", 11.).is_err());
+    assert!(params.set("depth", 1.).is_err());
+    assert!(params.get("depth").is_err());
+    assert!(params.add(Param::new("width", 1.)).is_err());
+    assert!(params.add(Param::new("2nd", 1.)).is_err());
+    assert!(params
+        .add(Param::new("big", 5.).with_range(0., 1.))
+        .is_err());
+    assert_relative_eq!(Plate::from_params(&params).unwrap().thickness, 4.5);
+
+    assert_eq!(
+        params.customizer_header(),
+        "/* [Parameters] */\n\
+         // Plate width (in)\n\
+         width = 2; // [1:0.5:4]\n\
+         // thickness (mm)\n\
+         thickness = 4.5; // [1:10]\n\
+         // holes\n\
+         holes = 4;\n\
+         /* [Hidden] */\n"
+    );
+
+    let schema = params.json_schema();
+    assert!(schema.contains(
+        "\"width\": {\n      \"type\": \"number\",\n      \
+         \"description\": \"Plate width (in)\",\n      \
+         \"default\": 2,\n      \"minimum\": 1,\n      \"maximum\": 4\n    }"
+    ));
+    assert!(schema.contains("\"thickness\": {"));
+}
+
+#[test]
+fn length_units() {
+    assert_relative_eq!(f32::from(Inch(1.)), 25.4);
+    assert_relative_eq!(Inch::from(Mm(50.8)).0, 2.);
+    assert_relative_eq!((Mm(10.) + Inch(1.)).0, 35.4);
+    assert_relative_eq!((Inch(1.) - Mm(12.7)).0, 0.5);
+    assert_relative_eq!((2. * Inch(0.5) / 4.).0, 0.25);
+    assert_relative_eq!(Mm(3.) / Mm(2.), 1.5);
+    assert_relative_eq!(Mm::from(Inch(0.5)).0, 12.7);
+    assert_relative_eq!(Unit::Inch.scale(), MM_PER_INCH);
+    let mut total = Mm(1.);
+    total += Inch(1.);
+    total -= Mm(0.4);
+    assert_relative_eq!(total.0, 26.);
+    assert!(Mm(1.) < Mm(2.));
+    assert_eq!(format!("{} {}", Mm(3.), -Inch(0.25)), "3mm -0.25in");
+
+    let dot = Dot::new(DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::origin(),
+        size: Inch(0.25).into(),
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    });
+    assert_relative_eq!(dot.size, 6.35);
+}
+
+#[test]
+fn validate_tree() {
+    let dot = |size| {
+        Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::origin(),
+            size,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        })
+    };
+    let polygon = |points: Vec<(f32, f32)>| {
+        Polygon2D::new(points.into_iter().map(|(x, y)| P2::new(x, y)).collect())
+            .unwrap()
+    };
+    let extrusion = |holes: Vec<Polygon2D>| Extrusion {
+        perimeter: polygon(vec![(0., 0.), (4., 0.), (0., 4.)]),
+        holes,
+        thickness: 1.,
+        bottom_z: 0.,
+        params: ExtrudeParams::default(),
+    };
+    let good = union![
+        hull![dot(1.), dot(2.)],
+        diff![dot(2.), dot(1.)],
+        extrusion(vec![polygon(vec![(1., 1.), (2., 1.), (1., 2.)])]),
+    ];
+    assert_eq!(good.validate(), Vec::new());
+
+    let bad = union![
+        hull![dot(0.)],
+        diff![Tree::union(Vec::<Tree>::new()), dot(1.)],
+        translate!([0., std::f32::NAN, 0.], dot(1.)),
+        extrusion(vec![polygon(vec![(3., 3.), (5., 3.), (3., 5.)])]),
+    ];
+    let warnings: Vec<_> =
+        bad.validate().iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        vec![
+            "union/hull[0]: hull has only one child",
+            "union/hull[0]/dot[0]: has zero or negative size",
+            "union/diff[1]: difference has an empty first child",
+            "union/translate[2]: has NaN or infinite values",
+            "union/extrusion[3]: extrusion hole isn't inside its perimeter",
+        ]
+    );
+    assert_eq!(bad.validate()[0].problem, ValidationProblem::SingleHull);
+}
+
+#[test]
+fn non_finite_specs() {
+    let spec = DotSpec {
+        pos: P3::origin(),
+        align: DotAlign::origin(),
+        size: 1.,
+        rot: R3::identity(),
+        shape: DotShape::Cube,
+    };
+    assert!(Dot::try_new(spec).is_ok());
+    // Normalizing a zero vector makes NaNs.
+    let nan_pos = P3::from(V3::zeros().normalize());
+    let is_numeric = |result: Result<_, ScadDotsError>| match result {
+        Err(e) => match e.root() {
+            ScadDotsError::Numeric => true,
+            _ => false,
+        },
+        _ => false,
+    };
+    assert!(is_numeric(Dot::try_new(spec.with_pos(nan_pos)).map(|_| ())));
+    assert!(is_numeric(
+        Dot::try_new(DotSpec {
+            size: std::f32::INFINITY,
+            ..spec
+        })
+        .map(|_| ())
+    ));
+    assert!(is_numeric(
+        Cylinder::try_new(CylinderSpec {
+            pos: P3::origin(),
+            align: CylinderAlign::Centroid,
+            diameter: std::f32::NAN,
+            height: 1.,
+            rot: R3::identity(),
+        })
+        .map(|_| ())
+    ));
+    assert!(is_numeric(
+        Rect::new(RectSpec {
+            pos: nan_pos,
+            align: RectAlign::origin(),
+            x_length: 5.,
+            y_length: 10.,
+            size: 1.,
+            rot: R3::identity(),
+            shapes: RectShapes::Cube,
+        })
+        .map(|_| ())
+    ));
+}
+
+#[test]
+fn named_part_errors() {
+    let spec = CuboidSpecCorners {
+        p000: P3::origin(),
+        p111: P3::new(2., 5., 5.),
+        size: 3.,
+        shapes: CuboidShapes::Cube,
+    };
+    let err = Cuboid::new_named("thumb_cluster", spec).unwrap_err();
+    match &err {
+        ScadDotsError::Part {
+            kind, name, spec, ..
+        } => {
+            assert_eq!(kind, "cuboid");
+            assert_eq!(name.as_ref().unwrap(), "thumb_cluster");
+            assert!(spec.starts_with("CuboidSpecCorners {"));
+        }
+        other => panic!("expected a part error, got {:?}", other),
+    }
+    assert!(err.to_string().starts_with("cuboid 'thumb_cluster': "));
+    match err.root() {
+        ScadDotsError::Dimension => (),
+        other => panic!("expected a dimension error, got {:?}", other),
+    }
+    assert!(Cuboid::new(spec)
+        .unwrap_err()
+        .to_string()
+        .starts_with("cuboid: "));
+}
+
+#[test]
+fn dimension_modes() {
+    let spec = RectSpec {
+        pos: P3::origin(),
+        align: RectAlign::origin(),
+        x_length: 2.,
+        y_length: 10.,
+        size: 3.,
+        rot: R3::identity(),
+        shapes: RectShapes::Cube,
+    };
+    let err = Rect::new_named("thumb_cluster", spec).unwrap_err();
+    assert!(err.to_string().starts_with(
+        "rect 'thumb_cluster': x_length 2 is smaller than dot size 3\n"
+    ));
+    let clamped = spec.check_dimensions(DimensionMode::Clamp).unwrap();
+    assert_relative_eq!(clamped.x_length, 3.);
+    assert_relative_eq!(clamped.y_length, 10.);
+    let rect = Rect::new(clamped).unwrap();
+    assert_relative_eq!(rect.p00.p000, rect.p10.p000);
+
+    let bad_size = RectSpec { size: 0., ..spec };
+    assert!(bad_size.check_dimensions(DimensionMode::Clamp).is_err());
+    let nan_length = RectSpec {
+        x_length: std::f32::NAN,
+        ..spec
+    };
+    assert!(nan_length.check_dimensions(DimensionMode::Clamp).is_err());
+
+    let ends = PostSpecEnds {
+        bot: P3::origin(),
+        top: P3::new(0., 0., 1.),
+        align_bot_face: C2::P00,
+        size: 2.,
+        x_axis_handle: P3::new(2., 1., 0.),
+        shapes: PostShapes::Cube,
+    };
+    assert!(Post::new(ends).is_err());
+    let clamped = ends.check_dimensions(DimensionMode::Clamp).unwrap();
+    assert_relative_eq!(clamped.top, P3::new(0., 0., 2.));
+    assert!(Post::new(clamped).is_ok());
+
+    let corners = CuboidSpecCorners {
+        p000: P3::origin(),
+        p111: P3::new(1., 5., 5.),
+        size: 2.,
+        shapes: CuboidShapes::Cube,
+    };
+    let clamped = corners.check_dimensions(DimensionMode::Clamp).unwrap();
+    assert_relative_eq!(clamped.p111, P3::new(2., 5., 5.));
+    assert!(Cuboid::new(clamped).is_ok());
+}
+
+#[test]
+fn keyboard_plate() {
+    let spec = PlateSpec {
+        margin: 1.,
+        ..Default::default()
+    };
+    let plate = switch_plate(&Layout::grid(2, 3), spec).unwrap();
+    let bounds = plate.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(-1., -2. * KEY_UNIT - 1., 0.));
+    assert_relative_eq!(
+        bounds.max,
+        P3::new(3. * KEY_UNIT + 1., 1., PLATE_THICKNESS),
+        epsilon = 0.0001
+    );
+
+    let layout = Layout::from_rows(&[vec![1., 2.], vec![1.5, 1.5]]);
+    assert_eq!(layout.keys.len(), 4);
+    assert_relative_eq!(
+        layout.keys[1].center,
+        P2::new(2. * KEY_UNIT, -KEY_UNIT / 2.)
+    );
+    assert_relative_eq!(
+        layout.keys[2].center,
+        P2::new(0.75 * KEY_UNIT, -1.5 * KEY_UNIT)
+    );
+
+    let rotated = Layout::grid(1, 2).rotated(90., P2::origin());
+    assert_relative_eq!(
+        rotated.keys[1].center,
+        P2::new(KEY_UNIT / 2., 1.5 * KEY_UNIT),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(rotated.keys[1].degrees, 90.);
+    let chamfered = PlateSpec {
+        chamfer: Some(0.5),
+        ..Default::default()
+    };
+    let plate = switch_plate(&rotated, chamfered).unwrap();
+    assert!(plate.validate().is_empty());
+    assert_relative_eq!(
+        plate.bound_length(Axis::Y),
+        2. * KEY_UNIT,
+        epsilon = 0.0001
+    );
+
+    assert!(switch_plate(&Layout::default(), spec).is_err());
+    let deep = PlateSpec {
+        chamfer: Some(PLATE_THICKNESS),
+        ..Default::default()
+    };
+    assert!(switch_plate(&Layout::grid(1, 1), deep).is_err());
+    let tiny = Layout {
+        keys: vec![Key::new(P2::origin()).with_size(0.5, 1.)],
+    };
+    assert!(switch_plate(&tiny, spec).is_err());
+}
+
+#[test]
+fn enclosure() {
+    let outline = Polygon2D::new(vec![
+        P2::new(0., 0.),
+        P2::new(20., 0.),
+        P2::new(20., 10.),
+        P2::new(0., 10.),
+    ])
+    .unwrap();
+    let spec = EnclosureSpec {
+        outline,
+        wall: 2.,
+        height: 15.,
+        corner_radius: 3.,
+        lid: LidStyle::FrictionFit {
+            lip_depth: 4.,
+            clearance: 0.2,
+        },
+    };
+    let enclosure = Enclosure::new(spec.clone()).unwrap();
+    let bounds = enclosure.body.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(-2., -2., 0.), epsilon = 0.0001);
+    assert_relative_eq!(bounds.max, P3::new(22., 12., 15.), epsilon = 0.0001);
+    let bounds = enclosure.lid.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(-2., -2., 11.), epsilon = 0.0001);
+    assert_relative_eq!(bounds.max, P3::new(22., 12., 17.), epsilon = 0.0001);
+    assert!(enclosure.body.validate().is_empty());
+    assert!(enclosure.lid.validate().is_empty());
+
+    let screwed = EnclosureSpec {
+        lid: LidStyle::Screwed {
+            boss_diameter: 6.,
+            pilot_diameter: 2.5,
+            clearance_diameter: 3.2,
+            head: Some(HoleHead::Countersink { diameter: 6. }),
+        },
+        ..spec.clone()
+    };
+    let enclosure = Enclosure::new(screwed.clone()).unwrap();
+    assert_relative_eq!(enclosure.lid.min_coord(Axis::Z), 15.);
+    assert_relative_eq!(
+        enclosure.body.bound_length(Axis::X),
+        24.,
+        epsilon = 0.0001
+    );
+
+    let dots: Vec<_> = [(0., 0.), (20., 0.), (10., 5.), (20., 10.), (0., 10.)]
+        .iter()
+        .map(|&(x, y)| {
+            Dot::new(DotSpec {
+                pos: P3::new(x, y, 3.),
+                align: DotAlign::centroid(),
+                size: 1.,
+                rot: R3::identity(),
+                shape: DotShape::Cube,
+            })
+        })
+        .collect();
+    assert_relative_eq!(outline_of_dots(&dots).unwrap().area(), 200.);
+
+    assert!(Enclosure::new(EnclosureSpec {
+        height: 2.,
+        ..spec.clone()
+    })
+    .is_err());
+    assert!(Enclosure::new(EnclosureSpec {
+        lid: LidStyle::FrictionFit {
+            lip_depth: 14.,
+            clearance: 0.2,
+        },
+        ..spec.clone()
+    })
+    .is_err());
+    let tiny =
+        Polygon2D::new(vec![P2::new(0., 0.), P2::new(4., 0.), P2::new(0., 4.)])
+            .unwrap();
+    assert!(Enclosure::new(EnclosureSpec {
+        outline: tiny,
+        ..screwed.clone()
+    })
+    .is_err());
+
+    // Bosses only go in the corners, not at every point along the sides.
+    let square = |side: f32| {
+        Polygon2D::new(vec![
+            P2::new(0., 0.),
+            P2::new(side / 2., 0.),
+            P2::new(side, 0.),
+            P2::new(side, side),
+            P2::new(0., side),
+        ])
+        .unwrap()
+    };
+    let boss_count = |spec: EnclosureSpec| {
+        let body = Enclosure::new(spec).unwrap().body;
+        match body {
+            Tree::Operator(TreeOperator::Diff(children)) => {
+                match &children[0] {
+                    Tree::Operator(TreeOperator::Union(parts)) => {
+                        match &parts[1] {
+                            Tree::Operator(TreeOperator::Union(bosses)) => {
+                                bosses.len()
+                            }
+                            other => panic!("expected bosses, got {:?}", other),
+                        }
+                    }
+                    other => panic!("expected a union, got {:?}", other),
+                }
+            }
+            other => panic!("expected a diff, got {:?}", other),
+        }
+    };
+    assert_eq!(
+        boss_count(EnclosureSpec {
+            outline: square(60.),
+            ..screwed.clone()
+        }),
+        4
+    );
+
+    // With large rounded corners, the bosses would stick out of the walls.
+    assert!(Enclosure::new(EnclosureSpec {
+        outline: square(60.),
+        corner_radius: 20.,
+        ..screwed.clone()
+    })
+    .is_err());
+    assert!(Enclosure::new(EnclosureSpec {
+        outline: square(60.),
+        corner_radius: 4.,
+        ..screwed
+    })
+    .is_ok());
+}
+
+#[test]
+fn gear_profiles() {
+    let spec = GearSpec {
+        module: 2.,
+        teeth: 20,
+        pressure_angle: STANDARD_PRESSURE_ANGLE,
+    };
+    assert_relative_eq!(spec.pitch_diameter(), 40.);
+    assert_relative_eq!(spec.outer_diameter(), 44.);
+    assert_relative_eq!(spec.root_diameter(), 35.);
+    let profile = spec.profile().unwrap();
+    let radii: Vec<f32> =
+        profile.points().iter().map(|p| p.coords.norm()).collect();
+    let max = radii.iter().cloned().fold(0., f32::max);
+    let min = radii.iter().cloned().fold(100., f32::min);
+    assert_relative_eq!(max, 22., epsilon = 0.0001);
+    assert_relative_eq!(min, 17.5, epsilon = 0.0001);
+    // A tooth points along the +x axis.
+    assert!(profile.contains(P2::new(21., 0.)));
+    let extrusion = spec.extrusion(5., 1.).unwrap();
+    assert_relative_eq!(extrusion.thickness, 5.);
+
+    // Meshing gears' teeth fit between each other without overlapping.
+    let small = GearSpec { teeth: 12, ..spec };
+    assert_relative_eq!(center_distance(&spec, &small).unwrap(), 32.);
+    let (center, degrees) =
+        mesh_position(&spec, P2::origin(), &small, 50.).unwrap();
+    assert_relative_eq!(
+        center,
+        P2::new(32. * cos_deg(50.), 32. * sin_deg(50.)),
+        epsilon = 0.0001
+    );
+    let placed = |degrees: f32| {
+        small
+            .extrusion(1., 0.)
+            .unwrap()
+            .rotate_z(degrees)
+            .translate(V3::new(center.x, center.y, 0.))
+            .perimeter
+    };
+    let overlaps = |polygon: Polygon2D| {
+        polygon.points().iter().any(|p| profile.contains(*p))
+    };
+    assert!(!overlaps(placed(degrees)));
+    assert!(overlaps(placed(degrees + small.tooth_angle() / 2.)));
+
+    assert!(center_distance(&spec, &GearSpec { module: 1., ..spec }).is_err());
+    assert!(GearSpec { teeth: 2, ..spec }.profile().is_err());
+    assert!(GearSpec {
+        module: -1.,
+        ..spec
+    }
+    .profile()
+    .is_err());
+}
+
+#[test]
+fn joints() {
+    let spec = JointSpec {
+        style: JointStyle::Finger,
+        tabs: 2,
+        depth: 4.,
+        clearance: 0.2,
+    };
+    let joint = Joint::new(spec, 50., 5.).unwrap();
+    let bounds = joint.positive.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(10., -1., 0.), epsilon = 0.0001);
+    assert_relative_eq!(bounds.max, P3::new(40., 4., 5.), epsilon = 0.0001);
+    let bounds = joint.negative.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(9.8, -1.2, -1.), epsilon = 0.0001);
+    assert_relative_eq!(bounds.max, P3::new(40.2, 4.2, 6.), epsilon = 0.0001);
+
+    let dovetail = JointSpec {
+        style: JointStyle::Dovetail { degrees: 45. / 2. },
+        tabs: 1,
+        ..spec
+    };
+    let joint = Joint::new(dovetail, 30., 5.).unwrap();
+    let flare = 4. * (45f32 / 2.).to_radians().tan();
+    assert_relative_eq!(
+        joint.positive.bound_length(Axis::X),
+        10. + 2. * flare,
+        epsilon = 0.0001
+    );
+
+    let cuboid = |x| {
+        Cuboid::new(CuboidSpec {
+            pos: P3::new(x, 0., 0.),
+            align: CuboidAlign::origin(),
+            x_length: 40.,
+            y_length: 30.,
+            z_length: 4.,
+            size: 1.,
+            rot: R3::identity(),
+            shapes: CuboidShapes::Cube,
+        })
+        .unwrap()
+    };
+    let (left, right) = (cuboid(0.), cuboid(40.));
+    let joint = Joint::between(spec, &left, CubeFace::X1, &right, CubeFace::X0)
+        .unwrap();
+    let bounds = joint.positive.bounds().unwrap();
+    assert_relative_eq!(bounds.min, P3::new(39., 6., 0.), epsilon = 0.0001);
+    assert_relative_eq!(bounds.max, P3::new(44., 24., 4.), epsilon = 0.0001);
+    assert!(
+        Joint::between(spec, &left, CubeFace::X1, &left, CubeFace::X0).is_err()
+    );
+
+    assert!(Joint::new(JointSpec { tabs: 0, ..spec }, 50., 5.).is_err());
+    assert!(Joint::new(JointSpec { tabs: 100, ..spec }, 50., 5.).is_err());
+    assert!(Joint::new(spec, 50., -5.).is_err());
+}
+
+#[test]
+fn split_tree() {
+    let block = || {
+        Tree::from(Dot::new(DotSpec {
+            pos: P3::origin(),
+            align: DotAlign::centroid(),
+            size: 10.,
+            rot: R3::identity(),
+            shape: DotShape::Cube,
+        }))
+    };
+    let plane = Plane::new(P3::new(1., 0., 0.), V3::new(1., 0., 0.));
+    check_model("split_pins", Action::Test, || {
+        let pins = PinHoles {
+            positions: vec![P3::new(5., 2., 2.), P3::new(1., -2., -2.)],
+            diameter: 1.,
+            depth: 2.,
+        };
+        let (front, back) = block().split_with_pins(plane, &pins)?;
+        Ok(union![front, translate!([-5., 0., 0.], back)])
+    });
+    let (front, back) = block().split(plane).unwrap();
+    assert_eq!(front.bounds(), block().bounds());
+    assert_eq!(back.bounds(), block().bounds());
+
+    let outside = Plane::new(P3::new(6., 0., 0.), V3::new(1., 0., 0.));
+    assert!(block().split(outside).is_err());
+    assert!(Tree::union(Vec::<Tree>::new()).split(plane).is_err());
+    let zero = Plane {
+        point: P3::origin(),
+        normal: V3::zeros(),
+    };
+    assert!(block().split(zero).is_err());
+}
+
+#[test]
+fn hardware() {
+    let m3 = MetricSize::M3.dims();
+    assert_relative_eq!(m3.diameter, 3.);
+    assert_relative_eq!(m3.nut_width, 5.5);
+    assert_eq!(BearingSize::B608.dims(), (8., 22., 7.));
+
+    // Hang a screw from the center of its head.
+    let spec = HardwareSpec {
+        pos: P3::new(0., 0., 10.),
+        align: CylinderAlign::EndCenter(C1::P1),
+        rot: R3::identity(),
+    };
+    let screw = Hardware::Screw {
+        size: MetricSize::M3,
+        length: 12.,
+    };
+    assert_relative_eq!(screw.height(), 15.);
+    let bounds = screw.model(spec).unwrap().bounds().unwrap();
+    assert_relative_eq!(
+        bounds.min,
+        P3::new(-2.75, -2.75, -5.),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(bounds.max, P3::new(2.75, 2.75, 10.), epsilon = 0.0001);
+    let bounds = screw.clearance(spec, 0.2).unwrap().bounds().unwrap();
+    assert_relative_eq!(
+        bounds.min,
+        P3::new(-2.95, -2.95, -5.2),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        bounds.max,
+        P3::new(2.95, 2.95, 10.2),
+        epsilon = 0.0001
+    );
+
+    // A nut lying on its side, with its bottom face centered on the origin.
+    let nut = Hardware::Nut(MetricSize::M3);
+    let spec = HardwareSpec {
+        pos: P3::origin(),
+        align: CylinderAlign::EndCenter(C1::P0),
+        rot: axis_degrees(Axis::Y, 90.),
+    };
+    let bounds = nut.model(spec).unwrap().bounds().unwrap();
+    let corner_radius = 5.5 / 2. / cos_deg(30.);
+    assert_relative_eq!(
+        bounds.min,
+        P3::new(0., -2.75, -corner_radius),
+        epsilon = 0.0001
+    );
+    assert_relative_eq!(
+        bounds.max,
+        P3::new(2.4, 2.75, corner_radius),
+        epsilon = 0.0001
+    );
+
+    let bearing = Hardware::Bearing(BearingSize::B608);
+    let bounds = bearing.clearance(spec, 0.5).unwrap().bounds().unwrap();
+    assert_relative_eq!(bounds.size(), V3::new(8., 23., 23.), epsilon = 0.0001);
+
+    assert!(bearing.clearance(spec, -0.1).is_err());
+    let bad_screw = Hardware::Screw {
+        size: MetricSize::M3,
+        length: 0.,
+    };
+    assert!(bad_screw.model(spec).is_err());
+}
+
+#[cfg(feature = "scad")]
+#[test]
+fn scad_generator_backend() {
+    let dot = |pos, shape| {
+        Dot::new(DotSpec {
+            pos,
+            align: DotAlign::centroid(),
+            size: 2.,
+            rot: axis_degrees(Axis::Z, 30.),
+            shape,
+        })
+    };
+    let tree = diff![
+        dot(P3::origin(), DotShape::Cube),
+        translate![
+            V3::new(0., 0., 1.),
+            dot(P3::new(1., 0., 0.), DotShape::Cylinder)
+        ],
+        mirror![V3::x(), red![dot(P3::new(2., 0., 0.), DotShape::Sphere)]]
+    ];
+    let expected = to_code(&tree, RenderQuality::Low).unwrap();
+    let code = scad_generator::to_code(&tree, RenderQuality::Low).unwrap();
+    assert!(scad_relative_eq(&code, &expected, MAX_RELATIVE).unwrap());
+
+    let label = Text {
+        text: "hi".to_owned(),
+        font_size: 3.,
+        thickness: 1.,
+        pos: P3::origin(),
+        rot: R3::identity(),
+    };
+    assert!(scad_generator::to_code(&label, RenderQuality::Low).is_err());
+}